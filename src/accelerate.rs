@@ -0,0 +1,177 @@
+use crate::error::Result;
+use crate::S3Bucket;
+use aws_sdk_s3::model::{AccelerateConfiguration, BucketAccelerateStatus};
+use aws_sigv4::http_request::{sign, PayloadChecksumKind, PercentEncodingMode, SignableBody, SignableRequest};
+use aws_sigv4::http_request::{SigningParams, SigningSettings};
+use aws_smithy_client::erase::DynConnector;
+use aws_smithy_http::body::SdkBody;
+use aws_smithy_http::result::ConnectorError;
+use aws_types::credentials::{ProvideCredentials, SharedCredentialsProvider};
+use http::header::{AUTHORIZATION, HOST};
+use http::{HeaderValue, Request, Response, Uri};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+use tower::{Service, ServiceExt};
+
+// -- constants
+/// The S3 Transfer Acceleration host, the buckets being `<bucket>.s3-accelerate.amazonaws.com`.
+const ACCELERATE_HOST: &str = "s3-accelerate.amazonaws.com";
+/// The client endpoint, the path-style requests being rewritten to the bucket host.
+pub(crate) const ACCELERATE_ENDPOINT: &str = "https://s3-accelerate.amazonaws.com";
+/// The SigV4 headers replaced by the new signature (`x-amz-content-sha256` is kept, as the payload hash).
+const SIGNING_HEADERS: [&str; 2] = ["x-amz-date", "x-amz-security-token"];
+const HEADER_CONTENT_SHA256: &str = "x-amz-content-sha256";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Connector sending the requests to the Transfer Acceleration endpoint of their bucket.
+///
+/// Note: this SDK version always sends path-style requests (`s3-accelerate.amazonaws.com/<bucket>/<key>`),
+/// which the acceleration endpoint does not serve, so this connector rewrites them virtual-hosted style
+/// (`<bucket>.s3-accelerate.amazonaws.com/<key>`) and signs them again (the host and path being signed).
+#[derive(Clone)]
+pub(crate) struct AccelerateConnector {
+	inner: DynConnector,
+	credentials: SharedCredentialsProvider,
+	region: String,
+}
+
+impl AccelerateConnector {
+	pub(crate) fn new(inner: DynConnector, credentials: SharedCredentialsProvider, region: &str) -> Self {
+		AccelerateConnector {
+			inner,
+			credentials,
+			region: region.to_string(),
+		}
+	}
+
+	async fn resign(&self, req: &mut Request<SdkBody>) -> std::result::Result<(), ConnectorError> {
+		let creds = self
+			.credentials
+			.provide_credentials()
+			.await
+			.map_err(|ex| ConnectorError::user(Box::new(ex)))?;
+
+		// Note: the payload hash computed by the SDK is kept (the body is not read again).
+		let host = req
+			.uri()
+			.authority()
+			.and_then(|a| HeaderValue::from_str(a.as_str()).ok());
+		let headers = req.headers_mut();
+		headers.remove(AUTHORIZATION);
+		if let Some(host) = host {
+			headers.insert(HOST, host);
+		}
+		for name in SIGNING_HEADERS {
+			headers.remove(name);
+		}
+		let payload_hash = headers
+			.get(HEADER_CONTENT_SHA256)
+			.and_then(|v| v.to_str().ok())
+			.unwrap_or(UNSIGNED_PAYLOAD)
+			.to_string();
+
+		let mut settings = SigningSettings::default();
+		settings.percent_encoding_mode = PercentEncodingMode::Single;
+		settings.payload_checksum_kind = PayloadChecksumKind::XAmzSha256;
+		let mut params = SigningParams::builder()
+			.access_key(creds.access_key_id())
+			.secret_key(creds.secret_access_key())
+			.region(&self.region)
+			.service_name("s3")
+			.time(SystemTime::now())
+			.settings(settings);
+		params.set_security_token(creds.session_token());
+		let params = params.build().map_err(|ex| ConnectorError::user(Box::new(ex)))?;
+
+		let signable = SignableRequest::new(
+			req.method(),
+			req.uri(),
+			req.headers(),
+			SignableBody::Precomputed(payload_hash),
+		);
+		let (instructions, _) = sign(signable, &params).map_err(ConnectorError::user)?.into_parts();
+		instructions.apply_to_request(req);
+
+		Ok(())
+	}
+}
+
+type ConnectorFuture = Pin<Box<dyn Future<Output = std::result::Result<Response<SdkBody>, ConnectorError>> + Send>>;
+
+impl Service<Request<SdkBody>> for AccelerateConnector {
+	type Response = Response<SdkBody>;
+	type Error = ConnectorError;
+	type Future = ConnectorFuture;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, mut req: Request<SdkBody>) -> Self::Future {
+		let Some(uri) = virtual_host_uri(req.uri()) else {
+			return Box::pin(self.inner.clone().oneshot(req));
+		};
+		let this = self.clone();
+		Box::pin(async move {
+			*req.uri_mut() = uri;
+			this.resign(&mut req).await?;
+			this.inner.oneshot(req).await
+		})
+	}
+}
+
+/// The virtual-hosted style uri of a path-style request to the acceleration endpoint
+/// (None for the other requests, e.g., without bucket).
+fn virtual_host_uri(uri: &Uri) -> Option<Uri> {
+	if uri.host() != Some(ACCELERATE_HOST) {
+		return None;
+	}
+	let (bucket, path) = match uri.path().strip_prefix('/')?.split_once('/') {
+		Some((bucket, key)) => (bucket, format!("/{key}")),
+		None => (uri.path().strip_prefix('/')?, "/".to_string()),
+	};
+	if bucket.is_empty() {
+		return None;
+	}
+	let query = uri.query().map(|q| format!("?{q}")).unwrap_or_default();
+	format!("https://{bucket}.{ACCELERATE_HOST}{path}{query}").parse().ok()
+}
+
+impl S3Bucket {
+	/// Enable (or suspend) the bucket Transfer Acceleration.
+	/// Note: the bucket name must be DNS compliant, without dots.
+	pub async fn set_accelerate(&self, enabled: bool) -> Result<()> {
+		// BUILD - aws request
+		let status = if enabled {
+			BucketAccelerateStatus::Enabled
+		} else {
+			BucketAccelerateStatus::Suspended
+		};
+		let config = AccelerateConfiguration::builder().status(status).build();
+		let req = self
+			.client()
+			.put_bucket_accelerate_configuration()
+			.bucket(self.name())
+			.accelerate_configuration(config);
+
+		// EXECUTE
+		req.send().await?;
+
+		Ok(())
+	}
+
+	/// The bucket Transfer Acceleration status, `Enabled` or `Suspended` (None if never enabled).
+	pub async fn accelerate_status(&self) -> Result<Option<String>> {
+		// BUILD & EXECUTE - aws request
+		let res = self
+			.client()
+			.get_bucket_accelerate_configuration()
+			.bucket(self.name())
+			.send()
+			.await?;
+
+		Ok(res.status().map(|s| s.as_str().to_string()))
+	}
+}
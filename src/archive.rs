@@ -0,0 +1,198 @@
+use crate::compress::ZSTD_LEVEL;
+use crate::error::{Result, S3DemoError};
+use crate::{Compression, S3Bucket};
+use bytes::Bytes;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::StreamReader;
+use tracing::{info, instrument};
+use walkdir::WalkDir;
+
+// -- constants
+/// The chunks sent from the tar thread to the upload.
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// The chunks in flight (so the tar thread waits on a slow upload).
+const CHUNK_QUEUE: usize = 16;
+
+/// The compression of a tar archive from its key extension: `.tar.gz` / `.tgz` (gzip),
+/// `.tar.zst` / `.tzst` (zstd), otherwise none.
+pub fn archive_compression(key: &str) -> Option<Compression> {
+	let key = key.to_lowercase();
+	if key.ends_with(".gz") || key.ends_with(".tgz") {
+		Some(Compression::Gzip)
+	} else if key.ends_with(".zst") || key.ends_with(".tzst") {
+		Some(Compression::Zstd)
+	} else {
+		None
+	}
+}
+
+impl S3Bucket {
+	/// Upload a tar of the directory `dir` (its content, with relative paths) to the object `key`, compressed with
+	/// `compression` (if any, as part of the content, not as `Content-Encoding`).
+	/// Returns the number of files archived.
+	///
+	/// The tar is written (in a blocking thread) as it is uploaded, one part at a time (see
+	/// [`S3Bucket::upload_from_reader`]), so there is no temporary file. On a read error, the upload is aborted.
+	#[instrument(name = "archive", skip_all, fields(bucket = %self.name(), dir = %dir.display(), key = %key))]
+	pub async fn upload_archive(&self, dir: &Path, key: &str, compression: Option<Compression>) -> Result<u64> {
+		// VALIDATE
+		if !dir.is_dir() {
+			return Err(S3DemoError::InvalidInput(format!(
+				"{} is not a directory",
+				dir.display()
+			)));
+		}
+
+		// ARCHIVE - to the channel, read by the upload
+		let (tx, rx) = mpsc::channel::<io::Result<Bytes>>(CHUNK_QUEUE);
+		let dir = dir.to_path_buf();
+		let tar_task = tokio::task::spawn_blocking(move || {
+			let writer = BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter { tx: tx.clone() });
+			let res = write_tar(&dir, compression, writer);
+			// Note: the error ends the stream, so the upload fails rather than completing a truncated archive.
+			if let Err(ex) = &res {
+				let _ = tx.blocking_send(Err(io::Error::other(ex.to_string())));
+			}
+			res
+		});
+
+		// UPLOAD
+		let mut reader = StreamReader::new(ReceiverStream::new(rx));
+		let uploaded = self.upload_from_reader(&mut reader, key, None).await;
+		// Note: dropping the reader stops the tar thread (on a failed upload).
+		drop(reader);
+		let archived = tar_task.await.map_err(|ex| S3DemoError::Io(io::Error::other(ex)))?;
+
+		// Note: a failed upload stops the tar thread with a closed channel, so the upload error is the one reported.
+		let (files, size) = match (uploaded, archived) {
+			(Err(ex), Err(S3DemoError::Io(tar_ex))) if tar_ex.kind() == io::ErrorKind::BrokenPipe => return Err(ex),
+			(_, Err(tar_ex)) => return Err(tar_ex),
+			(Err(ex), Ok(_)) => return Err(ex),
+			(Ok(size), Ok(files)) => (files, size),
+		};
+
+		info!(files, size, "archive uploaded");
+		Ok(files)
+	}
+
+	/// Extract the tar object `key` (compressed with `compression`, if any) into the directory `dir`,
+	/// as it is downloaded, without temporary file. Returns the number of entries extracted.
+	///
+	/// Note: the entries escaping `dir` (absolute, or with `..`) are skipped.
+	#[instrument(name = "extract", skip_all, fields(bucket = %self.name(), key = %key, dir = %dir.display()))]
+	pub async fn extract_archive(&self, key: &str, dir: &Path, compression: Option<Compression>) -> Result<u64> {
+		std::fs::create_dir_all(dir)?;
+
+		// EXTRACT - from the pipe, written by the download
+		let (reader, mut writer) = io::pipe()?;
+		let dir_path = dir.to_path_buf();
+		let tar_task = tokio::task::spawn_blocking(move || unpack_tar(reader, compression, &dir_path));
+
+		// DOWNLOAD
+		let downloaded = self.download_to_writer(key, &mut writer).await;
+		// Note: closing the pipe ends the archive (or stops the extraction, on a failed download).
+		drop(writer);
+		let extracted = tar_task.await.map_err(|ex| S3DemoError::Io(io::Error::other(ex)))?;
+
+		// Note: a failed extraction fails the download with a closed pipe, so its error is the one reported.
+		let entries = match (downloaded, extracted) {
+			(Err(S3DemoError::Io(ex)), Err(tar_ex)) if ex.kind() == io::ErrorKind::BrokenPipe => return Err(tar_ex),
+			(Err(ex), _) => return Err(ex),
+			(Ok(_), extracted) => extracted?,
+		};
+
+		info!(entries, "archive extracted");
+		Ok(entries)
+	}
+}
+
+/// Write the tar of `dir` to `writer`, returning the number of files.
+fn write_tar<W: Write>(dir: &Path, compression: Option<Compression>, writer: W) -> Result<u64> {
+	match compression {
+		None => {
+			let (files, mut writer) = write_entries(dir, writer)?;
+			writer.flush()?;
+			Ok(files)
+		}
+		Some(Compression::Gzip) => {
+			let encoder = GzEncoder::new(writer, flate2::Compression::default());
+			let (files, encoder) = write_entries(dir, encoder)?;
+			encoder.finish()?.flush()?;
+			Ok(files)
+		}
+		Some(Compression::Zstd) => {
+			let encoder = zstd::stream::write::Encoder::new(writer, ZSTD_LEVEL)?;
+			let (files, encoder) = write_entries(dir, encoder)?;
+			encoder.finish()?.flush()?;
+			Ok(files)
+		}
+	}
+}
+
+/// Append the files and directories under `dir` (sorted, so the archives of a same directory are identical).
+fn write_entries<W: Write>(dir: &Path, writer: W) -> Result<(u64, W)> {
+	let mut builder = tar::Builder::new(writer);
+	let mut files = 0;
+	for entry in WalkDir::new(dir).min_depth(1).sort_by_file_name() {
+		let entry = entry.map_err(|ex| S3DemoError::Io(ex.into()))?;
+		let name = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+		builder.append_path_with_name(entry.path(), name)?;
+		if entry.file_type().is_file() {
+			files += 1;
+		}
+	}
+	Ok((files, builder.into_inner()?))
+}
+
+/// Unpack the tar read from `reader` into `dir`, returning the number of entries.
+fn unpack_tar<R: Read>(reader: R, compression: Option<Compression>, dir: &Path) -> Result<u64> {
+	let reader: Box<dyn Read> = match compression {
+		None => Box::new(reader),
+		Some(Compression::Gzip) => Box::new(GzDecoder::new(reader)),
+		Some(Compression::Zstd) => Box::new(zstd::stream::read::Decoder::new(reader)?),
+	};
+	let mut archive = tar::Archive::new(reader);
+	let mut entries = 0;
+	for entry in archive.entries()? {
+		let mut entry = entry.map_err(|ex| invalid_archive(dir, ex))?;
+		if entry.unpack_in(dir).map_err(|ex| invalid_archive(dir, ex))? {
+			entries += 1;
+		}
+	}
+	Ok(entries)
+}
+
+fn invalid_archive(dir: &Path, err: io::Error) -> S3DemoError {
+	match err.kind() {
+		io::ErrorKind::InvalidData
+		| io::ErrorKind::InvalidInput
+		| io::ErrorKind::UnexpectedEof
+		| io::ErrorKind::Other => {
+			S3DemoError::InvalidResponse(format!("Cannot extract the archive to {}: {err}", dir.display()))
+		}
+		_ => S3DemoError::Io(err),
+	}
+}
+
+/// The writer sending its chunks to the upload.
+struct ChannelWriter {
+	tx: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.tx
+			.blocking_send(Ok(Bytes::copy_from_slice(buf)))
+			.map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "upload stopped"))?;
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
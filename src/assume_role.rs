@@ -0,0 +1,143 @@
+use aws_config::meta::credentials::LazyCachingCredentialsProvider;
+use aws_sdk_s3::{Credentials, Endpoint, Region};
+use aws_types::credentials::{self, future, CredentialsError, ProvideCredentials, SharedCredentialsProvider};
+use http::Uri;
+use std::time::{Duration, SystemTime};
+use tracing::info;
+
+// -- constants
+const DEFAULT_SESSION_PREFIX: &str = "s3demo";
+
+/// An IAM role assumed (STS AssumeRole) with the resolved credentials, for all the client requests.
+///
+/// The temporary credentials are refreshed before they expire (e.g., for the long-running syncs and daemon).
+/// Note: with MFA, the code cannot be used again, so the session must last the whole run (see [`AssumeRole::with_duration`]).
+#[derive(Debug, Clone)]
+pub struct AssumeRole {
+	role_arn: String,
+	external_id: Option<String>,
+	session_name: Option<String>,
+	mfa: Option<(String, String)>,
+	duration: Option<Duration>,
+}
+
+impl AssumeRole {
+	pub fn new(role_arn: impl Into<String>) -> Self {
+		AssumeRole {
+			role_arn: role_arn.into(),
+			external_id: None,
+			session_name: None,
+			mfa: None,
+			duration: None,
+		}
+	}
+
+	/// The external ID required by the role (typically for a role of another account).
+	pub fn with_external_id(mut self, external_id: impl Into<String>) -> Self {
+		self.external_id = Some(external_id.into());
+		self
+	}
+
+	/// The role session name (default: `s3demo-<epoch secs>`), visible in CloudTrail.
+	pub fn with_session_name(mut self, session_name: impl Into<String>) -> Self {
+		self.session_name = Some(session_name.into());
+		self
+	}
+
+	/// The MFA device (serial number or ARN) and its current code, for the roles requiring MFA.
+	pub fn with_mfa(mut self, serial_number: impl Into<String>, token_code: impl Into<String>) -> Self {
+		self.mfa = Some((serial_number.into(), token_code.into()));
+		self
+	}
+
+	/// The session duration (default: 1h, up to the role maximum session duration).
+	pub fn with_duration(mut self, duration: Duration) -> Self {
+		self.duration = Some(duration);
+		self
+	}
+
+	pub fn role_arn(&self) -> &str {
+		&self.role_arn
+	}
+}
+
+/// The credentials provider of the assumed `role`, calling STS with the `base` credentials (on `endpoint`,
+/// if any, as the S3 compatible services serve STS there), cached until they are about to expire.
+pub(crate) fn assume_role_provider(
+	role: &AssumeRole,
+	region: Region,
+	endpoint: Option<&Uri>,
+	base: SharedCredentialsProvider,
+) -> LazyCachingCredentialsProvider {
+	let mut conf_builder = aws_sdk_sts::Config::builder().region(region).credentials_provider(base);
+	if let Some(uri) = endpoint {
+		conf_builder = conf_builder.endpoint_resolver(Endpoint::immutable(uri.clone()));
+	}
+	let session_name = role.session_name.clone().unwrap_or_else(|| {
+		let secs = SystemTime::now()
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.map(|d| d.as_secs())
+			.unwrap_or(0);
+		format!("{DEFAULT_SESSION_PREFIX}-{secs}")
+	});
+	let provider = AssumeRoleProvider {
+		sts: aws_sdk_sts::Client::from_conf(conf_builder.build()),
+		role: role.clone(),
+		session_name,
+	};
+	LazyCachingCredentialsProvider::builder().load(provider).build()
+}
+
+#[derive(Debug)]
+struct AssumeRoleProvider {
+	sts: aws_sdk_sts::Client,
+	role: AssumeRole,
+	session_name: String,
+}
+
+impl AssumeRoleProvider {
+	async fn credentials(&self) -> credentials::Result {
+		info!(role_arn = %self.role.role_arn, session_name = %self.session_name, "assuming role");
+
+		// BUILD & EXECUTE - aws request
+		let role = &self.role;
+		let res = self
+			.sts
+			.assume_role()
+			.role_arn(&role.role_arn)
+			.role_session_name(&self.session_name)
+			.set_external_id(role.external_id.clone())
+			.set_serial_number(role.mfa.as_ref().map(|(serial, _)| serial.clone()))
+			.set_token_code(role.mfa.as_ref().map(|(_, code)| code.clone()))
+			.set_duration_seconds(role.duration.map(|d| d.as_secs() as i32))
+			.send()
+			.await
+			.map_err(CredentialsError::provider_error)?;
+
+		// COLLECT - the temporary credentials
+		let creds = res
+			.credentials()
+			.ok_or_else(|| CredentialsError::provider_error("No credentials in the AssumeRole response"))?;
+		let (Some(key_id), Some(key_secret)) = (creds.access_key_id(), creds.secret_access_key()) else {
+			return Err(CredentialsError::provider_error("Incomplete AssumeRole credentials"));
+		};
+		let expiry = creds.expiration().and_then(|d| SystemTime::try_from(*d).ok());
+
+		Ok(Credentials::new(
+			key_id,
+			key_secret,
+			creds.session_token().map(|s| s.to_string()),
+			expiry,
+			"AssumeRole",
+		))
+	}
+}
+
+impl ProvideCredentials for AssumeRoleProvider {
+	fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+	where
+		Self: 'a,
+	{
+		future::ProvideCredentials::new(self.credentials())
+	}
+}
@@ -0,0 +1,121 @@
+use crate::error::{Result, S3DemoError};
+use crate::S3Bucket;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// The mutating operation of an [`AuditEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOperation {
+	/// An object written (PutObject or a completed multipart upload).
+	Put,
+	/// An object copied server-side (CopyObject or a multipart copy).
+	Copy,
+	/// An object (or an object version) deleted.
+	Delete,
+}
+
+/// One line of an [`AuditLog`], for a successful object write or delete.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+	/// RFC 3339 time of the record (UTC, milliseconds).
+	pub timestamp: String,
+	pub operation: AuditOperation,
+	pub bucket: String,
+	pub key: String,
+	/// The version written (or delete marker placed), or the version deleted by a version delete.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub version_id: Option<String>,
+	/// The `<bucket>/<key>` source of a copy (with its `?versionId=`, if any).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub source: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub size: Option<u64>,
+	/// The ETag of the object written.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub etag: Option<String>,
+	/// The content SHA-256 (hex) of the object written, when the checksums are computed.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sha256: Option<String>,
+	/// The identity of the requests (see [`crate::ClientConfig::requester_identity`]).
+	pub requester: String,
+}
+
+impl AuditEntry {
+	/// An entry of `operation` on `key` in `bucket` (stamped by [`AuditLog::record`]).
+	pub(crate) fn new(operation: AuditOperation, bucket: &str, key: &str) -> Self {
+		AuditEntry {
+			timestamp: String::new(),
+			operation,
+			bucket: bucket.to_string(),
+			key: key.to_string(),
+			version_id: None,
+			source: None,
+			size: None,
+			etag: None,
+			sha256: None,
+			requester: String::new(),
+		}
+	}
+}
+
+/// The append-only JSONL log of the object writes and deletes of a bucket (one [`AuditEntry`] per line),
+/// set with [`S3Bucket::with_audit_log`].
+///
+/// Note: each entry is one append write, so several runs (or buckets) can share the same log.
+pub struct AuditLog {
+	path: PathBuf,
+	requester: String,
+	file: Mutex<File>,
+}
+
+impl AuditLog {
+	/// Open (or create) the log at `path`, its entries made by `requester`.
+	pub fn open(path: impl Into<PathBuf>, requester: impl Into<String>) -> Result<Self> {
+		let path = path.into();
+		let file = OpenOptions::new()
+			.create(true)
+			.append(true)
+			.open(&path)
+			.map_err(|ex| S3DemoError::AuditFailed(format!("cannot open {}: {ex}", path.display())))?;
+		Ok(AuditLog {
+			path,
+			requester: requester.into(),
+			file: Mutex::new(file),
+		})
+	}
+
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	pub fn requester(&self) -> &str {
+		&self.requester
+	}
+
+	/// Append the `entry`, stamped now and with the log requester.
+	pub fn record(&self, mut entry: AuditEntry) -> Result<()> {
+		entry.timestamp = humantime::format_rfc3339_millis(SystemTime::now()).to_string();
+		entry.requester = self.requester.clone();
+		let mut line = serde_json::to_string(&entry)?;
+		line.push('\n');
+
+		let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+		file.write_all(line.as_bytes())
+			.map_err(|ex| S3DemoError::AuditFailed(format!("cannot write {}: {ex}", self.path.display())))
+	}
+}
+
+impl S3Bucket {
+	/// Record the `entry` in the audit log (if any).
+	pub(crate) fn audit(&self, entry: AuditEntry) -> Result<()> {
+		match self.audit_log() {
+			Some(log) => log.record(entry),
+			None => Ok(()),
+		}
+	}
+}
@@ -0,0 +1,895 @@
+use crate::audit::{AuditEntry, AuditLog, AuditOperation};
+use crate::checksum::{file_checksums, file_sha256, verify_file, META_SHA256};
+use crate::compress::{compress_upload_file, Compression};
+use crate::conditional::write_error;
+use crate::cse::{cse_download_path, cse_upload_file, ClientEncryption};
+use crate::delta::PartManifestStore;
+use crate::dir::{key_path, path_key};
+use crate::download_state::{DownloadState, DownloadStateStore};
+use crate::encryption::{CustomerKey, Encryption};
+use crate::error::{Result, S3DemoError};
+use crate::file_attrs::{file_attrs_metadata, restore_file_attrs};
+use crate::headers::ContentHeaders;
+use crate::hooks::TransferHooks;
+use crate::key::{listing_key, validate_key};
+use crate::mime::MimeMap;
+use crate::multipart::{DEFAULT_MULTIPART_THRESHOLD, DEFAULT_PART_SIZE, MAX_PARTS, MIN_PART_SIZE};
+use crate::object::{DirListing, ObjectInfo};
+use crate::progress::TransferProgress;
+use crate::ranged::DEFAULT_PART_CONCURRENCY;
+use crate::split::META_SPLIT_CHUNKS;
+use crate::symlink::{restore_symlink, SymlinkMode, META_SYMLINK_TARGET};
+use crate::tagging::tagging_header;
+use crate::transfer::{Transfer, DEFAULT_CONCURRENCY};
+use crate::upload_state::{mtime_secs, UploadStateStore};
+use aws_sdk_s3::model::{EncodingType, RequestPayer, StorageClass};
+use aws_sdk_s3::{ByteStream, Client};
+use std::collections::HashMap;
+use std::fs::{create_dir_all, remove_file, rename};
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, instrument, Span};
+
+// -- constants
+/// The "directory" separator of the keys, for the listings by level.
+const KEY_DELIMITER: &str = "/";
+
+/// A S3 bucket bound to a client, exposing the common object operations.
+pub struct S3Bucket {
+	client: Client,
+	name: String,
+	part_size: usize,
+	part_concurrency: usize,
+	multipart_threshold: u64,
+	split_size: Option<u64>,
+	concurrency: usize,
+	progress: Option<Arc<dyn TransferProgress>>,
+	hooks: Option<Arc<dyn TransferHooks>>,
+	audit_log: Option<Arc<AuditLog>>,
+	upload_states: Option<UploadStateStore>,
+	download_states: Option<DownloadStateStore>,
+	part_manifests: Option<PartManifestStore>,
+	checksums: bool,
+	preserve_mode: bool,
+	symlinks: SymlinkMode,
+	encryption: Option<Encryption>,
+	client_encryption: Option<ClientEncryption>,
+	compression: Option<Compression>,
+	decompression: bool,
+	storage_class: Option<StorageClass>,
+	request_payer: Option<RequestPayer>,
+	tags: HashMap<String, String>,
+	metadata: HashMap<String, String>,
+	content_headers: ContentHeaders,
+	content_type: Option<String>,
+	mime_map: MimeMap,
+	transfer_deadline: Option<Duration>,
+	shutdown: CancellationToken,
+}
+
+/// The attributes of an object being uploaded (single or multipart).
+pub(crate) struct PutAttrs {
+	pub content_type: String,
+	pub metadata: HashMap<String, String>,
+	/// The `Content-Encoding` (of the compression, if any).
+	pub content_encoding: Option<String>,
+	/// When the whole upload must be done (see [`S3Bucket::with_transfer_deadline`]).
+	pub deadline: Option<Instant>,
+}
+
+/// The temporary file of a download to `file_path`, renamed to `file_path` once complete and verified
+/// (so `file_path` is never a partial or corrupted file).
+pub(crate) fn part_path(file_path: &Path) -> PathBuf {
+	let mut name = file_path.file_name().unwrap_or_default().to_os_string();
+	name.push(".part");
+	file_path.with_file_name(name)
+}
+
+/// Run `fut` until `deadline` (if any), failing with [`S3DemoError::Timeout`] once passed.
+pub(crate) async fn until_deadline<T>(
+	deadline: Option<Instant>,
+	key: &str,
+	fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+	let Some(deadline) = deadline else {
+		return fut.await;
+	};
+	match tokio::time::timeout_at(deadline, fut).await {
+		Ok(res) => res,
+		Err(_) => Err(S3DemoError::Timeout(format!("transfer of {key} exceeded its deadline"))),
+	}
+}
+
+impl S3Bucket {
+	pub fn new(client: Client, name: impl Into<String>) -> Self {
+		S3Bucket {
+			client,
+			name: name.into(),
+			part_size: DEFAULT_PART_SIZE,
+			part_concurrency: DEFAULT_PART_CONCURRENCY,
+			multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+			split_size: None,
+			concurrency: DEFAULT_CONCURRENCY,
+			progress: None,
+			hooks: None,
+			audit_log: None,
+			upload_states: None,
+			download_states: None,
+			part_manifests: None,
+			checksums: true,
+			preserve_mode: false,
+			symlinks: SymlinkMode::Skip,
+			encryption: None,
+			client_encryption: None,
+			compression: None,
+			decompression: true,
+			storage_class: None,
+			request_payer: None,
+			tags: HashMap::new(),
+			metadata: HashMap::new(),
+			content_headers: ContentHeaders::default(),
+			content_type: None,
+			mime_map: MimeMap::default(),
+			transfer_deadline: None,
+			shutdown: CancellationToken::new(),
+		}
+	}
+
+	/// Set the multipart part size (at least the S3 minimum of 5 MiB), raised for the files too large
+	/// for 10,000 parts of this size (see [`S3Bucket::part_size_for`]).
+	pub fn with_part_size(mut self, part_size: usize) -> Self {
+		self.part_size = part_size.max(MIN_PART_SIZE);
+		self
+	}
+
+	/// Set the number of parallel parts (or ranges) of each multipart upload and ranged download.
+	pub fn with_part_concurrency(mut self, part_concurrency: usize) -> Self {
+		self.part_concurrency = part_concurrency.max(1);
+		self
+	}
+
+	/// Set the file size above which uploads go through a multipart upload.
+	pub fn with_multipart_threshold(mut self, threshold: u64) -> Self {
+		self.multipart_threshold = threshold;
+		self
+	}
+
+	/// Set the default number of parallel transfers for the multi-object operations.
+	pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+		self.concurrency = concurrency.max(1);
+		self
+	}
+
+	/// Set the listener notified of the upload/download progress.
+	pub fn with_progress(mut self, progress: Arc<dyn TransferProgress>) -> Self {
+		self.progress = Some(progress);
+		self
+	}
+
+	/// Set the callbacks run before and after each file upload/download (e.g., to scan the files to upload).
+	pub fn with_hooks(mut self, hooks: Arc<dyn TransferHooks>) -> Self {
+		self.hooks = Some(hooks);
+		self
+	}
+
+	/// Record the object writes, copies and deletes (once done) in the `audit_log`, which can be shared by buckets.
+	pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+		self.audit_log = Some(audit_log);
+		self
+	}
+
+	/// Compute and verify the transfer checksums (default true).
+	///
+	/// Uploads send the `Content-MD5` of the content (or of each part), verified by S3, and store
+	/// the content SHA-256 in the `x-amz-meta-sha256` metadata. Downloads are verified against
+	/// this SHA-256, or else the ETag when it is the content MD5.
+	pub fn with_checksums(mut self, checksums: bool) -> Self {
+		self.checksums = checksums;
+		self
+	}
+
+	/// Also store the Unix permission bits of the uploaded files (in the `x-amz-meta-mode` metadata),
+	/// and restore them on download (default false, only the mtime is).
+	pub fn with_preserve_mode(mut self, preserve_mode: bool) -> Self {
+		self.preserve_mode = preserve_mode;
+		self
+	}
+
+	/// With [`SymlinkMode::Preserve`], upload the symbolic links as links (see [`crate::META_SYMLINK_TARGET`]),
+	/// and recreate the links on download (the other modes upload the link targets, and download the links
+	/// as empty files).
+	pub fn with_symlinks(mut self, symlinks: SymlinkMode) -> Self {
+		self.symlinks = symlinks;
+		self
+	}
+
+	/// Set the server-side encryption of the uploaded and copied objects
+	/// (otherwise, the bucket default encryption applies).
+	pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+		self.encryption = Some(encryption);
+		self
+	}
+
+	/// Upload the files above `split_size` as chunked objects of this size, with a manifest as the file object
+	/// (e.g., for the providers with a smaller maximum object size, or to retry a large upload per chunk).
+	/// The downloads of a manifest reassemble the chunks.
+	pub fn with_split_size(mut self, split_size: u64) -> Self {
+		self.split_size = Some(split_size.max(1));
+		self
+	}
+
+	/// Set the storage class of the uploaded and copied objects (otherwise STANDARD).
+	pub fn with_storage_class(mut self, storage_class: StorageClass) -> Self {
+		self.storage_class = Some(storage_class);
+		self
+	}
+
+	/// Compress the uploaded files (sent with the matching `Content-Encoding`, over the one of
+	/// [`S3Bucket::with_content_headers`]). The downloads are decompressed (see [`S3Bucket::with_decompression`]).
+	/// Note: the compression is before the client-side encryption (if any), the ciphertext not being compressible.
+	pub fn with_compression(mut self, compression: Compression) -> Self {
+		self.compression = Some(compression);
+		self
+	}
+
+	/// Whether to decompress the downloaded objects of a gzip or zstd `Content-Encoding` (default: true),
+	/// otherwise they are saved as stored.
+	pub fn with_decompression(mut self, decompression: bool) -> Self {
+		self.decompression = decompression;
+		self
+	}
+
+	/// Accept the request charges (`RequestPayer`) of the reads (GET, HEAD, LIST) of a requester-pays bucket,
+	/// which otherwise fail with 403 (Access Denied).
+	pub fn with_request_payer(mut self, request_payer: RequestPayer) -> Self {
+		self.request_payer = Some(request_payer);
+		self
+	}
+
+	/// Set the tags of the uploaded objects (at most [`crate::MAX_TAGS`]), e.g., for cost allocation
+	/// or lifecycle rules.
+	pub fn with_tags(mut self, tags: HashMap<String, String>) -> Self {
+		self.tags = tags;
+		self
+	}
+
+	/// Set the user metadata (`x-amz-meta-*` headers) of the uploaded objects, e.g., provenance info.
+	/// Note: the metadata set by this library (e.g., [`crate::META_SHA256`]) takes precedence.
+	pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+		self.metadata = metadata;
+		self
+	}
+
+	/// Set the Cache-Control, Content-Disposition, Content-Encoding and Content-Language of the uploaded objects.
+	pub fn with_content_headers(mut self, content_headers: ContentHeaders) -> Self {
+		self.content_headers = content_headers;
+		self
+	}
+
+	/// Set the Content-Type of all the uploaded objects (instead of guessing it from the file extension).
+	pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+		self.content_type = Some(content_type.into());
+		self
+	}
+
+	/// Set the custom extension to MIME type mapping, taking precedence over the default guesses.
+	pub fn with_mime_map(mut self, mime_map: MimeMap) -> Self {
+		self.mime_map = mime_map;
+		self
+	}
+
+	/// Encrypt the uploaded files client-side, and decrypt the downloaded objects that were
+	/// (see [`ClientEncryption`]).
+	pub fn with_client_encryption(mut self, client_encryption: ClientEncryption) -> Self {
+		self.client_encryption = Some(client_encryption);
+		self
+	}
+
+	/// Fail each upload or download not done within `deadline` (all its requests, parts and retries together).
+	/// Interrupted multipart uploads are aborted (unless resumable), and partial downloads deleted
+	/// (except for [`S3Bucket::download_resume`]).
+	pub fn with_transfer_deadline(mut self, deadline: Duration) -> Self {
+		self.transfer_deadline = Some(deadline);
+		self
+	}
+
+	/// Stop the transfers when `shutdown` is cancelled (e.g., on Ctrl-C): the transfers not started yet fail with
+	/// [`S3DemoError::Interrupted`], and the multipart uploads stop after their parts in flight, then are aborted
+	/// (unless resumable, see [`S3Bucket::with_resumable_uploads`]). The single requests in flight complete.
+	pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+		self.shutdown = shutdown;
+		self
+	}
+
+	/// Persist the multipart upload states in `state_dir` (e.g., [`crate::default_upload_state_dir`]),
+	/// so interrupted uploads resume instead of starting over (and are not aborted on failure).
+	pub fn with_resumable_uploads(mut self, state_dir: impl Into<PathBuf>) -> Self {
+		self.upload_states = Some(UploadStateStore::new(state_dir));
+		self
+	}
+
+	/// Remember the ETag of the downloaded objects in `state_dir` (e.g., [`crate::default_download_state_dir`]),
+	/// so the next downloads to the same (unchanged) files are conditional: an object not modified since
+	/// returns quickly (304), leaving the file untouched.
+	pub fn with_download_etags(mut self, state_dir: impl Into<PathBuf>) -> Self {
+		self.download_states = Some(DownloadStateStore::new(state_dir));
+		self
+	}
+
+	/// Record the part MD5s of the multipart uploads in `state_dir` (e.g., [`crate::default_part_manifest_dir`]),
+	/// so uploading a modified file again only sends its changed parts, the others being copied from the object.
+	/// Note: these uploads are not resumable, and there is no delta with client-side encryption.
+	pub fn with_delta_uploads(mut self, state_dir: impl Into<PathBuf>) -> Self {
+		self.part_manifests = Some(PartManifestStore::new(state_dir));
+		self
+	}
+}
+
+impl S3Bucket {
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn client(&self) -> &Client {
+		&self.client
+	}
+
+	pub fn part_size(&self) -> usize {
+		self.part_size
+	}
+
+	/// The part size of a file of `size` bytes: the part size, or for the files over 10,000 parts of it,
+	/// the smallest whole MiB fitting the file in 10,000 parts.
+	pub fn part_size_for(&self, size: u64) -> usize {
+		let min_part_size = size.div_ceil(MAX_PARTS as u64).next_multiple_of(1024 * 1024);
+		self.part_size.max(min_part_size as usize)
+	}
+
+	pub fn part_concurrency(&self) -> usize {
+		self.part_concurrency
+	}
+
+	pub fn multipart_threshold(&self) -> u64 {
+		self.multipart_threshold
+	}
+
+	pub fn concurrency(&self) -> usize {
+		self.concurrency
+	}
+
+	pub fn checksums(&self) -> bool {
+		self.checksums
+	}
+
+	pub fn preserve_mode(&self) -> bool {
+		self.preserve_mode
+	}
+
+	pub fn symlinks(&self) -> SymlinkMode {
+		self.symlinks
+	}
+
+	pub fn encryption(&self) -> Option<&Encryption> {
+		self.encryption.as_ref()
+	}
+
+	pub fn storage_class(&self) -> Option<&StorageClass> {
+		self.storage_class.as_ref()
+	}
+
+	pub fn compression(&self) -> Option<Compression> {
+		self.compression
+	}
+
+	/// The metadata of the upload of the file at `path`: the `with_metadata` ones and its attributes
+	/// (see [`crate::META_MTIME`]), which take precedence (as the sync and the downloads read them).
+	pub(crate) fn file_metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		let mut metadata = self.metadata.clone();
+		metadata.extend(file_attrs_metadata(&path.metadata()?, self.preserve_mode));
+		Ok(metadata)
+	}
+
+	/// The compression to undo for a downloaded object of `content_encoding` (None if not decompressing).
+	pub(crate) fn decompression_for(&self, content_encoding: Option<&str>) -> Option<Compression> {
+		match self.decompression {
+			true => content_encoding.and_then(Compression::from_content_encoding),
+			false => None,
+		}
+	}
+
+	pub fn request_payer(&self) -> Option<&RequestPayer> {
+		self.request_payer.as_ref()
+	}
+
+	pub fn tags(&self) -> &HashMap<String, String> {
+		&self.tags
+	}
+
+	pub fn metadata(&self) -> &HashMap<String, String> {
+		&self.metadata
+	}
+
+	pub fn content_headers(&self) -> &ContentHeaders {
+		&self.content_headers
+	}
+
+	pub fn transfer_deadline(&self) -> Option<Duration> {
+		self.transfer_deadline
+	}
+
+	pub fn is_shutting_down(&self) -> bool {
+		self.shutdown.is_cancelled()
+	}
+
+	/// Fail with [`S3DemoError::Interrupted`] once shutting down (see [`S3Bucket::with_shutdown`]).
+	pub(crate) fn check_shutdown(&self, key: &str) -> Result<()> {
+		if self.is_shutting_down() {
+			return Err(S3DemoError::Interrupted(key.to_string()));
+		}
+		Ok(())
+	}
+
+	pub(crate) fn shutdown(&self) -> &CancellationToken {
+		&self.shutdown
+	}
+
+	pub(crate) fn hooks(&self) -> Option<&Arc<dyn TransferHooks>> {
+		self.hooks.as_ref()
+	}
+
+	pub(crate) fn audit_log(&self) -> Option<&Arc<AuditLog>> {
+		self.audit_log.as_ref()
+	}
+
+	/// The deadline of a transfer starting now.
+	pub(crate) fn deadline(&self) -> Option<Instant> {
+		self.transfer_deadline.map(|d| Instant::now() + d)
+	}
+
+	/// The Content-Type of an upload from `path`: the override, else the custom mapping, else the
+	/// `mime_guess` default (`application/octet-stream` for unknown extensions).
+	pub fn content_type_for(&self, path: &Path) -> String {
+		if let Some(content_type) = &self.content_type {
+			return content_type.clone();
+		}
+		match self.mime_map.get(path) {
+			Some(content_type) => content_type.to_string(),
+			None => mime_guess::from_path(path).first_or_octet_stream().to_string(),
+		}
+	}
+
+	pub fn client_encryption(&self) -> Option<&ClientEncryption> {
+		self.client_encryption.as_ref()
+	}
+
+	/// The SSE-C key, required on all the object data requests.
+	pub(crate) fn customer_key(&self) -> Option<&CustomerKey> {
+		self.encryption.as_ref().and_then(|e| e.customer_key())
+	}
+
+	pub(crate) fn upload_states(&self) -> Option<&UploadStateStore> {
+		self.upload_states.as_ref()
+	}
+}
+
+impl S3Bucket {
+	pub(crate) fn progress_start(&self, key: &str, total: u64) {
+		if let Some(progress) = &self.progress {
+			progress.start(key, total);
+		}
+	}
+
+	pub(crate) fn progress_advance(&self, key: &str, bytes: u64) {
+		if let Some(progress) = &self.progress {
+			progress.advance(key, bytes);
+		}
+	}
+
+	pub(crate) fn progress_finish<T>(&self, key: &str, res: &Result<T>) {
+		if let Some(progress) = &self.progress {
+			progress.finish(key, res.as_ref().err());
+		}
+	}
+}
+
+impl S3Bucket {
+	/// Download the object `key` into `dir` (the key path is recreated under `dir`).
+	/// Returns the path of the downloaded file.
+	pub async fn download(&self, key: &str, dir: &Path) -> Result<PathBuf> {
+		// VALIDATE
+		if !dir.is_dir() {
+			return Err(S3DemoError::InvalidInput(format!(
+				"Path {} is not a directory",
+				dir.display()
+			)));
+		}
+
+		let file_path = key_path(dir, key)?;
+		self.download_to(key, &file_path).await?;
+
+		Ok(file_path)
+	}
+
+	/// Download the object `key` to the file `file_path` (parent dirs are created if needed).
+	///
+	/// The object is downloaded to `file_path` + `.part`, renamed to `file_path` once complete and verified,
+	/// so a failed download never leaves a partial `file_path` (nor replaces an existing one).
+	#[instrument(name = "download", skip_all, fields(bucket = %self.name, key = %key, bytes))]
+	pub async fn download_to(&self, key: &str, file_path: &Path) -> Result<()> {
+		let transfer = Transfer::Download {
+			key: key.to_string(),
+			path: file_path.to_path_buf(),
+		};
+		let res = self.hooked(transfer, self.exec_download_to(key, None, file_path)).await;
+		self.progress_finish(key, &res);
+		res
+	}
+
+	/// Download the `version_id` version of the object `key` to the file `file_path`.
+	#[instrument(name = "download", skip_all, fields(bucket = %self.name, key = %key, version_id = %version_id, bytes))]
+	pub async fn download_version_to(&self, key: &str, version_id: &str, file_path: &Path) -> Result<()> {
+		let transfer = Transfer::Download {
+			key: key.to_string(),
+			path: file_path.to_path_buf(),
+		};
+		let res = self
+			.hooked(transfer, self.exec_download_to(key, Some(version_id), file_path))
+			.await;
+		self.progress_finish(key, &res);
+		res
+	}
+
+	async fn exec_download_to(&self, key: &str, version_id: Option<&str>, file_path: &Path) -> Result<()> {
+		let deadline = self.deadline();
+
+		// create parent dir(s)
+		let parent_dir = file_path
+			.parent()
+			.ok_or_else(|| S3DemoError::InvalidInput(format!("Invalid parent dir for {:?}", file_path)))?;
+		if !parent_dir.exists() {
+			create_dir_all(parent_dir)?;
+		}
+
+		// DOWNLOAD - in parallel ranges (a single request for small objects), to a temporary file
+		// Note: with client-side encryption, the ciphertext is downloaded next to the file.
+		let download_path = match &self.client_encryption {
+			Some(_) => cse_download_path(file_path),
+			None => part_path(file_path),
+		};
+		// Note: conditional on the ETag of the previous download, if the file is unchanged since.
+		let previous = match (version_id, &self.download_states) {
+			(None, Some(store)) => store
+				.load(&self.name, key, file_path)
+				.filter(|state| state.is_file_unchanged(file_path)),
+			_ => None,
+		};
+		let res = until_deadline(
+			deadline,
+			key,
+			self.download_ranged(key, version_id, &download_path, previous.as_ref()),
+		)
+		.await;
+		let download = match res {
+			Ok(Some(download)) => download,
+			Ok(None) => {
+				info!("not modified");
+				return Ok(());
+			}
+			Err(ex) => {
+				let _ = remove_file(&download_path);
+				return Err(ex);
+			}
+		};
+
+		// LINK - a preserved symbolic link, instead of its empty object
+		let symlink_target = download.metadata.get(META_SYMLINK_TARGET);
+		if let Some(target) = symlink_target.filter(|_| self.symlinks == SymlinkMode::Preserve) {
+			let _ = remove_file(&download_path);
+			return restore_symlink(file_path, target);
+		}
+
+		// VERIFY - (delete the corrupted file on mismatch)
+		if self.checksums {
+			if let Err(ex) = verify_file(key, &download_path, &download.expected) {
+				let _ = remove_file(&download_path);
+				return Err(ex);
+			}
+		}
+
+		// REASSEMBLE - the chunks of a split file (this object being its manifest, not encrypted nor compressed)
+		if download.metadata.contains_key(META_SPLIT_CHUNKS) {
+			let res = self.download_split(key, &download_path, &part_path(file_path)).await;
+			if res.is_err() {
+				let _ = remove_file(part_path(file_path));
+			}
+			if self.client_encryption.is_some() {
+				let _ = remove_file(&download_path);
+			}
+			res?;
+		} else {
+			// DECRYPT - client side (if the object was encrypted)
+			if let Some(cse) = &self.client_encryption {
+				cse.decrypt_download(key, &download.metadata, &download_path, &part_path(file_path))?;
+			}
+
+			// DECOMPRESS - (if the object was compressed)
+			if let Some(compression) = self.decompression_for(download.content_encoding.as_deref()) {
+				compression.decompress_file(key, &part_path(file_path))?;
+			}
+		}
+
+		// COMPLETE - atomically replace the file (with the mtime of the uploaded one)
+		rename(part_path(file_path), file_path)?;
+		restore_file_attrs(file_path, &download.metadata, self.preserve_mode)?;
+		if let Ok(file_meta) = file_path.metadata() {
+			Span::current().record("bytes", file_meta.len());
+		}
+
+		// SAVE - the ETag, for the next download (of the latest version)
+		if let (None, Some(store), Some(e_tag)) = (version_id, &self.download_states, download.e_tag) {
+			store.save(&DownloadState {
+				bucket: self.name.clone(),
+				key: key.to_string(),
+				path: file_path.to_path_buf(),
+				e_tag,
+				last_modified: download.last_modified,
+				file_size: file_path.metadata()?.len(),
+				file_mtime: mtime_secs(file_path),
+			})?;
+		}
+
+		Ok(())
+	}
+
+	/// Upload the file at `path`, using the path (with `/` separators) as the object key.
+	pub async fn upload(&self, path: &Path) -> Result<()> {
+		let key = path_key(path).ok_or_else(|| S3DemoError::InvalidInput(format!("Invalid path {path:?}")))?;
+		self.upload_to(path, &key).await
+	}
+
+	/// Upload the file at `path` to the object `key`.
+	/// Files above the multipart threshold are sent with a multipart upload.
+	#[instrument(name = "upload", skip_all, fields(bucket = %self.name, key = %key, bytes))]
+	pub async fn upload_to(&self, path: &Path, key: &str) -> Result<()> {
+		let transfer = Transfer::Upload {
+			path: path.to_path_buf(),
+			key: key.to_string(),
+		};
+		self.hooked(transfer, self.exec_upload(path, key)).await
+	}
+
+	async fn exec_upload(&self, path: &Path, key: &str) -> Result<()> {
+		// VALIDATE
+		validate_key(key)?;
+		// Note: a preserved link is uploaded as is, even if broken.
+		if self.symlinks == SymlinkMode::Preserve && path.is_symlink() {
+			self.progress_start(key, 0);
+			let res = self.upload_symlink(path, key).await;
+			self.progress_finish(key, &res);
+			return res;
+		}
+		if !path.exists() {
+			return Err(S3DemoError::Io(io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("Path {} does not exist", path.display()),
+			)));
+		}
+
+		let size = path.metadata()?.len();
+		Span::current().record("bytes", size);
+		self.progress_start(key, size);
+		let res = match self.split_size {
+			Some(split_size) if size > split_size => self.upload_split(path, key, split_size).await,
+			_ => self.exec_upload_to(path, key).await,
+		};
+		self.progress_finish(key, &res);
+		res
+	}
+
+	async fn exec_upload_to(&self, path: &Path, key: &str) -> Result<()> {
+		// PREPARE
+		let mut attrs = PutAttrs {
+			content_type: self.content_type_for(path),
+			metadata: self.file_metadata(path)?,
+			content_encoding: self.content_headers.content_encoding.clone(),
+			deadline: self.deadline(),
+		};
+
+		// COMPRESS - (the compressed temp file is uploaded instead)
+		let Some(compression) = self.compression else {
+			return self.exec_upload_encrypted(path, key, attrs).await;
+		};
+		// Note: the temp file is removed when dropped, also when the upload fails or is cancelled.
+		let compress_path = compress_upload_file(compression)?;
+		compression.compress_file(path, &compress_path)?;
+		attrs.content_encoding = Some(compression.content_encoding().to_string());
+		self.exec_upload_encrypted(&compress_path, key, attrs).await
+	}
+
+	async fn exec_upload_encrypted(&self, path: &Path, key: &str, mut attrs: PutAttrs) -> Result<()> {
+		// ENCRYPT - client side (the ciphertext temp file is uploaded instead)
+		let Some(cse) = &self.client_encryption else {
+			return self.upload_file(path, key, attrs).await;
+		};
+		// Note: the temp file is removed when dropped, also when the upload fails or is cancelled.
+		let cse_path = cse_upload_file()?;
+		attrs.metadata.extend(cse.encrypt_file(path, &cse_path)?);
+		self.upload_file(&cse_path, key, attrs).await
+	}
+
+	async fn upload_file(&self, path: &Path, key: &str, mut attrs: PutAttrs) -> Result<()> {
+		let size = path.metadata()?.len();
+		if size > self.multipart_threshold {
+			debug!(size, threshold = self.multipart_threshold, "multipart upload");
+			if self.checksums {
+				attrs.metadata.insert(META_SHA256.to_string(), file_sha256(path)?);
+			}
+			if let (Some(store), None) = (&self.part_manifests, &self.client_encryption) {
+				return self.upload_delta(path, key, &attrs, store).await;
+			}
+			return self.upload_multipart(path, key, &attrs).await;
+		}
+		let content_md5 = if self.checksums {
+			let (md5, sha256) = file_checksums(path)?;
+			attrs.metadata.insert(META_SHA256.to_string(), sha256);
+			Some(md5)
+		} else {
+			None
+		};
+		let body = ByteStream::from_path(path).await?;
+
+		// EXECUTE
+		let deadline = attrs.deadline;
+		until_deadline(deadline, key, self.put_object_body(key, body, content_md5, attrs)).await?;
+		// Note: single part uploads are below the multipart threshold, so reported at once.
+		self.progress_advance(key, size);
+
+		Ok(())
+	}
+
+	/// Put a single part object.
+	pub(crate) async fn put_object_body(
+		&self,
+		key: &str,
+		body: ByteStream,
+		content_md5: Option<String>,
+		attrs: PutAttrs,
+	) -> Result<()> {
+		// BUILD - aws request
+		let body = body.into_inner();
+		let size = body.content_length();
+		let sha256 = attrs.metadata.get(META_SHA256).cloned();
+		let ck = self.customer_key();
+		let req = self
+			.client
+			.put_object()
+			.bucket(&self.name)
+			.key(key)
+			.body(ByteStream::new(body))
+			.content_type(attrs.content_type)
+			.set_content_md5(content_md5)
+			.set_metadata(Some(attrs.metadata))
+			.set_server_side_encryption(self.encryption.as_ref().and_then(|e| e.server_side_encryption()))
+			.set_ssekms_key_id(self.encryption.as_ref().and_then(|e| e.kms_key_id()))
+			.set_storage_class(self.storage_class.clone())
+			.set_tagging(tagging_header(&self.tags))
+			.set_cache_control(self.content_headers.cache_control.clone())
+			.set_content_disposition(self.content_headers.content_disposition.clone())
+			.set_content_encoding(attrs.content_encoding)
+			.set_content_language(self.content_headers.content_language.clone())
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()));
+
+		// EXECUTE
+		let res = req.send().await.map_err(|err| write_error(key, err.into()))?;
+
+		// AUDIT
+		let mut entry = AuditEntry::new(AuditOperation::Put, &self.name, key);
+		entry.version_id = res.version_id().map(|v| v.to_string());
+		entry.size = size;
+		entry.etag = res.e_tag().map(|v| v.to_string());
+		entry.sha256 = sha256;
+		self.audit(entry)?;
+
+		Ok(())
+	}
+
+	/// List all the object keys starting with `prefix` (empty for all).
+	pub async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+		let objects = self.list_objects(prefix).await?;
+		Ok(objects.into_iter().map(|o| o.key).collect())
+	}
+
+	/// List all the objects (with their metadata) starting with `prefix` (empty for all).
+	/// Follows the continuation tokens, so buckets with more than 1000 objects are fully listed.
+	#[instrument(name = "list", skip_all, fields(bucket = %self.name, prefix = %prefix, count))]
+	pub async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+		let mut objects: Vec<ObjectInfo> = Vec::new();
+		let mut continuation_token: Option<String> = None;
+
+		loop {
+			// BUILD - aws request
+			let req = self
+				.client
+				.list_objects_v2()
+				.prefix(prefix)
+				.encoding_type(EncodingType::Url)
+				.bucket(&self.name)
+				.set_request_payer(self.request_payer().cloned())
+				.set_continuation_token(continuation_token.take());
+
+			// EXECUTE
+			let res = req.send().await?;
+
+			// COLLECT
+			for obj in res.contents().unwrap_or_default().iter().filter(|o| o.key.is_some()) {
+				let mut info = ObjectInfo::from(obj);
+				info.key = listing_key(&info.key, res.encoding_type())?;
+				objects.push(info);
+			}
+
+			// NEXT - page (if any)
+			match res.next_continuation_token() {
+				Some(token) if res.is_truncated() => continuation_token = Some(token.to_string()),
+				_ => break,
+			}
+		}
+		Span::current().record("count", objects.len());
+
+		Ok(objects)
+	}
+
+	/// List the `prefix` "directory" level: the immediate objects, and the common prefixes
+	/// (up to the next `/`) as "sub-directories", e.g., `videos/` lists `videos/2022/` and `videos/a.mp4`.
+	#[instrument(name = "list_dir", skip_all, fields(bucket = %self.name, prefix = %prefix, count))]
+	pub async fn list_dir(&self, prefix: &str) -> Result<DirListing> {
+		let mut listing = DirListing::default();
+		let mut continuation_token: Option<String> = None;
+
+		loop {
+			// BUILD - aws request
+			let req = self
+				.client
+				.list_objects_v2()
+				.prefix(prefix)
+				.delimiter(KEY_DELIMITER)
+				.encoding_type(EncodingType::Url)
+				.bucket(&self.name)
+				.set_request_payer(self.request_payer().cloned())
+				.set_continuation_token(continuation_token.take());
+
+			// EXECUTE
+			let res = req.send().await?;
+
+			// COLLECT
+			for page_prefix in res
+				.common_prefixes()
+				.unwrap_or_default()
+				.iter()
+				.filter_map(|p| p.prefix())
+			{
+				listing.prefixes.push(listing_key(page_prefix, res.encoding_type())?);
+			}
+			for obj in res.contents().unwrap_or_default().iter().filter(|o| o.key.is_some()) {
+				let mut info = ObjectInfo::from(obj);
+				info.key = listing_key(&info.key, res.encoding_type())?;
+				listing.objects.push(info);
+			}
+
+			// NEXT - page (if any)
+			match res.next_continuation_token() {
+				Some(token) if res.is_truncated() => continuation_token = Some(token.to_string()),
+				_ => break,
+			}
+		}
+		Span::current().record("count", listing.prefixes.len() + listing.objects.len());
+
+		Ok(listing)
+	}
+}
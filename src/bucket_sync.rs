@@ -0,0 +1,142 @@
+use crate::dir::{dir_prefix, join_key};
+use crate::error::Result;
+use crate::overwrite::is_newer;
+use crate::sync::{compare, Change};
+use crate::{ObjectInfo, S3Bucket, SyncOptions};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use tracing::{info, instrument};
+
+/// An object copy of a bucket to bucket sync.
+#[derive(Debug, Clone)]
+pub struct ObjectCopy {
+	pub src_key: String,
+	pub dst_key: String,
+	pub size: u64,
+}
+
+/// The copies needed to bring the destination prefix in sync with the source one.
+#[derive(Debug, Default)]
+pub struct BucketSyncPlan {
+	pub copies: Vec<ObjectCopy>,
+	/// Number of objects already identical.
+	pub unchanged: usize,
+	/// Number of different objects kept as is, per [`SyncOptions::overwrite`].
+	pub skipped: usize,
+	/// Destination keys missing from the source, to delete (with [`SyncOptions::delete`]).
+	pub delete_keys: Vec<String>,
+}
+
+impl S3Bucket {
+	/// The copies from this bucket `prefix` to the `dst` bucket `dst_prefix` (by the keys relative to the prefixes),
+	/// with the `filter`, `delete` and `overwrite` of `opts` (the local file options do not apply).
+	#[instrument(name = "sync_plan", skip_all, fields(bucket = %self.name(), prefix = %prefix, dst_bucket = %dst.name(), dst_prefix = %dst_prefix))]
+	pub async fn sync_bucket_plan(
+		&self,
+		prefix: &str,
+		dst: &S3Bucket,
+		dst_prefix: &str,
+		opts: &SyncOptions,
+	) -> Result<BucketSyncPlan> {
+		// COLLECT - the source and destination objects by relative key
+		let prefix = dir_prefix(prefix);
+		let dst_prefix = dir_prefix(dst_prefix);
+		let relative = |objects: Vec<ObjectInfo>, prefix: &str| -> HashMap<String, ObjectInfo> {
+			objects
+				.into_iter()
+				.filter(|o| !o.key.ends_with('/'))
+				.filter_map(|o| Some((o.key.strip_prefix(prefix)?.to_string(), o)))
+				.filter(|(rel_key, _)| opts.filter.is_match(rel_key))
+				.collect()
+		};
+		let sources = relative(self.list_objects(&prefix).await?, &prefix);
+		let dsts = relative(dst.list_objects(&dst_prefix).await?, &dst_prefix);
+
+		// COMPARE
+		let mut plan = BucketSyncPlan::default();
+		if opts.delete {
+			plan.delete_keys = dsts
+				.iter()
+				.filter(|(rel_key, _)| !sources.contains_key(*rel_key))
+				.map(|(_, o)| o.key.clone())
+				.collect();
+			plan.delete_keys.sort();
+		}
+		let mut sources = sources.into_iter().collect::<Vec<_>>();
+		sources.sort_by(|a, b| a.0.cmp(&b.0));
+		for (rel_key, src) in sources {
+			let change = match dsts.get(&rel_key) {
+				Some(dst) => compare(opts.overwrite, src.last_modified, dst.last_modified, || {
+					Ok(is_different(&src, dst))
+				})?,
+				None => Change::Transfer,
+			};
+			match change {
+				Change::Transfer => plan.copies.push(ObjectCopy {
+					dst_key: join_key(&dst_prefix, &rel_key),
+					src_key: src.key,
+					size: src.size,
+				}),
+				Change::Unchanged => plan.unchanged += 1,
+				Change::Skipped => plan.skipped += 1,
+			}
+		}
+
+		info!(
+			copies = plan.copies.len(),
+			unchanged = plan.unchanged,
+			skipped = plan.skipped,
+			deletes = plan.delete_keys.len(),
+			"sync plan"
+		);
+
+		Ok(plan)
+	}
+
+	/// Run the copies of `plan` to the `dst` bucket (concurrently), then delete its extraneous objects.
+	/// With `server_side`, S3 copies the objects (CopyObject, sent with the `dst` client, as the source can be
+	/// in another region), otherwise (other account or endpoint) they are downloaded then uploaded by this host.
+	#[instrument(name = "sync", skip_all, fields(bucket = %self.name(), dst_bucket = %dst.name(), copies = plan.copies.len(), deletes = plan.delete_keys.len()))]
+	pub async fn sync_bucket_apply(
+		&self,
+		dst: &S3Bucket,
+		plan: BucketSyncPlan,
+		server_side: bool,
+	) -> Result<BucketSyncPlan> {
+		// COPY - the objects
+		stream::iter(plan.copies.iter())
+			.map(|copy| async move {
+				if server_side {
+					dst.copy_from(self, &copy.src_key, &copy.dst_key).await
+				} else {
+					self.copy_to_bucket(&copy.src_key, dst, &copy.dst_key).await
+				}
+			})
+			.buffer_unordered(self.concurrency())
+			.try_collect::<Vec<()>>()
+			.await?;
+
+		// DELETE - the extraneous objects
+		dst.delete_objects(&plan.delete_keys).await?;
+
+		Ok(plan)
+	}
+}
+
+/// Compare an object with its copy: the size, then the ETag, otherwise (multipart ETags,
+/// which depend on the part size) the source must be newer.
+fn is_different(src: &ObjectInfo, dst: &ObjectInfo) -> bool {
+	if src.size != dst.size {
+		return true;
+	}
+	if src.e_tag.is_some() && src.e_tag == dst.e_tag {
+		return false;
+	}
+	if let (Some(src_md5), Some(dst_md5)) = (src.content_md5(), dst.content_md5()) {
+		return src_md5 != dst_md5;
+	}
+	match (src.last_modified, dst.last_modified) {
+		(Some(src), Some(dst)) => is_newer(src, dst),
+		_ => true,
+	}
+}
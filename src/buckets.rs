@@ -0,0 +1,143 @@
+use crate::error::Result;
+use crate::filter::KeyFilter;
+use crate::object::ser_time;
+use crate::S3Bucket;
+use aws_sdk_s3::model::{BucketLocationConstraint, CreateBucketConfiguration};
+use aws_sdk_s3::{Client, SdkError};
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::time::SystemTime;
+
+// -- constants
+/// The default region, where buckets are created without location constraint.
+const US_EAST_1: &str = "us-east-1";
+const STATUS_NOT_FOUND: u16 = 404;
+/// Header of the bucket region, in the HeadBucket responses (even the 301 redirects and 403).
+const HEADER_BUCKET_REGION: &str = "x-amz-bucket-region";
+/// Legacy location constraint of eu-west-1.
+const LOCATION_EU: &str = "EU";
+/// Number of parallel GetBucketLocation requests.
+const LOCATION_CONCURRENCY: usize = 8;
+
+/// A bucket of the account, from ListBuckets.
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketInfo {
+	pub name: String,
+	#[serde(serialize_with = "ser_time")]
+	pub creation_date: Option<SystemTime>,
+	/// From GetBucketLocation (None when not requested, or not allowed).
+	pub region: Option<String>,
+}
+
+/// List the buckets of the account, with their region when `regions` (one more request per bucket).
+pub async fn list_buckets(client: &Client, regions: bool) -> Result<Vec<BucketInfo>> {
+	// BUILD & EXECUTE - aws request
+	let res = client.list_buckets().send().await?;
+
+	// COLLECT
+	let mut buckets = res
+		.buckets()
+		.unwrap_or_default()
+		.iter()
+		.filter_map(|b| {
+			Some(BucketInfo {
+				name: b.name()?.to_string(),
+				creation_date: b.creation_date().and_then(|d| SystemTime::try_from(*d).ok()),
+				region: None,
+			})
+		})
+		.collect::<Vec<_>>();
+
+	// FETCH - the regions, in parallel
+	if regions {
+		let locations = stream::iter(buckets.iter().map(|b| bucket_region(client, &b.name)))
+			.buffered(LOCATION_CONCURRENCY)
+			.collect::<Vec<_>>()
+			.await;
+		for (bucket, region) in buckets.iter_mut().zip(locations) {
+			// Note: best effort, e.g., GetBucketLocation may not be allowed for some buckets.
+			bucket.region = region.ok();
+		}
+	}
+
+	Ok(buckets)
+}
+
+/// The region of the bucket `name`, from its location constraint.
+pub async fn bucket_region(client: &Client, name: &str) -> Result<String> {
+	// BUILD & EXECUTE - aws request
+	let res = client.get_bucket_location().bucket(name).send().await?;
+
+	// Note: no (empty) constraint is us-east-1, and EU the legacy name of eu-west-1.
+	let region = match res.location_constraint().map(|c| c.as_str()) {
+		None | Some("") => US_EAST_1,
+		Some(LOCATION_EU) => "eu-west-1",
+		Some(region) => region,
+	};
+
+	Ok(region.to_string())
+}
+
+/// The region of the bucket `name`: `client_region` when a HeadBucket succeeds, else its `x-amz-bucket-region`
+/// header (also on the 301 redirects and 403), else from GetBucketLocation.
+pub async fn resolve_bucket_region(client: &Client, name: &str, client_region: &str) -> Result<String> {
+	// BUILD & EXECUTE - aws request
+	match client.head_bucket().bucket(name).send().await {
+		Ok(_) => return Ok(client_region.to_string()),
+		Err(SdkError::ServiceError { raw, .. }) => {
+			let region = raw.http().headers().get(HEADER_BUCKET_REGION);
+			if let Some(region) = region.and_then(|r| r.to_str().ok()) {
+				return Ok(region.to_string());
+			}
+		}
+		Err(_) => (),
+	}
+
+	bucket_region(client, name).await
+}
+
+impl S3Bucket {
+	/// Create the bucket in `region` (which should be the client region).
+	pub async fn create_bucket(&self, region: &str) -> Result<()> {
+		// BUILD - aws request
+		// Note: us-east-1 is the only region without (and rejecting) a location constraint.
+		let config = (region != US_EAST_1).then(|| {
+			CreateBucketConfiguration::builder()
+				.location_constraint(BucketLocationConstraint::from(region))
+				.build()
+		});
+		let req = self
+			.client()
+			.create_bucket()
+			.bucket(self.name())
+			.set_create_bucket_configuration(config);
+
+		// EXECUTE
+		req.send().await?;
+
+		Ok(())
+	}
+
+	/// Delete the bucket, which must be empty (see [`S3Bucket::empty_bucket`]).
+	pub async fn delete_bucket(&self) -> Result<()> {
+		// BUILD & EXECUTE - aws request
+		self.client().delete_bucket().bucket(self.name()).send().await?;
+
+		Ok(())
+	}
+
+	/// Delete all the objects of the bucket. Returns the deleted keys.
+	pub async fn empty_bucket(&self) -> Result<Vec<String>> {
+		self.delete_prefix("", &KeyFilter::default()).await
+	}
+
+	/// Whether the bucket exists. Fails if it exists but is not accessible (e.g., owned by another account).
+	pub async fn bucket_exists(&self) -> Result<bool> {
+		// BUILD & EXECUTE - aws request
+		match self.client().head_bucket().bucket(self.name()).send().await {
+			Ok(_) => Ok(true),
+			Err(SdkError::ServiceError { raw, .. }) if raw.http().status() == STATUS_NOT_FOUND => Ok(false),
+			Err(ex) => Err(ex.into()),
+		}
+	}
+}
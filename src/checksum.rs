@@ -0,0 +1,154 @@
+use crate::error::{Result, S3DemoError};
+use crate::object::etag_md5;
+use aws_sdk_s3::model::ServerSideEncryption;
+use aws_sdk_s3::output::GetObjectOutput;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use md5::{Digest, Md5};
+use sha2::Sha256;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+// -- constants
+/// User metadata key (`x-amz-meta-sha256`) of the hex SHA-256 of the uploaded content.
+pub const META_SHA256: &str = "sha256";
+
+/// The hex MD5 of the file content (what S3 uses as ETag for single part uploads).
+pub fn file_md5(path: &Path) -> Result<String> {
+	let mut file = File::open(path)?;
+	let mut hasher = Md5::new();
+	io::copy(&mut file, &mut hasher)?;
+	Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The hex SHA-256 of the file content.
+pub fn file_sha256(path: &Path) -> Result<String> {
+	let mut file = File::open(path)?;
+	let mut hasher = Sha256::new();
+	io::copy(&mut file, &mut hasher)?;
+	Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The (base64 MD5, hex SHA-256) of the file content, in one read.
+/// The base64 MD5 is the `Content-MD5` header value S3 verifies on upload.
+pub fn file_checksums(path: &Path) -> Result<(String, String)> {
+	let mut file = File::open(path)?;
+	let mut hashers = Hashers::default();
+	io::copy(&mut file, &mut hashers)?;
+	let md5 = BASE64.encode(hashers.md5.finalize());
+	let sha256 = format!("{:x}", hashers.sha256.finalize());
+	Ok((md5, sha256))
+}
+
+/// The hex MD5 of `data`.
+pub fn md5_hex(data: &[u8]) -> String {
+	format!("{:x}", Md5::digest(data))
+}
+
+/// The hex SHA-256 of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+	format!("{:x}", Sha256::digest(data))
+}
+
+/// The base64 MD5 of `data` (`Content-MD5` header value).
+pub fn md5_base64(data: &[u8]) -> String {
+	BASE64.encode(Md5::digest(data))
+}
+
+/// The checksums a downloaded object is verified against.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ExpectedChecksums {
+	/// From the `x-amz-meta-sha256` metadata (set by our uploads).
+	pub sha256: Option<String>,
+	/// From the ETag, when it is the content MD5.
+	pub content_md5: Option<String>,
+}
+
+impl ExpectedChecksums {
+	/// The checksums of a GetObject response.
+	pub fn from_get_output(res: &GetObjectOutput) -> Self {
+		// Note: the ETag of SSE-KMS and SSE-C objects is not the content MD5.
+		let encrypted = res.sse_customer_algorithm().is_some()
+			|| matches!(res.server_side_encryption(), Some(ServerSideEncryption::AwsKms));
+		ExpectedChecksums {
+			sha256: res.metadata().and_then(|m| m.get(META_SHA256)).cloned(),
+			content_md5: etag_md5(res.e_tag()).filter(|_| !encrypted).map(|s| s.to_string()),
+		}
+	}
+}
+
+/// Verify the downloaded file against the expected SHA-256, or else the content MD5.
+/// Nothing to verify is not an error.
+pub(crate) fn verify_file(key: &str, path: &Path, expected: &ExpectedChecksums) -> Result<()> {
+	if expected.sha256.is_some() {
+		verify(key, expected, None, Some(&file_sha256(path)?))
+	} else if expected.content_md5.is_some() {
+		verify(key, expected, Some(&file_md5(path)?), None)
+	} else {
+		Ok(())
+	}
+}
+
+/// Checksums computed while the content streams through (when it cannot be re-read from a file).
+#[derive(Default)]
+pub(crate) struct StreamChecksums {
+	hashers: Hashers,
+}
+
+impl StreamChecksums {
+	pub fn update(&mut self, data: &[u8]) {
+		self.hashers.md5.update(data);
+		self.hashers.sha256.update(data);
+	}
+
+	/// Verify the streamed content against the expected SHA-256, or else the content MD5.
+	pub fn verify(self, key: &str, expected: &ExpectedChecksums) -> Result<()> {
+		let md5 = format!("{:x}", self.hashers.md5.finalize());
+		let sha256 = format!("{:x}", self.hashers.sha256.finalize());
+		verify(key, expected, Some(&md5), Some(&sha256))
+	}
+}
+
+fn verify(key: &str, expected: &ExpectedChecksums, md5: Option<&str>, sha256: Option<&str>) -> Result<()> {
+	if let (Some(expected), Some(actual)) = (&expected.sha256, sha256) {
+		if actual != expected {
+			return Err(S3DemoError::ChecksumMismatch {
+				key: key.to_string(),
+				algorithm: "SHA-256",
+				expected: expected.clone(),
+				actual: actual.to_string(),
+			});
+		}
+	} else if let (Some(expected), Some(actual)) = (&expected.content_md5, md5) {
+		if actual != expected {
+			return Err(S3DemoError::ChecksumMismatch {
+				key: key.to_string(),
+				algorithm: "MD5",
+				expected: format!("ETag {expected}"),
+				actual: actual.to_string(),
+			});
+		}
+	}
+
+	Ok(())
+}
+
+/// Feed the md5 and sha256 hashers at once.
+#[derive(Default)]
+struct Hashers {
+	md5: Md5,
+	sha256: Sha256,
+}
+
+impl Write for Hashers {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.md5.update(buf);
+		self.sha256.update(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
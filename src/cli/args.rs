@@ -0,0 +1,1345 @@
+use super::{usage_error, CompletionShell, ListFormat, LogFormat, OutputFormat, ProgressFormat, ProgressStream};
+use anyhow::Result;
+use aws_sdk_s3::model::{RequestPayer, StorageClass, Tier};
+use clap::{ArgAction, ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use rust_aws_sdk_s3::DEFAULT_MAX_ATTEMPTS;
+use rust_aws_sdk_s3::{
+	AssumeRole, ByteRange, Compression, ContentHeaders, CustomerKey, Encryption, FindQuery, KeyFilter, NotifyTarget,
+	Overwrite, RetryPolicy, SelectFormat, SymlinkMode,
+};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(name = "s3demo", version, about = "Small S3 tool built on the official Rust AWS SDK")]
+pub struct Cli {
+	/// Config file of the defaults and remotes (default: ~/.config/s3demo/config.toml, if any)
+	#[arg(long, global = true, env = "S3DEMO_CONFIG", value_name = "PATH")]
+	pub config: Option<PathBuf>,
+
+	/// Target bucket (default: the config one, else rust-aws-sdk-s3-demo)
+	#[arg(long, global = true, env = "S3_BUCKET")]
+	pub bucket: Option<String>,
+
+	/// AWS region of the bucket (default: the config one, else the region of the bucket, detected)
+	#[arg(long, global = true, env = "S3_REGION")]
+	pub region: Option<String>,
+
+	/// Custom S3 endpoint URL (e.g., http://localhost:9000 for MinIO)
+	#[arg(long, global = true, env = "S3_ENDPOINT_URL")]
+	pub endpoint_url: Option<String>,
+
+	/// AWS profile, to use the AWS default credential chain instead of S3_KEY_ID / S3_KEY_SECRET
+	#[arg(long, global = true)]
+	pub profile: Option<String>,
+
+	/// Number of parallel transfers for multi-file operations
+	#[arg(long, short = 'j', global = true)]
+	pub concurrency: Option<usize>,
+
+	/// Multipart part size (default 8MiB, at least 5MiB), raised for the files over 10,000 parts of it
+	#[arg(long, global = true, value_name = "SIZE", value_parser = parse_size)]
+	pub part_size: Option<u64>,
+
+	/// Number of parallel parts of each multipart upload or ranged download (default 4)
+	#[arg(long, global = true, value_name = "N")]
+	pub part_concurrency: Option<usize>,
+
+	/// Persist the multipart upload states so interrupted uploads resume on the next run
+	#[arg(long, global = true)]
+	pub resumable: bool,
+
+	/// Remember the ETag of the downloaded objects, so downloading an unchanged object again is a quick
+	/// conditional request (304 Not Modified) leaving the local file untouched
+	#[arg(long, global = true)]
+	pub etag_cache: bool,
+
+	/// Fail the uploads to an existing key instead of replacing it (conditional PUT with If-None-Match: *)
+	#[arg(long, global = true)]
+	pub no_overwrite: bool,
+
+	/// Send the requests unsigned (anonymous, no credentials needed), e.g., for the public open-data buckets
+	#[arg(long, global = true)]
+	pub no_sign_request: bool,
+
+	/// Transfer through the bucket Transfer Acceleration endpoint (see `bucket accelerate on`)
+	#[arg(long, global = true, conflicts_with_all = ["endpoint_url", "no_sign_request"])]
+	pub accelerate: bool,
+
+	/// Upload only the changed parts of the modified large files, the unchanged ones being copied
+	/// server-side from the existing object (from the part MD5s recorded on the previous upload)
+	#[arg(long, global = true)]
+	pub delta: bool,
+
+	/// Do not compute and verify the transfer checksums (Content-MD5 and SHA-256)
+	#[arg(long, global = true)]
+	pub no_checksum: bool,
+
+	#[command(flatten)]
+	pub encryption: EncryptionArgs,
+
+	#[command(flatten)]
+	pub headers: HeaderArgs,
+
+	#[command(flatten)]
+	pub retry: RetryArgs,
+
+	#[command(flatten)]
+	pub timeouts: TimeoutArgs,
+
+	#[command(flatten)]
+	pub role: RoleArgs,
+
+	/// Cap the upload and download throughput (e.g., 500KB/s, 10MB/s, 1MiB/s)
+	#[arg(long, global = true, value_name = "RATE", value_parser = parse_rate)]
+	pub limit_rate: Option<u64>,
+
+	/// Tag of the uploaded objects (e.g., --tag project=demo), can be repeated
+	#[arg(long = "tag", global = true, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+	pub tags: Vec<(String, String)>,
+
+	/// User metadata (x-amz-meta-*) of the uploaded objects (e.g., --metadata source=camera-1), can be repeated
+	#[arg(long, global = true, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+	pub metadata: Vec<(String, String)>,
+
+	/// TOML file of custom `extension = "mime/type"` entries, taking precedence over the default guesses
+	#[arg(long, global = true, value_name = "PATH")]
+	pub mime_types: Option<PathBuf>,
+
+	/// Storage class of the uploaded and copied objects
+	#[arg(long, global = true)]
+	pub storage_class: Option<StorageClassArg>,
+
+	/// Accept the request charges of a requester-pays bucket (GET, HEAD and LIST requests)
+	#[arg(long, global = true)]
+	pub request_payer: Option<RequestPayerArg>,
+
+	/// Client-side encryption key file (raw 32 bytes or base64): files are encrypted before upload,
+	/// and client-side encrypted objects decrypted after download
+	#[arg(long, global = true, value_name = "PATH")]
+	pub cse_key_file: Option<PathBuf>,
+
+	/// Client-side encryption key (base64), preferably from the env var
+	#[arg(
+		long,
+		global = true,
+		env = "S3_CSE_KEY",
+		hide_env_values = true,
+		conflicts_with = "cse_key_file"
+	)]
+	pub cse_key: Option<String>,
+
+	/// Save the downloads of a gzip or zstd Content-Encoding as stored, instead of decompressing them
+	#[arg(long, global = true)]
+	pub no_decompress: bool,
+
+	/// Also store the Unix permission bits of the uploaded files, and restore them on download (the mtime always is)
+	#[arg(long, global = true)]
+	pub preserve_mode: bool,
+
+	/// Shell command run before each file upload/download, failing the transfer when it fails (the transfer is in
+	/// the S3DEMO_OPERATION, S3DEMO_BUCKET, S3DEMO_KEY, S3DEMO_PATH and S3DEMO_SIZE env vars)
+	#[arg(long, global = true, value_name = "COMMAND")]
+	pub pre_hook: Option<String>,
+
+	/// Shell command run after each file upload/download (with the S3DEMO_STATUS, ok or failed, and S3DEMO_ERROR
+	/// env vars too)
+	#[arg(long, global = true, value_name = "COMMAND")]
+	pub post_hook: Option<String>,
+
+	/// Send the JSON summary of the multi-file runs (directory uploads, recursive downloads, syncs, batches) and
+	/// daemon jobs, once finished, to this webhook URL (POST) or SNS topic ARN (arn:aws:sns:<region>:<account>:<topic>)
+	#[arg(long, global = true, value_name = "URL|ARN", value_parser = NotifyTarget::from_str)]
+	pub notify: Option<NotifyTarget>,
+
+	/// Append a JSON line per object written, copied or deleted (time, key, size, ETag, SHA-256, requester) to this
+	/// audit log file
+	#[arg(long, global = true, value_name = "PATH")]
+	pub audit_log: Option<PathBuf>,
+
+	/// Write the JSON report of the multi-file run (counts, bytes, throughput, elapsed time) to this file
+	#[arg(long, global = true, value_name = "PATH")]
+	pub report: Option<PathBuf>,
+
+	#[command(flatten)]
+	pub symlinks: SymlinkArgs,
+
+	/// Do not show the progress bars
+	#[arg(long, short, global = true)]
+	pub quiet: bool,
+
+	/// Format of the transfer progress
+	#[arg(long, global = true, value_enum, default_value_t)]
+	pub progress: ProgressFormat,
+
+	/// Stream of the JSON progress events (see --progress json)
+	#[arg(long, global = true, value_enum, default_value_t)]
+	pub progress_stream: ProgressStream,
+
+	/// Log the operations (-v), the requests (-vv), everything (-vvv) on stderr (or per RUST_LOG)
+	#[arg(long, short, global = true, action = ArgAction::Count)]
+	pub verbose: u8,
+
+	/// Format of the logs
+	#[arg(long, global = true, value_enum, default_value_t)]
+	pub log_format: LogFormat,
+
+	/// Only print what upload, download, sync, rm, cp and mv would transfer or delete (and the total size)
+	#[arg(long, global = true)]
+	pub dry_run: bool,
+
+	#[command(subcommand)]
+	pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+	/// Upload a local file or directory (the path is used as the key, unless --key or --prefix)
+	#[command(visible_alias = "put")]
+	Upload {
+		/// Local file or directory, or `-` to read from stdin (requires --key)
+		#[arg(required_unless_present = "from_manifest", conflicts_with = "from_manifest")]
+		path: Option<PathBuf>,
+
+		/// Destination key (single file upload only)
+		#[arg(long, conflicts_with_all = ["prefix", "from_manifest"])]
+		key: Option<String>,
+
+		/// Destination "directory" (the file name, or relative paths for a directory, are appended)
+		#[arg(long)]
+		prefix: Option<String>,
+
+		/// Content-Type of the uploaded object(s), instead of guessing it from the extension
+		#[arg(long)]
+		content_type: Option<String>,
+
+		/// Compress the file(s) before upload, with the matching Content-Encoding (decompressed on download)
+		#[arg(long, value_name = "CODEC")]
+		compress: Option<CompressArg>,
+
+		/// Upload the files above this size as chunk objects (<key>.part-0001, ...) and a manifest as <key>,
+		/// reassembled on download (e.g., 1GB)
+		#[arg(long, value_name = "SIZE", value_parser = parse_size)]
+		split_size: Option<u64>,
+
+		/// Also upload hidden files when uploading a directory
+		#[arg(long)]
+		hidden: bool,
+
+		/// Do not honor the .s3ignore file of the directory
+		#[arg(long)]
+		no_ignore_file: bool,
+
+		#[command(flatten)]
+		filter: FilterArgs,
+
+		#[command(flatten)]
+		overwrite: OverwriteArgs,
+
+		/// Check each destination object first (HEAD), skipping the files with the same size and checksum
+		#[arg(long)]
+		skip_identical: bool,
+
+		#[command(flatten)]
+		batch: BatchArgs,
+	},
+
+	/// Download an object (or all objects under a prefix) into a local directory
+	Download {
+		#[arg(required_unless_present = "from_manifest", conflicts_with = "from_manifest")]
+		key: Option<String>,
+
+		/// Treat the key as a prefix and download all the objects under it
+		#[arg(long, short)]
+		recursive: bool,
+
+		/// Resume from the existing partial file (single object only)
+		#[arg(long, short = 'c', conflicts_with_all = ["recursive", "overwrite"])]
+		resume: bool,
+
+		/// Download this version of the object (see ls --versions)
+		#[arg(long, conflicts_with_all = ["recursive", "resume", "overwrite"])]
+		version_id: Option<String>,
+
+		/// Destination directory
+		#[arg(long, short, default_value = ".")]
+		dir: PathBuf,
+
+		#[command(flatten)]
+		overwrite: OverwriteArgs,
+
+		#[command(flatten)]
+		batch: BatchArgs,
+	},
+
+	/// Stream an object to stdout (e.g., `s3demo cat logs/today.gz | zcat | grep ERROR`)
+	Cat { key: String },
+
+	/// Upload a tar of a local directory as one object, compressed per the key extension (.tar.gz, .tar.zst)
+	Archive {
+		/// Local directory (its content is archived, with relative paths)
+		dir: PathBuf,
+
+		/// Destination key (e.g., backups/2024.tar.zst)
+		#[arg(long)]
+		key: String,
+	},
+
+	/// Extract a tar object (see archive) into a local directory
+	Extract {
+		key: String,
+
+		/// Destination directory
+		#[arg(long, short, default_value = ".")]
+		dir: PathBuf,
+	},
+
+	/// Fetch only a byte range of an object, to stdout (or --file)
+	#[command(group(ArgGroup::new("byte_range").required(true).args(["range", "head", "tail"])))]
+	GetRange {
+		key: String,
+
+		/// The bytes, inclusive (e.g., 0-1048575, 1000- to the end, -500 for the last 500)
+		#[arg(long, value_parser = parse_byte_range, allow_hyphen_values = true)]
+		range: Option<ByteRange>,
+
+		/// The first bytes (e.g., 1KB)
+		#[arg(long, value_name = "SIZE", value_parser = parse_size)]
+		head: Option<u64>,
+
+		/// The last bytes (e.g., 64KB)
+		#[arg(long, value_name = "SIZE", value_parser = parse_size)]
+		tail: Option<u64>,
+
+		/// Write the bytes to this file instead of stdout
+		#[arg(long, short)]
+		file: Option<PathBuf>,
+	},
+
+	/// Query a CSV, JSON Lines or Parquet object with S3 Select (server-side), streaming the results to stdout
+	Select {
+		key: String,
+
+		/// The SQL expression (e.g., "SELECT s.ts, s.msg FROM S3Object s WHERE s.level = 'ERROR'")
+		#[arg(long)]
+		sql: String,
+
+		/// Object format (default: from the extension, .csv/.tsv, .json/.jsonl/.ndjson, .parquet, otherwise csv),
+		/// gzip or bzip2 compressed for .gz/.bz2
+		#[arg(long, value_enum)]
+		input: Option<SelectFormatArg>,
+
+		/// Results format (default: the object one, json for parquet)
+		#[arg(long, short, value_enum)]
+		output: Option<SelectFormatArg>,
+
+		/// The CSV object has no header line (the columns are then s._1, s._2, ...)
+		#[arg(long)]
+		no_header: bool,
+
+		/// CSV field delimiter (default: ',', or tab for .tsv)
+		#[arg(long)]
+		delimiter: Option<String>,
+	},
+
+	/// List the "sub-directories" and objects of a prefix (all the objects under it with --recursive)
+	Ls {
+		prefix: Option<String>,
+
+		/// List all the objects under the prefix (flat), instead of its "directory" level
+		#[arg(long, short)]
+		recursive: bool,
+
+		/// Show all the objects under the prefix as a tree, with the count and size of each "directory"
+		#[arg(long, conflicts_with_all = ["recursive", "versions", "output"])]
+		tree: bool,
+
+		/// Output format (text for keys only; table, json and csv include size, last modified, etag and storage class)
+		#[arg(long, short, value_enum, default_value_t)]
+		output: ListFormat,
+
+		/// List all the versions and delete markers (ListObjectVersions)
+		#[arg(long)]
+		versions: bool,
+
+		#[command(flatten)]
+		filter: FilterArgs,
+	},
+
+	/// Show the object count and total size of each top-level "directory" under a prefix
+	Du {
+		prefix: Option<String>,
+
+		/// Sort order (size and count are largest first)
+		#[arg(long, short, value_enum, default_value_t)]
+		sort: DuSort,
+
+		#[arg(long, short, value_enum, default_value_t)]
+		output: OutputFormat,
+	},
+
+	/// Find the objects under a prefix matching the predicates (all the given ones), to list, delete or copy them.
+	/// The global --storage-class is the storage class to find (e.g., find logs/ --storage-class GLACIER)
+	Find {
+		prefix: Option<String>,
+
+		#[command(flatten)]
+		query: FindArgs,
+
+		#[command(flatten)]
+		filter: FilterArgs,
+
+		#[arg(long, short, value_enum, default_value_t)]
+		output: ListFormat,
+
+		/// Delete the found objects (after confirmation)
+		#[arg(long)]
+		delete: bool,
+
+		/// Copy (server-side) the found objects under this prefix, with their key relative to the searched "directory"
+		#[arg(long, value_name = "PREFIX", conflicts_with = "delete")]
+		copy_to: Option<String>,
+
+		/// Do not ask for confirmation before deleting
+		#[arg(long, requires = "delete")]
+		yes: bool,
+
+		/// Delete nothing if more than this number of objects are found (even with --yes)
+		#[arg(long, value_name = "N", requires = "delete")]
+		max_delete: Option<usize>,
+	},
+
+	/// Copy an object (server-side), within the bucket or to --dest-bucket, or to another remote (e.g., `cp prod:key staging:key`)
+	Cp {
+		src_key: String,
+		dst_key: String,
+
+		/// Destination bucket (default to --bucket)
+		#[arg(long)]
+		dest_bucket: Option<String>,
+	},
+
+	/// Move an object (server-side copy, then delete), within the bucket or to --dest-bucket, or to another remote
+	Mv {
+		src_key: String,
+		dst_key: String,
+
+		/// Destination bucket (default to --bucket)
+		#[arg(long)]
+		dest_bucket: Option<String>,
+	},
+
+	/// Abort the in-progress multipart upload(s) of a key (deleting their uploaded parts)
+	AbortMultipart {
+		key: String,
+
+		/// Only this upload (default to all the uploads of the key)
+		#[arg(long)]
+		upload_id: Option<String>,
+	},
+
+	/// List or clean up the in-progress multipart uploads (their uploaded parts are billed until aborted)
+	Multipart {
+		#[command(subcommand)]
+		command: MultipartCommand,
+	},
+
+	/// Delete an object (or all objects under a prefix with --recursive)
+	Rm {
+		key: String,
+
+		/// Treat the key as a prefix and delete all the objects under it
+		#[arg(long, short)]
+		recursive: bool,
+
+		/// Permanently delete this version (or delete marker) of the object
+		#[arg(long, conflicts_with = "recursive")]
+		version_id: Option<String>,
+
+		/// Soft delete (delete marker, see undelete), requires the bucket versioning to be enabled
+		#[arg(long, conflicts_with = "version_id")]
+		trash: bool,
+
+		/// Do not ask for confirmation before deleting recursively
+		#[arg(long, requires = "recursive")]
+		yes: bool,
+
+		/// Delete nothing if more than this number of objects would be deleted recursively (even with --yes)
+		#[arg(long, value_name = "N", requires = "recursive")]
+		max_delete: Option<usize>,
+
+		#[command(flatten)]
+		filter: FilterArgs,
+	},
+
+	/// Restore an object soft deleted (e.g., with rm --trash) by removing its delete marker
+	Undelete { key: String },
+
+	/// Restore the previous version of an object (copied over as the new latest version)
+	Rollback { key: String },
+
+	/// Manage the object versions (prune)
+	Versions {
+		#[command(subcommand)]
+		command: VersionsCommand,
+	},
+
+	/// Generate a presigned download (or upload with --put) URL for an object
+	Presign {
+		key: String,
+
+		/// Validity of the URL (e.g., 30m, 1h, 7d; at most 7 days)
+		#[arg(long, default_value = "1h", value_parser = humantime::parse_duration)]
+		expires: Duration,
+
+		/// Presign an upload (PUT) instead of a download
+		#[arg(long)]
+		put: bool,
+
+		/// Content-Type the uploader must send (signed, --put only)
+		#[arg(long, requires = "put")]
+		content_type: Option<String>,
+	},
+
+	/// Restore an archived (GLACIER, DEEP_ARCHIVE) object for some days, or show its restore status
+	Restore {
+		key: String,
+
+		/// Number of days the restored copy is kept
+		#[arg(long, default_value_t = 1)]
+		days: i32,
+
+		/// Retrieval tier (Expedited: minutes, Standard: hours, Bulk: up to 12 hours for GLACIER)
+		#[arg(long, value_enum, default_value_t)]
+		tier: RestoreTier,
+
+		/// Wait until the restore completes (polling the status)
+		#[arg(long)]
+		wait: bool,
+
+		/// Status polling interval with --wait
+		#[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+		poll_interval: Duration,
+
+		/// Only show the restore status (no restore request)
+		#[arg(long, conflicts_with_all = ["days", "tier"])]
+		status: bool,
+	},
+
+	/// Show the tags of an object, or replace them with the given ones
+	Tag {
+		key: String,
+
+		/// The new tags (replacing all the existing ones)
+		#[arg(value_name = "KEY=VALUE", value_parser = parse_key_value)]
+		tags: Vec<(String, String)>,
+
+		/// Remove all the tags
+		#[arg(long, conflicts_with = "tags")]
+		delete: bool,
+	},
+
+	/// List the buckets of the account, with their creation date and region
+	Buckets {
+		/// Do not fetch the bucket regions (one request per bucket)
+		#[arg(long)]
+		no_region: bool,
+
+		#[arg(long, short, value_enum, default_value_t)]
+		output: OutputFormat,
+	},
+
+	/// Manage the --bucket itself (create, rm, exists)
+	Bucket {
+		#[command(subcommand)]
+		command: BucketCommand,
+	},
+
+	/// Show the metadata of an object
+	Stat {
+		key: String,
+
+		#[arg(long, short, value_enum, default_value_t)]
+		output: OutputFormat,
+	},
+
+	/// Sync a local directory to a prefix (or the prefix to the directory with --down),
+	/// or a `<remote>:<prefix>` to another bucket prefix (server-side copies)
+	Sync {
+		/// Local directory, or `<remote>:<prefix>` for a bucket to bucket sync
+		dir: PathBuf,
+
+		#[arg(default_value = "")]
+		prefix: String,
+
+		/// Sync from the bucket prefix to the local directory
+		#[arg(long)]
+		down: bool,
+
+		/// Also sync hidden local files
+		#[arg(long)]
+		hidden: bool,
+
+		/// Do not honor the .s3ignore file of the local directory
+		#[arg(long)]
+		no_ignore_file: bool,
+
+		/// Delete the destination objects/files missing from the source (after confirmation)
+		#[arg(long)]
+		delete: bool,
+
+		/// Do not ask for confirmation before deleting
+		#[arg(long, requires = "delete")]
+		yes: bool,
+
+		/// Delete nothing (and transfer nothing) if more than this number of files/objects would be deleted
+		/// (even with --yes)
+		#[arg(long, value_name = "N", requires = "delete")]
+		max_delete: Option<usize>,
+
+		/// Index the files found identical to their object (size, mtime, ETag), so the next syncs do not hash
+		/// them again while unchanged (in <local data dir>/s3demo/sync.db)
+		#[arg(long)]
+		index: bool,
+
+		#[command(flatten)]
+		filter: FilterArgs,
+
+		#[command(flatten)]
+		overwrite: OverwriteArgs,
+	},
+
+	/// Compare a local directory with a prefix: the files only local, only remote, and different (size or checksum),
+	/// without transferring anything
+	Diff {
+		dir: PathBuf,
+
+		#[arg(default_value = "")]
+		prefix: String,
+
+		/// Also compare hidden local files (and objects)
+		#[arg(long)]
+		hidden: bool,
+
+		/// Do not honor the .s3ignore file of the local directory
+		#[arg(long)]
+		no_ignore_file: bool,
+
+		#[command(flatten)]
+		filter: FilterArgs,
+
+		#[arg(long, short, value_enum, default_value_t)]
+		output: OutputFormat,
+	},
+
+	/// Upload the files of a local directory as soon as they are created or modified (until Ctrl-C)
+	Watch {
+		dir: PathBuf,
+
+		#[arg(default_value = "")]
+		prefix: String,
+
+		/// Also upload hidden files
+		#[arg(long)]
+		hidden: bool,
+
+		/// How long a file must stay unchanged before being uploaded
+		#[arg(long, default_value = "500ms", value_parser = humantime::parse_duration)]
+		debounce: Duration,
+
+		/// Do not honor the .s3ignore file of the local directory
+		#[arg(long)]
+		no_ignore_file: bool,
+
+		/// Filters of the files to upload (e.g., --exclude '**/*.tmp' to ignore the temporary files)
+		#[command(flatten)]
+		filter: FilterArgs,
+
+		/// Serve the transfer metrics (Prometheus) on http://ADDR/metrics, e.g., 127.0.0.1:9464
+		#[arg(long, value_name = "ADDR")]
+		metrics_addr: Option<SocketAddr>,
+	},
+
+	/// Run the sync jobs of the config ([[daemon.jobs]]) on their cron schedules, recording each run in a status file
+	Daemon {
+		/// Run the jobs once now, then exit
+		#[arg(long)]
+		once: bool,
+
+		/// Only this job (default to all), can be repeated
+		#[arg(long = "job", value_name = "NAME")]
+		jobs: Vec<String>,
+
+		/// Serve the transfer and job metrics (Prometheus) on http://ADDR/metrics, e.g., 127.0.0.1:9464
+		#[arg(long, value_name = "ADDR")]
+		metrics_addr: Option<SocketAddr>,
+	},
+
+	/// Print the shell completion script (e.g., `source <(s3demo completions bash)`), which also completes the remote
+	/// keys of download and cat (listed from the bucket)
+	Completions {
+		#[arg(value_enum)]
+		shell: CompletionShell,
+	},
+}
+
+impl Command {
+	/// The object keys and prefixes of the command (which can be prefixed with a remote name),
+	/// except the cp/mv destination key (see [`Command::dst_key_mut`]).
+	pub fn remote_keys_mut(&mut self) -> Vec<&mut String> {
+		match self {
+			Command::Upload { key, prefix, .. } => key.iter_mut().chain(prefix.iter_mut()).collect(),
+			Command::Archive { key, .. } | Command::Extract { key, .. } => vec![key],
+			Command::Ls { prefix, .. } | Command::Du { prefix, .. } => prefix.iter_mut().collect(),
+			Command::Find { prefix, copy_to, .. } => prefix.iter_mut().chain(copy_to.iter_mut()).collect(),
+			Command::Cp { src_key, .. } | Command::Mv { src_key, .. } => vec![src_key],
+			Command::Download { key, .. } => key.iter_mut().collect(),
+			Command::Cat { key }
+			| Command::GetRange { key, .. }
+			| Command::Select { key, .. }
+			| Command::AbortMultipart { key, .. }
+			| Command::Rm { key, .. }
+			| Command::Undelete { key }
+			| Command::Rollback { key }
+			| Command::Presign { key, .. }
+			| Command::Restore { key, .. }
+			| Command::Tag { key, .. }
+			| Command::Stat { key, .. } => vec![key],
+			Command::Sync { prefix, .. } | Command::Diff { prefix, .. } | Command::Watch { prefix, .. } => vec![prefix],
+			Command::Versions {
+				command: VersionsCommand::Prune { prefix, .. },
+			} => vec![prefix],
+			Command::Multipart {
+				command: MultipartCommand::List { prefix, .. } | MultipartCommand::Abort { prefix, .. },
+			} => vec![prefix],
+			Command::Buckets { .. } | Command::Bucket { .. } | Command::Daemon { .. } | Command::Completions { .. } => {
+				Vec::new()
+			}
+		}
+	}
+
+	/// Whether Ctrl-C lets the transfers in flight finish (for the transfer commands), rather than exiting now.
+	pub fn graceful_shutdown(&self) -> bool {
+		matches!(
+			self,
+			Command::Upload { .. }
+				| Command::Download { .. }
+				| Command::Archive { .. }
+				| Command::Cp { .. }
+				| Command::Mv { .. }
+				| Command::Sync { .. }
+				| Command::Watch { .. }
+				| Command::Daemon { .. }
+		)
+	}
+
+	/// Whether the command writes the object data to stdout (cat, select, get-range without --file).
+	pub fn writes_stdout(&self) -> bool {
+		match self {
+			Command::Cat { .. } | Command::Select { .. } => true,
+			Command::GetRange { file, .. } => file.is_none(),
+			_ => false,
+		}
+	}
+
+	/// The address to serve the metrics on (watch and daemon).
+	pub fn metrics_addr(&self) -> Option<SocketAddr> {
+		match self {
+			Command::Watch { metrics_addr, .. } | Command::Daemon { metrics_addr, .. } => *metrics_addr,
+			_ => None,
+		}
+	}
+
+	/// The cp/mv destination key (which can target another remote than the source).
+	pub fn dst_key_mut(&mut self) -> Option<&mut String> {
+		match self {
+			Command::Cp { dst_key, .. } | Command::Mv { dst_key, .. } => Some(dst_key),
+			_ => None,
+		}
+	}
+
+	/// The cp/mv destination bucket (when set).
+	pub fn dest_bucket(&self) -> Option<&str> {
+		match self {
+			Command::Cp { dest_bucket, .. } | Command::Mv { dest_bucket, .. } => dest_bucket.as_deref(),
+			_ => None,
+		}
+	}
+
+	/// Set the cp/mv destination bucket (from the remote of the destination key).
+	pub fn set_dest_bucket(&mut self, bucket: String) -> Result<()> {
+		match self {
+			Command::Cp { dest_bucket, .. } | Command::Mv { dest_bucket, .. } => {
+				if dest_bucket.is_some() {
+					return Err(usage_error("--dest-bucket cannot be used with a destination remote"));
+				}
+				*dest_bucket = Some(bucket);
+				Ok(())
+			}
+			_ => Err(usage_error("Only cp and mv have a destination bucket")),
+		}
+	}
+}
+
+#[derive(Subcommand)]
+pub enum BucketCommand {
+	/// Create the bucket in --region
+	Create,
+
+	/// Delete the bucket (which must be empty, unless --force)
+	Rm {
+		/// Delete all the objects first (after confirmation)
+		#[arg(long)]
+		force: bool,
+
+		/// Do not ask for confirmation before deleting the objects
+		#[arg(long, requires = "force")]
+		yes: bool,
+
+		/// Delete nothing if the bucket has more than this number of objects (even with --yes)
+		#[arg(long, value_name = "N", requires = "force")]
+		max_delete: Option<usize>,
+	},
+
+	/// Tell if the bucket exists (exit code 4 if not)
+	Exists,
+
+	/// Enable or suspend the bucket versioning, or show its status
+	Versioning {
+		#[arg(value_enum)]
+		action: VersioningAction,
+	},
+
+	/// Enable or suspend the bucket Transfer Acceleration (for --accelerate), or show its status
+	Accelerate {
+		#[arg(value_enum)]
+		action: AccelerateAction,
+	},
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VersioningAction {
+	Enable,
+	Suspend,
+	Status,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AccelerateAction {
+	On,
+	Off,
+	Status,
+}
+
+#[derive(Subcommand)]
+pub enum VersionsCommand {
+	/// Delete the non-current versions older than --older-than and/or beyond the last --keep of each key.
+	/// Only a preview (dry run) without --yes
+	#[command(group(ArgGroup::new("policy").required(true).multiple(true).args(["older_than", "keep"])))]
+	Prune {
+		#[arg(default_value = "")]
+		prefix: String,
+
+		/// Non-current for longer than this (e.g., 30d)
+		#[arg(long, value_parser = humantime::parse_duration)]
+		older_than: Option<Duration>,
+
+		/// Number of versions to keep per key, including the current one
+		#[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+		keep: Option<u64>,
+
+		/// Actually delete the versions (after reviewing the preview)
+		#[arg(long)]
+		yes: bool,
+	},
+}
+
+#[derive(Subcommand)]
+pub enum MultipartCommand {
+	/// List the in-progress multipart uploads (initiation time, key, upload id)
+	List {
+		#[arg(default_value = "")]
+		prefix: String,
+
+		/// Only the uploads initiated longer ago than this (e.g., 7d)
+		#[arg(long, value_parser = humantime::parse_duration)]
+		older_than: Option<Duration>,
+	},
+
+	/// Abort the in-progress multipart uploads initiated longer ago than --older-than (deleting their parts)
+	Abort {
+		#[arg(default_value = "")]
+		prefix: String,
+
+		/// Initiated longer ago than this (e.g., 7d, or 0s for all)
+		#[arg(long, value_parser = humantime::parse_duration)]
+		older_than: Duration,
+	},
+}
+
+/// Include/exclude filters (full keys for ls/rm, relative paths for directory upload and sync).
+#[derive(Args)]
+pub struct FilterArgs {
+	/// Only the keys/paths matching this glob (e.g., '**/*.jpg'), can be repeated
+	#[arg(long, value_name = "GLOB")]
+	pub include: Vec<String>,
+
+	/// Skip the keys/paths matching this glob, can be repeated
+	#[arg(long, value_name = "GLOB")]
+	pub exclude: Vec<String>,
+
+	/// Only the keys/paths matching this regex
+	#[arg(long, value_name = "REGEX")]
+	pub regex: Option<String>,
+}
+
+impl FilterArgs {
+	pub fn to_filter(&self) -> Result<KeyFilter> {
+		let filter = KeyFilter::new(&self.include, &self.exclude)?;
+		match &self.regex {
+			Some(regex) => Ok(filter.with_regex(regex)?),
+			None => Ok(filter),
+		}
+	}
+}
+
+/// Which existing destination files/objects are overwritten (default: the changed ones for sync, all for
+/// upload and download).
+#[derive(Args)]
+#[group(id = "overwrite", multiple = false)]
+pub struct OverwriteArgs {
+	/// Never overwrite the existing destination files/objects
+	#[arg(long, short = 'n')]
+	pub no_clobber: bool,
+
+	/// Only overwrite the destination files/objects older than their source
+	#[arg(long)]
+	pub if_newer: bool,
+
+	/// Overwrite all the destination files/objects, even the identical ones for sync
+	#[arg(long)]
+	pub force: bool,
+}
+
+impl OverwriteArgs {
+	pub fn to_overwrite(&self) -> Overwrite {
+		match (self.no_clobber, self.if_newer, self.force) {
+			(true, _, _) => Overwrite::Never,
+			(_, true, _) => Overwrite::IfNewer,
+			(_, _, true) => Overwrite::Always,
+			_ => Overwrite::Changed,
+		}
+	}
+}
+
+/// What the directory uploads do with the symbolic links (default: skip them, with a warning).
+#[derive(Args)]
+#[group(id = "symlinks", multiple = false)]
+pub struct SymlinkArgs {
+	/// Upload the symlink targets (files, and directories content) as if they were in the directory
+	#[arg(long, global = true)]
+	pub follow_symlinks: bool,
+
+	/// Skip the symlinks (the default)
+	#[arg(long, global = true)]
+	pub skip_symlinks: bool,
+
+	/// Upload the symlinks as empty objects with their target in the metadata, and recreate them on download
+	/// (from trusted buckets only, as a link can point anywhere)
+	#[arg(long, global = true)]
+	pub preserve_symlinks: bool,
+}
+
+impl SymlinkArgs {
+	pub fn to_symlink_mode(&self) -> SymlinkMode {
+		match (self.follow_symlinks, self.preserve_symlinks) {
+			(true, _) => SymlinkMode::Follow,
+			(_, true) => SymlinkMode::Preserve,
+			_ => SymlinkMode::Skip,
+		}
+	}
+}
+
+/// The predicates of `find`.
+#[derive(Args)]
+pub struct FindArgs {
+	/// Only the objects larger than this size (e.g., 100MB)
+	#[arg(long, value_name = "SIZE", value_parser = parse_size)]
+	pub larger_than: Option<u64>,
+
+	/// Only the objects smaller than this size
+	#[arg(long, value_name = "SIZE", value_parser = parse_size)]
+	pub smaller_than: Option<u64>,
+
+	/// Only the objects last modified longer ago than this (e.g., 30d)
+	#[arg(long, value_name = "AGE", value_parser = humantime::parse_duration)]
+	pub older_than: Option<Duration>,
+
+	/// Only the objects last modified more recently than this (e.g., 12h)
+	#[arg(long, value_name = "AGE", value_parser = humantime::parse_duration)]
+	pub newer_than: Option<Duration>,
+
+	/// Only the objects with a name (after the last `/`) matching this glob (e.g., '*.log')
+	#[arg(long, value_name = "GLOB")]
+	pub name: Option<String>,
+
+	/// The storage class to find (from the global --storage-class, moved here by main)
+	#[arg(skip)]
+	pub storage_class: Option<StorageClassArg>,
+}
+
+impl FindArgs {
+	pub fn to_query(&self, filter: KeyFilter) -> Result<FindQuery> {
+		let mut query = FindQuery::default();
+		query.larger_than = self.larger_than;
+		query.smaller_than = self.smaller_than;
+		query.older_than = self.older_than;
+		query.newer_than = self.newer_than;
+		query.storage_class = self.storage_class.map(|c| c.to_storage_class().as_str().to_string());
+		query.filter = filter;
+		match &self.name {
+			Some(name) => Ok(query.with_name(name)?),
+			None => Ok(query),
+		}
+	}
+}
+
+#[derive(Args)]
+pub struct HeaderArgs {
+	/// Cache-Control of the uploaded objects (e.g., 'public, max-age=86400')
+	#[arg(long, global = true)]
+	pub cache_control: Option<String>,
+
+	/// Content-Disposition of the uploaded objects (e.g., 'attachment; filename="report.pdf"')
+	#[arg(long, global = true)]
+	pub content_disposition: Option<String>,
+
+	/// Content-Encoding of the uploaded objects (e.g., gzip, for pre-compressed files)
+	#[arg(long, global = true)]
+	pub content_encoding: Option<String>,
+
+	/// Content-Language of the uploaded objects (e.g., en-US)
+	#[arg(long, global = true)]
+	pub content_language: Option<String>,
+}
+
+impl HeaderArgs {
+	pub fn to_content_headers(&self) -> ContentHeaders {
+		ContentHeaders {
+			cache_control: self.cache_control.clone(),
+			content_disposition: self.content_disposition.clone(),
+			content_encoding: self.content_encoding.clone(),
+			content_language: self.content_language.clone(),
+		}
+	}
+}
+
+/// Server-side encryption mode (`--sse`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SseMode {
+	/// SSE-S3, keys managed by S3
+	#[value(name = "AES256")]
+	Aes256,
+	/// SSE-KMS
+	#[value(name = "aws:kms")]
+	AwsKms,
+}
+
+#[derive(Args)]
+pub struct RetryArgs {
+	/// Attempts per request, including the first one (1 disables the retries)
+	#[arg(long, global = true, default_value_t = DEFAULT_MAX_ATTEMPTS, value_parser = clap::value_parser!(u32).range(1..))]
+	pub max_attempts: u32,
+
+	/// Base delay of the exponential backoff (with jitter) between the attempts
+	#[arg(long, global = true, default_value = "100ms", value_parser = humantime::parse_duration)]
+	pub retry_base_delay: Duration,
+
+	/// Do not retry the throttled requests (429, 503 SlowDown)
+	#[arg(long, global = true)]
+	pub no_retry_throttling: bool,
+
+	/// Do not retry the server errors (5xx) and network errors
+	#[arg(long, global = true)]
+	pub no_retry_server_errors: bool,
+}
+
+impl RetryArgs {
+	pub fn to_retry_policy(&self) -> RetryPolicy {
+		RetryPolicy {
+			max_attempts: self.max_attempts,
+			base_delay: self.retry_base_delay,
+			retry_throttling: !self.no_retry_throttling,
+			retry_server_errors: !self.no_retry_server_errors,
+			..Default::default()
+		}
+	}
+}
+
+#[derive(Args)]
+pub struct TimeoutArgs {
+	/// Timeout of each connection attempt (e.g., 5s)
+	#[arg(long, global = true, value_parser = humantime::parse_duration)]
+	pub connect_timeout: Option<Duration>,
+
+	/// Timeout of each request attempt, until the response headers (e.g., 30s, 5m for big parts)
+	#[arg(long, global = true, value_parser = humantime::parse_duration)]
+	pub request_timeout: Option<Duration>,
+
+	/// Deadline of each file or object transfer, all its requests and retries included (e.g., 1h)
+	#[arg(long, global = true, value_parser = humantime::parse_duration)]
+	pub transfer_timeout: Option<Duration>,
+}
+
+#[derive(Args)]
+pub struct RoleArgs {
+	/// IAM role to assume (STS AssumeRole) with the resolved credentials, refreshed before they expire
+	#[arg(long, global = true, value_name = "ARN")]
+	pub role_arn: Option<String>,
+
+	/// External ID required by the role (e.g., a role of another account)
+	#[arg(long, global = true, requires = "role_arn")]
+	pub external_id: Option<String>,
+
+	/// Role session name, visible in CloudTrail (default: s3demo-<epoch secs>)
+	#[arg(long, global = true, requires = "role_arn")]
+	pub role_session_name: Option<String>,
+
+	/// Role session duration (e.g., 12h, default: 1h, up to the role maximum session duration)
+	#[arg(long, global = true, requires = "role_arn", value_parser = humantime::parse_duration)]
+	pub role_duration: Option<Duration>,
+
+	/// MFA device serial number (or ARN), for the roles requiring MFA
+	#[arg(long, global = true, requires_all = ["role_arn", "mfa_token"])]
+	pub mfa_serial: Option<String>,
+
+	/// Current code of the MFA device
+	#[arg(long, global = true, requires = "mfa_serial")]
+	pub mfa_token: Option<String>,
+}
+
+impl RoleArgs {
+	pub fn to_assume_role(&self) -> Option<AssumeRole> {
+		let mut role = AssumeRole::new(self.role_arn.as_deref()?);
+		if let Some(external_id) = &self.external_id {
+			role = role.with_external_id(external_id);
+		}
+		if let Some(session_name) = &self.role_session_name {
+			role = role.with_session_name(session_name);
+		}
+		if let Some(duration) = self.role_duration {
+			role = role.with_duration(duration);
+		}
+		if let (Some(serial), Some(token)) = (&self.mfa_serial, &self.mfa_token) {
+			role = role.with_mfa(serial, token);
+		}
+		Some(role)
+	}
+}
+
+#[derive(Args)]
+pub struct EncryptionArgs {
+	/// Server-side encryption of the uploaded and copied objects (default: the bucket default encryption)
+	#[arg(long, global = true)]
+	pub sse: Option<SseMode>,
+
+	/// KMS key ID (or ARN) for SSE-KMS (implies --sse aws:kms)
+	#[arg(long, global = true)]
+	pub sse_kms_key_id: Option<String>,
+
+	/// SSE-C customer key file (raw 32 bytes or base64), also needed to download/stat the objects
+	#[arg(long, global = true, value_name = "PATH", conflicts_with_all = ["sse", "sse_kms_key_id"])]
+	pub sse_c_key_file: Option<PathBuf>,
+
+	/// SSE-C customer key (base64), preferably from the env var
+	#[arg(long, global = true, env = "S3_SSE_C_KEY", hide_env_values = true, conflicts_with_all = ["sse", "sse_kms_key_id", "sse_c_key_file"])]
+	pub sse_c_key: Option<String>,
+}
+
+impl EncryptionArgs {
+	pub fn to_encryption(&self) -> Result<Option<Encryption>> {
+		if let Some(path) = &self.sse_c_key_file {
+			return Ok(Some(Encryption::Customer(CustomerKey::from_file(path)?)));
+		}
+		if let Some(key) = &self.sse_c_key {
+			return Ok(Some(Encryption::Customer(CustomerKey::from_base64(key)?)));
+		}
+		match (self.sse, &self.sse_kms_key_id) {
+			(Some(SseMode::Aes256), Some(_)) => Err(usage_error("--sse-kms-key-id requires --sse aws:kms")),
+			(Some(SseMode::Aes256), None) => Ok(Some(Encryption::S3)),
+			(Some(SseMode::AwsKms), key_id) | (None, key_id @ Some(_)) => {
+				Ok(Some(Encryption::Kms { key_id: key_id.clone() }))
+			}
+			(None, None) => Ok(None),
+		}
+	}
+}
+
+/// Storage class of the uploaded objects (`--storage-class`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StorageClassArg {
+	Standard,
+	StandardIa,
+	OnezoneIa,
+	IntelligentTiering,
+	Glacier,
+	GlacierIr,
+	DeepArchive,
+	ReducedRedundancy,
+}
+
+impl StorageClassArg {
+	pub fn to_storage_class(self) -> StorageClass {
+		match self {
+			StorageClassArg::Standard => StorageClass::Standard,
+			StorageClassArg::StandardIa => StorageClass::StandardIa,
+			StorageClassArg::OnezoneIa => StorageClass::OnezoneIa,
+			StorageClassArg::IntelligentTiering => StorageClass::IntelligentTiering,
+			StorageClassArg::Glacier => StorageClass::Glacier,
+			StorageClassArg::GlacierIr => StorageClass::GlacierIr,
+			StorageClassArg::DeepArchive => StorageClass::DeepArchive,
+			StorageClassArg::ReducedRedundancy => StorageClass::ReducedRedundancy,
+		}
+	}
+}
+
+/// The batch transfers of `upload` and `download`.
+#[derive(Args)]
+pub struct BatchArgs {
+	/// Transfer the sources listed in this file: one path (upload) or key (download) per line, or for a .csv
+	/// `source,destination` rows (the destination key, or local path under --dir)
+	#[arg(long, value_name = "PATH")]
+	pub from_manifest: Option<PathBuf>,
+
+	/// Write the status, size and SHA-256 of each transfer to this file (.csv, or .json)
+	#[arg(long, value_name = "PATH", requires = "from_manifest")]
+	pub result_manifest: Option<PathBuf>,
+}
+
+/// Compression of the uploads (`upload --compress`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompressArg {
+	Gzip,
+	Zstd,
+}
+
+impl CompressArg {
+	pub fn to_compression(self) -> Compression {
+		match self {
+			CompressArg::Gzip => Compression::Gzip,
+			CompressArg::Zstd => Compression::Zstd,
+		}
+	}
+}
+
+/// Payer of the request charges (`--request-payer`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RequestPayerArg {
+	Requester,
+}
+
+impl RequestPayerArg {
+	pub fn to_request_payer(self) -> RequestPayer {
+		match self {
+			RequestPayerArg::Requester => RequestPayer::Requester,
+		}
+	}
+}
+
+/// Object and results format of `select`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SelectFormatArg {
+	Csv,
+	Json,
+	Parquet,
+}
+
+impl SelectFormatArg {
+	pub fn to_select_format(self) -> SelectFormat {
+		match self {
+			SelectFormatArg::Csv => SelectFormat::Csv,
+			SelectFormatArg::Json => SelectFormat::Json,
+			SelectFormatArg::Parquet => SelectFormat::Parquet,
+		}
+	}
+}
+
+/// Sort order of `du`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DuSort {
+	#[default]
+	Size,
+	Count,
+	Name,
+}
+
+/// Glacier retrieval tier (`restore --tier`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "PascalCase")]
+pub enum RestoreTier {
+	Expedited,
+	#[default]
+	Standard,
+	Bulk,
+}
+
+impl RestoreTier {
+	pub fn to_tier(self) -> Tier {
+		match self {
+			RestoreTier::Expedited => Tier::Expedited,
+			RestoreTier::Standard => Tier::Standard,
+			RestoreTier::Bulk => Tier::Bulk,
+		}
+	}
+}
+
+/// Parse a rate like `10MB/s` into bytes per second (see [`parse_size`] for the units).
+fn parse_byte_range(arg: &str) -> Result<ByteRange, String> {
+	arg.parse().map_err(|ex: rust_aws_sdk_s3::S3DemoError| ex.to_string())
+}
+
+fn parse_rate(arg: &str) -> Result<u64, String> {
+	let rate = parse_size(arg.trim().trim_end_matches("/s"))?;
+	if rate == 0 {
+		return Err("the rate must be positive".to_string());
+	}
+	Ok(rate)
+}
+
+/// Parse a size like `16MiB` into bytes (KB, MB, GB are powers of 1000, KiB, MiB, GiB of 1024).
+pub(super) fn parse_size(arg: &str) -> Result<u64, String> {
+	let value = arg.trim();
+	let split = value
+		.find(|c: char| !c.is_ascii_digit() && c != '.')
+		.unwrap_or(value.len());
+	let (number, unit) = value.split_at(split);
+	let multiplier: u64 = match unit.trim() {
+		"" | "B" => 1,
+		"K" | "KB" => 1_000,
+		"M" | "MB" => 1_000_000,
+		"G" | "GB" => 1_000_000_000,
+		"KiB" => 1 << 10,
+		"MiB" => 1 << 20,
+		"GiB" => 1 << 30,
+		unit => {
+			return Err(format!(
+				"unknown unit '{unit}' (expected B, KB, MB, GB, KiB, MiB or GiB)"
+			))
+		}
+	};
+	let number: f64 = number
+		.parse()
+		.map_err(|_| format!("expected a size like 16MiB, got '{arg}'"))?;
+	Ok((number * multiplier as f64) as u64)
+}
+
+/// Parse a `key=value` argument.
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+	match arg.split_once('=') {
+		Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+		_ => Err(format!("expected KEY=VALUE, got '{arg}'")),
+	}
+}
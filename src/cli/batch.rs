@@ -0,0 +1,127 @@
+use anyhow::{Context, Result};
+use rust_aws_sdk_s3::{file_sha256, Transfer, TransferResult};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::path::Path;
+
+/// One entry of a transfer manifest: the source (local path or key), and its destination (key or
+/// local path, otherwise the default one).
+#[derive(Debug, Clone)]
+pub struct BatchEntry {
+	pub source: String,
+	pub destination: Option<String>,
+}
+
+/// Read a transfer manifest: one source per line, or for a `.csv` file `source,destination` rows
+/// (the destination being optional). The blank lines and `#` comments are skipped.
+pub fn read_batch_manifest(path: &Path) -> Result<Vec<BatchEntry>> {
+	let is_csv = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+	if !is_csv {
+		let content = fs::read_to_string(path).with_context(|| format!("Cannot read manifest {}", path.display()))?;
+		let entries = content
+			.lines()
+			.map(str::trim)
+			.filter(|line| !line.is_empty() && !line.starts_with('#'))
+			.map(|line| BatchEntry {
+				source: line.to_string(),
+				destination: None,
+			})
+			.collect();
+		return Ok(entries);
+	}
+
+	let file = File::open(path).with_context(|| format!("Cannot read manifest {}", path.display()))?;
+	let mut reader = csv::ReaderBuilder::new()
+		.has_headers(false)
+		.flexible(true)
+		.comment(Some(b'#'))
+		.trim(csv::Trim::All)
+		.from_reader(file);
+	let mut entries = Vec::new();
+	for (index, record) in reader.records().enumerate() {
+		let record = record.with_context(|| format!("Invalid manifest {} (row {})", path.display(), index + 1))?;
+		let Some(source) = record.get(0).filter(|s| !s.is_empty()) else {
+			continue;
+		};
+		entries.push(BatchEntry {
+			source: source.to_string(),
+			destination: record.get(1).filter(|s| !s.is_empty()).map(|s| s.to_string()),
+		});
+	}
+	Ok(entries)
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+	Ok,
+	/// The destination exists (per the overwrite policy).
+	Skipped,
+	Failed,
+}
+
+/// One row of a result manifest (`--result-manifest`), with the size and SHA-256 of the local file
+/// of the transfers done (the uploaded or downloaded file).
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchResult {
+	pub source: String,
+	pub destination: String,
+	pub status: BatchStatus,
+	pub size: Option<u64>,
+	pub sha256: Option<String>,
+	pub error: Option<String>,
+}
+
+impl BatchResult {
+	pub fn from_result(res: &TransferResult) -> Self {
+		let mut row = Self::new(&res.transfer, BatchStatus::Ok);
+		match &res.result {
+			Ok(()) => {
+				let path = res.transfer.path();
+				row.size = path.metadata().ok().map(|m| m.len());
+				row.sha256 = file_sha256(path).ok();
+			}
+			Err(ex) => {
+				row.status = BatchStatus::Failed;
+				row.error = Some(ex.to_string());
+			}
+		}
+		row
+	}
+
+	pub fn skipped(transfer: &Transfer) -> Self {
+		Self::new(transfer, BatchStatus::Skipped)
+	}
+
+	fn new(transfer: &Transfer, status: BatchStatus) -> Self {
+		let path = transfer.path().display().to_string();
+		let key = transfer.key().to_string();
+		let (source, destination) = match transfer {
+			Transfer::Upload { .. } => (path, key),
+			Transfer::Download { .. } => (key, path),
+		};
+		BatchResult {
+			source,
+			destination,
+			status,
+			size: None,
+			sha256: None,
+			error: None,
+		}
+	}
+}
+
+/// Write the result manifest, as JSON for a `.json` file, otherwise as CSV (with a header).
+pub fn write_batch_results(path: &Path, rows: &[BatchResult]) -> Result<()> {
+	let is_json = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+	if is_json {
+		fs::write(path, serde_json::to_string_pretty(rows)?)?;
+	} else {
+		let mut writer = csv::Writer::from_path(path)?;
+		for row in rows {
+			writer.serialize(row)?;
+		}
+		writer.flush()?;
+	}
+	Ok(())
+}
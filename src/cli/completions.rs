@@ -0,0 +1,65 @@
+use super::Cli;
+use anyhow::Result;
+use clap::{CommandFactory, ValueEnum};
+use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
+use clap_complete::CompleteEnv;
+use std::ffi::{OsStr, OsString};
+
+// -- constants
+/// The env var of the completion requests, set by the registered scripts (see [`complete`]).
+const COMPLETE_VAR: &str = "COMPLETE";
+
+/// The completer of the remote keys, given the key being completed.
+pub type KeyCompleter = fn(&OsStr) -> Vec<CompletionCandidate>;
+
+/// The shells of `completions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+	Bash,
+	Zsh,
+	Fish,
+	Powershell,
+}
+
+impl CompletionShell {
+	fn name(&self) -> &'static str {
+		match self {
+			CompletionShell::Bash => "bash",
+			CompletionShell::Zsh => "zsh",
+			CompletionShell::Fish => "fish",
+			CompletionShell::Powershell => "powershell",
+		}
+	}
+}
+
+/// The cli command, with the `key_completer` on the keys of download and cat.
+fn completion_command(key_completer: KeyCompleter) -> clap::Command {
+	let with_keys = |cmd: clap::Command| cmd.mut_arg("key", |arg| arg.add(ArgValueCompleter::new(key_completer)));
+	Cli::command()
+		.mut_subcommand("download", with_keys)
+		.mut_subcommand("cat", with_keys)
+}
+
+/// Answer the completion request of a registered script (the `COMPLETE` env var set), then exit.
+/// Otherwise, a regular run, return (before anything is printed).
+///
+/// Note: the script calls the program with the words of the command line (after `--`), on each completion.
+pub fn complete(key_completer: KeyCompleter) {
+	CompleteEnv::with_factory(move || completion_command(key_completer)).complete();
+}
+
+/// Print the registration script of the completions for `shell` (calling this program, see [`complete`]).
+pub fn print_completions(shell: CompletionShell, key_completer: KeyCompleter) -> Result<()> {
+	// Note: the script of a completion request without words.
+	std::env::set_var(COMPLETE_VAR, shell.name());
+	let program = std::env::args_os().take(1).collect::<Vec<_>>();
+	let current_dir = std::env::current_dir().ok();
+	CompleteEnv::with_factory(move || completion_command(key_completer))
+		.try_complete(program, current_dir.as_deref())?;
+	Ok(())
+}
+
+/// The words of the command line being completed (the `--` escaped args of the completion request).
+pub fn completion_words() -> Vec<OsString> {
+	std::env::args_os().skip_while(|arg| arg != "--").skip(1).collect()
+}
@@ -0,0 +1,198 @@
+use super::args::parse_size;
+use super::daemon::DaemonConfig;
+use super::{usage_error, Cli, Command};
+use anyhow::{Context, Result};
+use rust_aws_sdk_s3::{NotifyTarget, DEFAULT_CONCURRENCY};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// -- constants
+const DEFAULT_BUCKET_NAME: &str = "rust-aws-sdk-s3-demo";
+const DEFAULT_REGION: &str = "us-west-2";
+/// Separator of the remote name in the keys (e.g., `prod:videos/`).
+const REMOTE_SEP: char = ':';
+
+/// The `~/.config/s3demo/config.toml` (or `--config`) defaults, e.g.:
+///
+/// ```toml
+/// bucket = "my-bucket"
+/// region = "eu-west-1"
+/// concurrency = 16
+/// part_size = "16MiB"
+/// part_concurrency = 8
+/// notify = "https://hooks.example.com/s3demo"
+/// audit_log = "/var/log/s3demo/audit.jsonl"
+///
+/// [remotes.prod]
+/// bucket = "acme-prod"
+/// region = "us-east-1"
+/// profile = "prod"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+	pub bucket: Option<String>,
+	pub region: Option<String>,
+	pub endpoint_url: Option<String>,
+	pub profile: Option<String>,
+	pub concurrency: Option<usize>,
+	/// Multipart part size, in bytes or with a unit (e.g., "16MiB").
+	pub part_size: Option<SizeValue>,
+	/// Parallel parts of each multipart upload or ranged download.
+	pub part_concurrency: Option<usize>,
+	/// The webhook URL or SNS topic ARN notified of the finished multi-file runs and daemon jobs.
+	pub notify: Option<String>,
+	/// The JSONL audit log of the object writes, copies and deletes.
+	pub audit_log: Option<PathBuf>,
+	/// The named buckets, targeted with `<name>:<key>` (e.g., `prod:videos/`).
+	pub remotes: HashMap<String, Remote>,
+	/// The sync jobs of the `daemon` command.
+	pub daemon: DaemonConfig,
+}
+
+/// A named bucket, with how to reach it (the unset values default to the flags, then the config).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Remote {
+	pub bucket: String,
+	pub region: Option<String>,
+	pub endpoint_url: Option<String>,
+	pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SizeValue {
+	Bytes(u64),
+	Text(String),
+}
+
+/// The bucket targeted by an invocation, and how to reach it.
+#[derive(Debug, Clone)]
+pub struct Target {
+	pub bucket: String,
+	pub region: String,
+	pub endpoint_url: Option<String>,
+	pub profile: Option<String>,
+	/// No region is configured (the default one is used), so the bucket region is detected.
+	pub detect_region: bool,
+}
+
+impl Config {
+	/// Load the `path` config, else the default config file when it exists (else an empty config).
+	pub fn load(path: Option<&Path>) -> Result<Config> {
+		let path = match path {
+			Some(path) => path.to_path_buf(),
+			None => match default_config_path().filter(|p| p.is_file()) {
+				Some(path) => path,
+				None => return Ok(Config::default()),
+			},
+		};
+		let content = fs::read_to_string(&path).with_context(|| format!("Cannot read config {}", path.display()))?;
+		let config = toml::from_str(&content).with_context(|| format!("Invalid config {}", path.display()))?;
+		Ok(config)
+	}
+
+	/// The target of the command: the `remote` (when its keys were prefixed with a remote name),
+	/// else the flags (and env vars), else this config, else the defaults.
+	pub fn target(&self, cli: &Cli, remote: Option<&Remote>) -> Target {
+		let bucket = remote.map(|r| r.bucket.clone());
+		let region = remote.and_then(|r| r.region.clone());
+		let endpoint_url = remote.and_then(|r| r.endpoint_url.clone());
+		let profile = remote.and_then(|r| r.profile.clone());
+		let region = region.or_else(|| cli.region.clone()).or_else(|| self.region.clone());
+		Target {
+			bucket: bucket
+				.or_else(|| cli.bucket.clone())
+				.or_else(|| self.bucket.clone())
+				.unwrap_or_else(|| DEFAULT_BUCKET_NAME.to_string()),
+			detect_region: region.is_none(),
+			region: region.unwrap_or_else(|| DEFAULT_REGION.to_string()),
+			endpoint_url: endpoint_url
+				.or_else(|| cli.endpoint_url.clone())
+				.or_else(|| self.endpoint_url.clone()),
+			profile: profile.or_else(|| cli.profile.clone()).or_else(|| self.profile.clone()),
+		}
+	}
+
+	pub fn concurrency(&self, cli: &Cli) -> usize {
+		cli.concurrency.or(self.concurrency).unwrap_or(DEFAULT_CONCURRENCY)
+	}
+
+	/// The part size of the flag, else of the config.
+	pub fn part_size(&self, cli: &Cli) -> Result<Option<usize>> {
+		let size = match (cli.part_size, &self.part_size) {
+			(Some(size), _) => size,
+			(None, None) => return Ok(None),
+			(None, Some(SizeValue::Bytes(size))) => *size,
+			(None, Some(SizeValue::Text(size))) => {
+				parse_size(size).map_err(|ex| usage_error(format!("Invalid part_size: {ex}")))?
+			}
+		};
+		Ok(Some(size as usize))
+	}
+
+	pub fn part_concurrency(&self, cli: &Cli) -> Option<usize> {
+		cli.part_concurrency.or(self.part_concurrency)
+	}
+
+	/// The notification target of the flag, else of the config.
+	pub fn notify(&self, cli: &Cli) -> Result<Option<NotifyTarget>> {
+		match (&cli.notify, &self.notify) {
+			(Some(target), _) => Ok(Some(target.clone())),
+			(None, Some(target)) => Ok(Some(target.parse().context("Invalid notify")?)),
+			(None, None) => Ok(None),
+		}
+	}
+
+	pub fn audit_log<'a>(&'a self, cli: &'a Cli) -> Option<&'a Path> {
+		cli.audit_log.as_deref().or(self.audit_log.as_deref())
+	}
+
+	/// Strip the `<remote>:` prefix of the command keys, returning the remote (see [`Config::take_key_remote`]).
+	pub fn take_remote(&self, command: &mut Command) -> Result<Option<Remote>> {
+		let mut remotes = command
+			.remote_keys_mut()
+			.into_iter()
+			.map(|key| self.take_key_remote(key))
+			.collect::<Vec<_>>();
+
+		// Note: all the keys of a command are in the same bucket (except the cp/mv destination).
+		remotes.dedup_by(|a, b| a.as_ref().map(|r| &r.bucket) == b.as_ref().map(|r| &r.bucket));
+		match remotes.len() {
+			0 | 1 => Ok(remotes.pop().flatten()),
+			_ => Err(usage_error(
+				"All the keys of a command must target the same remote (or none)",
+			)),
+		}
+	}
+
+	/// Strip the `<remote>:` prefix of `key`, returning the remote.
+	/// Note: only the names of the configured remotes are stripped, as keys can contain `:`.
+	pub fn take_key_remote(&self, key: &mut String) -> Option<Remote> {
+		let (name, rest) = key.split_once(REMOTE_SEP)?;
+		let remote = self.remotes.get(name)?.clone();
+		*key = rest.to_string();
+		Some(remote)
+	}
+}
+
+impl Target {
+	/// Whether both targets are reached with the same client (so server-side copies work).
+	pub fn same_client(&self, other: &Target) -> bool {
+		self.region == other.region && self.endpoint_url == other.endpoint_url && self.profile == other.profile
+	}
+
+	/// Whether both targets are reached with the same endpoint and credentials, maybe in other regions
+	/// (so server-side copies work when sent to the destination region).
+	pub fn same_account(&self, other: &Target) -> bool {
+		self.endpoint_url == other.endpoint_url && self.profile == other.profile
+	}
+}
+
+/// `~/.config/s3demo/config.toml`
+pub fn default_config_path() -> Option<PathBuf> {
+	dirs::home_dir().map(|home| home.join(".config").join("s3demo").join("config.toml"))
+}
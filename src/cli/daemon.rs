@@ -0,0 +1,147 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The `[daemon]` config: the sync jobs and their schedules, e.g.:
+///
+/// ```toml
+/// [[daemon.jobs]]
+/// name = "site"
+/// schedule = "*/15 * * * *"
+/// dir = "./site"
+/// prefix = "prod:web/"
+/// delete = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+	/// The status file (default: `<local data dir>/s3demo/daemon/status.json`).
+	pub status_file: Option<PathBuf>,
+	pub jobs: Vec<Job>,
+}
+
+/// A sync scheduled by the daemon (the fields mirror the `sync` flags).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+	/// Name of the job, in the logs, the status file and the lock file.
+	pub name: String,
+	/// Cron expression: `min hour day month weekday` (with an optional leading seconds field),
+	/// or `@hourly`, `@daily`, ... (local time).
+	pub schedule: String,
+	pub dir: PathBuf,
+	/// Bucket prefix, which can be prefixed with a remote name (e.g., `prod:web/`).
+	#[serde(default)]
+	pub prefix: String,
+	#[serde(default)]
+	pub down: bool,
+	#[serde(default)]
+	pub hidden: bool,
+	/// Also delete the destination objects/files missing from the source (without confirmation).
+	#[serde(default)]
+	pub delete: bool,
+	#[serde(default)]
+	pub include: Vec<String>,
+	#[serde(default)]
+	pub exclude: Vec<String>,
+	/// Index the files found identical, so the next runs do not hash them again (see `sync --index`).
+	#[serde(default)]
+	pub index: bool,
+}
+
+impl Job {
+	pub fn parse_schedule(&self) -> Result<Schedule> {
+		// Note: the cron crate expressions start with the seconds.
+		let expr = self.schedule.trim();
+		let expr = if expr.split_whitespace().count() == 5 {
+			format!("0 {expr}")
+		} else {
+			expr.to_string()
+		};
+		Schedule::from_str(&expr).with_context(|| format!("Invalid schedule '{}' of job {}", self.schedule, self.name))
+	}
+}
+
+/// The last run of each job, written to the status file after each run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DaemonStatus {
+	pub jobs: BTreeMap<String, JobStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatus {
+	/// RFC 3339 start time of the last run.
+	pub last_run: String,
+	pub duration_secs: f64,
+	/// `ok`, `failed`, or `skipped` (when another run of the job held the lock).
+	pub result: String,
+	pub transfers: usize,
+	pub unchanged: usize,
+	pub deleted: usize,
+	pub error: Option<String>,
+	/// RFC 3339 time of the next scheduled run (if any).
+	pub next_run: Option<String>,
+}
+
+impl DaemonStatus {
+	/// Load the status file (empty if missing or unreadable, as it is only a report).
+	pub fn load(path: &Path) -> DaemonStatus {
+		fs::read_to_string(path)
+			.ok()
+			.and_then(|content| serde_json::from_str(&content).ok())
+			.unwrap_or_default()
+	}
+
+	/// Write the status file (through a temporary file, so readers never see a partial file).
+	pub fn save(&self, path: &Path) -> Result<()> {
+		if let Some(parent_dir) = path.parent() {
+			fs::create_dir_all(parent_dir)?;
+		}
+		let tmp_path = path.with_extension("json.tmp");
+		fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+		fs::rename(&tmp_path, path).with_context(|| format!("Cannot write status file {}", path.display()))?;
+		Ok(())
+	}
+}
+
+/// The exclusive lock of a job, released when dropped (or when the process exits).
+pub struct JobLock {
+	_file: File,
+}
+
+impl JobLock {
+	/// Lock the job, or None if it is already locked (i.e., running in another process).
+	pub fn try_lock(name: &str) -> Result<Option<JobLock>> {
+		let dir = daemon_dir();
+		fs::create_dir_all(&dir)?;
+		let path = dir.join(format!("{name}.lock"));
+		let file = File::create(&path).with_context(|| format!("Cannot create lock file {}", path.display()))?;
+		match file.try_lock() {
+			Ok(()) => Ok(Some(JobLock { _file: file })),
+			Err(TryLockError::WouldBlock) => Ok(None),
+			Err(TryLockError::Error(ex)) => Err(anyhow!("Cannot lock {}: {ex}", path.display())),
+		}
+	}
+}
+
+/// The next run of `schedule` after `after`.
+pub fn next_run(schedule: &Schedule, after: &DateTime<Local>) -> Option<DateTime<Local>> {
+	schedule.after(after).next()
+}
+
+/// `<local data dir>/s3demo/daemon/status.json`
+pub fn default_status_path() -> PathBuf {
+	daemon_dir().join("status.json")
+}
+
+fn daemon_dir() -> PathBuf {
+	dirs::data_local_dir()
+		.unwrap_or_else(std::env::temp_dir)
+		.join("s3demo")
+		.join("daemon")
+}
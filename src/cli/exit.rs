@@ -0,0 +1,153 @@
+use aws_types::credentials::CredentialsError;
+use rust_aws_sdk_s3::S3DemoError;
+use std::io;
+
+// -- constants
+/// Some of the transfers or deletes of a multi-file run failed (the others are done).
+pub const EXIT_PARTIAL_FAILURE: i32 = 1;
+/// Invalid arguments (as clap exits on the invalid command lines), config or input.
+pub const EXIT_USAGE: i32 = 2;
+/// Access denied, or invalid or missing credentials.
+pub const EXIT_AUTH: i32 = 3;
+/// The bucket, object, version, multipart upload or local file does not exist.
+pub const EXIT_NOT_FOUND: i32 = 4;
+/// Any other failure (network, service error, checksum mismatch, ...).
+pub const EXIT_FAILURE: i32 = 5;
+/// The exit status of an interrupted run (128 + SIGINT, as the shells report it).
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// The signing error of the requests without credentials (none resolved by the credentials chain).
+/// Note: its type is of the SDK signing crate, not a dependency.
+const MISSING_CREDENTIALS_ERROR: &str = "No credentials in the property bag";
+/// The S3 error codes of invalid or expired credentials (sent with a 400 or 403 status).
+const AUTH_ERROR_CODES: &[&str] = &[
+	"InvalidAccessKeyId",
+	"SignatureDoesNotMatch",
+	"ExpiredToken",
+	"InvalidToken",
+	"TokenRefreshRequired",
+	"AccountProblem",
+];
+
+/// An error of the invalid arguments (or config) detected by the commands, exiting with [`EXIT_USAGE`].
+pub fn usage_error(msg: impl Into<String>) -> anyhow::Error {
+	S3DemoError::InvalidInput(msg.into()).into()
+}
+
+/// The exit status of a failed run, per its first library (or io) error, otherwise a failure.
+pub fn exit_code(ex: &anyhow::Error) -> i32 {
+	// Note: the credentials errors are the sources of the transport errors (the request could not be signed).
+	let is_credentials_error = |cause: &(dyn std::error::Error + 'static)| {
+		cause.downcast_ref::<CredentialsError>().is_some() || cause.to_string() == MISSING_CREDENTIALS_ERROR
+	};
+	if ex.chain().any(is_credentials_error) {
+		return EXIT_AUTH;
+	}
+	for cause in ex.chain() {
+		if let Some(ex) = cause.downcast_ref::<S3DemoError>() {
+			return lib_exit_code(ex);
+		}
+		if let Some(ex) = cause.downcast_ref::<io::Error>() {
+			return io_exit_code(ex);
+		}
+	}
+	EXIT_FAILURE
+}
+
+fn lib_exit_code(ex: &S3DemoError) -> i32 {
+	match ex {
+		S3DemoError::TransfersFailed { .. } | S3DemoError::DeleteFailed { .. } => EXIT_PARTIAL_FAILURE,
+		S3DemoError::InvalidInput(_) => EXIT_USAGE,
+		S3DemoError::AccessDenied(_) | S3DemoError::Credentials(_) => EXIT_AUTH,
+		S3DemoError::Service { code, status, .. } if *status == 401 || AUTH_ERROR_CODES.contains(&code.as_str()) => {
+			EXIT_AUTH
+		}
+		S3DemoError::NotFound(_) | S3DemoError::NoSuchBucket(_) => EXIT_NOT_FOUND,
+		S3DemoError::Io(ex) => io_exit_code(ex),
+		S3DemoError::MultipartInterrupted { source, .. } => lib_exit_code(source),
+		_ => EXIT_FAILURE,
+	}
+}
+
+fn io_exit_code(ex: &io::Error) -> i32 {
+	match ex.kind() {
+		io::ErrorKind::NotFound => EXIT_NOT_FOUND,
+		_ => EXIT_FAILURE,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use anyhow::Context;
+
+	fn service(code: &str, status: u16) -> S3DemoError {
+		S3DemoError::Service {
+			code: code.to_string(),
+			status,
+			message: String::new(),
+		}
+	}
+
+	#[test]
+	fn test_lib_exit_code() {
+		let failed = || vec!["a.txt: failed".to_string()];
+		assert_eq!(
+			lib_exit_code(&S3DemoError::TransfersFailed {
+				errors: failed(),
+				total: 2
+			}),
+			EXIT_PARTIAL_FAILURE
+		);
+		assert_eq!(
+			lib_exit_code(&S3DemoError::DeleteFailed {
+				errors: failed(),
+				total: 2
+			}),
+			EXIT_PARTIAL_FAILURE
+		);
+		assert_eq!(lib_exit_code(&S3DemoError::InvalidInput("key".into())), EXIT_USAGE);
+		assert_eq!(lib_exit_code(&S3DemoError::AccessDenied("key".into())), EXIT_AUTH);
+		assert_eq!(lib_exit_code(&S3DemoError::Credentials("expired".into())), EXIT_AUTH);
+		assert_eq!(lib_exit_code(&service("ExpiredToken", 400)), EXIT_AUTH);
+		assert_eq!(lib_exit_code(&service("Unauthorized", 401)), EXIT_AUTH);
+		assert_eq!(lib_exit_code(&S3DemoError::NotFound("key".into())), EXIT_NOT_FOUND);
+		assert_eq!(
+			lib_exit_code(&S3DemoError::NoSuchBucket("bucket".into())),
+			EXIT_NOT_FOUND
+		);
+		assert_eq!(
+			lib_exit_code(&S3DemoError::Io(io::Error::from(io::ErrorKind::NotFound))),
+			EXIT_NOT_FOUND
+		);
+		assert_eq!(lib_exit_code(&service("InternalError", 500)), EXIT_FAILURE);
+		assert_eq!(lib_exit_code(&S3DemoError::Timeout("key".into())), EXIT_FAILURE);
+		assert_eq!(
+			lib_exit_code(&S3DemoError::Io(io::Error::from(io::ErrorKind::PermissionDenied))),
+			EXIT_FAILURE
+		);
+
+		// Note: an interrupted multipart upload exits per the error which interrupted it.
+		let interrupted = S3DemoError::MultipartInterrupted {
+			key: "key".into(),
+			parts: 1,
+			source: Box::new(S3DemoError::AccessDenied("key".into())),
+		};
+		assert_eq!(lib_exit_code(&interrupted), EXIT_AUTH);
+	}
+
+	#[test]
+	fn test_exit_code() {
+		assert_eq!(exit_code(&usage_error("--key is required")), EXIT_USAGE);
+		assert_eq!(exit_code(&anyhow::anyhow!(MISSING_CREDENTIALS_ERROR)), EXIT_AUTH);
+		// Note: the first library (or io) error of the chain, under the contexts.
+		let not_found = Err::<(), _>(S3DemoError::NotFound("key".into())).context("Cannot download");
+		assert_eq!(exit_code(&not_found.unwrap_err()), EXIT_NOT_FOUND);
+		let io_error = Err::<(), _>(io::Error::from(io::ErrorKind::NotFound)).context("Cannot read");
+		assert_eq!(exit_code(&io_error.unwrap_err()), EXIT_NOT_FOUND);
+		assert_eq!(
+			exit_code(&anyhow::anyhow!("Rm aborted (nothing deleted)")),
+			EXIT_FAILURE
+		);
+	}
+}
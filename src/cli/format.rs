@@ -0,0 +1,235 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use rust_aws_sdk_s3::{DirListing, ObjectInfo, ObjectVersion};
+use std::collections::BTreeMap;
+use std::io;
+use std::time::SystemTime;
+
+/// Output format of the commands returning data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+	#[default]
+	Text,
+	Json,
+}
+
+/// Output format of the object listings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+	/// keys only
+	#[default]
+	Text,
+	/// aligned columns
+	Table,
+	Json,
+	Csv,
+}
+
+/// Size with a binary unit (e.g., `12.3 MiB`).
+pub fn human_size(size: u64) -> String {
+	const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+	let mut value = size as f64;
+	let mut unit = 0;
+	while value >= 1024. && unit < UNITS.len() - 1 {
+		value /= 1024.;
+		unit += 1;
+	}
+	if unit == 0 {
+		format!("{size} B")
+	} else {
+		format!("{value:.1} {}", UNITS[unit])
+	}
+}
+
+/// Count with thousands separators (e.g., `1,234`).
+pub fn human_count(count: usize) -> String {
+	let digits = count.to_string();
+	let mut res = String::with_capacity(digits.len() + digits.len() / 3);
+	for (i, c) in digits.chars().enumerate() {
+		if i > 0 && (digits.len() - i).is_multiple_of(3) {
+			res.push(',');
+		}
+		res.push(c);
+	}
+	res
+}
+
+pub fn human_time(time: Option<SystemTime>) -> String {
+	time.map(|t| humantime::format_rfc3339_seconds(t).to_string())
+		.unwrap_or_default()
+}
+
+pub fn print_objects(objects: &[ObjectInfo], format: ListFormat) -> Result<()> {
+	match format {
+		ListFormat::Text => {
+			for obj in objects {
+				println!("{}", obj.key);
+			}
+		}
+		ListFormat::Json => println!("{}", serde_json::to_string_pretty(objects)?),
+		ListFormat::Csv => {
+			let mut writer = csv::Writer::from_writer(io::stdout());
+			for obj in objects {
+				writer.serialize(obj)?;
+			}
+			writer.flush()?;
+		}
+		ListFormat::Table => {
+			for obj in objects {
+				println!(
+					"{:<20}  {:>10}  {:<34}  {:<19}  {}",
+					human_time(obj.last_modified),
+					human_size(obj.size),
+					obj.e_tag.as_deref().unwrap_or_default(),
+					obj.storage_class.as_deref().unwrap_or_default(),
+					obj.key
+				);
+			}
+		}
+	}
+	Ok(())
+}
+
+/// Print a "directory" level: the prefixes (as `PRE` rows in the table), then the objects.
+/// Note: in csv, the prefixes are rows with only the key.
+pub fn print_listing(listing: &DirListing, format: ListFormat) -> Result<()> {
+	match format {
+		ListFormat::Json => println!("{}", serde_json::to_string_pretty(listing)?),
+		ListFormat::Csv => {
+			// Note: the header is written before the prefix rows (not by the first serialized object).
+			let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(io::stdout());
+			writer.write_record(["key", "size", "last_modified", "e_tag", "storage_class"])?;
+			for prefix in listing.prefixes.iter() {
+				writer.write_record([prefix.as_str(), "", "", "", ""])?;
+			}
+			for obj in listing.objects.iter() {
+				writer.serialize(obj)?;
+			}
+			writer.flush()?;
+		}
+		ListFormat::Text => {
+			for prefix in listing.prefixes.iter() {
+				println!("{prefix}");
+			}
+			print_objects(&listing.objects, format)?;
+		}
+		ListFormat::Table => {
+			for prefix in listing.prefixes.iter() {
+				println!("{:<20}  {:>10}  {:<34}  {:<19}  {prefix}", "", "PRE", "", "");
+			}
+			print_objects(&listing.objects, format)?;
+		}
+	}
+	Ok(())
+}
+
+/// Print the objects under the `prefix` "directory" as an indented tree (like `tree`),
+/// with the object count and total size of each "directory".
+pub fn print_tree(prefix: &str, objects: &[ObjectInfo]) {
+	let mut root = TreeNode::default();
+	for obj in objects {
+		let rel_key = obj.key.strip_prefix(prefix).unwrap_or(&obj.key);
+		root.insert(rel_key, obj.size);
+	}
+
+	let root_label = if prefix.is_empty() { "." } else { prefix };
+	println!("{root_label}  ({})", root.summary());
+	root.print_children("");
+	println!("\n{} directories, {} objects", root.dir_count(), root.objects);
+}
+
+/// A "directory" (with the totals of all the objects under it), or an object.
+#[derive(Default)]
+struct TreeNode {
+	is_dir: bool,
+	children: BTreeMap<String, TreeNode>,
+	objects: u64,
+	bytes: u64,
+}
+
+impl TreeNode {
+	fn insert(&mut self, rel_key: &str, size: u64) {
+		if rel_key.is_empty() {
+			return;
+		}
+		// Note: the "folder" placeholder keys (ending with `/`) only make their directory.
+		if !rel_key.ends_with('/') {
+			self.objects += 1;
+			self.bytes += size;
+		}
+		match rel_key.split_once('/') {
+			Some((dir, rest)) => {
+				let child = self.children.entry(format!("{dir}/")).or_default();
+				child.is_dir = true;
+				child.insert(rest, size);
+			}
+			None => {
+				let child = self.children.entry(rel_key.to_string()).or_default();
+				child.objects = 1;
+				child.bytes = size;
+			}
+		}
+	}
+
+	fn print_children(&self, indent: &str) {
+		let count = self.children.len();
+		for (i, (name, child)) in self.children.iter().enumerate() {
+			let (branch, next_indent) = if i + 1 == count {
+				("└── ", "    ")
+			} else {
+				("├── ", "│   ")
+			};
+			if child.is_dir {
+				println!("{indent}{branch}{name}  ({})", child.summary());
+				child.print_children(&format!("{indent}{next_indent}"));
+			} else {
+				println!("{indent}{branch}{name}  ({})", human_size(child.bytes));
+			}
+		}
+	}
+
+	fn summary(&self) -> String {
+		format!("{} objects, {}", self.objects, human_size(self.bytes))
+	}
+
+	fn dir_count(&self) -> usize {
+		self.children
+			.values()
+			.filter(|c| c.is_dir)
+			.map(|c| 1 + c.dir_count())
+			.sum()
+	}
+}
+
+pub fn print_versions(versions: &[ObjectVersion], format: ListFormat) -> Result<()> {
+	match format {
+		ListFormat::Json => println!("{}", serde_json::to_string_pretty(versions)?),
+		ListFormat::Csv => {
+			let mut writer = csv::Writer::from_writer(io::stdout());
+			for version in versions {
+				writer.serialize(version)?;
+			}
+			writer.flush()?;
+		}
+		// Note: the version ids are the point of the listing, so text is the table as well.
+		ListFormat::Text | ListFormat::Table => {
+			for v in versions {
+				let flag = match (v.is_delete_marker, v.is_latest) {
+					(true, true) => "deleted",
+					(true, false) => "marker",
+					(false, true) => "latest",
+					(false, false) => "",
+				};
+				println!(
+					"{:<20}  {:>10}  {:<32}  {:<7}  {}",
+					human_time(v.last_modified),
+					human_size(v.size),
+					v.version_id,
+					flag,
+					v.key
+				);
+			}
+		}
+	}
+	Ok(())
+}
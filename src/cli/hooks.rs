@@ -0,0 +1,79 @@
+use rust_aws_sdk_s3::{HookEvent, HookStatus, S3DemoError, Transfer, TransferHooks};
+use std::process::Command;
+
+/// The `--pre-hook` and `--post-hook` shell commands, run with the transfer in the environment:
+/// - `S3DEMO_OPERATION` (`upload` or `download`), `S3DEMO_BUCKET`, `S3DEMO_KEY`, `S3DEMO_PATH`,
+///   and `S3DEMO_SIZE` (the local file size, when it exists).
+/// - for the post hook, `S3DEMO_STATUS` (`ok` or `failed`), and `S3DEMO_ERROR` when failed.
+///
+/// A failing (non zero exit) pre hook fails the transfer, e.g., `--pre-hook 'clamscan "$S3DEMO_PATH"'`.
+pub struct CommandHooks {
+	pre: Option<String>,
+	post: Option<String>,
+}
+
+impl CommandHooks {
+	pub fn new(pre: Option<String>, post: Option<String>) -> Self {
+		CommandHooks { pre, post }
+	}
+}
+
+impl TransferHooks for CommandHooks {
+	fn before(&self, event: &HookEvent) -> rust_aws_sdk_s3::Result<()> {
+		match &self.pre {
+			Some(command) => run_hook("pre", command, event, None),
+			None => Ok(()),
+		}
+	}
+
+	fn after(&self, event: &HookEvent, status: &HookStatus) -> rust_aws_sdk_s3::Result<()> {
+		match &self.post {
+			Some(command) => run_hook("post", command, event, Some(status)),
+			None => Ok(()),
+		}
+	}
+}
+
+fn run_hook(name: &str, command: &str, event: &HookEvent, status: Option<&HookStatus>) -> rust_aws_sdk_s3::Result<()> {
+	let operation = match &event.transfer {
+		Transfer::Upload { .. } => "upload",
+		Transfer::Download { .. } => "download",
+	};
+	let mut cmd = shell_command(command);
+	cmd.env("S3DEMO_OPERATION", operation)
+		.env("S3DEMO_BUCKET", &event.bucket)
+		.env("S3DEMO_KEY", event.transfer.key())
+		.env("S3DEMO_PATH", event.transfer.path());
+	if let Some(size) = event.size {
+		cmd.env("S3DEMO_SIZE", size.to_string());
+	}
+	if let Some(status) = status {
+		cmd.env("S3DEMO_STATUS", status.as_str());
+		if let HookStatus::Failed(error) = status {
+			cmd.env("S3DEMO_ERROR", error);
+		}
+	}
+
+	let key = event.transfer.key();
+	let exit = cmd
+		.status()
+		.map_err(|ex| S3DemoError::HookFailed(format!("{name} hook of {key} could not run: {ex}")))?;
+	if !exit.success() {
+		return Err(S3DemoError::HookFailed(format!("{name} hook of {key} failed ({exit})")));
+	}
+	Ok(())
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+	let mut cmd = Command::new("sh");
+	cmd.arg("-c").arg(command);
+	cmd
+}
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+	let mut cmd = Command::new("cmd");
+	cmd.arg("/C").arg(command);
+	cmd
+}
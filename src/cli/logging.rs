@@ -0,0 +1,40 @@
+use clap::ValueEnum;
+use std::io::{self, IsTerminal};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Format of the logs (on stderr).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+	#[default]
+	Text,
+	/// one JSON object per line
+	Json,
+}
+
+/// Init the logs on stderr, per the `-v` count (or `RUST_LOG` when set).
+///
+/// - 0: the warnings (e.g., retries) of this tool,
+/// - 1 (-v): the operations (upload, download, ...) with their bytes and duration,
+/// - 2 (-vv): the requests (parts, ranges, ...), and the info of the dependencies,
+/// - 3+ (-vvv): everything.
+pub fn init_logging(verbose: u8, format: LogFormat) {
+	let directives = match verbose {
+		0 => "error,rust_aws_sdk_s3=warn,s3demo=warn",
+		1 => "warn,rust_aws_sdk_s3=info,s3demo=info",
+		2 => "info,rust_aws_sdk_s3=debug,s3demo=debug",
+		_ => "trace",
+	};
+	let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(directives));
+
+	// Note: the span close events carry the operation duration (time.busy + time.idle).
+	let builder = tracing_subscriber::fmt()
+		.with_env_filter(filter)
+		.with_span_events(FmtSpan::CLOSE)
+		.with_ansi(io::stderr().is_terminal())
+		.with_writer(io::stderr);
+	match format {
+		LogFormat::Text => builder.init(),
+		LogFormat::Json => builder.json().with_current_span(true).with_span_list(false).init(),
+	}
+}
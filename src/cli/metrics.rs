@@ -0,0 +1,383 @@
+use anyhow::{Context, Result};
+use rust_aws_sdk_s3::{S3DemoError, TransferProgress};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+// -- constants
+/// The upper bounds (in seconds) of the duration histogram buckets.
+const DURATION_BUCKETS: &[f64] = &[0.1, 0.5, 1., 5., 10., 30., 60., 300., 900.];
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// The wait before accepting again after a failed accept (doubled on each failure, e.g., out of file descriptors).
+const ACCEPT_MIN_BACKOFF: Duration = Duration::from_millis(100);
+const ACCEPT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// The metrics of the process, once served (see [`serve_metrics`]).
+static METRICS: OnceLock<Arc<Metrics>> = OnceLock::new();
+
+/// The transfer and daemon job metrics, in the Prometheus text format on `/metrics`.
+#[derive(Default)]
+pub struct Metrics {
+	state: Mutex<MetricsState>,
+}
+
+#[derive(Default)]
+struct MetricsState {
+	started: u64,
+	finished: u64,
+	failed: u64,
+	bytes: u64,
+	/// The start of the in-flight transfers, by transfer id.
+	active: HashMap<u64, Instant>,
+	/// The ids of the in-flight transfers per key, in start order (a key can be transferred twice at once).
+	active_ids: HashMap<String, VecDeque<u64>>,
+	next_id: u64,
+	transfer_durations: Histogram,
+	/// The runs per job and result (ok, failed, skipped).
+	job_runs: BTreeMap<(String, String), u64>,
+	job_durations: BTreeMap<String, Histogram>,
+}
+
+#[derive(Default, Clone)]
+struct Histogram {
+	/// The count per bucket (not cumulative), the last one being `+Inf`.
+	counts: [u64; DURATION_BUCKETS.len() + 1],
+	sum: f64,
+	count: u64,
+}
+
+impl Histogram {
+	fn observe(&mut self, secs: f64) {
+		let index = DURATION_BUCKETS
+			.iter()
+			.position(|le| secs <= *le)
+			.unwrap_or(DURATION_BUCKETS.len());
+		self.counts[index] += 1;
+		self.sum += secs;
+		self.count += 1;
+	}
+
+	fn render(&self, out: &mut String, name: &str, labels: &str) {
+		let mut cumulative = 0;
+		for (i, count) in self.counts.iter().enumerate() {
+			cumulative += count;
+			let le = DURATION_BUCKETS.get(i).map_or("+Inf".to_string(), |le| le.to_string());
+			let sep = if labels.is_empty() { "" } else { "," };
+			let _ = writeln!(out, "{name}_bucket{{{labels}{sep}le=\"{le}\"}} {cumulative}");
+		}
+		let labels = if labels.is_empty() {
+			String::new()
+		} else {
+			format!("{{{labels}}}")
+		};
+		let _ = writeln!(out, "{name}_sum{labels} {}", self.sum);
+		let _ = writeln!(out, "{name}_count{labels} {}", self.count);
+	}
+}
+
+impl Metrics {
+	/// Count the failed transfers (the progress does not tell the transfer results).
+	pub fn transfers_failed(&self, count: u64) {
+		self.state.lock().unwrap().failed += count;
+	}
+
+	/// Count a daemon job run, with its result (ok, failed, skipped) and duration.
+	pub fn job_run(&self, job: &str, result: &str, duration: Duration) {
+		let mut state = self.state.lock().unwrap();
+		*state.job_runs.entry((job.to_string(), result.to_string())).or_default() += 1;
+		state
+			.job_durations
+			.entry(job.to_string())
+			.or_default()
+			.observe(duration.as_secs_f64());
+	}
+
+	/// The metrics in the Prometheus text exposition format.
+	pub fn render(&self) -> String {
+		let state = self.state.lock().unwrap();
+		let mut out = String::new();
+		let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+			let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}");
+		};
+		metric(
+			"s3demo_transfers_started_total",
+			"counter",
+			"Transfers started.",
+			state.started,
+		);
+		metric(
+			"s3demo_transfers_finished_total",
+			"counter",
+			"Transfers finished (success or failure).",
+			state.finished,
+		);
+		metric(
+			"s3demo_transfers_failed_total",
+			"counter",
+			"Transfers failed.",
+			state.failed,
+		);
+		metric(
+			"s3demo_transfers_active",
+			"gauge",
+			"Transfers in flight.",
+			state.active.len() as u64,
+		);
+		metric(
+			"s3demo_transfer_bytes_total",
+			"counter",
+			"Bytes uploaded and downloaded.",
+			state.bytes,
+		);
+
+		let name = "s3demo_transfer_duration_seconds";
+		let _ = writeln!(out, "# HELP {name} Duration of the transfers.\n# TYPE {name} histogram");
+		state.transfer_durations.render(&mut out, name, "");
+
+		// Note: the daemon job metrics only (not for watch).
+		if state.job_runs.is_empty() {
+			return out;
+		}
+		let name = "s3demo_job_runs_total";
+		let _ = writeln!(out, "# HELP {name} Daemon job runs, per result.\n# TYPE {name} counter");
+		for ((job, result), count) in &state.job_runs {
+			let _ = writeln!(
+				out,
+				"{name}{{job=\"{}\",result=\"{result}\"}} {count}",
+				escape_label(job)
+			);
+		}
+
+		let name = "s3demo_job_duration_seconds";
+		let _ = writeln!(
+			out,
+			"# HELP {name} Duration of the daemon job runs.\n# TYPE {name} histogram"
+		);
+		for (job, histogram) in &state.job_durations {
+			histogram.render(&mut out, name, &format!("job=\"{}\"", escape_label(job)));
+		}
+
+		out
+	}
+}
+
+impl TransferProgress for Metrics {
+	fn start(&self, key: &str, _total: u64) {
+		let mut state = self.state.lock().unwrap();
+		state.started += 1;
+		let id = state.next_id;
+		state.next_id += 1;
+		state.active.insert(id, Instant::now());
+		state.active_ids.entry(key.to_string()).or_default().push_back(id);
+	}
+
+	fn advance(&self, _key: &str, bytes: u64) {
+		self.state.lock().unwrap().bytes += bytes;
+	}
+
+	fn finish(&self, key: &str, _error: Option<&S3DemoError>) {
+		let mut state = self.state.lock().unwrap();
+		state.finished += 1;
+		// Note: a transfer failing early finishes without start (so without duration).
+		let Some(ids) = state.active_ids.get_mut(key) else {
+			return;
+		};
+		let id = ids.pop_front();
+		if ids.is_empty() {
+			state.active_ids.remove(key);
+		}
+		if let Some(started) = id.and_then(|id| state.active.remove(&id)) {
+			state.transfer_durations.observe(started.elapsed().as_secs_f64());
+		}
+	}
+}
+
+/// The progress listener feeding the metrics, and then the `inner` one (if any, e.g., the bars).
+pub struct MetricsProgress {
+	metrics: Arc<Metrics>,
+	inner: Option<Arc<dyn TransferProgress>>,
+}
+
+impl MetricsProgress {
+	pub fn new(metrics: Arc<Metrics>, inner: Option<Arc<dyn TransferProgress>>) -> Self {
+		MetricsProgress { metrics, inner }
+	}
+}
+
+impl TransferProgress for MetricsProgress {
+	fn start(&self, key: &str, total: u64) {
+		self.metrics.start(key, total);
+		if let Some(inner) = &self.inner {
+			inner.start(key, total);
+		}
+	}
+
+	fn advance(&self, key: &str, bytes: u64) {
+		self.metrics.advance(key, bytes);
+		if let Some(inner) = &self.inner {
+			inner.advance(key, bytes);
+		}
+	}
+
+	fn finish(&self, key: &str, error: Option<&S3DemoError>) {
+		self.metrics.finish(key, error);
+		if let Some(inner) = &self.inner {
+			inner.finish(key, error);
+		}
+	}
+}
+
+/// The metrics of the process, when served.
+pub fn metrics() -> Option<Arc<Metrics>> {
+	METRICS.get().cloned()
+}
+
+/// Serve the metrics on `http://<addr>/metrics` (in the background, for the process lifetime).
+pub async fn serve_metrics(addr: SocketAddr) -> Result<()> {
+	let listener = TcpListener::bind(addr)
+		.await
+		.with_context(|| format!("Cannot listen on {addr} for the metrics"))?;
+	let metrics = METRICS.get_or_init(Default::default).clone();
+	info!(%addr, "serving metrics");
+
+	tokio::spawn(async move {
+		let mut backoff = ACCEPT_MIN_BACKOFF;
+		loop {
+			let (stream, peer) = match listener.accept().await {
+				Ok(accepted) => {
+					backoff = ACCEPT_MIN_BACKOFF;
+					accepted
+				}
+				Err(ex) => {
+					warn!("metrics accept failed (retrying in {backoff:?}): {ex}");
+					tokio::time::sleep(backoff).await;
+					backoff = (backoff * 2).min(ACCEPT_MAX_BACKOFF);
+					continue;
+				}
+			};
+			let metrics = metrics.clone();
+			tokio::spawn(async move {
+				if let Err(ex) = handle_request(stream, &metrics).await {
+					debug!(%peer, "metrics request failed: {ex}");
+				}
+			});
+		}
+	});
+	Ok(())
+}
+
+/// Answer one HTTP request (then close the connection): the metrics on `GET /metrics`, otherwise a 404.
+async fn handle_request(mut stream: TcpStream, metrics: &Metrics) -> Result<()> {
+	// READ - the request head
+	let mut buf = Vec::new();
+	let mut chunk = [0; 1024];
+	tokio::time::timeout(REQUEST_TIMEOUT, async {
+		while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_SIZE {
+			let len = stream.read(&mut chunk).await?;
+			if len == 0 {
+				break;
+			}
+			buf.extend_from_slice(&chunk[..len]);
+		}
+		Ok::<_, std::io::Error>(())
+	})
+	.await
+	.context("Request timeout")??;
+
+	// RESPOND
+	let head = String::from_utf8_lossy(&buf);
+	let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+	let (method, path) = (parts.next(), parts.next().map(|p| p.split('?').next().unwrap_or(p)));
+	let (status, body) = match (method, path) {
+		(Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+		_ => ("404 Not Found", "Not found, see /metrics\n".to_string()),
+	};
+	let response = format!(
+		"HTTP/1.1 {status}\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+		body.len()
+	);
+	stream.write_all(response.as_bytes()).await?;
+	stream.shutdown().await?;
+	Ok(())
+}
+
+fn escape_label(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_metrics_render() {
+		let metrics = Metrics::default();
+		metrics.start("a.txt", 10);
+		metrics.advance("a.txt", 10);
+		// Note: the same key transferred twice at once, each transfer finishing with its own duration.
+		metrics.start("b.txt", 5);
+		metrics.start("b.txt", 5);
+		metrics.advance("b.txt", 5);
+		metrics.finish("a.txt", None);
+		metrics.finish("b.txt", None);
+		metrics.finish("c.txt", None);
+		metrics.transfers_failed(1);
+
+		let out = metrics.render();
+		let lines = out.lines().collect::<Vec<_>>();
+		for line in [
+			"# HELP s3demo_transfers_started_total Transfers started.",
+			"# TYPE s3demo_transfers_started_total counter",
+			"s3demo_transfers_started_total 3",
+			"s3demo_transfers_finished_total 3",
+			"s3demo_transfers_failed_total 1",
+			"# TYPE s3demo_transfers_active gauge",
+			"s3demo_transfers_active 1",
+			"s3demo_transfer_bytes_total 15",
+			"# TYPE s3demo_transfer_duration_seconds histogram",
+			"s3demo_transfer_duration_seconds_bucket{le=\"0.1\"} 2",
+			"s3demo_transfer_duration_seconds_bucket{le=\"+Inf\"} 2",
+			"s3demo_transfer_duration_seconds_count 2",
+		] {
+			assert!(lines.contains(&line), "missing {line:?} in:\n{out}");
+		}
+		assert!(
+			!out.contains("s3demo_job_runs_total"),
+			"no job metrics without daemon runs"
+		);
+
+		metrics.finish("b.txt", None);
+		assert!(metrics.render().lines().any(|l| l == "s3demo_transfers_active 0"));
+	}
+
+	#[test]
+	fn test_metrics_render_jobs() {
+		let metrics = Metrics::default();
+		metrics.job_run("site \"prod\"", "ok", Duration::from_secs(2));
+		metrics.job_run("site \"prod\"", "ok", Duration::from_secs(20));
+		metrics.job_run("logs", "failed", Duration::from_millis(50));
+
+		let out = metrics.render();
+		let lines = out.lines().collect::<Vec<_>>();
+		for line in [
+			"# TYPE s3demo_job_runs_total counter",
+			"s3demo_job_runs_total{job=\"logs\",result=\"failed\"} 1",
+			"s3demo_job_runs_total{job=\"site \\\"prod\\\"\",result=\"ok\"} 2",
+			"# TYPE s3demo_job_duration_seconds histogram",
+			"s3demo_job_duration_seconds_bucket{job=\"site \\\"prod\\\"\",le=\"1\"} 0",
+			"s3demo_job_duration_seconds_bucket{job=\"site \\\"prod\\\"\",le=\"5\"} 1",
+			"s3demo_job_duration_seconds_bucket{job=\"site \\\"prod\\\"\",le=\"30\"} 2",
+			"s3demo_job_duration_seconds_sum{job=\"site \\\"prod\\\"\"} 22",
+			"s3demo_job_duration_seconds_count{job=\"logs\"} 1",
+		] {
+			assert!(lines.contains(&line), "missing {line:?} in:\n{out}");
+		}
+	}
+}
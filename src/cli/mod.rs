@@ -0,0 +1,31 @@
+mod args;
+mod batch;
+mod completions;
+mod config;
+mod daemon;
+mod exit;
+mod format;
+mod hooks;
+mod logging;
+mod metrics;
+mod progress;
+mod shutdown;
+
+pub use args::{
+	AccelerateAction, BatchArgs, BucketCommand, Cli, Command, DuSort, MultipartCommand, VersioningAction,
+	VersionsCommand,
+};
+pub use batch::{read_batch_manifest, write_batch_results, BatchResult};
+pub use completions::{complete, completion_words, print_completions, CompletionShell};
+pub use config::{Config, Remote, Target};
+pub use daemon::{default_status_path, next_run, DaemonStatus, Job, JobLock, JobStatus};
+pub use exit::{exit_code, usage_error, EXIT_INTERRUPTED, EXIT_NOT_FOUND};
+pub use format::{
+	human_count, human_size, human_time, print_listing, print_objects, print_tree, print_versions, ListFormat,
+	OutputFormat,
+};
+pub use hooks::CommandHooks;
+pub use logging::{init_logging, LogFormat};
+pub use metrics::{metrics, serve_metrics, MetricsProgress};
+pub use progress::{BarProgress, JsonProgress, ProgressFormat, ProgressStream};
+pub use shutdown::{handle_ctrl_c, shutdown_token};
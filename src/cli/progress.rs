@@ -0,0 +1,278 @@
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use rust_aws_sdk_s3::{S3DemoError, TransferProgress};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+// -- constants
+const BAR_TEMPLATE: &str = "{msg:30!} [{bar:30}] {bytes}/{total_bytes} {bytes_per_sec} eta {eta}";
+/// The minimum interval between two `progress` events of a transfer (and between two `total` events).
+const JSON_EVENT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Format of the transfer progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ProgressFormat {
+	/// progress bars on stderr (unless --quiet)
+	#[default]
+	Bars,
+	/// one JSON event per line (start, progress, finish, total)
+	Json,
+}
+
+/// Stream of the JSON progress events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ProgressStream {
+	#[default]
+	Stderr,
+	Stdout,
+}
+
+/// Indicatif progress bars, one per in-flight transfer.
+pub struct BarProgress {
+	multi: MultiProgress,
+	bars: Mutex<HashMap<String, ProgressBar>>,
+}
+
+impl BarProgress {
+	pub fn new() -> Self {
+		BarProgress {
+			multi: MultiProgress::new(),
+			bars: Mutex::new(HashMap::new()),
+		}
+	}
+}
+
+impl TransferProgress for BarProgress {
+	fn start(&self, key: &str, total: u64) {
+		let style = ProgressStyle::with_template(BAR_TEMPLATE)
+			.expect("valid template")
+			.progress_chars("=> ");
+		let bar = self.multi.add(ProgressBar::new(total).with_style(style));
+		bar.set_message(key.to_string());
+		self.bars.lock().unwrap().insert(key.to_string(), bar);
+	}
+
+	fn advance(&self, key: &str, bytes: u64) {
+		if let Some(bar) = self.bars.lock().unwrap().get(key) {
+			bar.inc(bytes);
+		}
+	}
+
+	fn finish(&self, key: &str, _error: Option<&S3DemoError>) {
+		if let Some(bar) = self.bars.lock().unwrap().remove(key) {
+			bar.finish_and_clear();
+			self.multi.remove(&bar);
+		}
+	}
+}
+
+/// JSON lines progress events, for the wrappers (CI, GUIs):
+///
+/// - `{"event":"start","key":..,"total":..}` when a transfer starts,
+/// - `{"event":"progress","key":..,"bytes":..,"total":..}` at most every 500ms per transfer,
+/// - `{"event":"finish","key":..,"bytes":..,"total":..,"status":"ok"}` when a transfer is done,
+///   or `"status":"error","error":..` when it failed,
+/// - `{"event":"total","started":..,"finished":..,"bytes":..,"total":..}` the aggregate of all the transfers,
+///   after each finish and at most every 500ms.
+///
+/// Each event also has the `time` (RFC 3339).
+pub struct JsonProgress {
+	stream: ProgressStream,
+	state: Mutex<JsonState>,
+}
+
+#[derive(Default)]
+struct JsonState {
+	transfers: HashMap<String, JsonTransfer>,
+	started: u64,
+	finished: u64,
+	/// The bytes and total of the finished transfers.
+	done_bytes: u64,
+	done_total: u64,
+	last_total: Option<Instant>,
+}
+
+struct JsonTransfer {
+	bytes: u64,
+	total: u64,
+	last_event: Instant,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum JsonEvent<'a> {
+	Start {
+		key: &'a str,
+		total: u64,
+	},
+	Progress {
+		key: &'a str,
+		bytes: u64,
+		total: u64,
+	},
+	Finish {
+		key: &'a str,
+		bytes: u64,
+		total: u64,
+		status: JsonStatus,
+		#[serde(skip_serializing_if = "Option::is_none")]
+		error: Option<String>,
+	},
+	Total {
+		started: u64,
+		finished: u64,
+		bytes: u64,
+		total: u64,
+	},
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum JsonStatus {
+	Ok,
+	Error,
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+	#[serde(flatten)]
+	event: JsonEvent<'a>,
+	time: String,
+}
+
+impl JsonProgress {
+	pub fn new(stream: ProgressStream) -> Self {
+		JsonProgress {
+			stream,
+			state: Mutex::new(JsonState::default()),
+		}
+	}
+
+	fn emit(&self, event: JsonEvent) {
+		let line = JsonLine {
+			event,
+			time: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+		};
+		let Ok(line) = serde_json::to_string(&line) else {
+			return;
+		};
+		let line = format!("{line}\n");
+		// Note: best effort, a closed stream must not fail the transfers.
+		let _ = match self.stream {
+			ProgressStream::Stderr => io::stderr().lock().write_all(line.as_bytes()),
+			ProgressStream::Stdout => {
+				let mut stdout = io::stdout().lock();
+				stdout.write_all(line.as_bytes()).and_then(|_| stdout.flush())
+			}
+		};
+	}
+
+	fn emit_total(&self, state: &mut JsonState) {
+		let (bytes, total) = state
+			.transfers
+			.values()
+			.fold((state.done_bytes, state.done_total), |(b, t), tr| {
+				(b + tr.bytes, t + tr.total)
+			});
+		state.last_total = Some(Instant::now());
+		self.emit(JsonEvent::Total {
+			started: state.started,
+			finished: state.finished,
+			bytes,
+			total,
+		});
+	}
+}
+
+impl TransferProgress for JsonProgress {
+	fn start(&self, key: &str, total: u64) {
+		let mut state = self.state.lock().unwrap();
+		state.started += 1;
+		let transfer = JsonTransfer {
+			bytes: 0,
+			total,
+			last_event: Instant::now(),
+		};
+		state.transfers.insert(key.to_string(), transfer);
+		self.emit(JsonEvent::Start { key, total });
+	}
+
+	fn advance(&self, key: &str, bytes: u64) {
+		let mut state = self.state.lock().unwrap();
+		let Some(transfer) = state.transfers.get_mut(key) else {
+			return;
+		};
+		transfer.bytes += bytes;
+		if transfer.last_event.elapsed() >= JSON_EVENT_INTERVAL {
+			transfer.last_event = Instant::now();
+			let (bytes, total) = (transfer.bytes, transfer.total);
+			self.emit(JsonEvent::Progress { key, bytes, total });
+		}
+		if state
+			.last_total
+			.is_none_or(|last| last.elapsed() >= JSON_EVENT_INTERVAL)
+		{
+			self.emit_total(&mut state);
+		}
+	}
+
+	fn finish(&self, key: &str, error: Option<&S3DemoError>) {
+		let mut state = self.state.lock().unwrap();
+		let Some(transfer) = state.transfers.remove(key) else {
+			return;
+		};
+		state.finished += 1;
+		state.done_bytes += transfer.bytes;
+		state.done_total += transfer.total;
+		let (bytes, total) = (transfer.bytes, transfer.total);
+		let status = if error.is_some() {
+			JsonStatus::Error
+		} else {
+			JsonStatus::Ok
+		};
+		let error = error.map(|err| err.to_string());
+		self.emit(JsonEvent::Finish {
+			key,
+			bytes,
+			total,
+			status,
+			error,
+		});
+		self.emit_total(&mut state);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_json_finish_event() {
+		let event = JsonEvent::Finish {
+			key: "a.txt",
+			bytes: 3,
+			total: 3,
+			status: JsonStatus::Ok,
+			error: None,
+		};
+		assert_eq!(
+			serde_json::to_string(&event).unwrap(),
+			r#"{"event":"finish","key":"a.txt","bytes":3,"total":3,"status":"ok"}"#
+		);
+
+		let event = JsonEvent::Finish {
+			key: "a.txt",
+			bytes: 1,
+			total: 3,
+			status: JsonStatus::Error,
+			error: Some("Timeout".to_string()),
+		};
+		assert_eq!(
+			serde_json::to_string(&event).unwrap(),
+			r#"{"event":"finish","key":"a.txt","bytes":1,"total":3,"status":"error","error":"Timeout"}"#
+		);
+	}
+}
@@ -0,0 +1,28 @@
+use super::exit::EXIT_INTERRUPTED;
+use std::sync::LazyLock;
+use tokio_util::sync::CancellationToken;
+
+static SHUTDOWN: LazyLock<CancellationToken> = LazyLock::new(CancellationToken::new);
+
+/// The token cancelled on Ctrl-C (see [`handle_ctrl_c`]), for the buckets (see `S3Bucket::with_shutdown`).
+pub fn shutdown_token() -> CancellationToken {
+	SHUTDOWN.clone()
+}
+
+/// On Ctrl-C, with `graceful`, cancel the shutdown token so the transfers in flight can finish (a second
+/// Ctrl-C exits now), otherwise exit now.
+pub fn handle_ctrl_c(graceful: bool) {
+	tokio::spawn(async move {
+		if tokio::signal::ctrl_c().await.is_err() {
+			return;
+		}
+		if !graceful {
+			std::process::exit(EXIT_INTERRUPTED);
+		}
+		eprintln!("Interrupted, finishing the transfers in flight (Ctrl-C again to exit now)");
+		SHUTDOWN.cancel();
+		if tokio::signal::ctrl_c().await.is_ok() {
+			std::process::exit(EXIT_INTERRUPTED);
+		}
+	});
+}
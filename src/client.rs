@@ -0,0 +1,402 @@
+use crate::accelerate::{AccelerateConnector, ACCELERATE_ENDPOINT};
+use crate::assume_role::{assume_role_provider, AssumeRole};
+use crate::conditional::NoOverwriteConnector;
+use crate::error::{Result, S3DemoError};
+use crate::retry::{RetryConnector, RetryPolicy};
+use crate::throttle::ThrottleConnector;
+use crate::unsigned::UnsignedConnector;
+use aws_config::default_provider::credentials::DefaultCredentialsChain;
+use aws_sdk_s3::{config, Client, Credentials, Endpoint, Region, RetryConfig};
+use aws_smithy_async::rt::sleep::default_async_sleep;
+use aws_smithy_client::conns;
+use aws_smithy_client::erase::DynConnector;
+use aws_smithy_client::hyper_ext::Adapter;
+use aws_smithy_client::timeout;
+use aws_types::credentials::{ProvideCredentials, SharedCredentialsProvider};
+use http::Uri;
+use std::env;
+use std::time::{Duration, SystemTime};
+use tracing::debug;
+
+// -- constants
+const ENV_CRED_KEY_ID: &str = "S3_KEY_ID";
+const ENV_CRED_KEY_SECRET: &str = "S3_KEY_SECRET";
+const ENV_CRED_SESSION_TOKEN: &str = "S3_SESSION_TOKEN";
+const ENV_CRED_EXPIRATION: &str = "S3_SESSION_EXPIRATION";
+// Note: the fallbacks, as exported by the SSO/STS tools (e.g., `aws configure export-credentials --format env`).
+const ENV_AWS_SESSION_TOKEN: &str = "AWS_SESSION_TOKEN";
+const ENV_AWS_EXPIRATION: &str = "AWS_CREDENTIAL_EXPIRATION";
+/// The requester identity of the unsigned requests.
+const ANONYMOUS_REQUESTER: &str = "anonymous";
+
+/// Build a S3 client for `region` with the credentials from the `S3_KEY_ID` / `S3_KEY_SECRET` env vars
+/// (and the optional session token, see [`ClientConfig`]).
+pub fn get_aws_client(region: &str) -> Result<Client> {
+	// get the id/secret from env
+	let key_id = env::var(ENV_CRED_KEY_ID).map_err(|_| S3DemoError::Credentials("Missing S3_KEY_ID".to_string()))?;
+	let key_secret =
+		env::var(ENV_CRED_KEY_SECRET).map_err(|_| S3DemoError::Credentials("Missing S3_KEY_SECRET".to_string()))?;
+
+	// build the aws cred
+	let cred = env_credentials(key_id, key_secret)?;
+
+	// build the aws client
+	let region = Region::new(region.to_string());
+	let conf_builder = config::Builder::new().region(region).credentials_provider(cred);
+	let conf = conf_builder.build();
+
+	// build aws client
+	let client = Client::from_conf(conf);
+	Ok(client)
+}
+
+/// Configuration of the S3 client.
+///
+/// Credentials are resolved as:
+/// - with a profile, the AWS default provider chain for this profile,
+/// - otherwise, the `S3_KEY_ID` / `S3_KEY_SECRET` env vars when set, with the `S3_SESSION_TOKEN`
+///   (or `AWS_SESSION_TOKEN`) of temporary credentials and their `S3_SESSION_EXPIRATION`
+///   (or `AWS_CREDENTIAL_EXPIRATION`, RFC 3339) if any,
+/// - otherwise, the AWS default provider chain (`AWS_*` env vars, `AWS_PROFILE`,
+///   `~/.aws/credentials`, web identity, SSO, ECS/IMDS).
+///
+/// With [`ClientConfig::with_assume_role`], these credentials assume the role, whose credentials sign the requests.
+/// With [`ClientConfig::with_no_sign_request`], there are no credentials (anonymous requests).
+///
+/// A custom endpoint (e.g., `http://localhost:9000`) targets S3 compatible services
+/// (MinIO, LocalStack, Ceph RGW, ...). Note: this SDK version always sends path-style
+/// requests (`<endpoint>/<bucket>/<key>`), which is what those services expect.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+	region: String,
+	profile: Option<String>,
+	endpoint: Option<String>,
+	retry: Option<RetryPolicy>,
+	limit_rate: Option<u64>,
+	connect_timeout: Option<Duration>,
+	request_timeout: Option<Duration>,
+	no_overwrite: bool,
+	no_sign_request: bool,
+	assume_role: Option<AssumeRole>,
+	accelerate: bool,
+}
+
+impl ClientConfig {
+	pub fn new(region: impl Into<String>) -> Self {
+		ClientConfig {
+			region: region.into(),
+			profile: None,
+			endpoint: None,
+			retry: None,
+			limit_rate: None,
+			connect_timeout: None,
+			request_timeout: None,
+			no_overwrite: false,
+			no_sign_request: false,
+			assume_role: None,
+			accelerate: false,
+		}
+	}
+
+	/// Use the AWS default provider chain with this named profile.
+	pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+		self.profile = Some(profile.into());
+		self
+	}
+
+	/// Send the requests to this endpoint URL instead of the AWS one.
+	pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+		self.endpoint = Some(endpoint.into());
+		self
+	}
+
+	/// Retry the requests with this policy (instead of the SDK standard retries).
+	pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+		self.retry = Some(retry);
+		self
+	}
+
+	/// Cap the upload and download throughput of the client (all requests together) to `bytes_per_sec`.
+	pub fn with_limit_rate(mut self, bytes_per_sec: u64) -> Self {
+		self.limit_rate = Some(bytes_per_sec);
+		self
+	}
+
+	/// Fail the connections not established within `connect_timeout` (TCP connect, retried per the retry policy).
+	pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+		self.connect_timeout = Some(connect_timeout);
+		self
+	}
+
+	/// Fail the requests not answered within `request_timeout`, for each attempt (from sending the request
+	/// to receiving the response headers, so including the upload of the body, but not the download).
+	pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+		self.request_timeout = Some(request_timeout);
+		self
+	}
+
+	/// Make the uploads conditional (`If-None-Match: *`), so they fail with [`S3DemoError::PreconditionFailed`]
+	/// instead of replacing an existing key (e.g., for immutable content). Requires the S3 conditional writes
+	/// (other S3 compatible services may ignore it).
+	pub fn with_no_overwrite(mut self, no_overwrite: bool) -> Self {
+		self.no_overwrite = no_overwrite;
+		self
+	}
+
+	/// Send the requests unsigned (anonymous), without resolving any credentials,
+	/// e.g., to list and download the objects of the public buckets (open data).
+	pub fn with_no_sign_request(mut self, no_sign_request: bool) -> Self {
+		self.no_sign_request = no_sign_request;
+		self
+	}
+
+	/// Assume this IAM role (STS AssumeRole) with the resolved credentials, refreshed before they expire.
+	pub fn with_assume_role(mut self, assume_role: AssumeRole) -> Self {
+		self.assume_role = Some(assume_role);
+		self
+	}
+
+	/// Send the requests to the bucket Transfer Acceleration endpoint (`<bucket>.s3-accelerate.amazonaws.com`),
+	/// which must be enabled on the bucket (see [`S3Bucket::set_accelerate`](crate::S3Bucket::set_accelerate)).
+	/// Not for a custom endpoint, nor unsigned requests.
+	pub fn with_accelerate(mut self, accelerate: bool) -> Self {
+		self.accelerate = accelerate;
+		self
+	}
+
+	pub fn region(&self) -> &str {
+		&self.region
+	}
+
+	pub fn profile(&self) -> Option<&str> {
+		self.profile.as_deref()
+	}
+
+	pub fn endpoint(&self) -> Option<&str> {
+		self.endpoint.as_deref()
+	}
+
+	pub fn retry(&self) -> Option<&RetryPolicy> {
+		self.retry.as_ref()
+	}
+
+	pub fn limit_rate(&self) -> Option<u64> {
+		self.limit_rate
+	}
+
+	pub fn connect_timeout(&self) -> Option<Duration> {
+		self.connect_timeout
+	}
+
+	pub fn request_timeout(&self) -> Option<Duration> {
+		self.request_timeout
+	}
+
+	pub fn no_overwrite(&self) -> bool {
+		self.no_overwrite
+	}
+
+	pub fn no_sign_request(&self) -> bool {
+		self.no_sign_request
+	}
+
+	pub fn assume_role(&self) -> Option<&AssumeRole> {
+		self.assume_role.as_ref()
+	}
+
+	pub fn accelerate(&self) -> bool {
+		self.accelerate
+	}
+}
+
+impl ClientConfig {
+	pub async fn build_client(&self) -> Result<Client> {
+		let region = Region::new(self.region.clone());
+		let mut conf_builder = config::Builder::new().region(region.clone());
+		// Note: the SDK retries and timeouts require a sleep implementation (the tokio one).
+		if let Some(sleep_impl) = default_async_sleep() {
+			conf_builder = conf_builder.sleep_impl(sleep_impl);
+		}
+
+		// custom endpoint (if any)
+		let endpoint = self.endpoint_uri()?;
+		if let Some(uri) = &endpoint {
+			conf_builder = conf_builder.endpoint_resolver(Endpoint::immutable(uri.clone()));
+		}
+		if self.accelerate {
+			if self.endpoint.is_some() || self.no_sign_request {
+				return Err(S3DemoError::InvalidInput(
+					"Transfer acceleration is for the signed requests to AWS (no custom endpoint)".to_string(),
+				));
+			}
+			let uri = Uri::from_static(ACCELERATE_ENDPOINT);
+			conf_builder = conf_builder.endpoint_resolver(Endpoint::immutable(uri));
+		}
+
+		// resolve the aws cred
+		// Note: the placeholder credentials sign the requests, whose signature is stripped (see UnsignedConnector).
+		let Some(cred_provider) = self.credentials_provider(region, endpoint.as_ref()).await? else {
+			let cred = Credentials::new("anonymous", "anonymous", None, None, "no-sign-request");
+			return self.build_with_conf(conf_builder.credentials_provider(cred), None);
+		};
+		let conf_builder = conf_builder.credentials_provider(cred_provider.clone());
+
+		self.build_with_conf(conf_builder, Some(cred_provider))
+	}
+
+	/// The identity of the requests (e.g., for an [`crate::AuditLog`]): the caller ARN (STS GetCallerIdentity),
+	/// else the access key ID (e.g., with an S3 compatible service without STS), or `anonymous` for the unsigned requests.
+	pub async fn requester_identity(&self) -> Result<String> {
+		let region = Region::new(self.region.clone());
+		let endpoint = self.endpoint_uri()?;
+		let Some(cred_provider) = self.credentials_provider(region.clone(), endpoint.as_ref()).await? else {
+			return Ok(ANONYMOUS_REQUESTER.to_string());
+		};
+
+		// BUILD & EXECUTE - aws request (on the endpoint, if any, like the AssumeRole ones)
+		let mut conf_builder = aws_sdk_sts::Config::builder()
+			.region(region)
+			.credentials_provider(cred_provider.clone());
+		if let Some(uri) = endpoint {
+			conf_builder = conf_builder.endpoint_resolver(Endpoint::immutable(uri));
+		}
+		let sts = aws_sdk_sts::Client::from_conf(conf_builder.build());
+		let res = sts.get_caller_identity().send().await;
+		match res.as_ref().map(|res| res.arn()) {
+			Ok(Some(arn)) => Ok(arn.to_string()),
+			_ => {
+				debug!(error = ?res.err(), "no caller identity, the access key id is the requester");
+				let creds = cred_provider
+					.provide_credentials()
+					.await
+					.map_err(|ex| S3DemoError::Transport(Box::new(ex)))?;
+				Ok(creds.access_key_id().to_string())
+			}
+		}
+	}
+
+	/// The endpoint URL (if any), parsed.
+	pub(crate) fn endpoint_uri(&self) -> Result<Option<Uri>> {
+		let Some(endpoint) = &self.endpoint else {
+			return Ok(None);
+		};
+		let uri = endpoint
+			.parse::<Uri>()
+			.map_err(|_| S3DemoError::InvalidInput(format!("Invalid endpoint url {endpoint}")))?;
+		Ok(Some(uri))
+	}
+
+	/// The credentials signing the requests (see [`ClientConfig`]), None for the unsigned requests.
+	pub(crate) async fn credentials_provider(
+		&self,
+		region: Region,
+		endpoint: Option<&Uri>,
+	) -> Result<Option<SharedCredentialsProvider>> {
+		if self.no_sign_request {
+			return Ok(None);
+		}
+		let custom_env = (env::var(ENV_CRED_KEY_ID), env::var(ENV_CRED_KEY_SECRET));
+		let cred_provider = match (&self.profile, custom_env) {
+			(None, (Ok(key_id), Ok(key_secret))) => {
+				SharedCredentialsProvider::new(env_credentials(key_id, key_secret)?)
+			}
+			(profile, _) => {
+				let mut chain = DefaultCredentialsChain::builder().region(region.clone());
+				if let Some(profile) = profile {
+					chain = chain.profile_name(profile);
+				}
+				SharedCredentialsProvider::new(chain.build().await)
+			}
+		};
+
+		// assume the role (if any), with the resolved credentials
+		let cred_provider = match &self.assume_role {
+			Some(role) => SharedCredentialsProvider::new(assume_role_provider(role, region, endpoint, cred_provider)),
+			None => cred_provider,
+		};
+
+		Ok(Some(cred_provider))
+	}
+
+	/// Note: the `cred_provider` is the one of the client, for the connectors signing the requests again.
+	fn build_with_conf(
+		&self,
+		conf_builder: config::Builder,
+		cred_provider: Option<SharedCredentialsProvider>,
+	) -> Result<Client> {
+		// build aws client (with the timeouts, throttle, retry, no-overwrite, unsigned and accelerate connectors, if any)
+		let has_timeouts = self.connect_timeout.is_some() || self.request_timeout.is_some();
+		let has_connectors = self.retry.is_some()
+			|| self.limit_rate.is_some()
+			|| self.no_overwrite
+			|| self.no_sign_request
+			|| self.accelerate;
+		if !has_connectors && !has_timeouts {
+			return Ok(Client::from_conf(conf_builder.build()));
+		}
+		let mut conf_builder = conf_builder;
+		let mut timeouts = timeout::Settings::new();
+		if let Some(connect_timeout) = self.connect_timeout {
+			timeouts = timeouts.with_connect_timeout(connect_timeout);
+		}
+		// Note: the adapter "read" timeout is for the response (headers) of each request sent.
+		if let Some(request_timeout) = self.request_timeout {
+			timeouts = timeouts.with_read_timeout(request_timeout);
+		}
+		let adapter = Adapter::builder().timeout(&timeouts).build(conns::https());
+		let mut conn = DynConnector::new(adapter);
+		if let Some(limit_rate) = self.limit_rate {
+			conn = DynConnector::new(ThrottleConnector::new(conn, limit_rate));
+		}
+		if let Some(retry) = &self.retry {
+			conf_builder = conf_builder.retry_config(RetryConfig::disabled());
+			conn = DynConnector::new(RetryConnector::new(conn, retry.clone()));
+		}
+		if self.no_overwrite {
+			conn = DynConnector::new(NoOverwriteConnector::new(conn));
+		}
+		if self.no_sign_request {
+			conn = DynConnector::new(UnsignedConnector::new(conn));
+		}
+		if let (true, Some(cred_provider)) = (self.accelerate, cred_provider) {
+			conn = DynConnector::new(AccelerateConnector::new(conn, cred_provider, &self.region));
+		}
+		Ok(Client::from_conf_conn(conf_builder.build(), conn))
+	}
+}
+
+/// The env credentials, with the session token and expiry of temporary credentials (e.g., from SSO/STS).
+/// Note: the expired credentials fail here, rather than with the signature errors of each request.
+fn env_credentials(key_id: String, key_secret: String) -> Result<Credentials> {
+	let env_any = |names: [&str; 2]| {
+		names
+			.into_iter()
+			.find_map(|name| env::var(name).ok().filter(|v| !v.is_empty()))
+	};
+	let session_token = env_any([ENV_CRED_SESSION_TOKEN, ENV_AWS_SESSION_TOKEN]);
+	let expiry = match env_any([ENV_CRED_EXPIRATION, ENV_AWS_EXPIRATION]) {
+		Some(expiration) => {
+			let expiry = chrono::DateTime::parse_from_rfc3339(&expiration).map_err(|_| {
+				S3DemoError::Credentials(format!(
+					"Invalid credentials expiration {expiration} (expected RFC 3339)"
+				))
+			})?;
+			let expiry = SystemTime::from(expiry);
+			if expiry <= SystemTime::now() {
+				return Err(S3DemoError::Credentials(format!(
+					"Session credentials expired at {expiration}"
+				)));
+			}
+			Some(expiry)
+		}
+		None => None,
+	};
+
+	Ok(Credentials::new(
+		key_id,
+		key_secret,
+		session_token,
+		expiry,
+		"loaded-from-custom-env",
+	))
+}
@@ -0,0 +1,163 @@
+use crate::error::{Result, S3DemoError};
+use flate2::write::{GzDecoder, GzEncoder};
+use std::fs::{remove_file, rename, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use tempfile::TempPath;
+use zstd::stream::zio;
+
+// -- constants
+/// The zstd level of the uploads (the zstd default, a good speed/ratio balance).
+pub(crate) const ZSTD_LEVEL: i32 = 3;
+
+/// The compression of the uploaded files, sent as their `Content-Encoding` (see [`crate::S3Bucket::with_compression`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+	Gzip,
+	Zstd,
+}
+
+impl Compression {
+	/// The `Content-Encoding` header value.
+	pub fn content_encoding(&self) -> &'static str {
+		match self {
+			Compression::Gzip => "gzip",
+			Compression::Zstd => "zstd",
+		}
+	}
+
+	/// The compression of a `Content-Encoding` (None for the other encodings, e.g., `identity`).
+	pub fn from_content_encoding(content_encoding: &str) -> Option<Self> {
+		match content_encoding.trim().to_lowercase().as_str() {
+			"gzip" | "x-gzip" => Some(Compression::Gzip),
+			"zstd" => Some(Compression::Zstd),
+			_ => None,
+		}
+	}
+
+	/// Compress the file at `src` into `dst`.
+	pub(crate) fn compress_file(&self, src: &Path, dst: &Path) -> Result<()> {
+		let mut reader = BufReader::new(File::open(src)?);
+		let writer = BufWriter::new(File::create(dst)?);
+		match self {
+			Compression::Gzip => {
+				let mut encoder = GzEncoder::new(writer, flate2::Compression::default());
+				io::copy(&mut reader, &mut encoder)?;
+				encoder.finish()?.flush()?;
+			}
+			Compression::Zstd => {
+				let mut encoder = zstd::stream::write::Encoder::new(writer, ZSTD_LEVEL)?;
+				io::copy(&mut reader, &mut encoder)?;
+				encoder.finish()?.flush()?;
+			}
+		}
+		Ok(())
+	}
+
+	/// Decompress, in place, the downloaded file at `path` (of the object `key`).
+	pub(crate) fn decompress_file(&self, key: &str, path: &Path) -> Result<()> {
+		let mut name = path.file_name().unwrap_or_default().to_os_string();
+		name.push(".z");
+		let src = path.with_file_name(name);
+		rename(path, &src)?;
+
+		let res = (|| {
+			let mut reader = BufReader::new(File::open(&src)?);
+			let mut decoder = decoder(Some(*self), key, BufWriter::new(File::create(path)?))?;
+			io::copy(&mut reader, &mut decoder).map_err(|ex| decode_error(key, ex))?;
+			decoder.finish()?.flush()?;
+			Ok(())
+		})();
+		let _ = remove_file(&src);
+		if res.is_err() {
+			let _ = remove_file(path);
+		}
+
+		res
+	}
+}
+
+/// The writer decompressing the content as it comes into `writer` (e.g., streamed to stdout),
+/// passing it as is without `compression`.
+pub(crate) fn decoder<W: Write>(compression: Option<Compression>, key: &str, writer: W) -> Result<Decoder<W>> {
+	let inner = match compression {
+		None => DecoderInner::Identity(writer),
+		Some(Compression::Gzip) => DecoderInner::Gzip(GzDecoder::new(writer)),
+		Some(Compression::Zstd) => {
+			let decoder = zstd::stream::raw::Decoder::new().map_err(|ex| decode_error(key, ex))?;
+			DecoderInner::Zstd(zio::Writer::new(writer, decoder))
+		}
+	};
+	Ok(Decoder {
+		key: key.to_string(),
+		inner,
+	})
+}
+
+/// A new temp file for the compressed content to upload (created exclusively, with a unique name, and removed
+/// when dropped).
+pub(crate) fn compress_upload_file(compression: Compression) -> Result<TempPath> {
+	let suffix = match compression {
+		Compression::Gzip => ".gz",
+		Compression::Zstd => ".zst",
+	};
+	Ok(tempfile::Builder::new()
+		.prefix("s3demo-")
+		.suffix(suffix)
+		.tempfile()?
+		.into_temp_path())
+}
+
+/// A streaming decompressor, writing the decompressed content to the inner writer.
+pub(crate) struct Decoder<W: Write> {
+	key: String,
+	inner: DecoderInner<W>,
+}
+
+enum DecoderInner<W: Write> {
+	Identity(W),
+	Gzip(GzDecoder<W>),
+	/// Note: the zio writer rather than `write::Decoder`, as only its `finish` detects an incomplete frame.
+	Zstd(zio::Writer<W, zstd::stream::raw::Decoder<'static>>),
+}
+
+impl<W: Write> Decoder<W> {
+	/// Check the end of the compressed content, and return the inner writer.
+	pub fn finish(self) -> Result<W> {
+		let res = match self.inner {
+			DecoderInner::Identity(writer) => Ok(writer),
+			DecoderInner::Gzip(decoder) => decoder.finish(),
+			DecoderInner::Zstd(mut decoder) => decoder.finish().map(|_| decoder.into_inner().0),
+		};
+		res.map_err(|ex| decode_error(&self.key, ex))
+	}
+}
+
+impl<W: Write> Write for Decoder<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		match &mut self.inner {
+			DecoderInner::Identity(writer) => writer.write(buf),
+			DecoderInner::Gzip(decoder) => decoder.write(buf),
+			DecoderInner::Zstd(decoder) => decoder.write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		match &mut self.inner {
+			DecoderInner::Identity(writer) => writer.flush(),
+			DecoderInner::Gzip(decoder) => decoder.flush(),
+			DecoderInner::Zstd(decoder) => decoder.flush(),
+		}
+	}
+}
+
+/// A corrupted (or not actually compressed) content, unless the error is from the inner writer (e.g., a closed pipe).
+pub(crate) fn decode_error(key: &str, err: io::Error) -> S3DemoError {
+	match err.kind() {
+		io::ErrorKind::InvalidData
+		| io::ErrorKind::InvalidInput
+		| io::ErrorKind::UnexpectedEof
+		| io::ErrorKind::Other => S3DemoError::InvalidResponse(format!("Cannot decompress {key}: {err}")),
+		_ => S3DemoError::Io(err),
+	}
+}
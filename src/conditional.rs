@@ -0,0 +1,62 @@
+use crate::error::S3DemoError;
+use aws_smithy_client::erase::DynConnector;
+use aws_smithy_http::body::SdkBody;
+use aws_smithy_http::result::ConnectorError;
+use http::header::IF_NONE_MATCH;
+use http::{HeaderValue, Request, Response};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Service, ServiceExt};
+
+/// The operations creating an object (by their `x-id` query), made conditional.
+/// Note: the parts of a multipart upload are not objects, only its completion is.
+const OBJECT_WRITE_QUERIES: [&str; 2] = ["x-id=PutObject", "x-id=CompleteMultipartUpload"];
+
+/// Connector sending the object writes (PutObject, CompleteMultipartUpload) with `If-None-Match: *`,
+/// so S3 rejects them (412 Precondition Failed) when the key already exists, atomically.
+///
+/// Note: this SDK version has no conditional write parameter, and the header is not signed
+/// (which S3 accepts for the standard headers).
+#[derive(Clone)]
+pub(crate) struct NoOverwriteConnector {
+	inner: DynConnector,
+}
+
+impl NoOverwriteConnector {
+	pub(crate) fn new(inner: DynConnector) -> Self {
+		NoOverwriteConnector { inner }
+	}
+}
+
+type ConnectorFuture = Pin<Box<dyn Future<Output = Result<Response<SdkBody>, ConnectorError>> + Send>>;
+
+impl Service<Request<SdkBody>> for NoOverwriteConnector {
+	type Response = Response<SdkBody>;
+	type Error = ConnectorError;
+	type Future = ConnectorFuture;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, mut req: Request<SdkBody>) -> Self::Future {
+		if is_object_write(&req) {
+			req.headers_mut().insert(IF_NONE_MATCH, HeaderValue::from_static("*"));
+		}
+		Box::pin(self.inner.clone().oneshot(req))
+	}
+}
+
+/// The error of an object write of `key`, naming the key when rejected as existing.
+pub(crate) fn write_error(key: &str, err: S3DemoError) -> S3DemoError {
+	match err {
+		S3DemoError::PreconditionFailed(_) => S3DemoError::PreconditionFailed(format!("{key} already exists")),
+		err => err,
+	}
+}
+
+fn is_object_write<B>(req: &Request<B>) -> bool {
+	let query = req.uri().query().unwrap_or_default();
+	query.split('&').any(|param| OBJECT_WRITE_QUERIES.contains(&param))
+}
@@ -0,0 +1,179 @@
+use crate::audit::{AuditEntry, AuditOperation};
+use crate::bucket::part_path;
+use crate::error::Result;
+use crate::key::validate_key;
+use crate::multipart_copy::{CopySource, MAX_COPY_SIZE};
+use crate::S3Bucket;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::fs::remove_file;
+use tempfile::TempPath;
+use tracing::instrument;
+
+// -- constants
+/// Characters left as is in the `x-amz-copy-source` key (everything else is percent-encoded).
+const COPY_SOURCE_SAFE: &AsciiSet = &NON_ALPHANUMERIC
+	.remove(b'/')
+	.remove(b'-')
+	.remove(b'_')
+	.remove(b'.')
+	.remove(b'~');
+
+impl S3Bucket {
+	/// Server-side copy of `src_key` (in this bucket) to `dst_key` in `dst_bucket`
+	/// (which can be this bucket). The data does not transit through the client.
+	pub async fn copy_object(&self, src_key: &str, dst_bucket: &str, dst_key: &str) -> Result<()> {
+		let src = CopySource {
+			bucket: self.name(),
+			key: src_key,
+			version_id: None,
+		};
+		self.exec_copy_object(self, &src, dst_bucket, dst_key).await
+	}
+
+	/// Server-side copy of `src_key` of the `src` bucket (which can be in another region) to `dst_key` in this bucket.
+	/// The source is read (HEAD) with the `src` client, and the copy sent to this bucket region,
+	/// so both must be reached with the same credentials.
+	pub async fn copy_from(&self, src: &S3Bucket, src_key: &str, dst_key: &str) -> Result<()> {
+		let copy_src = CopySource {
+			bucket: src.name(),
+			key: src_key,
+			version_id: None,
+		};
+		self.exec_copy_object(src, &copy_src, self.name(), dst_key).await
+	}
+
+	/// Server-side move of `src_key` of the `src` bucket to `dst_key` in this bucket (see [`S3Bucket::copy_from`]).
+	pub async fn move_from(&self, src: &S3Bucket, src_key: &str, dst_key: &str) -> Result<()> {
+		self.copy_from(src, src_key, dst_key).await?;
+		src.delete_object(src_key).await?;
+
+		Ok(())
+	}
+
+	/// Server-side copy of the `version_id` version of `src_key` to `dst_key` in `dst_bucket`.
+	pub async fn copy_object_version(
+		&self,
+		src_key: &str,
+		version_id: &str,
+		dst_bucket: &str,
+		dst_key: &str,
+	) -> Result<()> {
+		let src = CopySource {
+			bucket: self.name(),
+			key: src_key,
+			version_id: Some(version_id),
+		};
+		self.exec_copy_object(self, &src, dst_bucket, dst_key).await
+	}
+
+	/// CopyObject (sent with this bucket client), or a multipart copy for the sources over [`MAX_COPY_SIZE`].
+	/// The source is read with the `reader` client (in the source region).
+	#[instrument(name = "copy", skip_all, fields(bucket = %src.bucket, key = %src.key, version_id = src.version_id, dst_bucket = %dst_bucket, dst_key = %dst_key))]
+	async fn exec_copy_object(
+		&self,
+		reader: &S3Bucket,
+		src: &CopySource<'_>,
+		dst_bucket: &str,
+		dst_key: &str,
+	) -> Result<()> {
+		// VALIDATE
+		validate_key(dst_key)?;
+
+		// CHECK - the source size
+		let head = reader.head_copy_source(src).await?;
+		let mut entry = AuditEntry::new(AuditOperation::Copy, dst_bucket, dst_key);
+		entry.source = Some(match src.version_id {
+			Some(version_id) => format!("{}/{}?versionId={version_id}", src.bucket, src.key),
+			None => format!("{}/{}", src.bucket, src.key),
+		});
+		entry.size = Some(head.content_length() as u64);
+		if head.content_length() as u64 > MAX_COPY_SIZE {
+			self.copy_object_multipart(reader, src, &head, dst_bucket, dst_key)
+				.await?;
+			return self.audit(entry);
+		}
+
+		// BUILD - aws request
+		// Note: with SSE-C, the source is expected to be encrypted with the same key.
+		let ck = self.customer_key();
+		let req = self
+			.client()
+			.copy_object()
+			.copy_source(copy_source(src.bucket, src.key, src.version_id))
+			.bucket(dst_bucket)
+			.key(dst_key)
+			.set_server_side_encryption(self.encryption().and_then(|e| e.server_side_encryption()))
+			.set_ssekms_key_id(self.encryption().and_then(|e| e.kms_key_id()))
+			.set_storage_class(self.storage_class().cloned())
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()))
+			.set_copy_source_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_copy_source_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_copy_source_sse_customer_key_md5(ck.map(|k| k.key_md5()));
+
+		// EXECUTE
+		let res = req.send().await?;
+
+		// AUDIT
+		entry.version_id = res.version_id().map(|v| v.to_string());
+		entry.etag = res.copy_object_result().and_then(|r| r.e_tag()).map(|v| v.to_string());
+		self.audit(entry)
+	}
+
+	/// Server-side move of `src_key` (in this bucket) to `dst_key` in `dst_bucket`,
+	/// i.e., copy then delete the source.
+	#[instrument(name = "move", skip_all, fields(bucket = %self.name(), key = %src_key, dst_bucket = %dst_bucket, dst_key = %dst_key))]
+	pub async fn move_object(&self, src_key: &str, dst_bucket: &str, dst_key: &str) -> Result<()> {
+		self.copy_object(src_key, dst_bucket, dst_key).await?;
+		self.delete_object(src_key).await?;
+
+		Ok(())
+	}
+}
+
+impl S3Bucket {
+	/// Copy `src_key` to `dst_key` in the `dst` bucket through this host (download to a temp file,
+	/// then upload), for buckets not reachable with the same client (other account, endpoint or region).
+	/// The checksums (when enabled) are verified on both transfers.
+	#[instrument(name = "copy", skip_all, fields(bucket = %self.name(), key = %src_key, dst_bucket = %dst.name(), dst_key = %dst_key))]
+	pub async fn copy_to_bucket(&self, src_key: &str, dst: &S3Bucket, dst_key: &str) -> Result<()> {
+		// Note: the temp file is removed when dropped, also when a transfer fails or is cancelled.
+		let tmp_path = copy_temp_file()?;
+		match self.download_to(src_key, &tmp_path).await {
+			Ok(()) => dst.upload_to(&tmp_path, dst_key).await,
+			Err(ex) => {
+				let _ = remove_file(part_path(&tmp_path));
+				Err(ex)
+			}
+		}
+	}
+
+	/// Move `src_key` to `dst_key` in the `dst` bucket through this host (see [`S3Bucket::copy_to_bucket`]).
+	pub async fn move_to_bucket(&self, src_key: &str, dst: &S3Bucket, dst_key: &str) -> Result<()> {
+		self.copy_to_bucket(src_key, dst, dst_key).await?;
+		self.delete_object(src_key).await?;
+
+		Ok(())
+	}
+}
+
+/// A new temp file for a copy through this host (created exclusively, with a unique name, and removed when dropped).
+fn copy_temp_file() -> Result<TempPath> {
+	Ok(tempfile::Builder::new()
+		.prefix("s3demo-copy-")
+		.tempfile()?
+		.into_temp_path())
+}
+
+/// The `x-amz-copy-source` value, `bucket/key` with the key url-encoded (and `?versionId=` if any).
+pub(crate) fn copy_source(bucket: &str, key: &str, version_id: Option<&str>) -> String {
+	let key = utf8_percent_encode(key, COPY_SOURCE_SAFE);
+	match version_id {
+		Some(version_id) => format!(
+			"{bucket}/{key}?versionId={}",
+			utf8_percent_encode(version_id, COPY_SOURCE_SAFE)
+		),
+		None => format!("{bucket}/{key}"),
+	}
+}
@@ -0,0 +1,222 @@
+use crate::encryption::{check_key_len, decode_key_base64, read_key_file};
+use crate::error::{Result, S3DemoError};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm::aead::{KeyInit, OsRng};
+use aes_gcm::Aes256Gcm;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::HashMap;
+use std::fs::{remove_file, rename, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use tempfile::TempPath;
+
+// -- constants
+/// User metadata key of the client-side encryption algorithm (marks the object as encrypted).
+pub const META_CSE_ALGORITHM: &str = "cse-algorithm";
+/// User metadata key of the base64 nonce prefix of the encryption stream.
+pub const META_CSE_NONCE: &str = "cse-nonce";
+/// AES-256-GCM over 64 KiB segments (the `aead` STREAM construction, big endian 32 bits counter).
+const CSE_ALGORITHM: &str = "AES-256-GCM-STREAM-64K";
+const SEGMENT_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+/// The 12 bytes GCM nonce, minus the 4 bytes counter and 1 byte last-segment flag.
+const NONCE_PREFIX_SIZE: usize = 7;
+
+/// Client-side encryption of the object content, before upload and after download, so S3 only
+/// ever stores the ciphertext.
+///
+/// The content is encrypted in 64 KiB authenticated segments (AES-256-GCM), and the algorithm and
+/// nonce are stored in the object metadata (`x-amz-meta-cse-algorithm`, `x-amz-meta-cse-nonce`).
+/// The key never leaves the client, and is needed to decrypt the objects.
+#[derive(Clone)]
+pub struct ClientEncryption {
+	key: Vec<u8>,
+}
+
+impl ClientEncryption {
+	/// From the raw 32 bytes key.
+	pub fn new(key: &[u8]) -> Result<Self> {
+		check_key_len(key)?;
+		Ok(ClientEncryption { key: key.to_vec() })
+	}
+
+	/// From the base64 of the key (e.g., from an env var).
+	pub fn from_base64(key_b64: &str) -> Result<Self> {
+		Self::new(&decode_key_base64(key_b64)?)
+	}
+
+	/// From a file holding the raw 32 bytes key, or its base64.
+	pub fn from_file(path: &Path) -> Result<Self> {
+		Self::new(&read_key_file(path)?)
+	}
+}
+
+impl ClientEncryption {
+	/// Encrypt the file `src` into `dst`. Returns the encryption metadata to set on the object.
+	pub(crate) fn encrypt_file(&self, src: &Path, dst: &Path) -> Result<HashMap<String, String>> {
+		let mut nonce = [0u8; NONCE_PREFIX_SIZE];
+		OsRng.fill_bytes(&mut nonce);
+		let cipher = self.cipher()?;
+		let mut encryptor = EncryptorBE32::from_aead(cipher, (&nonce).into());
+
+		let mut reader = BufReader::new(File::open(src)?);
+		let mut writer = BufWriter::new(File::create(dst)?);
+
+		// Note: read one segment ahead, as the last segment is encrypted differently.
+		let mut segment = read_segment(&mut reader)?;
+		loop {
+			let next = read_segment(&mut reader)?;
+			if next.is_empty() {
+				let data = encryptor
+					.encrypt_last(segment.as_slice())
+					.map_err(|_| S3DemoError::Crypto(format!("Cannot encrypt {}", src.display())))?;
+				writer.write_all(&data)?;
+				break;
+			}
+			let data = encryptor
+				.encrypt_next(segment.as_slice())
+				.map_err(|_| S3DemoError::Crypto(format!("Cannot encrypt {}", src.display())))?;
+			writer.write_all(&data)?;
+			segment = next;
+		}
+		writer.flush()?;
+
+		let metadata = HashMap::from([
+			(META_CSE_ALGORITHM.to_string(), CSE_ALGORITHM.to_string()),
+			(META_CSE_NONCE.to_string(), BASE64.encode(nonce)),
+		]);
+		Ok(metadata)
+	}
+
+	/// The decryptor of an object from its metadata, None when it was not encrypted client-side.
+	pub(crate) fn decryptor(&self, key: &str, metadata: &HashMap<String, String>) -> Result<Option<Decryptor>> {
+		let Some(algorithm) = metadata.get(META_CSE_ALGORITHM) else {
+			return Ok(None);
+		};
+		if algorithm != CSE_ALGORITHM {
+			return Err(S3DemoError::Crypto(format!(
+				"Unsupported client-side encryption {algorithm} for {key}"
+			)));
+		}
+		let nonce = metadata
+			.get(META_CSE_NONCE)
+			.and_then(|n| BASE64.decode(n).ok())
+			.filter(|n| n.len() == NONCE_PREFIX_SIZE)
+			.ok_or_else(|| S3DemoError::Crypto(format!("Missing or invalid {META_CSE_NONCE} metadata for {key}")))?;
+		let cipher = self.cipher()?;
+
+		Ok(Some(Decryptor {
+			key: key.to_string(),
+			inner: DecryptorBE32::from_aead(cipher, nonce.as_slice().into()),
+			buf: Vec::new(),
+		}))
+	}
+
+	fn cipher(&self) -> Result<Aes256Gcm> {
+		// Note: the key length is checked on construction.
+		Aes256Gcm::new_from_slice(&self.key)
+			.map_err(|_| S3DemoError::Crypto("Invalid client-side encryption key".to_string()))
+	}
+
+	/// Decrypt the downloaded ciphertext `src` into `dst` and remove it (just renamed when the object
+	/// was not encrypted client-side).
+	pub(crate) fn decrypt_download(
+		&self,
+		key: &str,
+		metadata: &HashMap<String, String>,
+		src: &Path,
+		dst: &Path,
+	) -> Result<()> {
+		match self.decryptor(key, metadata)? {
+			Some(decryptor) => {
+				let res = decryptor.decrypt_file(src, dst);
+				if res.is_err() {
+					let _ = remove_file(dst);
+				}
+				remove_file(src)?;
+				res
+			}
+			None => Ok(rename(src, dst)?),
+		}
+	}
+}
+
+/// A new temp file for the ciphertext to upload (created exclusively, with a unique name, and removed when dropped).
+pub(crate) fn cse_upload_file() -> Result<TempPath> {
+	Ok(tempfile::Builder::new()
+		.prefix("s3demo-")
+		.suffix(".cse")
+		.tempfile()?
+		.into_temp_path())
+}
+
+/// The ciphertext file next to the downloaded `file_path` (also the partial file to resume from).
+pub(crate) fn cse_download_path(file_path: &Path) -> PathBuf {
+	let mut name = file_path.file_name().unwrap_or_default().to_os_string();
+	name.push(".cse");
+	file_path.with_file_name(name)
+}
+
+/// Decrypt the ciphertext as it comes (e.g., streamed to stdout).
+pub(crate) struct Decryptor {
+	key: String,
+	inner: DecryptorBE32<Aes256Gcm>,
+	buf: Vec<u8>,
+}
+
+impl Decryptor {
+	/// Decrypt the complete segments of `data` (the rest is kept for the next call) into `writer`.
+	pub fn update<W: Write>(&mut self, data: &[u8], writer: &mut W) -> Result<()> {
+		self.buf.extend_from_slice(data);
+		// Note: keep at least one segment, as the last one is decrypted differently.
+		while self.buf.len() > SEGMENT_SIZE + TAG_SIZE {
+			let rest = self.buf.split_off(SEGMENT_SIZE + TAG_SIZE);
+			let data = self.inner.decrypt_next(self.buf.as_slice()).map_err(|_| self.error())?;
+			writer.write_all(&data)?;
+			self.buf = rest;
+		}
+		Ok(())
+	}
+
+	/// Decrypt the whole (downloaded) file `src` into `dst`.
+	pub fn decrypt_file(mut self, src: &Path, dst: &Path) -> Result<()> {
+		let mut reader = BufReader::new(File::open(src)?);
+		let mut writer = BufWriter::new(File::create(dst)?);
+		let mut buf = vec![0; SEGMENT_SIZE + TAG_SIZE];
+		loop {
+			let len = reader.read(&mut buf)?;
+			if len == 0 {
+				break;
+			}
+			self.update(&buf[..len], &mut writer)?;
+		}
+		self.finish(&mut writer)?;
+		writer.flush()?;
+
+		Ok(())
+	}
+
+	/// Decrypt the last segment into `writer`.
+	pub fn finish<W: Write>(self, writer: &mut W) -> Result<()> {
+		let error = self.error();
+		let data = self.inner.decrypt_last(self.buf.as_slice()).map_err(|_| error)?;
+		writer.write_all(&data)?;
+		Ok(())
+	}
+
+	fn error(&self) -> S3DemoError {
+		S3DemoError::Crypto(format!(
+			"Cannot decrypt {} (wrong client-side encryption key, or corrupted content)",
+			self.key
+		))
+	}
+}
+
+/// Read up to a segment (less only at end of file).
+fn read_segment<R: Read>(reader: &mut R) -> Result<Vec<u8>> {
+	let mut buf = Vec::with_capacity(SEGMENT_SIZE);
+	reader.take(SEGMENT_SIZE as u64).read_to_end(&mut buf)?;
+	Ok(buf)
+}
@@ -0,0 +1,140 @@
+use crate::audit::{AuditEntry, AuditOperation};
+use crate::dir::dir_prefix;
+use crate::error::{Result, S3DemoError};
+use crate::filter::KeyFilter;
+use crate::key::is_xml_safe;
+use crate::S3Bucket;
+use aws_sdk_s3::model::{Delete, ObjectIdentifier};
+use std::collections::HashSet;
+use tracing::{instrument, warn};
+
+// -- constants
+/// S3 maximum number of keys per DeleteObjects request.
+pub const MAX_DELETE_BATCH: usize = 1000;
+
+impl S3Bucket {
+	/// Delete the object `key` (S3 does not fail if the key does not exist).
+	#[instrument(name = "delete", skip_all, fields(bucket = %self.name(), key = %key))]
+	pub async fn delete_object(&self, key: &str) -> Result<()> {
+		// BUILD & EXECUTE - aws request
+		let res = self
+			.client()
+			.delete_object()
+			.bucket(self.name())
+			.key(key)
+			.send()
+			.await?;
+
+		// AUDIT
+		let mut entry = AuditEntry::new(AuditOperation::Delete, self.name(), key);
+		entry.version_id = res.version_id().map(|v| v.to_string());
+		self.audit(entry)
+	}
+
+	/// Permanently delete the `version_id` version (or delete marker) of the object `key`.
+	#[instrument(name = "delete", skip_all, fields(bucket = %self.name(), key = %key, version_id = %version_id))]
+	pub async fn delete_object_version(&self, key: &str, version_id: &str) -> Result<()> {
+		// BUILD & EXECUTE - aws request
+		self.client()
+			.delete_object()
+			.bucket(self.name())
+			.key(key)
+			.version_id(version_id)
+			.send()
+			.await?;
+
+		// AUDIT
+		let mut entry = AuditEntry::new(AuditOperation::Delete, self.name(), key);
+		entry.version_id = Some(version_id.to_string());
+		self.audit(entry)
+	}
+
+	/// Delete the `keys` with batched DeleteObjects requests (up to 1000 keys per request).
+	/// Fails if any of the keys could not be deleted (the others are still deleted).
+	pub async fn delete_objects(&self, keys: &[String]) -> Result<()> {
+		let objects = keys
+			.iter()
+			.map(|key| ObjectIdentifier::builder().key(key).build())
+			.collect::<Vec<_>>();
+		self.delete_identifiers(objects).await
+	}
+
+	/// Permanently delete the `(key, version_id)` versions, batched like [`S3Bucket::delete_objects`].
+	pub async fn delete_object_versions(&self, versions: &[(String, String)]) -> Result<()> {
+		let objects = versions
+			.iter()
+			.map(|(key, version_id)| ObjectIdentifier::builder().key(key).version_id(version_id).build())
+			.collect::<Vec<_>>();
+		self.delete_identifiers(objects).await
+	}
+
+	#[instrument(name = "delete_batch", skip_all, fields(bucket = %self.name(), count = objects.len()))]
+	async fn delete_identifiers(&self, objects: Vec<ObjectIdentifier>) -> Result<()> {
+		let mut errors: Vec<String> = Vec::new();
+		let total = objects.len();
+
+		// DELETE - one at a time, the keys which cannot be in the XML body (e.g., with control characters)
+		let (objects, unsafe_objects): (Vec<_>, Vec<_>) = objects
+			.into_iter()
+			.partition(|o| is_xml_safe(o.key().unwrap_or_default()));
+		for object in unsafe_objects {
+			let key = object.key().unwrap_or_default();
+			let res = match object.version_id() {
+				Some(version_id) => self.delete_object_version(key, version_id).await,
+				None => self.delete_object(key).await,
+			};
+			if let Err(ex) = res {
+				warn!(key, "key not deleted: {ex}");
+				errors.push(format!("{key:?}: {ex}"));
+			}
+		}
+
+		for batch in objects.chunks(MAX_DELETE_BATCH) {
+			// BUILD - aws request
+			let delete = Delete::builder().set_objects(Some(batch.to_vec())).quiet(true).build();
+			let req = self.client().delete_objects().bucket(self.name()).delete(delete);
+
+			// EXECUTE
+			let res = req.send().await?;
+
+			// COLLECT - the per key errors
+			let mut failed = HashSet::new();
+			for err in res.errors().unwrap_or_default() {
+				failed.insert((err.key(), err.version_id()));
+				warn!(
+					key = err.key().unwrap_or_default(),
+					code = err.code().unwrap_or_default(),
+					"key not deleted"
+				);
+				errors.push(format!(
+					"{}: {} {}",
+					err.key().unwrap_or_default(),
+					err.code().unwrap_or_default(),
+					err.message().unwrap_or_default()
+				));
+			}
+
+			// AUDIT - the deleted keys (the quiet response only lists the errors)
+			for object in batch.iter().filter(|o| !failed.contains(&(o.key(), o.version_id()))) {
+				let mut entry = AuditEntry::new(AuditOperation::Delete, self.name(), object.key().unwrap_or_default());
+				entry.version_id = object.version_id().map(|v| v.to_string());
+				self.audit(entry)?;
+			}
+		}
+
+		if errors.is_empty() {
+			Ok(())
+		} else {
+			Err(S3DemoError::DeleteFailed { errors, total })
+		}
+	}
+
+	/// Delete all the objects under the `prefix` "directory" (or the whole bucket if empty)
+	/// matching `filter` (on the full key). Returns the deleted keys.
+	pub async fn delete_prefix(&self, prefix: &str, filter: &KeyFilter) -> Result<Vec<String>> {
+		let keys = self.list(&dir_prefix(prefix)).await?;
+		let keys = keys.into_iter().filter(|k| filter.is_match(k)).collect::<Vec<_>>();
+		self.delete_objects(&keys).await?;
+		Ok(keys)
+	}
+}
@@ -0,0 +1,199 @@
+use crate::bucket::{until_deadline, PutAttrs};
+use crate::checksum::md5_hex;
+use crate::copy::copy_source;
+use crate::error::{io_context, Result, S3DemoError};
+use crate::multipart::{read_part, MAX_PARTS};
+use crate::upload_state::PartState;
+use crate::S3Bucket;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tracing::{debug, info, warn};
+
+/// The part MD5s of an object uploaded with delta uploads (see [`S3Bucket::with_delta_uploads`]),
+/// so the next upload of a modified file copies the unchanged parts instead of sending them again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PartManifest {
+	pub bucket: String,
+	pub key: String,
+	/// ETag of the object when uploaded, which must not change for its parts to be reused.
+	pub e_tag: String,
+	pub part_size: usize,
+	/// The hex MD5 of each part, in order.
+	pub part_md5s: Vec<String>,
+}
+
+/// Directory of the [`PartManifest`] json files (one per bucket/key).
+#[derive(Debug, Clone)]
+pub(crate) struct PartManifestStore {
+	dir: PathBuf,
+}
+
+impl PartManifestStore {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		PartManifestStore { dir: dir.into() }
+	}
+
+	pub fn load(&self, bucket: &str, key: &str) -> Option<PartManifest> {
+		let content = fs::read_to_string(self.manifest_file(bucket, key)).ok()?;
+		serde_json::from_str(&content).ok()
+	}
+
+	pub fn save(&self, manifest: &PartManifest) -> Result<()> {
+		fs::create_dir_all(&self.dir)
+			.map_err(|ex| io_context(ex, format!("Cannot create state dir {}", self.dir.display())))?;
+		let file = self.manifest_file(&manifest.bucket, &manifest.key);
+
+		// Note: write then rename, so an interruption never leaves a truncated manifest.
+		let tmp_file = file.with_extension("json.tmp");
+		fs::write(&tmp_file, serde_json::to_string_pretty(manifest)?)?;
+		fs::rename(&tmp_file, &file)?;
+
+		Ok(())
+	}
+
+	fn manifest_file(&self, bucket: &str, key: &str) -> PathBuf {
+		self.dir
+			.join(format!("{}.json", md5_hex(format!("{bucket}\n{key}").as_bytes())))
+	}
+}
+
+/// Default directory of the delta upload part manifests (`<local data dir>/s3demo/parts`).
+pub fn default_part_manifest_dir() -> PathBuf {
+	dirs::data_local_dir()
+		.unwrap_or_else(std::env::temp_dir)
+		.join("s3demo")
+		.join("parts")
+}
+
+impl S3Bucket {
+	/// Multipart upload of the file at `path` reusing the unchanged parts of the existing `key` object:
+	/// a part with the same MD5 as the one previously uploaded is copied server-side (UploadPartCopy),
+	/// only the changed ones are sent. Without a manifest (or when the object changed since), all the parts are sent.
+	///
+	/// Note: the parts are fixed-size blocks, so this helps for in-place changes, not for inserted bytes.
+	pub(crate) async fn upload_delta(
+		&self,
+		path: &Path,
+		key: &str,
+		attrs: &PutAttrs,
+		store: &PartManifestStore,
+	) -> Result<()> {
+		// LOAD - the manifest, if still for the current object
+		let previous = match store.load(self.name(), key) {
+			Some(manifest) => match self.stat(key).await {
+				Ok(stat) if stat.e_tag.as_deref() == Some(manifest.e_tag.as_str()) => Some(manifest),
+				Ok(_) => {
+					debug!("object changed since its manifest, full upload");
+					None
+				}
+				Err(S3DemoError::NotFound(_)) => None,
+				Err(ex) => return Err(ex),
+			},
+			None => None,
+		};
+		// Note: the previous part size, unless too small for the file now (then no part is copied).
+		let file_size = path.metadata()?.len();
+		let min_part_size = self.part_size_for(file_size);
+		let part_size = previous
+			.as_ref()
+			.map(|m| m.part_size)
+			.filter(|part_size| *part_size >= min_part_size)
+			.unwrap_or(min_part_size);
+
+		// EXECUTE - the multipart upload (aborted on failure)
+		let upload_id = until_deadline(attrs.deadline, key, self.create_multipart(key, attrs)).await?;
+		let res = until_deadline(
+			attrs.deadline,
+			key,
+			self.upload_delta_parts(path, key, &upload_id, part_size, previous.as_ref()),
+		)
+		.await;
+		let (parts, part_md5s) = match res {
+			Ok(parts) => parts,
+			Err(ex) => return Err(self.abort_delta(key, &upload_id, ex).await),
+		};
+		let res = until_deadline(
+			attrs.deadline,
+			key,
+			self.complete_multipart(key, &upload_id, &parts, file_size),
+		)
+		.await;
+		let e_tag = match res {
+			Ok(e_tag) => e_tag,
+			Err(ex) => return Err(self.abort_delta(key, &upload_id, ex).await),
+		};
+
+		// SAVE - the manifest for the next upload
+		if let Some(e_tag) = e_tag {
+			store.save(&PartManifest {
+				bucket: self.name().to_string(),
+				key: key.to_string(),
+				e_tag,
+				part_size,
+				part_md5s,
+			})?;
+		}
+
+		Ok(())
+	}
+
+	/// Copy or upload each part of the file. Returns the parts and their hex MD5s.
+	async fn upload_delta_parts(
+		&self,
+		path: &Path,
+		key: &str,
+		upload_id: &str,
+		part_size: usize,
+		previous: Option<&PartManifest>,
+	) -> Result<(Vec<PartState>, Vec<String>)> {
+		let mut file = File::open(path).await?;
+		let mut parts = Vec::new();
+		let mut part_md5s = Vec::new();
+		let mut copied = 0;
+
+		loop {
+			let buf = read_part(&mut file, part_size).await?;
+			if buf.is_empty() {
+				break;
+			}
+			if parts.len() >= MAX_PARTS {
+				return Err(S3DemoError::InvalidInput(format!(
+					"File {} needs more than {MAX_PARTS} parts of {part_size} bytes",
+					path.display()
+				)));
+			}
+			self.check_shutdown(key)?;
+			let index = parts.len();
+			let part_number = index as i32 + 1;
+			let buf_len = buf.len() as u64;
+			let md5 = md5_hex(&buf);
+
+			let unchanged = previous.and_then(|m| m.part_md5s.get(index)) == Some(&md5);
+			let part = if unchanged {
+				copied += 1;
+				let start = (index * part_size) as u64;
+				let range = (start, start + buf_len - 1);
+				let copy_source = copy_source(self.name(), key, None);
+				self.upload_part_copy(self.name(), key, upload_id, part_number, &copy_source, range)
+					.await?
+			} else {
+				self.upload_part(key, upload_id, part_number, buf).await?
+			};
+			parts.push(part);
+			part_md5s.push(md5);
+			self.progress_advance(key, buf_len);
+		}
+
+		info!(parts = parts.len(), copied, "delta upload");
+		Ok((parts, part_md5s))
+	}
+
+	async fn abort_delta(&self, key: &str, upload_id: &str, ex: S3DemoError) -> S3DemoError {
+		// Note: best effort, the original error is the one reported.
+		warn!(key = %key, upload_id = %upload_id, error = %ex, "aborting failed delta upload");
+		let _ = self.abort_multipart_upload(key, upload_id).await;
+		ex
+	}
+}
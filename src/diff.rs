@@ -0,0 +1,148 @@
+use crate::checksum::{file_md5, file_sha256};
+use crate::dir::{dir_prefix, is_hidden_key, list_files, load_ignore_rules};
+use crate::error::{Result, S3DemoError};
+use crate::store::ObjectStore;
+use crate::UploadDirOptions;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{info, instrument};
+
+/// Why a local file and its object differ, in a [`DirDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffReason {
+	Size,
+	/// Same size, different content MD5 (ETag) or SHA-256 ([`crate::META_SHA256`] metadata).
+	Checksum,
+}
+
+/// A local file different from its object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffEntry {
+	/// The path relative to the directory (the key relative to the prefix).
+	pub path: String,
+	pub reason: DiffReason,
+	pub local_size: u64,
+	pub remote_size: u64,
+}
+
+/// The differences between a local directory and a bucket prefix (see [`ObjectStore::diff`]),
+/// by path relative to the directory (sorted).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DirDiff {
+	/// The files without an object.
+	pub only_local: Vec<String>,
+	/// The objects without a file.
+	pub only_remote: Vec<String>,
+	pub different: Vec<DiffEntry>,
+	/// The files of the size of their object, but without a checksum to compare
+	/// (neither a content MD5 ETag nor the SHA-256 metadata, e.g., some multipart uploads).
+	pub unverified: Vec<String>,
+	/// Number of files identical to their object.
+	pub identical: usize,
+}
+
+impl DirDiff {
+	/// Whether the directory and the prefix have the same files (the unverified ones aside).
+	pub fn is_empty(&self) -> bool {
+		self.only_local.is_empty() && self.only_remote.is_empty() && self.different.is_empty()
+	}
+}
+
+/// See [`ObjectStore::diff`].
+#[instrument(name = "diff", skip_all, fields(bucket = %store.name(), dir = %local_dir.display(), prefix = %prefix))]
+pub(crate) async fn dir_diff<S: ObjectStore + ?Sized>(
+	store: &S,
+	local_dir: &Path,
+	prefix: &str,
+	opts: &UploadDirOptions,
+) -> Result<DirDiff> {
+	// VALIDATE
+	if !local_dir.is_dir() {
+		return Err(S3DemoError::InvalidInput(format!(
+			"Path {} is not a directory",
+			local_dir.display()
+		)));
+	}
+
+	// COLLECT - remote objects by relative key (as the sync, the hidden ones only with the hidden files)
+	let prefix = dir_prefix(prefix);
+	let ignore = load_ignore_rules(local_dir, opts.no_ignore_file)?;
+	let mut remotes = store
+		.list_objects(&prefix)
+		.await?
+		.into_iter()
+		.filter(|o| !o.key.ends_with('/'))
+		.filter_map(|o| Some((o.key.strip_prefix(&prefix)?.to_string(), o)))
+		.filter(|(rel_key, _)| opts.filter.is_match(rel_key))
+		.filter(|(rel_key, _)| !ignore.as_ref().is_some_and(|ignore| ignore.is_ignored_key(rel_key)))
+		.filter(|(rel_key, _)| opts.include_hidden || !is_hidden_key(rel_key))
+		.collect::<HashMap<_, _>>();
+
+	// COMPARE - size first, then the content MD5 of the listing ETag
+	let mut diff = DirDiff::default();
+	let mut by_sha256 = Vec::new();
+	for (path, rel_path) in list_files(local_dir, opts)? {
+		let Some(remote) = remotes.remove(&rel_path) else {
+			diff.only_local.push(rel_path);
+			continue;
+		};
+		let local_size = path.metadata()?.len();
+		let reason = if local_size != remote.size {
+			Some(DiffReason::Size)
+		} else if let Some(remote_md5) = remote.content_md5() {
+			(file_md5(&path)? != remote_md5).then_some(DiffReason::Checksum)
+		} else {
+			by_sha256.push((path, rel_path, remote.key, local_size));
+			continue;
+		};
+		match reason {
+			Some(reason) => diff.different.push(DiffEntry {
+				path: rel_path,
+				reason,
+				local_size,
+				remote_size: remote.size,
+			}),
+			None => diff.identical += 1,
+		}
+	}
+	diff.only_remote = remotes.into_keys().collect();
+
+	// COMPARE - the SHA-256 metadata of the objects without a content MD5 ETag (one HEAD each)
+	let sha256s = stream::iter(by_sha256)
+		.map(|(path, rel_path, key, size)| async move {
+			Ok::<_, S3DemoError>((path, rel_path, size, store.object_sha256(&key).await?))
+		})
+		.buffer_unordered(store.concurrency())
+		.try_collect::<Vec<_>>()
+		.await?;
+	for (path, rel_path, size, sha256) in sha256s {
+		match sha256 {
+			Some(sha256) if file_sha256(&path)? != sha256 => diff.different.push(DiffEntry {
+				path: rel_path,
+				reason: DiffReason::Checksum,
+				local_size: size,
+				remote_size: size,
+			}),
+			Some(_) => diff.identical += 1,
+			None => diff.unverified.push(rel_path),
+		}
+	}
+
+	diff.only_local.sort();
+	diff.only_remote.sort();
+	diff.different.sort_by(|a, b| a.path.cmp(&b.path));
+	diff.unverified.sort();
+
+	info!(
+		only_local = diff.only_local.len(),
+		only_remote = diff.only_remote.len(),
+		different = diff.different.len(),
+		identical = diff.identical,
+		"diff"
+	);
+
+	Ok(diff)
+}
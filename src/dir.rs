@@ -0,0 +1,251 @@
+use crate::error::{Result, S3DemoError};
+use crate::filter::KeyFilter;
+use crate::ignore::IgnoreRules;
+use crate::store::ObjectStore;
+use crate::symlink::SymlinkMode;
+use crate::transfer::{into_transfers, Transfer, TransferManager};
+use std::path::{Component, Path, PathBuf, MAIN_SEPARATOR};
+use tracing::{instrument, warn};
+use walkdir::{DirEntry, WalkDir};
+
+/// Options for [`ObjectStore::upload_dir`].
+#[derive(Debug, Clone, Default)]
+pub struct UploadDirOptions {
+	/// Also upload the hidden files and directories (names starting with `.`).
+	pub include_hidden: bool,
+	/// Filter on the file paths relative to the directory.
+	pub filter: KeyFilter,
+	/// What to do with the symbolic links (skipped by default).
+	pub symlinks: SymlinkMode,
+	/// Do not honor the [`crate::IGNORE_FILE`] of the directory (if any).
+	pub no_ignore_file: bool,
+}
+
+/// See [`ObjectStore::upload_dir`].
+#[instrument(name = "upload_dir", skip_all, fields(bucket = %store.name(), dir = %local_dir.display(), prefix = %key_prefix))]
+pub(crate) async fn upload_dir<S: ObjectStore + ?Sized>(
+	store: &S,
+	local_dir: &Path,
+	key_prefix: &str,
+	opts: &UploadDirOptions,
+) -> Result<Vec<String>> {
+	let transfers = upload_dir_plan(local_dir, key_prefix, opts)?;
+
+	// UPLOAD - concurrently
+	let results = TransferManager::new(store).run(transfers).await;
+	let keys = into_transfers(results)?
+		.into_iter()
+		.map(|t| t.key().to_string())
+		.collect();
+
+	Ok(keys)
+}
+
+/// See [`ObjectStore::download_prefix`].
+#[instrument(name = "download_prefix", skip_all, fields(bucket = %store.name(), prefix = %prefix, dir = %dest_dir.display()))]
+pub(crate) async fn download_prefix<S: ObjectStore + ?Sized>(
+	store: &S,
+	prefix: &str,
+	dest_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+	let transfers = download_prefix_plan(store, prefix, dest_dir).await?;
+
+	// DOWNLOAD - concurrently
+	let results = TransferManager::new(store).run(transfers).await;
+	let files = into_transfers(results)?
+		.into_iter()
+		.map(|t| t.path().to_path_buf())
+		.collect();
+
+	Ok(files)
+}
+
+/// See [`ObjectStore::download_prefix_plan`].
+pub(crate) async fn download_prefix_plan<S: ObjectStore + ?Sized>(
+	store: &S,
+	prefix: &str,
+	dest_dir: &Path,
+) -> Result<Vec<Transfer>> {
+	// VALIDATE
+	if !dest_dir.is_dir() {
+		return Err(S3DemoError::InvalidInput(format!(
+			"Path {} is not a directory",
+			dest_dir.display()
+		)));
+	}
+
+	// COLLECT - the keys (skipping the "folder" placeholder keys)
+	let objects = store.list_objects(prefix).await?;
+	let transfers = objects
+		.into_iter()
+		.map(|o| o.key)
+		.filter(|k| !k.ends_with('/'))
+		.map(|key| {
+			Ok(Transfer::Download {
+				path: key_path(dest_dir, &key)?,
+				key,
+			})
+		})
+		.collect::<Result<_>>()?;
+
+	Ok(transfers)
+}
+
+/// The uploads of a [`ObjectStore::upload_dir`], without executing them.
+pub fn upload_dir_plan(local_dir: &Path, key_prefix: &str, opts: &UploadDirOptions) -> Result<Vec<Transfer>> {
+	// VALIDATE
+	if !local_dir.is_dir() {
+		return Err(S3DemoError::InvalidInput(format!(
+			"Path {} is not a directory",
+			local_dir.display()
+		)));
+	}
+
+	// COLLECT - the files to upload
+	let transfers = list_files(local_dir, opts)?
+		.into_iter()
+		.map(|(path, rel_path)| Transfer::Upload {
+			key: join_key(key_prefix, &rel_path),
+			path,
+		})
+		.collect();
+
+	Ok(transfers)
+}
+
+/// Returns the (full path, relative path with `/` separators) of the files under `dir`.
+pub(crate) fn list_files(dir: &Path, opts: &UploadDirOptions) -> Result<Vec<(PathBuf, String)>> {
+	let include_hidden = opts.include_hidden;
+	let ignore = load_ignore_rules(dir, opts.no_ignore_file)?;
+	let is_ignored = |e: &DirEntry| match (&ignore, rel_path(dir, e.path())) {
+		(Some(ignore), Some(rel_path)) => ignore.is_ignored(&rel_path, e.file_type().is_dir()),
+		// Note: a non UTF-8 name is kept, to be rejected below.
+		_ => false,
+	};
+	let walker = WalkDir::new(dir)
+		.follow_links(opts.symlinks == SymlinkMode::Follow)
+		.into_iter()
+		.filter_entry(|e| e.depth() == 0 || ((include_hidden || !is_hidden(e)) && !is_ignored(e)));
+
+	let mut files = Vec::new();
+	for entry in walker {
+		let entry = match entry {
+			Ok(entry) => entry,
+			// Note: only when following the links (otherwise the links are not read).
+			Err(ex) if ex.loop_ancestor().is_some() || ex.path().is_some_and(|p| p.is_symlink()) => {
+				warn!(path = ?ex.path(), "skipping the broken or looping symlink: {ex}");
+				continue;
+			}
+			Err(ex) => return Err(ex.into()),
+		};
+		let is_link = entry.path_is_symlink() && opts.symlinks != SymlinkMode::Follow;
+		if is_link && opts.symlinks == SymlinkMode::Skip {
+			warn!(path = ?entry.path(), "skipping the symlink (see the symlink modes)");
+			continue;
+		}
+		if !is_link && !entry.file_type().is_file() {
+			continue;
+		}
+		// Note: the walked entries are all under `dir`, so only a non UTF-8 name has no key.
+		let Some(rel_path) = rel_path(dir, entry.path()) else {
+			return Err(S3DemoError::InvalidInput(format!(
+				"Invalid path {:?} (not UTF-8, so no key)",
+				entry.path()
+			)));
+		};
+		if !opts.filter.is_match(&rel_path) {
+			continue;
+		}
+		files.push((entry.into_path(), rel_path));
+	}
+
+	Ok(files)
+}
+
+/// The [`crate::IGNORE_FILE`] rules of `dir` (None if there is none, or with `no_ignore_file`).
+pub(crate) fn load_ignore_rules(dir: &Path, no_ignore_file: bool) -> Result<Option<IgnoreRules>> {
+	if no_ignore_file {
+		return Ok(None);
+	}
+	IgnoreRules::load(dir)
+}
+
+/// The path relative to `dir`, with `/` separators (None if not under `dir`, or not valid UTF-8).
+pub(crate) fn rel_path(dir: &Path, path: &Path) -> Option<String> {
+	let rel_path = path.strip_prefix(dir).ok()?;
+	let rel_path = rel_path
+		.components()
+		.map(|c| c.as_os_str().to_str())
+		.collect::<Option<Vec<_>>>()?
+		.join("/");
+	Some(rel_path)
+}
+
+/// The key of the local `path`, with `/` separators (e.g., `docs\b.md` on Windows is `docs/b.md`).
+/// None if the path is not valid UTF-8.
+///
+/// Note: on Unix, a `\` is a valid file name character, so it is kept.
+pub fn path_key(path: &Path) -> Option<String> {
+	let path = path.to_str()?;
+	if MAIN_SEPARATOR == '/' {
+		Some(path.to_string())
+	} else {
+		Some(path.replace(MAIN_SEPARATOR, "/"))
+	}
+}
+
+/// The local path of the (relative) `key` under `dir`, with the platform separators
+/// (e.g., `docs/b.md` is `<dir>\docs\b.md` on Windows).
+///
+/// The empty and `.` names are skipped (e.g., `/docs//./b.md` is `<dir>/docs/b.md`), and the keys which could
+/// escape `dir` or are not valid file names are rejected: `..`, a drive or root (e.g., `C:`), a `\` on Windows,
+/// or a control character.
+pub fn key_path(dir: &Path, key: &str) -> Result<PathBuf> {
+	let unsafe_key =
+		|reason: &str| S3DemoError::InvalidInput(format!("Unsafe key {key:?} for a local path ({reason})"));
+
+	let mut path = dir.to_path_buf();
+	for name in key.split('/').filter(|name| !name.is_empty() && *name != ".") {
+		if name.chars().any(char::is_control) {
+			return Err(unsafe_key("control character"));
+		}
+		// Note: a single normal component, so not `..`, nor a prefix/root, nor a name with `\` on Windows.
+		let mut components = Path::new(name).components();
+		if !matches!(
+			(components.next(), components.next()),
+			(Some(Component::Normal(_)), None)
+		) {
+			return Err(unsafe_key("outside of the directory"));
+		}
+		path.push(name);
+	}
+	if path == dir {
+		return Err(unsafe_key("no file name"));
+	}
+
+	Ok(path)
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+	entry.file_name().to_str().map(|s| s.starts_with('.')).unwrap_or(false)
+}
+
+/// A key with a hidden "directory" or "file" name (starting with `.`).
+pub(crate) fn is_hidden_key(rel_key: &str) -> bool {
+	rel_key.split('/').any(|name| name.starts_with('.'))
+}
+
+/// Join a key prefix (treated as a directory) and a relative key.
+pub fn join_key(prefix: &str, rel_key: &str) -> String {
+	let prefix = prefix.trim_end_matches('/');
+	if prefix.is_empty() {
+		rel_key.to_string()
+	} else {
+		format!("{prefix}/{rel_key}")
+	}
+}
+
+/// The prefix as a "directory" prefix (i.e., ending with `/`, or empty).
+pub(crate) fn dir_prefix(prefix: &str) -> String {
+	join_key(prefix, "")
+}
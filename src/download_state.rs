@@ -0,0 +1,70 @@
+use crate::error::{io_context, Result};
+use crate::upload_state::{mtime_secs, state_file_id};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The ETag of a downloaded object, with the fingerprint of its local file, so the next download
+/// of the object is conditional (see [`crate::S3Bucket::with_download_etags`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct DownloadState {
+	pub bucket: String,
+	pub key: String,
+	pub path: PathBuf,
+	pub e_tag: String,
+	/// The object last modified (epoch seconds).
+	pub last_modified: Option<i64>,
+	/// Fingerprint of the downloaded file, which must not change for the object download to be skipped.
+	pub file_size: u64,
+	pub file_mtime: u64,
+}
+
+impl DownloadState {
+	/// Whether the local file is still the one downloaded.
+	pub fn is_file_unchanged(&self, path: &Path) -> bool {
+		let file_size = path.metadata().map(|m| m.len()).ok();
+		file_size == Some(self.file_size) && mtime_secs(path) == self.file_mtime
+	}
+}
+
+/// Directory of the [`DownloadState`] json files (one per bucket/key/path).
+#[derive(Debug, Clone)]
+pub(crate) struct DownloadStateStore {
+	dir: PathBuf,
+}
+
+impl DownloadStateStore {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		DownloadStateStore { dir: dir.into() }
+	}
+
+	pub fn load(&self, bucket: &str, key: &str, path: &Path) -> Option<DownloadState> {
+		let content = fs::read_to_string(self.state_file(bucket, key, path)).ok()?;
+		serde_json::from_str(&content).ok()
+	}
+
+	pub fn save(&self, state: &DownloadState) -> Result<()> {
+		fs::create_dir_all(&self.dir)
+			.map_err(|ex| io_context(ex, format!("Cannot create state dir {}", self.dir.display())))?;
+		let file = self.state_file(&state.bucket, &state.key, &state.path);
+
+		// Note: write then rename, so an interruption never leaves a truncated state file.
+		let tmp_file = file.with_extension("json.tmp");
+		fs::write(&tmp_file, serde_json::to_string_pretty(state)?)?;
+		fs::rename(&tmp_file, &file)?;
+
+		Ok(())
+	}
+
+	fn state_file(&self, bucket: &str, key: &str, path: &Path) -> PathBuf {
+		self.dir.join(format!("{}.json", state_file_id(bucket, key, path)))
+	}
+}
+
+/// Default directory of the download ETags (`<local data dir>/s3demo/downloads`).
+pub fn default_download_state_dir() -> PathBuf {
+	dirs::data_local_dir()
+		.unwrap_or_else(std::env::temp_dir)
+		.join("s3demo")
+		.join("downloads")
+}
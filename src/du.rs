@@ -0,0 +1,42 @@
+use crate::dir::dir_prefix;
+use crate::error::Result;
+use crate::S3Bucket;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The object count and total size of a "directory" (see [`S3Bucket::du`]).
+#[derive(Debug, Clone, Serialize)]
+pub struct PrefixUsage {
+	/// The "directory" prefix (ending with `/`), or the listed prefix itself for its immediate objects.
+	pub prefix: String,
+	pub objects: u64,
+	pub bytes: u64,
+}
+
+impl S3Bucket {
+	/// The usage of each top-level "directory" under the `prefix` "directory" (sorted by prefix),
+	/// with one more entry (the prefix itself) for the immediate objects, if any.
+	pub async fn du(&self, prefix: &str) -> Result<Vec<PrefixUsage>> {
+		let prefix = dir_prefix(prefix);
+		let objects = self.list_objects(&prefix).await?;
+
+		// AGGREGATE - by the first "directory" after the prefix
+		let mut usages: BTreeMap<String, PrefixUsage> = BTreeMap::new();
+		for object in objects {
+			let rel_key = object.key.strip_prefix(&prefix).unwrap_or(&object.key);
+			let group = match rel_key.split_once('/') {
+				Some((dir, _)) => format!("{prefix}{dir}/"),
+				None => prefix.clone(),
+			};
+			let usage = usages.entry(group).or_insert_with_key(|group| PrefixUsage {
+				prefix: group.clone(),
+				objects: 0,
+				bytes: 0,
+			});
+			usage.objects += 1;
+			usage.bytes += object.size;
+		}
+
+		Ok(usages.into_values().collect())
+	}
+}
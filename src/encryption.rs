@@ -0,0 +1,121 @@
+use crate::checksum::md5_base64;
+use crate::error::{io_context, Result, S3DemoError};
+use aws_sdk_s3::model::ServerSideEncryption;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// -- constants
+/// The only SSE-C algorithm supported by S3.
+const SSE_CUSTOMER_ALGORITHM: &str = "AES256";
+/// SSE-C (and client-side encryption) keys are 256 bits.
+const KEY_LEN: usize = 32;
+
+/// The server-side encryption of the uploaded (and copied) objects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Encryption {
+	/// SSE-S3, keys managed by S3 (`AES256`).
+	S3,
+	/// SSE-KMS, with the given KMS key (the AWS managed `aws/s3` key when None).
+	Kms { key_id: Option<String> },
+	/// SSE-C, with a customer-provided key (also required to download the objects).
+	Customer(CustomerKey),
+}
+
+impl Encryption {
+	/// The `x-amz-server-side-encryption` value (None for SSE-C, which has its own headers).
+	pub(crate) fn server_side_encryption(&self) -> Option<ServerSideEncryption> {
+		match self {
+			Encryption::S3 => Some(ServerSideEncryption::Aes256),
+			Encryption::Kms { .. } => Some(ServerSideEncryption::AwsKms),
+			Encryption::Customer(_) => None,
+		}
+	}
+
+	pub(crate) fn kms_key_id(&self) -> Option<String> {
+		match self {
+			Encryption::Kms { key_id } => key_id.clone(),
+			_ => None,
+		}
+	}
+
+	pub(crate) fn customer_key(&self) -> Option<&CustomerKey> {
+		match self {
+			Encryption::Customer(key) => Some(key),
+			_ => None,
+		}
+	}
+}
+
+/// A SSE-C customer-provided 256 bits key.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CustomerKey {
+	key: Vec<u8>,
+}
+
+impl CustomerKey {
+	pub fn new(key: &[u8]) -> Result<Self> {
+		check_key_len(key)?;
+		Ok(CustomerKey { key: key.to_vec() })
+	}
+
+	/// From the base64 of the key (e.g., from an env var).
+	pub fn from_base64(key_b64: &str) -> Result<Self> {
+		Self::new(&decode_key_base64(key_b64)?)
+	}
+
+	/// From a file holding the raw 32 bytes key, or its base64.
+	pub fn from_file(path: &Path) -> Result<Self> {
+		Self::new(&read_key_file(path)?)
+	}
+
+	/// `x-amz-server-side-encryption-customer-algorithm` value.
+	pub(crate) fn algorithm(&self) -> String {
+		SSE_CUSTOMER_ALGORITHM.to_string()
+	}
+
+	/// `x-amz-server-side-encryption-customer-key` value.
+	pub(crate) fn key_base64(&self) -> String {
+		BASE64.encode(&self.key)
+	}
+
+	/// `x-amz-server-side-encryption-customer-key-MD5` value.
+	pub(crate) fn key_md5(&self) -> String {
+		md5_base64(&self.key)
+	}
+}
+
+// Note: never print the key.
+impl fmt::Debug for CustomerKey {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("CustomerKey").field("key_md5", &self.key_md5()).finish()
+	}
+}
+
+pub(crate) fn decode_key_base64(key_b64: &str) -> Result<Vec<u8>> {
+	BASE64
+		.decode(key_b64.trim())
+		.map_err(|_| S3DemoError::InvalidInput("Key is not valid base64".to_string()))
+}
+
+/// Read a key file holding the raw 32 bytes key, or its base64.
+pub(crate) fn read_key_file(path: &Path) -> Result<Vec<u8>> {
+	let content = fs::read(path).map_err(|ex| io_context(ex, format!("Cannot read key file {}", path.display())))?;
+	if content.len() == KEY_LEN {
+		Ok(content)
+	} else {
+		decode_key_base64(&String::from_utf8_lossy(&content))
+	}
+}
+
+pub(crate) fn check_key_len(key: &[u8]) -> Result<()> {
+	if key.len() != KEY_LEN {
+		return Err(S3DemoError::InvalidInput(format!(
+			"Key must be {KEY_LEN} bytes (256 bits), got {}",
+			key.len()
+		)));
+	}
+	Ok(())
+}
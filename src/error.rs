@@ -0,0 +1,179 @@
+use aws_sdk_s3::SdkError;
+use aws_smithy_http::event_stream::RawMessage;
+use aws_smithy_types::retry::ProvideErrorKind;
+use std::io;
+
+pub type Result<T> = std::result::Result<T, S3DemoError>;
+
+/// The library errors, by failure kind (the S3 error responses are mapped from their status and code).
+#[derive(Debug, thiserror::Error)]
+pub enum S3DemoError {
+	/// The object, version or multipart upload does not exist (404, NoSuchKey, ...).
+	#[error("Not found: {0}")]
+	NotFound(String),
+
+	/// 403 AccessDenied (missing permission, wrong credentials, bucket policy, ...).
+	#[error("Access denied: {0}")]
+	AccessDenied(String),
+
+	/// Missing, invalid or expired credentials (e.g., an expired session, before any request).
+	#[error("Invalid credentials: {0}")]
+	Credentials(String),
+
+	#[error("No such bucket: {0}")]
+	NoSuchBucket(String),
+
+	/// 412 Precondition Failed, e.g., the key of a no-overwrite upload exists (see `ClientConfig::with_no_overwrite`).
+	#[error("Precondition failed: {0}")]
+	PreconditionFailed(String),
+
+	/// The transferred content does not match its checksum (`algorithm` is `SHA-256` or `MD5`).
+	#[error("Checksum mismatch for {key}: {algorithm} {actual} (expected {expected})")]
+	ChecksumMismatch {
+		key: String,
+		algorithm: &'static str,
+		expected: String,
+		actual: String,
+	},
+
+	#[error("Downloaded {key} has {actual} bytes, expected {expected}")]
+	SizeMismatch { key: String, expected: u64, actual: u64 },
+
+	/// Client-side encryption failure (e.g., wrong key, corrupted content, unsupported algorithm).
+	#[error("{0}")]
+	Crypto(String),
+
+	/// Invalid argument or state (path, key, configuration, ...).
+	#[error("{0}")]
+	InvalidInput(String),
+
+	/// An interrupted multipart upload, kept to be resumed (see `S3Bucket::with_resumable_uploads`).
+	#[error(
+		"Multipart upload of {key} interrupted after {parts} parts, run again to resume (or abort-multipart {key})"
+	)]
+	MultipartInterrupted {
+		key: String,
+		parts: usize,
+		#[source]
+		source: Box<S3DemoError>,
+	},
+
+	/// A `TransferHooks::before` hook rejected the transfer (e.g., a failing pre-transfer command).
+	#[error("Hook failed: {0}")]
+	HookFailed(String),
+
+	/// A notification was answered with an error status (see `Notifier::notify`).
+	#[error("Notification failed: {0}")]
+	NotificationFailed(String),
+
+	/// An audit log could not be written (see `S3Bucket::with_audit_log`), the operation itself being done.
+	#[error("Audit log failed: {0}")]
+	AuditFailed(String),
+
+	/// The transfer was stopped by the shutdown (see `S3Bucket::with_shutdown`).
+	#[error("Interrupted: {0}")]
+	Interrupted(String),
+
+	#[error("{} of {total} keys could not be deleted:\n{}", .errors.len(), .errors.join("\n"))]
+	DeleteFailed { errors: Vec<String>, total: usize },
+
+	#[error("{} of {total} transfers failed:\n{}", .errors.len(), .errors.join("\n"))]
+	TransfersFailed { errors: Vec<String>, total: usize },
+
+	/// A successful S3 response missing an expected value.
+	#[error("Invalid S3 response: {0}")]
+	InvalidResponse(String),
+
+	/// Any other S3 error response.
+	#[error("S3 error {code} (status {status}): {message}")]
+	Service { code: String, status: u16, message: String },
+
+	/// A connect or request timeout (see `ClientConfig`), or a transfer deadline (see `S3Bucket::with_transfer_deadline`).
+	#[error("Timed out: {0}")]
+	Timeout(String),
+
+	/// The request could not be sent, or the response not read (network, timeout, ...).
+	#[error(transparent)]
+	Transport(Box<dyn std::error::Error + Send + Sync>),
+
+	#[error(transparent)]
+	Io(#[from] io::Error),
+
+	#[error(transparent)]
+	Json(#[from] serde_json::Error),
+}
+
+impl<E> From<SdkError<E>> for S3DemoError
+where
+	E: ProvideErrorKind + std::error::Error + Send + Sync + 'static,
+{
+	fn from(err: SdkError<E>) -> Self {
+		match err {
+			SdkError::ServiceError { err, raw } => {
+				let status = raw.http().status().as_u16();
+				let code = err.code().unwrap_or("Unknown").to_string();
+				let message = err.to_string();
+				match (status, code.as_str()) {
+					(_, "NoSuchBucket") => S3DemoError::NoSuchBucket(message),
+					(404, _) | (_, "NoSuchKey" | "NoSuchVersion" | "NoSuchUpload") => S3DemoError::NotFound(message),
+					(403, _) | (_, "AccessDenied") => S3DemoError::AccessDenied(message),
+					(412, _) | (_, "PreconditionFailed") => S3DemoError::PreconditionFailed(message),
+					_ => S3DemoError::Service { code, status, message },
+				}
+			}
+			SdkError::TimeoutError(err) => S3DemoError::Timeout(err.to_string()),
+			SdkError::DispatchFailure(err) if err.is_timeout() => {
+				// Note: the source is the actual timeout (the connector error only prefixes it with "timeout:").
+				let message = std::error::Error::source(&err).map(|e| e.to_string());
+				S3DemoError::Timeout(message.unwrap_or_else(|| err.to_string()))
+			}
+			err => S3DemoError::Transport(Box::new(err)),
+		}
+	}
+}
+
+/// The errors of an event stream (e.g., S3 Select), received once the response started (status 200).
+impl<E> From<SdkError<E, RawMessage>> for S3DemoError
+where
+	E: ProvideErrorKind + std::error::Error + Send + Sync + 'static,
+{
+	fn from(err: SdkError<E, RawMessage>) -> Self {
+		match err {
+			SdkError::ServiceError { err, .. } => S3DemoError::Service {
+				code: err.code().unwrap_or("Unknown").to_string(),
+				status: 200,
+				message: err.to_string(),
+			},
+			err => S3DemoError::Transport(Box::new(err)),
+		}
+	}
+}
+
+impl From<aws_smithy_http::byte_stream::Error> for S3DemoError {
+	fn from(err: aws_smithy_http::byte_stream::Error) -> Self {
+		S3DemoError::Transport(Box::new(err))
+	}
+}
+
+/// Prefix the message of an io error (keeping its kind).
+pub(crate) fn io_context(err: io::Error, context: String) -> S3DemoError {
+	S3DemoError::Io(io::Error::new(err.kind(), format!("{context}: {err}")))
+}
+
+impl From<walkdir::Error> for S3DemoError {
+	fn from(err: walkdir::Error) -> Self {
+		S3DemoError::Io(err.into())
+	}
+}
+
+impl From<notify::Error> for S3DemoError {
+	fn from(err: notify::Error) -> Self {
+		S3DemoError::Io(io::Error::other(err))
+	}
+}
+
+impl From<rusqlite::Error> for S3DemoError {
+	fn from(err: rusqlite::Error) -> Self {
+		S3DemoError::Io(io::Error::other(format!("Sync index: {err}")))
+	}
+}
@@ -0,0 +1,86 @@
+use crate::error::Result;
+use std::collections::HashMap;
+use std::fs::{File, Metadata};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+// -- constants
+/// The metadata of the modification time of an uploaded file, in seconds since the epoch (e.g., `1700000000.5`),
+/// restored on download.
+pub const META_MTIME: &str = "mtime";
+/// The metadata of the Unix permission bits of an uploaded file, in octal (e.g., `755`),
+/// see [`crate::S3Bucket::with_preserve_mode`].
+pub const META_MODE: &str = "mode";
+
+/// The metadata of the attributes of the local file `meta` (the mtime, and the mode with `preserve_mode`).
+pub(crate) fn file_attrs_metadata(meta: &Metadata, preserve_mode: bool) -> HashMap<String, String> {
+	let mut metadata = HashMap::new();
+	if let Some(mtime) = meta.modified().ok().and_then(format_mtime) {
+		metadata.insert(META_MTIME.to_string(), mtime);
+	}
+	if let Some(mode) = file_mode(meta).filter(|_| preserve_mode) {
+		metadata.insert(META_MODE.to_string(), format!("{mode:o}"));
+	}
+	metadata
+}
+
+/// Set the attributes of the downloaded file at `path` from its object `metadata` (if any),
+/// the mode only with `preserve_mode`.
+pub(crate) fn restore_file_attrs(path: &Path, metadata: &HashMap<String, String>, preserve_mode: bool) -> Result<()> {
+	if let Some(mtime) = metadata.get(META_MTIME).and_then(|v| parse_mtime(v)) {
+		File::options().write(true).open(path)?.set_modified(mtime)?;
+	}
+	if let Some(mode) = metadata.get(META_MODE).filter(|_| preserve_mode) {
+		set_file_mode(path, mode)?;
+	}
+	Ok(())
+}
+
+/// The time of a [`META_MTIME`] value (None if invalid).
+pub fn parse_mtime(value: &str) -> Option<SystemTime> {
+	let (secs, frac) = value.split_once('.').unwrap_or((value, ""));
+	let secs = secs.parse::<u64>().ok()?;
+	// Note: the fraction digits as nanoseconds (e.g., `.5` is 500000000).
+	let frac = &frac[..frac.len().min(9)];
+	let nanos = match frac {
+		"" => 0,
+		_ => frac.parse::<u32>().ok()? * 10u32.pow(9 - frac.len() as u32),
+	};
+	SystemTime::UNIX_EPOCH.checked_add(Duration::new(secs, nanos))
+}
+
+fn format_mtime(mtime: SystemTime) -> Option<String> {
+	let since_epoch = mtime.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+	let (secs, nanos) = (since_epoch.as_secs(), since_epoch.subsec_nanos());
+	if nanos == 0 {
+		return Some(secs.to_string());
+	}
+	let nanos = format!("{nanos:09}");
+	Some(format!("{secs}.{}", nanos.trim_end_matches('0')))
+}
+
+#[cfg(unix)]
+fn file_mode(meta: &Metadata) -> Option<u32> {
+	use std::os::unix::fs::PermissionsExt;
+	Some(meta.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_meta: &Metadata) -> Option<u32> {
+	None
+}
+
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: &str) -> Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+	// Note: an invalid mode is ignored (not a reason to fail the download).
+	if let Ok(mode) = u32::from_str_radix(mode, 8) {
+		std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777))?;
+	}
+	Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: &str) -> Result<()> {
+	Ok(())
+}
@@ -0,0 +1,61 @@
+use crate::error::{Result, S3DemoError};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::Regex;
+
+/// Include/exclude filter on keys (or relative paths).
+///
+/// Globs follow the gitignore conventions: `*` does not cross `/`, `**` does
+/// (e.g., `**/*.jpg` matches `a.jpg` and `photos/2022/a.jpg`).
+///
+/// A key matches when it matches one of the includes (or there are none), none of the excludes,
+/// and the regex (if any).
+#[derive(Debug, Clone, Default)]
+pub struct KeyFilter {
+	include: Option<GlobSet>,
+	exclude: Option<GlobSet>,
+	regex: Option<Regex>,
+}
+
+impl KeyFilter {
+	pub fn new(includes: &[String], excludes: &[String]) -> Result<Self> {
+		Ok(KeyFilter {
+			include: build_glob_set(includes)?,
+			exclude: build_glob_set(excludes)?,
+			regex: None,
+		})
+	}
+
+	/// Also require the key to match `regex`.
+	pub fn with_regex(mut self, regex: &str) -> Result<Self> {
+		self.regex =
+			Some(Regex::new(regex).map_err(|ex| S3DemoError::InvalidInput(format!("Invalid regex {regex}: {ex}")))?);
+		Ok(self)
+	}
+
+	pub fn is_match(&self, key: &str) -> bool {
+		let included = self.include.as_ref().map(|set| set.is_match(key)).unwrap_or(true);
+		let excluded = self.exclude.as_ref().map(|set| set.is_match(key)).unwrap_or(false);
+		let regex_ok = self.regex.as_ref().map(|re| re.is_match(key)).unwrap_or(true);
+		included && !excluded && regex_ok
+	}
+}
+
+fn build_glob_set(globs: &[String]) -> Result<Option<GlobSet>> {
+	if globs.is_empty() {
+		return Ok(None);
+	}
+
+	let mut builder = GlobSetBuilder::new();
+	for glob in globs {
+		let glob = GlobBuilder::new(glob)
+			.literal_separator(true)
+			.build()
+			.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid glob {glob}: {ex}")))?;
+		builder.add(glob);
+	}
+
+	let set = builder
+		.build()
+		.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid globs: {ex}")))?;
+	Ok(Some(set))
+}
@@ -0,0 +1,91 @@
+use crate::error::{Result, S3DemoError};
+use crate::filter::KeyFilter;
+use crate::{ObjectInfo, S3Bucket};
+use globset::{GlobBuilder, GlobMatcher};
+use std::time::{Duration, SystemTime};
+use tracing::{instrument, Span};
+
+/// The predicates of [`S3Bucket::find`] (an object matches when it matches all the set ones).
+#[derive(Debug, Clone, Default)]
+pub struct FindQuery {
+	/// Size strictly above, in bytes.
+	pub larger_than: Option<u64>,
+	/// Size strictly below, in bytes.
+	pub smaller_than: Option<u64>,
+	/// Last modified longer ago than this.
+	pub older_than: Option<Duration>,
+	/// Last modified more recently than this.
+	pub newer_than: Option<Duration>,
+	/// Storage class, e.g., `GLACIER` (the objects listed without one are `STANDARD`).
+	pub storage_class: Option<String>,
+	/// Filter on the full keys.
+	pub filter: KeyFilter,
+	/// Glob on the object names (after the last `/`).
+	name: Option<GlobMatcher>,
+}
+
+impl FindQuery {
+	/// Also require the object name (after the last `/`) to match `glob` (e.g., `*.log`).
+	pub fn with_name(mut self, glob: &str) -> Result<Self> {
+		let glob = GlobBuilder::new(glob)
+			.literal_separator(true)
+			.build()
+			.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid glob {glob}: {ex}")))?;
+		self.name = Some(glob.compile_matcher());
+		Ok(self)
+	}
+
+	pub fn is_match(&self, obj: &ObjectInfo, now: SystemTime) -> bool {
+		if self.larger_than.is_some_and(|size| obj.size <= size)
+			|| self.smaller_than.is_some_and(|size| obj.size >= size)
+		{
+			return false;
+		}
+
+		// Note: the objects without a last modified never match the age predicates.
+		let age = obj.last_modified.and_then(|t| now.duration_since(t).ok());
+		let age_match = |check: fn(Duration, Duration) -> bool, limit: Option<Duration>| match (limit, age) {
+			(None, _) => true,
+			(Some(limit), Some(age)) => check(age, limit),
+			(Some(_), None) => false,
+		};
+		if !age_match(|age, limit| age > limit, self.older_than)
+			|| !age_match(|age, limit| age < limit, self.newer_than)
+		{
+			return false;
+		}
+
+		if let Some(storage_class) = &self.storage_class {
+			let obj_class = obj.storage_class.as_deref().unwrap_or("STANDARD");
+			if !obj_class.eq_ignore_ascii_case(storage_class) {
+				return false;
+			}
+		}
+
+		if let Some(name) = &self.name {
+			let obj_name = obj.key.rsplit('/').next().unwrap_or_default();
+			if !name.is_match(obj_name) {
+				return false;
+			}
+		}
+
+		self.filter.is_match(&obj.key)
+	}
+}
+
+impl S3Bucket {
+	/// The objects under `prefix` matching the `query`.
+	#[instrument(name = "find", skip_all, fields(bucket = %self.name(), prefix = %prefix, count))]
+	pub async fn find(&self, prefix: &str, query: &FindQuery) -> Result<Vec<ObjectInfo>> {
+		let now = SystemTime::now();
+		let objects = self
+			.list_objects(prefix)
+			.await?
+			.into_iter()
+			.filter(|o| query.is_match(o, now))
+			.collect::<Vec<_>>();
+		Span::current().record("count", objects.len());
+
+		Ok(objects)
+	}
+}
@@ -0,0 +1,13 @@
+/// The standard HTTP headers of the uploaded objects, returned as is on download
+/// (e.g., for a CDN in front of the bucket).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentHeaders {
+	/// e.g., `public, max-age=31536000, immutable`
+	pub cache_control: Option<String>,
+	/// e.g., `attachment; filename="report.pdf"`
+	pub content_disposition: Option<String>,
+	/// e.g., `gzip` for pre-compressed content
+	pub content_encoding: Option<String>,
+	/// e.g., `en-US`
+	pub content_language: Option<String>,
+}
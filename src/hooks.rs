@@ -0,0 +1,91 @@
+use crate::error::{Result, S3DemoError};
+use crate::transfer::Transfer;
+use crate::S3Bucket;
+use std::future::Future;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Callbacks around each file transfer, set with [`crate::S3Bucket::with_hooks`].
+///
+/// They run on a blocking thread (so they can run commands), and the transfers can run in parallel,
+/// so calls for different keys interleave.
+pub trait TransferHooks: Send + Sync {
+	/// Before the transfer, failed with the returned error when any (e.g., a virus scan rejecting the file).
+	fn before(&self, event: &HookEvent) -> Result<()>;
+
+	/// After the transfer (done or failed, but not rejected by `before`). An error is only logged.
+	fn after(&self, event: &HookEvent, status: &HookStatus) -> Result<()>;
+}
+
+/// The transfer given to the [`TransferHooks`].
+#[derive(Debug, Clone)]
+pub struct HookEvent {
+	pub bucket: String,
+	pub transfer: Transfer,
+	/// The local file size, when it exists (the file to upload, or the downloaded one in `after`).
+	pub size: Option<u64>,
+}
+
+/// The outcome of a transfer, for [`TransferHooks::after`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookStatus {
+	Ok,
+	Failed(String),
+}
+
+impl HookStatus {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			HookStatus::Ok => "ok",
+			HookStatus::Failed(_) => "failed",
+		}
+	}
+}
+
+impl S3Bucket {
+	/// Run `exec` (the transfer) between the `before` and `after` hooks (if any).
+	pub(crate) async fn hooked(&self, transfer: Transfer, exec: impl Future<Output = Result<()>>) -> Result<()> {
+		let Some(hooks) = self.hooks() else {
+			return exec.await;
+		};
+		let size = match &transfer {
+			Transfer::Upload { path, .. } => path.metadata().ok().map(|m| m.len()),
+			Transfer::Download { .. } => None,
+		};
+		let mut event = HookEvent {
+			bucket: self.name().to_string(),
+			transfer,
+			size,
+		};
+
+		// BEFORE - (rejecting the transfer on error)
+		run_blocking(hooks.clone(), event.clone(), |hooks, event| hooks.before(&event)).await?;
+
+		// TRANSFER
+		let res = exec.await;
+
+		// AFTER - (only logged on error)
+		event.size = event.transfer.path().metadata().ok().map(|m| m.len());
+		let status = match &res {
+			Ok(()) => HookStatus::Ok,
+			Err(ex) => HookStatus::Failed(ex.to_string()),
+		};
+		let key = event.transfer.key().to_string();
+		if let Err(ex) = run_blocking(hooks.clone(), event, move |hooks, event| hooks.after(&event, &status)).await {
+			warn!(%key, error = %ex, "after transfer hook failed");
+		}
+
+		res
+	}
+}
+
+async fn run_blocking(
+	hooks: Arc<dyn TransferHooks>,
+	event: HookEvent,
+	f: impl FnOnce(Arc<dyn TransferHooks>, HookEvent) -> Result<()> + Send + 'static,
+) -> Result<()> {
+	let key = event.transfer.key().to_string();
+	tokio::task::spawn_blocking(move || f(hooks, event))
+		.await
+		.map_err(|ex| S3DemoError::HookFailed(format!("hook of {key} panicked: {ex}")))?
+}
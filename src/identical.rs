@@ -0,0 +1,60 @@
+use crate::checksum::{file_md5, file_sha256, META_SHA256};
+use crate::error::{Result, S3DemoError};
+use crate::transfer::Transfer;
+use crate::S3Bucket;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::path::Path;
+use tracing::instrument;
+
+impl S3Bucket {
+	/// Whether the object `key` has the content of the file at `path`: the same size, and the same SHA-256
+	/// (`x-amz-meta-sha256` metadata), or else the same MD5 (ETag, when it is the content MD5).
+	/// False when the object does not exist or has no checksum to compare.
+	///
+	/// Note: with client-side encryption, the object is the ciphertext, so never identical.
+	#[instrument(level = "debug", skip_all, fields(key = %key))]
+	pub async fn is_identical(&self, path: &Path, key: &str) -> Result<bool> {
+		if self.client_encryption().is_some() {
+			return Ok(false);
+		}
+
+		// COMPARE - size first (from a HEAD), then the checksum
+		let stat = match self.stat(key).await {
+			Ok(stat) => stat,
+			Err(S3DemoError::NotFound(_)) => return Ok(false),
+			Err(ex) => return Err(ex),
+		};
+		if path.metadata()?.len() != stat.size {
+			return Ok(false);
+		}
+		if let Some(sha256) = stat.metadata.get(META_SHA256) {
+			return Ok(file_sha256(path)? == *sha256);
+		}
+		match stat.content_md5() {
+			Some(md5) => Ok(file_md5(path)? == md5),
+			None => Ok(false),
+		}
+	}
+
+	/// Split `transfers` into the ones to run and the uploads skipped as identical to their
+	/// object (see [`S3Bucket::is_identical`]), with one HEAD per upload (concurrently).
+	pub async fn skip_identical(&self, transfers: Vec<Transfer>) -> Result<(Vec<Transfer>, Vec<Transfer>)> {
+		let compared = stream::iter(transfers)
+			.map(|transfer| async move {
+				let identical = match &transfer {
+					Transfer::Upload { path, key } => self.is_identical(path, key).await?,
+					Transfer::Download { .. } => false,
+				};
+				Ok::<_, S3DemoError>((transfer, identical))
+			})
+			.buffered(self.concurrency())
+			.try_collect::<Vec<_>>()
+			.await?;
+
+		let (skipped, kept): (Vec<_>, Vec<_>) = compared.into_iter().partition(|(_, identical)| *identical);
+		let kept = kept.into_iter().map(|(t, _)| t).collect();
+		let skipped = skipped.into_iter().map(|(t, _)| t).collect();
+
+		Ok((kept, skipped))
+	}
+}
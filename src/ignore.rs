@@ -0,0 +1,95 @@
+use crate::error::{Result, S3DemoError};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::fs::read_to_string;
+use std::io;
+use std::path::Path;
+
+// -- constants
+/// The file of ignore rules honored at the root of the uploaded/synced directories (see [`IgnoreRules`]).
+pub const IGNORE_FILE: &str = ".s3ignore";
+
+/// The gitignore-style rules of an [`IGNORE_FILE`], on the paths relative to its directory (with `/` separators).
+///
+/// One pattern per line (the empty lines and the `#` comments are skipped):
+/// - `*.log` (no `/`) matches the names at any depth, `build/out` or `/secret.env` (with a `/`) from the root only.
+/// - `node_modules/` (trailing `/`) matches the directories only.
+/// - `!keep.log` re-includes what a previous pattern ignored (the last matching pattern wins).
+///
+/// Note: like git, the content of an ignored directory is ignored, whatever the following patterns.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+	set: GlobSet,
+	rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+	negated: bool,
+	dir_only: bool,
+}
+
+impl IgnoreRules {
+	pub fn parse(content: &str) -> Result<Self> {
+		let mut builder = GlobSetBuilder::new();
+		let mut rules = Vec::new();
+		for line in content.lines() {
+			let line = line.trim_end();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let (negated, pattern) = match line.strip_prefix('!') {
+				Some(pattern) => (true, pattern),
+				None => (false, line),
+			};
+			let (dir_only, pattern) = match pattern.strip_suffix('/') {
+				Some(pattern) => (true, pattern),
+				None => (false, pattern),
+			};
+			// Note: a pattern with a `/` is anchored to the root, otherwise it matches at any depth.
+			let glob = match pattern.strip_prefix('/') {
+				Some(pattern) => pattern.to_string(),
+				None if pattern.contains('/') => pattern.to_string(),
+				None => format!("**/{pattern}"),
+			};
+			let glob = GlobBuilder::new(&glob)
+				.literal_separator(true)
+				.build()
+				.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid {IGNORE_FILE} pattern {line}: {ex}")))?;
+			builder.add(glob);
+			rules.push(Rule { negated, dir_only });
+		}
+
+		let set = builder
+			.build()
+			.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid {IGNORE_FILE} patterns: {ex}")))?;
+		Ok(IgnoreRules { set, rules })
+	}
+
+	/// The rules of the [`IGNORE_FILE`] of `dir` (None if there is none).
+	pub fn load(dir: &Path) -> Result<Option<Self>> {
+		match read_to_string(dir.join(IGNORE_FILE)) {
+			Ok(content) => Ok(Some(Self::parse(&content)?)),
+			Err(ex) if ex.kind() == io::ErrorKind::NotFound => Ok(None),
+			Err(ex) => Err(ex.into()),
+		}
+	}
+
+	/// Whether the file (or directory with `is_dir`) `rel_path` is ignored, its parent directories not checked
+	/// (e.g., when walking the directory, the ignored directories being skipped).
+	pub fn is_ignored(&self, rel_path: &str, is_dir: bool) -> bool {
+		let last_match = self
+			.set
+			.matches(rel_path)
+			.into_iter()
+			.filter(|&i| is_dir || !self.rules[i].dir_only)
+			.max();
+		last_match.is_some_and(|i| !self.rules[i].negated)
+	}
+
+	/// Whether the file `rel_key` is ignored, itself or one of its parent directories (e.g., `build/a.o`
+	/// with `build/`).
+	pub fn is_ignored_key(&self, rel_key: &str) -> bool {
+		let mut parents = rel_key.match_indices('/').map(|(i, _)| &rel_key[..i]);
+		parents.any(|dir| self.is_ignored(dir, true)) || self.is_ignored(rel_key, false)
+	}
+}
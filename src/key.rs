@@ -0,0 +1,88 @@
+use crate::error::{Result, S3DemoError};
+use aws_sdk_s3::model::EncodingType;
+use percent_encoding::percent_decode_str;
+
+// -- constants
+/// S3 maximum key size, in bytes (of its UTF-8 encoding).
+pub const MAX_KEY_SIZE: usize = 1024;
+
+/// Check that `key` can be written: not empty, and at most [`MAX_KEY_SIZE`] bytes.
+///
+/// Note: any UTF-8 key is valid otherwise (spaces, `+`, `%`, ...), the SDK percent-encodes it in the URI.
+pub(crate) fn validate_key(key: &str) -> Result<()> {
+	if key.is_empty() {
+		return Err(S3DemoError::InvalidInput("Empty key".to_string()));
+	}
+	if key.len() > MAX_KEY_SIZE {
+		return Err(S3DemoError::InvalidInput(format!(
+			"Key {}... is {} bytes, above the S3 maximum of {MAX_KEY_SIZE}",
+			key.chars().take(40).collect::<String>(),
+			key.len()
+		)));
+	}
+	Ok(())
+}
+
+/// A key (or prefix, marker) of a listing response, decoded when the response is URL encoded
+/// (see `encoding-type=url`, requested so the keys with characters invalid in XML can be listed).
+///
+/// Note: S3 encodes the spaces as `+` (and a `+` as `%2B`).
+pub(crate) fn listing_key(value: &str, encoding_type: Option<&EncodingType>) -> Result<String> {
+	if encoding_type != Some(&EncodingType::Url) {
+		return Ok(value.to_string());
+	}
+	let value = value.replace('+', " ");
+	let key = percent_decode_str(&value)
+		.decode_utf8()
+		.map_err(|_| S3DemoError::InvalidResponse(format!("Invalid URL encoded key {value}")))?;
+	Ok(key.into_owned())
+}
+
+/// Whether `key` can be sent in an XML request body (e.g., DeleteObjects), i.e., has only XML 1.0 characters
+/// (no control characters other than tab and newlines).
+pub(crate) fn is_xml_safe(key: &str) -> bool {
+	key.chars()
+		.all(|c| matches!(c, '\t' | '\n' | '\r') || (c >= ' ' && c != '\u{fffe}' && c != '\u{ffff}'))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_validate_key() {
+		assert!(matches!(validate_key(""), Err(S3DemoError::InvalidInput(_))));
+		assert!(validate_key("a b+c%20d").is_ok());
+		assert!(validate_key(&"a".repeat(MAX_KEY_SIZE)).is_ok());
+		assert!(matches!(
+			validate_key(&"a".repeat(MAX_KEY_SIZE + 1)),
+			Err(S3DemoError::InvalidInput(_))
+		));
+		// Note: the size is of the UTF-8 bytes, not the characters.
+		assert!(validate_key(&"é".repeat(MAX_KEY_SIZE / 2)).is_ok());
+		assert!(validate_key(&format!("{}a", "é".repeat(MAX_KEY_SIZE / 2))).is_err());
+	}
+
+	#[test]
+	fn test_listing_key() -> Result<()> {
+		let url = Some(&EncodingType::Url);
+		assert_eq!(listing_key("a+b%2Bc", url)?, "a b+c");
+		assert_eq!(listing_key("dir%2Fr%C3%A9sum%C3%A9.txt", url)?, "dir/résumé.txt");
+		assert_eq!(listing_key("100%25", url)?, "100%");
+		// Note: not decoded when the response is not URL encoded.
+		assert_eq!(listing_key("a+b%2Bc", None)?, "a+b%2Bc");
+		assert!(matches!(
+			listing_key("a%FFb", url),
+			Err(S3DemoError::InvalidResponse(_))
+		));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_is_xml_safe() {
+		assert!(is_xml_safe("dir/a b\tc\n.txt"));
+		assert!(!is_xml_safe("a\u{1}b"));
+		assert!(!is_xml_safe("a\u{ffff}"));
+	}
+}
@@ -0,0 +1,120 @@
+//! Small library around the official Rust AWS SDK for S3.
+//!
+//! ```no_run
+//! # async fn xp() -> rust_aws_sdk_s3::Result<()> {
+//! use rust_aws_sdk_s3::{get_aws_client, S3Bucket};
+//!
+//! let bucket = S3Bucket::new(get_aws_client("us-west-2")?, "my-bucket");
+//! let keys = bucket.list("").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod accelerate;
+mod archive;
+mod assume_role;
+mod audit;
+mod bucket;
+mod bucket_sync;
+mod buckets;
+mod checksum;
+mod client;
+mod compress;
+mod conditional;
+mod copy;
+mod cse;
+mod delete;
+mod delta;
+mod diff;
+mod dir;
+mod download_state;
+mod du;
+mod encryption;
+mod error;
+mod file_attrs;
+mod filter;
+mod find;
+mod headers;
+mod hooks;
+mod identical;
+mod ignore;
+mod key;
+mod memory;
+mod mime;
+mod multipart;
+mod multipart_copy;
+mod notify;
+mod object;
+mod overwrite;
+mod presign;
+mod progress;
+mod ranged;
+mod restore;
+mod resume;
+mod retry;
+mod select;
+mod split;
+mod stat;
+mod store;
+mod stream;
+mod symlink;
+mod sync;
+mod sync_index;
+mod tagging;
+mod throttle;
+mod transfer;
+mod trash;
+mod unsigned;
+mod upload_state;
+mod versions;
+mod watch;
+
+pub use archive::archive_compression;
+pub use assume_role::AssumeRole;
+pub use audit::{AuditEntry, AuditLog, AuditOperation};
+pub use bucket::S3Bucket;
+pub use bucket_sync::{BucketSyncPlan, ObjectCopy};
+pub use buckets::{bucket_region, list_buckets, resolve_bucket_region, BucketInfo};
+pub use checksum::{file_md5, file_sha256, META_SHA256};
+pub use client::{get_aws_client, ClientConfig};
+pub use compress::Compression;
+pub use cse::{ClientEncryption, META_CSE_ALGORITHM, META_CSE_NONCE};
+pub use delete::MAX_DELETE_BATCH;
+pub use delta::default_part_manifest_dir;
+pub use diff::{DiffEntry, DiffReason, DirDiff};
+pub use dir::{join_key, key_path, path_key, upload_dir_plan, UploadDirOptions};
+pub use download_state::default_download_state_dir;
+pub use du::PrefixUsage;
+pub use encryption::{CustomerKey, Encryption};
+pub use error::{Result, S3DemoError};
+pub use file_attrs::{parse_mtime, META_MODE, META_MTIME};
+pub use filter::KeyFilter;
+pub use find::FindQuery;
+pub use headers::ContentHeaders;
+pub use hooks::{HookEvent, HookStatus, TransferHooks};
+pub use ignore::{IgnoreRules, IGNORE_FILE};
+pub use key::MAX_KEY_SIZE;
+pub use memory::MemoryStore;
+pub use mime::MimeMap;
+pub use multipart::{MultipartUploadInfo, DEFAULT_MULTIPART_THRESHOLD, DEFAULT_PART_SIZE, MAX_PARTS, MIN_PART_SIZE};
+pub use multipart_copy::MAX_COPY_SIZE;
+pub use notify::{Notifier, NotifyTarget, RunSummary};
+pub use object::{DirListing, ObjectInfo};
+pub use overwrite::Overwrite;
+pub use progress::TransferProgress;
+pub use ranged::DEFAULT_PART_CONCURRENCY;
+pub use restore::RestoreStatus;
+pub use retry::{RetryPolicy, DEFAULT_MAX_ATTEMPTS, DEFAULT_RETRY_BASE_DELAY, DEFAULT_RETRY_MAX_DELAY};
+pub use select::{SelectFormat, SelectQuery, SelectStats};
+pub use split::META_SPLIT_CHUNKS;
+pub use stat::ObjectStat;
+pub use store::ObjectStore;
+pub use stream::ByteRange;
+pub use symlink::{SymlinkMode, META_SYMLINK_TARGET};
+pub use sync::{SyncDirection, SyncOptions, SyncPlan};
+pub use sync_index::default_sync_index_path;
+pub use tagging::MAX_TAGS;
+pub use transfer::{Transfer, TransferManager, TransferResult, DEFAULT_CONCURRENCY};
+pub use upload_state::default_upload_state_dir;
+pub use versions::{ObjectVersion, PrunePolicy};
+pub use watch::{WatchOptions, DEFAULT_WATCH_DEBOUNCE};
@@ -1,128 +1,2136 @@
 #![allow(unused)] // silence unused warnings while exploring (to comment out)
 
-use anyhow::{anyhow, bail, Context, Result}; // (xp) (thiserror in prod)
-use aws_sdk_s3::{config, ByteStream, Client, Credentials, Region};
-use std::env;
-use std::fs::{create_dir_all, File};
-use std::io::{BufWriter, Write};
-use std::path::Path;
-use tokio_stream::StreamExt;
+mod cli;
+
+use anyhow::{anyhow, bail, Result}; // (the lib errors are S3DemoError)
+use chrono::Local;
+use clap::Parser;
+use clap_complete::engine::CompletionCandidate;
+use cli::{
+	complete, completion_words, default_status_path, exit_code, handle_ctrl_c, human_count, human_size, human_time,
+	init_logging, metrics, next_run, print_completions, print_listing, print_objects, print_tree, print_versions,
+	read_batch_manifest, serve_metrics, shutdown_token, usage_error, write_batch_results, AccelerateAction,
+	BarProgress, BatchArgs, BatchResult, BucketCommand, Cli, Command, CommandHooks, Config, DaemonStatus, DuSort, Job,
+	JobLock, JobStatus, JsonProgress, ListFormat, MetricsProgress, MultipartCommand, OutputFormat, ProgressFormat,
+	ProgressStream, Remote, Target, VersioningAction, VersionsCommand, EXIT_INTERRUPTED, EXIT_NOT_FOUND,
+};
+use cron::Schedule;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use rust_aws_sdk_s3::{
+	archive_compression, default_download_state_dir, default_part_manifest_dir, default_sync_index_path,
+	default_upload_state_dir, join_key, key_path, list_buckets, path_key, resolve_bucket_region, upload_dir_plan,
+	AuditLog, ByteRange, ClientConfig, ClientEncryption, DiffReason, DirDiff, KeyFilter, MimeMap, Notifier, ObjectInfo,
+	ObjectStat, ObjectStore, Overwrite, PrunePolicy, RestoreStatus, RunSummary, S3Bucket, S3DemoError, SelectQuery,
+	SyncDirection, SyncOptions, SyncPlan, Transfer, TransferManager, TransferProgress, UploadDirOptions, WatchOptions,
+};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::runtime::Handle;
 
 // -- constants
-const ENV_CRED_KEY_ID: &str = "S3_KEY_ID";
-const ENV_CRED_KEY_SECRET: &str = "S3_KEY_SECRET";
-const BUCKET_NAME: &str = "rust-aws-sdk-s3-demo";
-const REGION: &str = "us-west-2";
+/// The upload path reading the content from stdin.
+const STDIN_PATH: &str = "-";
 
 #[tokio::main]
-async fn main() -> Result<()> {
-	let client = get_aws_client(REGION)?;
+async fn main() {
+	complete(complete_remote_key);
+	// Note: clap exits with the usage error status (2) on an invalid command line.
+	let cli = Cli::parse();
+	handle_ctrl_c(cli.command.graceful_shutdown());
+	let res = run(cli).await;
+	if let Err(ex) = &res {
+		eprintln!("Error: {ex:?}");
+	}
+
+	// Note: an interrupted run has its own exit status (once its transfers in flight are done).
+	if shutdown_token().is_cancelled() {
+		std::process::exit(EXIT_INTERRUPTED);
+	}
+	if let Err(ex) = res {
+		std::process::exit(exit_code(&ex));
+	}
+}
+
+async fn run(mut cli: Cli) -> Result<()> {
+	if let Command::Completions { shell } = cli.command {
+		return print_completions(shell, complete_remote_key);
+	}
+	init_logging(cli.verbose, cli.log_format);
+	// Note: the JSON progress events would be mixed with the object data.
+	if cli.progress == ProgressFormat::Json
+		&& cli.progress_stream == ProgressStream::Stdout
+		&& cli.command.writes_stdout()
+	{
+		return Err(usage_error(
+			"--progress-stream stdout cannot be used with cat, select and get-range to stdout",
+		));
+	}
+	let config = Config::load(cli.config.as_deref())?;
+	if let Some(addr) = cli.command.metrics_addr().filter(|_| !cli.dry_run) {
+		serve_metrics(addr).await?;
+	}
+
+	// Note: for find, --storage-class is a predicate (not the storage class of the copies).
+	if let Command::Find { query, .. } = &mut cli.command {
+		query.storage_class = cli.storage_class.take();
+	}
 
-	let keys = list_keys(&client, BUCKET_NAME).await?;
-	println!("List:\n{}", keys.join("\n"));
+	// sync of a remote prefix to another bucket
+	if let Some((src_remote, src_prefix)) = sync_source_remote(&config, &cli.command) {
+		return sync_buckets(cli, &config, src_remote, &src_prefix).await;
+	}
 
-	let path = Path::new("src/main.rs");
-	upload_file(&client, BUCKET_NAME, path).await?;
-	println!("Uploaded file {}", path.display());
+	let dst_remote = cli.command.dst_key_mut().and_then(|key| config.take_key_remote(key));
+	let remote = config.take_remote(&mut cli.command)?;
+	let mut target = config.target(&cli, remote.as_ref());
+	// Note: for the bucket commands, the bucket may not exist (yet).
+	if !matches!(cli.command, Command::Bucket { .. } | Command::Buckets { .. }) {
+		detect_region(&cli, &mut target).await?;
+	}
+	let mut bucket = open_bucket(&cli, &config, &target).await?;
 
-	let dir = Path::new(".test-data/downloads/");
-	let key = "videos/ski-02.mp4";
-	download_file(&client, BUCKET_NAME, key, dir).await?;
-	println!("Downloaded {key} in directory {}", dir.display());
+	// cp/mv to another remote or bucket: each bucket is reached in its own region, and the copy is
+	// server-side when both are reached with the same credentials (the flag), else through this host
+	let mut dst_target = match dst_remote {
+		Some(dst_remote) => {
+			let mut dst_target = config.target(&cli, Some(&dst_remote));
+			detect_region(&cli, &mut dst_target).await?;
+			cli.command.set_dest_bucket(dst_target.bucket.clone())?;
+			Some(dst_target)
+		}
+		None => cli.command.dest_bucket().map(|name| Target {
+			bucket: name.to_string(),
+			..target.clone()
+		}),
+	};
+	let mut dst_bucket: Option<(S3Bucket, bool)> = None;
+	if let Some(dst_target) = dst_target.as_mut().filter(|t| t.bucket != target.bucket) {
+		if resolve_regions(&bucket, &mut target, dst_target).await? {
+			bucket = open_bucket(&cli, &config, &target).await?;
+		}
+		if !dst_target.same_client(&target) {
+			let dst = open_bucket(&cli, &config, dst_target).await?;
+			dst_bucket = Some((dst, dst_target.same_account(&target)));
+		}
+	}
+
+	if cli.dry_run {
+		return dry_run(&bucket, cli.command).await;
+	}
+	if let Command::Daemon { once, jobs, .. } = &cli.command {
+		return run_daemon(&cli, &config, *once, jobs).await;
+	}
+	let notifier = build_notifier(&cli, &config, &target).await?;
+	let report = cli.report.clone();
+
+	match cli.command {
+		Command::Upload {
+			prefix,
+			overwrite,
+			batch: BatchArgs {
+				from_manifest: Some(manifest),
+				result_manifest,
+			},
+			..
+		} => {
+			let transfers = upload_batch_plan(&manifest, prefix)?;
+			let timer = Instant::now();
+			let res = run_batch(&bucket, transfers, overwrite.to_overwrite(), result_manifest.as_deref()).await;
+			let source = manifest.display().to_string();
+			let summary = batch_summary("upload", source, bucket.name().to_string(), timer, &res);
+			if let Ok((done, skipped, _)) = &res {
+				println!(
+					"Uploaded {done} files from manifest {}{}",
+					manifest.display(),
+					skipped_label(*skipped, 0)
+				);
+			}
+			finish_run(summary, "uploaded", report.as_deref(), notifier.as_ref()).await?;
+			res?;
+		}
+		Command::Download {
+			dir,
+			recursive,
+			resume,
+			version_id,
+			overwrite,
+			batch: BatchArgs {
+				from_manifest: Some(manifest),
+				result_manifest,
+			},
+			..
+		} => {
+			if recursive || resume || version_id.is_some() {
+				return Err(usage_error(
+					"--from-manifest cannot be combined with --recursive, --resume or --version-id",
+				));
+			}
+			let transfers = download_batch_plan(&manifest, &dir)?;
+			let timer = Instant::now();
+			let res = run_batch(&bucket, transfers, overwrite.to_overwrite(), result_manifest.as_deref()).await;
+			let destination = dir.display().to_string();
+			let summary = batch_summary("download", bucket.name().to_string(), destination, timer, &res);
+			if let Ok((done, skipped, _)) = &res {
+				println!(
+					"Downloaded {done} files from manifest {} in directory {}{}",
+					manifest.display(),
+					dir.display(),
+					skipped_label(*skipped, 0)
+				);
+			}
+			finish_run(summary, "downloaded", report.as_deref(), notifier.as_ref()).await?;
+			res?;
+		}
+		Command::Upload {
+			path: Some(path),
+			key,
+			overwrite,
+			..
+		} if path == Path::new(STDIN_PATH) => {
+			let key = key.ok_or_else(|| usage_error("--key is required to upload from stdin"))?;
+			// Note: stdin has no modification time, so it is newer than any existing object.
+			if !will_transfer(
+				&bucket,
+				Transfer::Upload { path, key: key.clone() },
+				overwrite.to_overwrite(),
+			)
+			.await?
+			{
+				println!("Skipped stdin upload ({key} exists)");
+				return Ok(());
+			}
+			let size = bucket.upload_from_reader(&mut tokio::io::stdin(), &key, None).await?;
+			println!("Uploaded {} from stdin to {key}", human_size(size));
+		}
+		Command::Upload {
+			path: Some(path),
+			key,
+			prefix,
+			hidden,
+			no_ignore_file,
+			filter,
+			overwrite,
+			skip_identical,
+			..
+		} if path.is_dir() => {
+			if key.is_some() {
+				return Err(usage_error(
+					"--key is for single file uploads, use --prefix for directories",
+				));
+			}
+			let timer = Instant::now();
+			let prefix = match prefix {
+				Some(prefix) => prefix,
+				None => path_key(&path).ok_or_else(|| usage_error(format!("Invalid path {path:?}")))?,
+			};
+			let opts = UploadDirOptions {
+				include_hidden: hidden,
+				filter: filter.to_filter()?,
+				symlinks: cli.symlinks.to_symlink_mode(),
+				no_ignore_file,
+			};
+			let transfers = upload_dir_plan(&path, &prefix, &opts)?;
+			let (transfers, skipped) = bucket
+				.skip_existing(transfers, &prefix, overwrite.to_overwrite())
+				.await?;
+			let (transfers, identical) = if skip_identical {
+				bucket.skip_identical(transfers).await?
+			} else {
+				(transfers, Vec::new())
+			};
+			let res = bucket.run_transfers(transfers).await.map_err(anyhow::Error::from);
+			let (source, destination) = (path.display().to_string(), format!("{}/{prefix}", bucket.name()));
+			let summary = transfers_summary("upload", source, destination, timer, &res, skipped + identical.len());
+			if let Ok(transfers) = &res {
+				println!(
+					"Uploaded {} files from {} to {prefix}{}",
+					transfers.len(),
+					path.display(),
+					skipped_label(skipped, identical.len())
+				);
+			}
+			finish_run(summary, "uploaded", report.as_deref(), notifier.as_ref()).await?;
+			res?;
+		}
+		Command::Upload {
+			path: Some(path),
+			key,
+			prefix,
+			overwrite,
+			skip_identical,
+			..
+		} => {
+			let key = upload_key(&path, key, prefix)?;
+			let transfer = Transfer::Upload {
+				path: path.clone(),
+				key: key.clone(),
+			};
+			if !will_transfer(&bucket, transfer, overwrite.to_overwrite()).await? {
+				println!("Skipped file {} ({key} exists)", path.display());
+				return Ok(());
+			}
+			if skip_identical && bucket.is_identical(&path, &key).await? {
+				println!("Skipped file {} (identical to {key})", path.display());
+				return Ok(());
+			}
+			bucket.upload_to(&path, &key).await?;
+			println!("Uploaded file {} to {key}", path.display());
+		}
+		Command::Download {
+			key: Some(key),
+			dir,
+			recursive: true,
+			overwrite,
+			..
+		} => {
+			let timer = Instant::now();
+			let transfers = bucket.download_prefix_plan(&key, &dir).await?;
+			let (transfers, skipped) = bucket.skip_existing(transfers, &key, overwrite.to_overwrite()).await?;
+			let res = bucket.run_transfers(transfers).await.map_err(anyhow::Error::from);
+			let (source, destination) = (format!("{}/{key}", bucket.name()), dir.display().to_string());
+			let summary = transfers_summary("download", source, destination, timer, &res, skipped);
+			if let Ok(transfers) = &res {
+				println!(
+					"Downloaded {} files from {key} in directory {}{}",
+					transfers.len(),
+					dir.display(),
+					skipped_label(skipped, 0)
+				);
+			}
+			finish_run(summary, "downloaded", report.as_deref(), notifier.as_ref()).await?;
+			res?;
+		}
+		Command::Download {
+			key: Some(key),
+			dir,
+			version_id: Some(version_id),
+			..
+		} => {
+			bucket
+				.download_version_to(&key, &version_id, &key_path(&dir, &key)?)
+				.await?;
+			println!("Downloaded {key} (version {version_id}) in directory {}", dir.display());
+		}
+		Command::Download {
+			key: Some(key),
+			dir,
+			resume,
+			overwrite,
+			..
+		} => {
+			let path = key_path(&dir, &key)?;
+			if resume {
+				bucket.download_resume(&key, &path).await?;
+			} else {
+				let transfer = Transfer::Download {
+					key: key.clone(),
+					path: path.clone(),
+				};
+				if !will_transfer(&bucket, transfer, overwrite.to_overwrite()).await? {
+					println!("Skipped {key} ({} exists)", path.display());
+					return Ok(());
+				}
+				bucket.download(&key, &dir).await?;
+			}
+			println!("Downloaded {key} in directory {}", dir.display());
+		}
+		Command::Upload { path: None, .. } | Command::Download { key: None, .. } => {
+			return Err(usage_error("A source (or --from-manifest) is required"));
+		}
+		Command::Cat { key } => {
+			let mut stdout = io::stdout().lock();
+			match bucket.download_to_writer(&key, &mut stdout).await {
+				// Note: the reader closed the pipe early (e.g., `| head`), not an error.
+				Err(ex) if is_broken_pipe(&ex) => (),
+				res => {
+					res?;
+				}
+			}
+		}
+		Command::Archive { dir, key } => {
+			let files = bucket.upload_archive(&dir, &key, archive_compression(&key)).await?;
+			println!("Archived {files} files from {} to {key}", dir.display());
+		}
+		Command::Extract { key, dir } => {
+			let entries = bucket.extract_archive(&key, &dir, archive_compression(&key)).await?;
+			println!("Extracted {entries} entries from {key} to {}", dir.display());
+		}
+		Command::GetRange {
+			key,
+			range,
+			head,
+			tail,
+			file,
+		} => {
+			let range = match (range, head, tail) {
+				(Some(range), _, _) => range,
+				(_, Some(head), _) => ByteRange::head(head),
+				(_, _, Some(tail)) => ByteRange::Last(tail),
+				_ => return Err(usage_error("One of --range, --head or --tail is required")),
+			};
+			match file {
+				Some(file) => {
+					let mut writer = io::BufWriter::new(std::fs::File::create(&file)?);
+					let written = bucket.download_range_to_writer(&key, range, &mut writer).await?;
+					println!("Downloaded {} of {key} to {}", human_size(written), file.display());
+				}
+				None => {
+					let mut stdout = io::stdout().lock();
+					match bucket.download_range_to_writer(&key, range, &mut stdout).await {
+						// Note: the reader closed the pipe early (e.g., `| head`), not an error.
+						Err(ex) if is_broken_pipe(&ex) => (),
+						res => {
+							res?;
+						}
+					}
+				}
+			}
+		}
+		Command::Select {
+			key,
+			sql,
+			input,
+			output,
+			no_header,
+			delimiter,
+		} => {
+			let mut query = SelectQuery::new(sql, &key);
+			if let Some(input) = input {
+				query = query.with_input(input.to_select_format());
+			}
+			if let Some(output) = output {
+				query = query.with_output(output.to_select_format());
+			}
+			if no_header {
+				query = query.with_csv_header(false);
+			}
+			if let Some(delimiter) = delimiter {
+				query = query.with_field_delimiter(delimiter);
+			}
+			let mut stdout = io::stdout().lock();
+			match bucket.select_to_writer(&key, &query, &mut stdout).await {
+				// Note: the reader closed the pipe early (e.g., `| head`), not an error.
+				Err(ex) if is_broken_pipe(&ex) => (),
+				res => {
+					res?;
+				}
+			}
+		}
+		Command::Ls {
+			prefix,
+			output,
+			versions: true,
+			filter,
+			..
+		} => {
+			let filter = filter.to_filter()?;
+			let versions = bucket
+				.list_object_versions(prefix.as_deref().unwrap_or_default())
+				.await?;
+			let versions = versions
+				.into_iter()
+				.filter(|v| filter.is_match(&v.key))
+				.collect::<Vec<_>>();
+			print_versions(&versions, output)?;
+		}
+		Command::Ls {
+			prefix,
+			tree: true,
+			filter,
+			..
+		} => {
+			let filter = filter.to_filter()?;
+			let prefix = join_key(prefix.as_deref().unwrap_or_default(), "");
+			let objects = bucket.list_objects(&prefix).await?;
+			let objects = objects
+				.into_iter()
+				.filter(|o| filter.is_match(&o.key))
+				.collect::<Vec<_>>();
+			print_tree(&prefix, &objects);
+		}
+		Command::Ls {
+			prefix,
+			output,
+			recursive: true,
+			filter,
+			..
+		} => {
+			let filter = filter.to_filter()?;
+			let objects = bucket.list_objects(prefix.as_deref().unwrap_or_default()).await?;
+			let objects = objects
+				.into_iter()
+				.filter(|o| filter.is_match(&o.key))
+				.collect::<Vec<_>>();
+			print_objects(&objects, output)?;
+		}
+		Command::Ls {
+			prefix, output, filter, ..
+		} => {
+			let filter = filter.to_filter()?;
+			let mut listing = bucket.list_dir(prefix.as_deref().unwrap_or_default()).await?;
+			listing.prefixes.retain(|p| filter.is_match(p));
+			listing.objects.retain(|o| filter.is_match(&o.key));
+			print_listing(&listing, output)?;
+		}
+		Command::Du { prefix, sort, output } => {
+			let mut usages = bucket.du(prefix.as_deref().unwrap_or_default()).await?;
+			match sort {
+				DuSort::Size => usages.sort_by_key(|u| std::cmp::Reverse(u.bytes)),
+				DuSort::Count => usages.sort_by_key(|u| std::cmp::Reverse(u.objects)),
+				DuSort::Name => (),
+			}
+			match output {
+				OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&usages)?),
+				OutputFormat::Text => {
+					for usage in usages.iter() {
+						// Note: the immediate objects of the bucket root have an empty prefix.
+						let prefix = if usage.prefix.is_empty() { "." } else { &usage.prefix };
+						println!("{:>10}  {:>8}  {prefix}", human_size(usage.bytes), usage.objects);
+					}
+					let objects = usages.iter().map(|u| u.objects).sum::<u64>();
+					let bytes = usages.iter().map(|u| u.bytes).sum::<u64>();
+					println!("{:>10}  {objects:>8}  total", human_size(bytes));
+				}
+			}
+		}
+		Command::Find {
+			prefix,
+			query,
+			filter,
+			output,
+			delete,
+			copy_to,
+			yes,
+			max_delete,
+		} => {
+			let prefix = prefix.unwrap_or_default();
+			let objects = bucket.find(&prefix, &query.to_query(filter.to_filter()?)?).await?;
+			if delete {
+				let keys = objects.into_iter().map(|o| o.key).collect::<Vec<_>>();
+				if keys.is_empty() {
+					println!("No object found");
+					return Ok(());
+				}
+				if !confirm_deletes(&keys, "objects", yes, max_delete)? {
+					bail!("Find aborted (nothing deleted)");
+				}
+				bucket.delete_objects(&keys).await?;
+				println!("Deleted {} objects", human_count(keys.len()));
+			} else if let Some(copy_to) = copy_to {
+				for (src_key, dst_key) in find_copies(&prefix, &copy_to, &objects) {
+					bucket.copy_object(&src_key, bucket.name(), &dst_key).await?;
+					println!("copy {src_key} -> {dst_key}");
+				}
+				println!("Copied {} objects to {copy_to}", objects.len());
+			} else {
+				print_objects(&objects, output)?;
+			}
+		}
+		Command::Cp {
+			src_key,
+			dst_key,
+			dest_bucket,
+		} => {
+			let dst_name = dest_bucket.as_deref().unwrap_or(bucket.name());
+			match &dst_bucket {
+				Some((dst, true)) => dst.copy_from(&bucket, &src_key, &dst_key).await?,
+				Some((dst, false)) => bucket.copy_to_bucket(&src_key, dst, &dst_key).await?,
+				None => bucket.copy_object(&src_key, dst_name, &dst_key).await?,
+			}
+			println!("Copied {src_key} to {dst_name}/{dst_key}");
+		}
+		Command::Mv {
+			src_key,
+			dst_key,
+			dest_bucket,
+		} => {
+			let dst_name = dest_bucket.as_deref().unwrap_or(bucket.name());
+			match &dst_bucket {
+				Some((dst, true)) => dst.move_from(&bucket, &src_key, &dst_key).await?,
+				Some((dst, false)) => bucket.move_to_bucket(&src_key, dst, &dst_key).await?,
+				None => bucket.move_object(&src_key, dst_name, &dst_key).await?,
+			}
+			println!("Moved {src_key} to {dst_name}/{dst_key}");
+		}
+		Command::AbortMultipart {
+			key,
+			upload_id: Some(upload_id),
+		} => {
+			bucket.abort_multipart_upload(&key, &upload_id).await?;
+			println!("Aborted multipart upload {upload_id} of {key}");
+		}
+		Command::AbortMultipart { key, upload_id: None } => {
+			let upload_ids = bucket.abort_multipart_uploads(&key).await?;
+			println!("Aborted {} multipart uploads of {key}", upload_ids.len());
+		}
+		Command::Multipart {
+			command: MultipartCommand::List { prefix, older_than },
+		} => {
+			let uploads = bucket.list_multipart_uploads(&prefix).await?;
+			let uploads = uploads
+				.into_iter()
+				.filter(|u| older_than.is_none_or(|age| u.is_older_than(age)))
+				.collect::<Vec<_>>();
+			for upload in &uploads {
+				println!(
+					"{:<20}  {}  {}",
+					human_time(upload.initiated),
+					upload.key,
+					upload.upload_id
+				);
+			}
+			println!("{} multipart uploads in progress", uploads.len());
+		}
+		Command::Multipart {
+			command: MultipartCommand::Abort { prefix, older_than },
+		} => {
+			let aborted = bucket.abort_stale_multipart_uploads(&prefix, older_than).await?;
+			for upload in &aborted {
+				println!("Aborted {} ({})", upload.key, upload.upload_id);
+			}
+			println!(
+				"Aborted {} multipart uploads older than {}",
+				aborted.len(),
+				humantime::format_duration(older_than)
+			);
+		}
+		Command::Rm {
+			key,
+			recursive: true,
+			trash: true,
+			yes,
+			max_delete,
+			filter,
+			..
+		} => {
+			let filter = filter.to_filter()?;
+			let keys = bucket.list(&join_key(&key, "")).await?;
+			let keys = keys.into_iter().filter(|k| filter.is_match(k)).collect::<Vec<_>>();
+			if !confirm_deletes(&keys, "objects (soft delete)", yes, max_delete)? {
+				bail!("Rm aborted (nothing deleted)");
+			}
+			bucket.trash_objects(&keys).await?;
+			println!("Trashed {} objects under {key} (see undelete)", human_count(keys.len()));
+		}
+		Command::Rm {
+			key,
+			recursive: true,
+			yes,
+			max_delete,
+			filter,
+			..
+		} => {
+			let filter = filter.to_filter()?;
+			let keys = bucket.list(&join_key(&key, "")).await?;
+			let keys = keys.into_iter().filter(|k| filter.is_match(k)).collect::<Vec<_>>();
+			if !confirm_deletes(&keys, "objects", yes, max_delete)? {
+				bail!("Rm aborted (nothing deleted)");
+			}
+			bucket.delete_objects(&keys).await?;
+			println!("Deleted {} objects under {key}", human_count(keys.len()));
+		}
+		Command::Rm {
+			key,
+			version_id: Some(version_id),
+			..
+		} => {
+			bucket.delete_object_version(&key, &version_id).await?;
+			println!("Deleted {key} version {version_id}");
+		}
+		Command::Rm { key, trash: true, .. } => {
+			let marker_id = bucket.trash_object(&key).await?;
+			println!("Trashed {key} (delete marker {marker_id}, see undelete)");
+		}
+		Command::Rm { key, .. } => {
+			bucket.delete_object(&key).await?;
+			println!("Deleted {key}");
+		}
+		Command::Undelete { key } => {
+			let version_id = bucket.undelete(&key).await?;
+			println!("Undeleted {key} (version {version_id})");
+		}
+		Command::Rollback { key } => {
+			let version_id = bucket.rollback(&key).await?;
+			println!("Rolled back {key} to version {version_id}");
+		}
+		Command::Versions {
+			command: VersionsCommand::Prune {
+				prefix,
+				older_than,
+				keep,
+				yes,
+			},
+		} => {
+			let policy = PrunePolicy {
+				older_than,
+				keep: keep.map(|keep| keep as usize),
+			};
+			let versions = bucket.prune_plan(&prefix, &policy).await?;
+			print_versions(&versions, ListFormat::Text)?;
+			if versions.is_empty() {
+				println!("No version to prune");
+			} else if yes {
+				bucket.prune_versions(&versions).await?;
+				println!("Deleted {} versions", versions.len());
+			} else {
+				println!(
+					"Dry run: {} versions would be deleted (re-run with --yes to delete)",
+					versions.len()
+				);
+			}
+		}
+		Command::Presign {
+			key,
+			expires,
+			put: false,
+			..
+		} => {
+			let url = bucket.presign_get(&key, expires).await?;
+			println!("{url}");
+		}
+		Command::Presign {
+			key,
+			expires,
+			content_type,
+			..
+		} => {
+			let url = bucket.presign_put(&key, expires, content_type.as_deref()).await?;
+			println!("{url}");
+		}
+		Command::Restore {
+			key,
+			days,
+			tier,
+			wait,
+			poll_interval,
+			status,
+		} => {
+			if !status {
+				bucket.restore_object(&key, days, tier.to_tier()).await?;
+				println!("Restore of {key} requested for {days} days ({tier:?} tier)");
+			}
+			let status = if wait {
+				println!(
+					"Waiting for the restore of {key} (polling every {})",
+					humantime::format_duration(poll_interval)
+				);
+				bucket.wait_restored(&key, poll_interval).await?
+			} else {
+				bucket.restore_status(&key).await?
+			};
+			println!("{key}: {}", restore_label(&status));
+		}
+		Command::Tag { key, delete: true, .. } => {
+			bucket.delete_object_tags(&key).await?;
+			println!("Removed the tags of {key}");
+		}
+		Command::Tag { key, tags, .. } if tags.is_empty() => {
+			let mut tags = bucket.get_object_tags(&key).await?.into_iter().collect::<Vec<_>>();
+			tags.sort();
+			for (name, value) in tags {
+				println!("{name}={value}");
+			}
+		}
+		Command::Tag { key, tags, .. } => {
+			let tags = tags.into_iter().collect::<HashMap<_, _>>();
+			bucket.put_object_tags(&key, &tags).await?;
+			println!("Set {} tags on {key}", tags.len());
+		}
+		Command::Buckets { no_region, output } => {
+			let buckets = list_buckets(bucket.client(), !no_region).await?;
+			match output {
+				OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&buckets)?),
+				OutputFormat::Text => {
+					for b in buckets {
+						let region = b.region.as_deref().unwrap_or("-");
+						println!("{}  {region:<14}  {}", human_time(b.creation_date), b.name);
+					}
+				}
+			}
+		}
+		Command::Bucket { command } => match command {
+			BucketCommand::Create => {
+				bucket.create_bucket(&target.region).await?;
+				println!("Created bucket {} in {}", bucket.name(), target.region);
+			}
+			BucketCommand::Rm { force, yes, max_delete } => {
+				if force {
+					let keys = bucket.list("").await?;
+					if !confirm_deletes(&keys, "objects", yes, max_delete)? {
+						bail!("Bucket rm aborted (nothing deleted)");
+					}
+					bucket.delete_objects(&keys).await?;
+					println!("Deleted {} objects from {}", human_count(keys.len()), bucket.name());
+				}
+				bucket.delete_bucket().await?;
+				println!("Deleted bucket {}", bucket.name());
+			}
+			BucketCommand::Versioning { action } => {
+				match action {
+					VersioningAction::Enable => bucket.set_versioning(true).await?,
+					VersioningAction::Suspend => bucket.set_versioning(false).await?,
+					VersioningAction::Status => (),
+				}
+				let status = bucket.versioning_status().await?;
+				println!(
+					"Versioning of {}: {}",
+					bucket.name(),
+					status.as_deref().unwrap_or("never enabled")
+				);
+			}
+			BucketCommand::Accelerate { action } => {
+				match action {
+					AccelerateAction::On => bucket.set_accelerate(true).await?,
+					AccelerateAction::Off => bucket.set_accelerate(false).await?,
+					AccelerateAction::Status => (),
+				}
+				let status = bucket.accelerate_status().await?;
+				println!(
+					"Transfer acceleration of {}: {}",
+					bucket.name(),
+					status.as_deref().unwrap_or("never enabled")
+				);
+			}
+			BucketCommand::Exists => {
+				if bucket.bucket_exists().await? {
+					println!("Bucket {} exists", bucket.name());
+				} else {
+					println!("Bucket {} does not exist", bucket.name());
+					std::process::exit(EXIT_NOT_FOUND);
+				}
+			}
+		},
+		Command::Stat { key, output } => {
+			let stat = bucket.stat(&key).await?;
+			print_stat(&stat, output)?;
+		}
+		Command::Sync {
+			dir,
+			prefix,
+			down,
+			hidden,
+			no_ignore_file,
+			delete,
+			yes,
+			max_delete,
+			index,
+			filter,
+			overwrite,
+		} => {
+			let direction = if down { SyncDirection::Down } else { SyncDirection::Up };
+			let opts = SyncOptions {
+				include_hidden: hidden,
+				filter: filter.to_filter()?,
+				delete,
+				overwrite: overwrite.to_overwrite(),
+				index: index.then(default_sync_index_path),
+				no_ignore_file,
+			};
+			let timer = Instant::now();
+			let res = async {
+				let plan = bucket.sync_plan(&dir, &prefix, direction, &opts).await?;
+				let targets = plan
+					.delete_keys
+					.iter()
+					.cloned()
+					.chain(plan.delete_paths.iter().map(|p| p.display().to_string()))
+					.collect::<Vec<_>>();
+				if !confirm_deletes(&targets, "files/objects", yes, max_delete)? {
+					bail!("Sync aborted (nothing transferred or deleted)");
+				}
+				Ok(bucket.sync_apply(plan).await?)
+			}
+			.await;
+			let remote = format!("{}/{prefix}", bucket.name());
+			let summary = sync_summary(&dir, remote, direction, timer, &res);
+			if let Ok(plan) = &res {
+				for transfer in plan.transfers.iter() {
+					println!("{} {}", transfer_label(transfer), transfer.key());
+				}
+				println!(
+					"Synced {} files ({} unchanged, {} skipped, {} deleted)",
+					plan.transfers.len(),
+					plan.unchanged,
+					plan.skipped,
+					plan.delete_count()
+				);
+			}
+			let verb = match direction {
+				SyncDirection::Up => "uploaded",
+				SyncDirection::Down => "downloaded",
+			};
+			finish_run(summary, verb, report.as_deref(), notifier.as_ref()).await?;
+			res?;
+		}
+		Command::Diff {
+			dir,
+			prefix,
+			hidden,
+			no_ignore_file,
+			filter,
+			output,
+		} => {
+			let opts = UploadDirOptions {
+				include_hidden: hidden,
+				filter: filter.to_filter()?,
+				no_ignore_file,
+				..Default::default()
+			};
+			let diff = bucket.diff(&dir, &prefix, &opts).await?;
+			match output {
+				OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+				OutputFormat::Text => print_diff(&diff),
+			}
+		}
+		Command::Watch {
+			dir,
+			prefix,
+			hidden,
+			debounce,
+			no_ignore_file,
+			filter,
+			..
+		} => {
+			let opts = WatchOptions {
+				include_hidden: hidden,
+				filter: filter.to_filter()?,
+				no_ignore_file,
+				debounce,
+			};
+			println!("Watching {} (uploading to {prefix}, Ctrl-C to stop)", dir.display());
+			bucket
+				.watch(&dir, &prefix, &opts, |res| match &res.result {
+					Ok(()) => println!("upload {}", res.transfer.key()),
+					Err(ex) => {
+						eprintln!("upload {} failed: {ex}", res.transfer.key());
+						if let Some(metrics) = metrics() {
+							metrics.transfers_failed(1);
+						}
+					}
+				})
+				.await?;
+		}
+		Command::Daemon { .. } | Command::Completions { .. } => unreachable!("run before the match"),
+	}
 
 	Ok(())
 }
 
-async fn download_file(client: &Client, bucket_name: &str, key: &str, dir: &Path) -> Result<()> {
-	// VALIDATE
-	if !dir.is_dir() {
-		bail!("Path {} is not a directory", dir.display());
+/// The keys and "directories" of the bucket level of the key being completed (the shell completions of the keys),
+/// the bucket (or `<remote>:`) being the one of the command line. None on failure (e.g., no credentials).
+fn complete_remote_key(current: &OsStr) -> Vec<CompletionCandidate> {
+	let current = current.to_string_lossy();
+	let keys = tokio::task::block_in_place(|| Handle::current().block_on(remote_keys(&current)));
+	keys.unwrap_or_default()
+		.into_iter()
+		.map(CompletionCandidate::new)
+		.collect()
+}
+
+async fn remote_keys(current: &str) -> Result<Vec<String>> {
+	// Note: the command line may not parse yet (e.g., a required arg missing), then only the env and config are used.
+	let cli = Cli::try_parse_from(completion_words()).or_else(|_| Cli::try_parse_from(["s3demo", "ls"]))?;
+	let config = Config::load(cli.config.as_deref())?;
+	let mut prefix = current.to_string();
+	let remote = config.take_key_remote(&mut prefix);
+	let mut target = config.target(&cli, remote.as_ref());
+	detect_region(&cli, &mut target).await?;
+	let bucket = S3Bucket::new(build_client(&cli, &target, false).await?, &target.bucket);
+
+	// LIST - the level of the prefix (the `<remote>:` kept)
+	let listing = bucket.list_dir(&prefix).await?;
+	let remote_prefix = &current[..current.len() - prefix.len()];
+	let keys = listing
+		.prefixes
+		.into_iter()
+		.chain(listing.objects.into_iter().map(|o| o.key))
+		.map(|key| format!("{remote_prefix}{key}"))
+		.collect();
+	Ok(keys)
+}
+
+/// Run the config sync jobs on their schedules (or all once), recording each run in the status file.
+/// Note: each run holds the job lock, so the runs of a job never overlap (e.g., with a `daemon --once`).
+async fn run_daemon(cli: &Cli, config: &Config, once: bool, names: &[String]) -> Result<()> {
+	// VALIDATE - the jobs and their schedules
+	let jobs = config
+		.daemon
+		.jobs
+		.iter()
+		.filter(|job| names.is_empty() || names.contains(&job.name))
+		.collect::<Vec<_>>();
+	if jobs.is_empty() {
+		return Err(usage_error(
+			"No daemon job to run (see the [[daemon.jobs]] of the config)",
+		));
 	}
+	let schedules = jobs
+		.iter()
+		.map(|job| job.parse_schedule())
+		.collect::<Result<Vec<_>>>()?;
+	let status_path = config.daemon.status_file.clone().unwrap_or_else(default_status_path);
+	let mut status = DaemonStatus::load(&status_path);
+	let notifier = build_notifier(cli, config, &config.target(cli, None)).await?;
 
-	// create file path and parent dir(s)
-	let file_path = dir.join(key);
-	let parent_dir = file_path
-		.parent()
-		.ok_or_else(|| anyhow!("Invalid parent dir for {:?}", file_path))?;
-	if !parent_dir.exists() {
-		create_dir_all(parent_dir)?;
+	let shutdown = shutdown_token();
+	if once {
+		for (job, schedule) in jobs.iter().zip(schedules.iter()) {
+			if shutdown.is_cancelled() {
+				break;
+			}
+			run_daemon_job(cli, config, job, schedule, &mut status, &status_path, notifier.as_ref()).await?;
+		}
+		return Ok(());
 	}
 
-	// BUILD - aws request
-	let req = client.get_object().bucket(bucket_name).key(key);
+	let mut next_runs = schedules
+		.iter()
+		.map(|schedule| next_run(schedule, &Local::now()))
+		.collect::<Vec<_>>();
+	println!(
+		"Daemon started with {} jobs (status in {})",
+		jobs.len(),
+		status_path.display()
+	);
+	loop {
+		// WAIT - until the next due job
+		let next = next_runs
+			.iter()
+			.flatten()
+			.min()
+			.copied()
+			.ok_or_else(|| anyhow!("No upcoming run in the job schedules"))?;
+		tokio::select! {
+			_ = tokio::time::sleep((next - Local::now()).to_std().unwrap_or_default()) => (),
+			_ = shutdown.cancelled() => return Ok(()),
+		}
 
-	// EXECUTE
-	let res = req.send().await?;
+		// RUN - the due jobs, one after the other
+		for (i, job) in jobs.iter().enumerate() {
+			if shutdown.is_cancelled() {
+				return Ok(());
+			}
+			if next_runs[i].is_some_and(|next| next <= Local::now()) {
+				run_daemon_job(
+					cli,
+					config,
+					job,
+					&schedules[i],
+					&mut status,
+					&status_path,
+					notifier.as_ref(),
+				)
+				.await?;
+				// Note: the runs missed while running are skipped.
+				next_runs[i] = next_run(&schedules[i], &Local::now());
+			}
+		}
+	}
+}
+
+/// Run one job (unless locked by another run), then update the status file (and notify the `notifier`, if any).
+async fn run_daemon_job(
+	cli: &Cli,
+	config: &Config,
+	job: &Job,
+	schedule: &Schedule,
+	status: &mut DaemonStatus,
+	status_path: &Path,
+	notifier: Option<&Notifier>,
+) -> Result<()> {
+	let started = Local::now();
+	let timer = Instant::now();
+	let label = format!("{} {}", started.format("%Y-%m-%d %H:%M:%S"), job.name);
+	let mut job_status = JobStatus {
+		last_run: started.to_rfc3339(),
+		duration_secs: 0.,
+		result: "ok".to_string(),
+		transfers: 0,
+		unchanged: 0,
+		deleted: 0,
+		error: None,
+		next_run: None,
+	};
 
-	// STREAM result to file
-	let mut data: ByteStream = res.body;
-	let file = File::create(&file_path)?;
-	let mut buf_writer = BufWriter::new(file);
-	while let Some(bytes) = data.try_next().await? {
-		buf_writer.write(&bytes)?;
+	match JobLock::try_lock(&job.name)? {
+		None => {
+			println!("{label}: skipped (already running)");
+			job_status.result = "skipped".to_string();
+		}
+		Some(_lock) => {
+			let res = run_sync_job(cli, config, job).await;
+			match &res {
+				Ok(plan) => {
+					println!(
+						"{label}: synced {} files ({} unchanged, {} deleted)",
+						plan.transfers.len(),
+						plan.unchanged,
+						plan.delete_count()
+					);
+					job_status.transfers = plan.transfers.len();
+					job_status.unchanged = plan.unchanged;
+					job_status.deleted = plan.delete_count();
+				}
+				Err(ex) => {
+					eprintln!("{label}: failed: {ex:#}");
+					if let (Some(metrics), Some(S3DemoError::TransfersFailed { errors, .. })) =
+						(metrics(), ex.downcast_ref::<S3DemoError>())
+					{
+						metrics.transfers_failed(errors.len() as u64);
+					}
+					job_status.result = "failed".to_string();
+					job_status.error = Some(format!("{ex:#}"));
+				}
+			}
+			let direction = if job.down {
+				SyncDirection::Down
+			} else {
+				SyncDirection::Up
+			};
+			let mut summary = sync_summary(&job.dir, job.prefix.clone(), direction, timer, &res);
+			summary.command = "daemon".to_string();
+			summary.job = Some(job.name.clone());
+			summary.bytes_per_sec = throughput(&summary);
+			notify(notifier, &summary).await;
+		}
 	}
-	buf_writer.flush()?;
 
-	Ok(())
+	if let Some(metrics) = metrics() {
+		metrics.job_run(&job.name, &job_status.result, timer.elapsed());
+	}
+	job_status.duration_secs = timer.elapsed().as_secs_f64();
+	job_status.next_run = next_run(schedule, &Local::now()).map(|next| next.to_rfc3339());
+	status.jobs.insert(job.name.clone(), job_status);
+	status.save(status_path)
 }
 
-async fn upload_file(client: &Client, bucket_name: &str, path: &Path) -> Result<()> {
-	// VALIDATE
-	if !path.exists() {
-		bail!("Path {} does not exists", path.display());
+/// The sync of a daemon job (its prefix can target a remote).
+async fn run_sync_job(cli: &Cli, config: &Config, job: &Job) -> Result<SyncPlan> {
+	let mut prefix = job.prefix.clone();
+	let remote = config.take_key_remote(&mut prefix);
+	let mut target = config.target(cli, remote.as_ref());
+	detect_region(cli, &mut target).await?;
+	let bucket = open_bucket(cli, config, &target).await?;
+
+	let direction = if job.down {
+		SyncDirection::Down
+	} else {
+		SyncDirection::Up
+	};
+	let opts = SyncOptions {
+		include_hidden: job.hidden,
+		filter: KeyFilter::new(&job.include, &job.exclude)?,
+		delete: job.delete,
+		index: job.index.then(default_sync_index_path),
+		..Default::default()
+	};
+	Ok(bucket.sync(&job.dir, &prefix, direction, &opts).await?)
+}
+
+/// The bucket of `target`, with the client and bucket options of the flags (and config).
+/// The client reaching the `target` (its region, endpoint and profile), with the retry, rate, timeouts and role flags,
+/// through the Transfer Acceleration endpoint with `accelerate`.
+async fn build_client(cli: &Cli, target: &Target, accelerate: bool) -> Result<aws_sdk_s3::Client> {
+	Ok(client_config(cli, target, accelerate).build_client().await?)
+}
+
+/// The client config of [`build_client`].
+fn client_config(cli: &Cli, target: &Target, accelerate: bool) -> ClientConfig {
+	let mut client_config = ClientConfig::new(&target.region)
+		.with_retry(cli.retry.to_retry_policy())
+		.with_no_overwrite(cli.no_overwrite)
+		.with_no_sign_request(cli.no_sign_request)
+		.with_accelerate(accelerate);
+	if let Some(profile) = &target.profile {
+		client_config = client_config.with_profile(profile);
+	}
+	if let Some(endpoint) = &target.endpoint_url {
+		client_config = client_config.with_endpoint(endpoint);
+	}
+	if let Some(limit_rate) = cli.limit_rate {
+		client_config = client_config.with_limit_rate(limit_rate);
 	}
-	let key = path.to_str().ok_or_else(|| anyhow!("Invalid path {path:?}"))?;
+	if let Some(connect_timeout) = cli.timeouts.connect_timeout {
+		client_config = client_config.with_connect_timeout(connect_timeout);
+	}
+	if let Some(request_timeout) = cli.timeouts.request_timeout {
+		client_config = client_config.with_request_timeout(request_timeout);
+	}
+	if let Some(role) = cli.role.to_assume_role() {
+		client_config = client_config.with_assume_role(role);
+	}
+	client_config
+}
 
-	// PREPARE
-	let body = ByteStream::from_path(&path).await?;
-	let content_type = mime_guess::from_path(&path).first_or_octet_stream().to_string();
+/// The notifier of the --notify target (if any), the SNS topics reached with the `target` credentials.
+async fn build_notifier(cli: &Cli, config: &Config, target: &Target) -> Result<Option<Notifier>> {
+	let Some(notify) = config.notify(cli)? else {
+		return Ok(None);
+	};
+	Ok(Some(Notifier::new(notify, &client_config(cli, target, false)).await?))
+}
 
-	// BUILD - aws request
-	let req = client
-		.put_object()
-		.bucket(bucket_name)
-		.key(key)
-		.body(body)
-		.content_type(content_type);
+/// The summary of a finished multi-file run, with the errors of its failure (if any).
+fn run_summary(
+	command: &str,
+	source: String,
+	destination: String,
+	timer: Instant,
+	error: Option<&anyhow::Error>,
+) -> RunSummary {
+	let mut summary = RunSummary {
+		command: command.to_string(),
+		status: "ok".to_string(),
+		source,
+		destination,
+		duration_secs: timer.elapsed().as_secs_f64(),
+		..Default::default()
+	};
+	if let Some(ex) = error {
+		summary.status = "failed".to_string();
+		summary.errors = match ex.downcast_ref::<S3DemoError>() {
+			Some(S3DemoError::TransfersFailed { errors, total }) => {
+				summary.transferred = total.saturating_sub(errors.len());
+				summary.failed = errors.len();
+				errors.clone()
+			}
+			_ => vec![format!("{ex:#}")],
+		};
+	}
+	summary
+}
 
-	// EXECUTE
-	req.send().await?;
+/// The summary of a finished sync of the local `dir` with the `remote` bucket prefix.
+fn sync_summary(
+	dir: &Path,
+	remote: String,
+	direction: SyncDirection,
+	timer: Instant,
+	res: &Result<SyncPlan>,
+) -> RunSummary {
+	let (source, destination) = match direction {
+		SyncDirection::Up => (dir.display().to_string(), remote),
+		SyncDirection::Down => (remote, dir.display().to_string()),
+	};
+	let mut summary = run_summary("sync", source, destination, timer, res.as_ref().err());
+	if let Ok(plan) = res {
+		summary.transferred = plan.transfers.len();
+		summary.bytes = transfers_bytes(&plan.transfers);
+		summary.unchanged = plan.unchanged;
+		summary.skipped = plan.skipped;
+		summary.deleted = plan.delete_count();
+	}
+	summary
+}
+
+/// The summary of a finished `--from-manifest` batch (see [`run_batch`]).
+fn batch_summary(
+	command: &str,
+	source: String,
+	destination: String,
+	timer: Instant,
+	res: &Result<(usize, usize, u64)>,
+) -> RunSummary {
+	let mut summary = run_summary(command, source, destination, timer, res.as_ref().err());
+	if let Ok((done, skipped, bytes)) = res {
+		(summary.transferred, summary.skipped, summary.bytes) = (*done, *skipped, *bytes);
+	}
+	summary
+}
+
+/// The summary of a finished directory upload or recursive download (of the `skipped` existing/identical files).
+fn transfers_summary(
+	command: &str,
+	source: String,
+	destination: String,
+	timer: Instant,
+	res: &Result<Vec<Transfer>>,
+	skipped: usize,
+) -> RunSummary {
+	let mut summary = run_summary(command, source, destination, timer, res.as_ref().err());
+	summary.skipped = skipped;
+	if let Ok(transfers) = res {
+		summary.transferred = transfers.len();
+		summary.bytes = transfers_bytes(transfers);
+	}
+	summary
+}
+
+/// The bytes of the local files of the `transfers` (uploaded or downloaded).
+fn transfers_bytes(transfers: &[Transfer]) -> u64 {
+	transfers
+		.iter()
+		.filter_map(|t| t.path().metadata().ok())
+		.map(|meta| meta.len())
+		.sum()
+}
+
+/// The average throughput (bytes per second) of the `summary` run.
+fn throughput(summary: &RunSummary) -> f64 {
+	if summary.duration_secs > 0. {
+		summary.bytes as f64 / summary.duration_secs
+	} else {
+		0.
+	}
+}
 
+/// The end of a multi-file run (done or failed): print its report (the transferred files being `verb`, e.g.,
+/// `uploaded`), write it to the `report` JSON file (if any), and send it with the `notifier` (if any).
+async fn finish_run(
+	mut summary: RunSummary,
+	verb: &str,
+	report: Option<&Path>,
+	notifier: Option<&Notifier>,
+) -> Result<()> {
+	summary.bytes_per_sec = throughput(&summary);
+	println!(
+		"Report: {} {verb}, {} skipped, {} failed, {} in {:.1}s ({}/s)",
+		summary.transferred,
+		summary.unchanged + summary.skipped,
+		summary.failed,
+		human_size(summary.bytes),
+		summary.duration_secs,
+		human_size(summary.bytes_per_sec as u64)
+	);
+	if let Some(path) = report {
+		std::fs::write(path, serde_json::to_string_pretty(&summary)?)
+			.map_err(|ex| anyhow!("Cannot write the report {}: {ex}", path.display()))?;
+	}
+	notify(notifier, &summary).await;
 	Ok(())
 }
 
-async fn list_keys(client: &Client, bucket_name: &str) -> Result<Vec<String>> {
-	// BUILD - aws request
-	let req = client.list_objects_v2().prefix("").bucket(bucket_name);
+/// Send the `summary` with the `notifier` (if any), a failure being only reported (the run is done).
+async fn notify(notifier: Option<&Notifier>, summary: &RunSummary) {
+	let Some(notifier) = notifier else {
+		return;
+	};
+	if let Err(ex) = notifier.notify(summary).await {
+		eprintln!("Warning: {ex}");
+	}
+}
+
+/// Set the region of the `target` bucket when none is configured (HeadBucket `x-amz-bucket-region`), so its
+/// requests are not redirected (301 PermanentRedirect). Best effort: the default region is kept on failure
+/// (e.g., a missing bucket, reported by the command).
+async fn detect_region(cli: &Cli, target: &mut Target) -> Result<()> {
+	if !target.detect_region {
+		return Ok(());
+	}
+	let client = build_client(cli, target, false).await?;
+	if let Ok(region) = resolve_bucket_region(&client, &target.bucket, &target.region).await {
+		target.region = region;
+	}
+	target.detect_region = false;
+	Ok(())
+}
+
+async fn open_bucket(cli: &Cli, config: &Config, target: &Target) -> Result<S3Bucket> {
+	// Note: the bucket configuration requests are not served by the acceleration endpoint.
+	let accelerate = cli.accelerate && !matches!(cli.command, Command::Bucket { .. });
+	let client = build_client(cli, target, accelerate).await?;
+	let mut bucket = S3Bucket::new(client, &target.bucket)
+		.with_concurrency(config.concurrency(cli))
+		.with_checksums(!cli.no_checksum)
+		.with_shutdown(shutdown_token());
+	if let Some(part_size) = config.part_size(cli)? {
+		bucket = bucket.with_part_size(part_size);
+	}
+	if let Some(part_concurrency) = config.part_concurrency(cli) {
+		bucket = bucket.with_part_concurrency(part_concurrency);
+	}
+	if let Some(transfer_timeout) = cli.timeouts.transfer_timeout {
+		bucket = bucket.with_transfer_deadline(transfer_timeout);
+	}
+	if cli.resumable || matches!(cli.command, Command::AbortMultipart { .. } | Command::Multipart { .. }) {
+		bucket = bucket.with_resumable_uploads(default_upload_state_dir());
+	}
+	if cli.etag_cache {
+		bucket = bucket.with_download_etags(default_download_state_dir());
+	}
+	if cli.delta {
+		bucket = bucket.with_delta_uploads(default_part_manifest_dir());
+	}
+	if let Some(encryption) = cli.encryption.to_encryption()? {
+		bucket = bucket.with_encryption(encryption);
+	}
+	if !cli.tags.is_empty() {
+		bucket = bucket.with_tags(cli.tags.iter().cloned().collect());
+	}
+	if !cli.metadata.is_empty() {
+		bucket = bucket.with_metadata(cli.metadata.iter().cloned().collect());
+	}
+	bucket = bucket.with_content_headers(cli.headers.to_content_headers());
+	if let Some(path) = &cli.mime_types {
+		bucket = bucket.with_mime_map(MimeMap::from_file(path)?);
+	}
+	if let Command::Upload {
+		content_type: Some(content_type),
+		..
+	} = &cli.command
+	{
+		bucket = bucket.with_content_type(content_type);
+	}
+	if let Command::Upload {
+		compress: Some(compress),
+		..
+	} = &cli.command
+	{
+		bucket = bucket.with_compression(compress.to_compression());
+	}
+	if let Command::Upload {
+		split_size: Some(split_size),
+		..
+	} = &cli.command
+	{
+		bucket = bucket.with_split_size(*split_size);
+	}
+	if cli.no_decompress {
+		bucket = bucket.with_decompression(false);
+	}
+	if cli.preserve_mode {
+		bucket = bucket.with_preserve_mode(true);
+	}
+	bucket = bucket.with_symlinks(cli.symlinks.to_symlink_mode());
+	if cli.pre_hook.is_some() || cli.post_hook.is_some() {
+		let hooks = CommandHooks::new(cli.pre_hook.clone(), cli.post_hook.clone());
+		bucket = bucket.with_hooks(Arc::new(hooks));
+	}
+	if let Some(path) = config.audit_log(cli) {
+		let requester = client_config(cli, target, false).requester_identity().await?;
+		bucket = bucket.with_audit_log(Arc::new(AuditLog::open(path, requester)?));
+	}
+	if let Some(storage_class) = cli.storage_class {
+		bucket = bucket.with_storage_class(storage_class.to_storage_class());
+	}
+	if let Some(request_payer) = cli.request_payer {
+		bucket = bucket.with_request_payer(request_payer.to_request_payer());
+	}
+	if let Some(path) = &cli.cse_key_file {
+		bucket = bucket.with_client_encryption(ClientEncryption::from_file(path)?);
+	} else if let Some(key) = &cli.cse_key {
+		bucket = bucket.with_client_encryption(ClientEncryption::from_base64(key)?);
+	}
+	// Note: no progress bars for the (unattended) daemon runs, while the JSON events are always emitted when asked.
+	let mut progress: Option<Arc<dyn TransferProgress>> = None;
+	if cli.progress == ProgressFormat::Json {
+		progress = Some(Arc::new(JsonProgress::new(cli.progress_stream)));
+	} else if !cli.quiet && !matches!(cli.command, Command::Daemon { .. }) {
+		progress = Some(Arc::new(BarProgress::new()));
+	}
+	if let Some(metrics) = metrics() {
+		progress = Some(Arc::new(MetricsProgress::new(metrics, progress)));
+	}
+	if let Some(progress) = progress {
+		bucket = bucket.with_progress(progress);
+	}
+
+	Ok(bucket)
+}
+
+/// The key of a single file upload: --key, or --prefix + the file name, or the path.
+fn upload_key(path: &Path, key: Option<String>, prefix: Option<String>) -> Result<String> {
+	let file_name = path.file_name().and_then(|s| s.to_str());
+	let key = match (key, prefix, file_name) {
+		(Some(key), _, _) => key,
+		(None, Some(prefix), Some(file_name)) => join_key(&prefix, file_name),
+		_ => path_key(path).ok_or_else(|| usage_error(format!("Invalid path {path:?}")))?,
+	};
+	Ok(key)
+}
+
+/// The uploads of an `upload --from-manifest`: the listed files, to their key (per [`upload_key`]) unless set.
+fn upload_batch_plan(manifest: &Path, prefix: Option<String>) -> Result<Vec<Transfer>> {
+	read_batch_manifest(manifest)?
+		.into_iter()
+		.map(|entry| {
+			let path = PathBuf::from(entry.source);
+			let key = match entry.destination {
+				Some(key) => key,
+				None => upload_key(&path, None, prefix.clone())?,
+			};
+			Ok(Transfer::Upload { path, key })
+		})
+		.collect()
+}
+
+/// The downloads of a `download --from-manifest`: the listed keys, to their path under `dir` (the key unless set).
+fn download_batch_plan(manifest: &Path, dir: &Path) -> Result<Vec<Transfer>> {
+	let transfers = read_batch_manifest(manifest)?
+		.into_iter()
+		.map(|entry| {
+			// Note: the destination is a local path (as given), while the key is sanitized (see `key_path`).
+			let path = match &entry.destination {
+				Some(destination) => dir.join(destination),
+				None => key_path(dir, &entry.source)?,
+			};
+			Ok(Transfer::Download {
+				key: entry.source,
+				path,
+			})
+		})
+		.collect::<Result<_>>()?;
+	Ok(transfers)
+}
+
+/// Run the transfers of a `--from-manifest` batch, skipping the existing destinations per `overwrite`,
+/// and write the result manifest (if any), in the manifest order. Returns the numbers of transfers done and skipped,
+/// and the bytes transferred.
+async fn run_batch(
+	bucket: &S3Bucket,
+	transfers: Vec<Transfer>,
+	overwrite: Overwrite,
+	result_manifest: Option<&Path>,
+) -> Result<(usize, usize, u64)> {
+	// PLAN - the transfers to run (with their manifest index), checking the destinations concurrently
+	let checked = stream::iter(transfers)
+		.map(|transfer| async move {
+			let will = will_transfer(bucket, transfer.clone(), overwrite).await?;
+			Ok::<_, anyhow::Error>((transfer, will))
+		})
+		.buffered(bucket.concurrency())
+		.try_collect::<Vec<_>>()
+		.await?;
+	let mut pending = Vec::new();
+	let mut order = HashMap::new();
+	let mut rows = Vec::new();
+	for (index, (transfer, will)) in checked.into_iter().enumerate() {
+		if will {
+			order.insert((transfer.key().to_string(), transfer.path().to_path_buf()), index);
+			pending.push(transfer);
+		} else {
+			rows.push((index, BatchResult::skipped(&transfer)));
+		}
+	}
+	let skipped = rows.len();
 
 	// EXECUTE
-	let res = req.send().await?;
+	let results = TransferManager::new(bucket).run(pending).await;
+	let mut errors = Vec::new();
+	for res in &results {
+		if let Err(ex) = &res.result {
+			errors.push(format!("{}: {ex}", res.transfer.key()));
+		}
+		let index = order.get(&(res.transfer.key().to_string(), res.transfer.path().to_path_buf()));
+		rows.push((index.copied().unwrap_or(usize::MAX), BatchResult::from_result(res)));
+	}
+	let bytes = rows.iter().filter_map(|(_, row)| row.size).sum();
 
-	// COLLECT
-	let keys = res.contents().unwrap_or_default();
-	let keys = keys
+	// REPORT - the result manifest
+	if let Some(path) = result_manifest {
+		rows.sort_by_key(|(index, _)| *index);
+		let rows = rows.into_iter().map(|(_, row)| row).collect::<Vec<_>>();
+		write_batch_results(path, &rows)?;
+	}
+
+	let total = results.len();
+	if !errors.is_empty() {
+		return Err(S3DemoError::TransfersFailed { errors, total }.into());
+	}
+	Ok((total, skipped, bytes))
+}
+
+/// The source remote and prefix of a bucket to bucket sync (when the sync "dir" is a `<remote>:<prefix>`).
+fn sync_source_remote(config: &Config, command: &Command) -> Option<(Remote, String)> {
+	let Command::Sync { dir, .. } = command else {
+		return None;
+	};
+	let mut prefix = dir.to_str()?.to_string();
+	let remote = config.take_key_remote(&mut prefix)?;
+	Some((remote, prefix))
+}
+
+/// Set the regions of the `src` and `dst` targets to the ones of their buckets (resolved with the `src_bucket` client),
+/// when reached with the same credentials, so that no request is redirected (301). Returns whether the `src` one changed.
+async fn resolve_regions(src_bucket: &S3Bucket, src: &mut Target, dst: &mut Target) -> Result<bool> {
+	if !src.same_account(dst) {
+		return Ok(false);
+	}
+	let src_region = resolve_bucket_region(src_bucket.client(), &src.bucket, &src.region).await?;
+	dst.region = resolve_bucket_region(src_bucket.client(), &dst.bucket, &src.region).await?;
+	if src_region == src.region {
+		return Ok(false);
+	}
+	src.region = src_region;
+	Ok(true)
+}
+
+/// Sync the `src_prefix` of the `src_remote` bucket to the sync prefix (which can target another remote),
+/// with server-side copies when both are reached with the same account (even in other regions).
+async fn sync_buckets(mut cli: Cli, config: &Config, src_remote: Remote, src_prefix: &str) -> Result<()> {
+	let dst_remote = config.take_remote(&mut cli.command)?;
+	let mut src_target = config.target(&cli, Some(&src_remote));
+	let mut dst_target = config.target(&cli, dst_remote.as_ref());
+	detect_region(&cli, &mut src_target).await?;
+	detect_region(&cli, &mut dst_target).await?;
+	let mut src = open_bucket(&cli, config, &src_target).await?;
+	if resolve_regions(&src, &mut src_target, &mut dst_target).await? {
+		src = open_bucket(&cli, config, &src_target).await?;
+	}
+	let dst = open_bucket(&cli, config, &dst_target).await?;
+	let notifier = build_notifier(&cli, config, &dst_target).await?;
+
+	let Command::Sync {
+		prefix,
+		down,
+		hidden,
+		no_ignore_file,
+		delete,
+		yes,
+		max_delete,
+		index,
+		filter,
+		overwrite,
+		..
+	} = cli.command
+	else {
+		return Err(usage_error("Only sync can copy a bucket prefix to another"));
+	};
+	if down || hidden || index || no_ignore_file {
+		return Err(usage_error(
+			"--down, --hidden, --index and --no-ignore-file are only for the local directory syncs",
+		));
+	}
+	let opts = SyncOptions {
+		filter: filter.to_filter()?,
+		delete,
+		overwrite: overwrite.to_overwrite(),
+		..Default::default()
+	};
+	let plan = src.sync_bucket_plan(src_prefix, &dst, &prefix, &opts).await?;
+
+	// DRY RUN - (list requests only)
+	if cli.dry_run {
+		let mut actions: Vec<(&str, String, String, Option<u64>)> = plan
+			.copies
+			.iter()
+			.map(|c| {
+				(
+					"copy",
+					c.src_key.clone(),
+					format!("{}/{}", dst.name(), c.dst_key),
+					Some(c.size),
+				)
+			})
+			.collect();
+		let sizes = remote_sizes(&dst, &prefix).await?;
+		for key in plan.delete_keys {
+			let size = sizes.get(&key).copied();
+			actions.push(("delete", format!("{}/{key}", dst.name()), String::new(), size));
+		}
+		print_dry_run(&actions);
+		return Ok(());
+	}
+
+	// CONFIRM - the deletes
+	let delete_count = plan.delete_keys.len();
+	let targets = plan
+		.delete_keys
 		.iter()
-		.filter_map(|o| o.key.as_ref())
-		.map(|s| s.to_string())
+		.map(|key| format!("{}/{key}", dst.name()))
 		.collect::<Vec<_>>();
+	if !confirm_deletes(&targets, "objects", yes, max_delete)? {
+		bail!("Sync aborted (nothing copied or deleted)");
+	}
 
-	Ok(keys)
+	let timer = Instant::now();
+	let res = src
+		.sync_bucket_apply(&dst, plan, src_target.same_account(&dst_target))
+		.await
+		.map_err(anyhow::Error::from);
+	let (source, destination) = (
+		format!("{}/{src_prefix}", src.name()),
+		format!("{}/{prefix}", dst.name()),
+	);
+	let mut summary = run_summary("sync", source, destination, timer, res.as_ref().err());
+	if let Ok(plan) = &res {
+		summary.transferred = plan.copies.len();
+		summary.bytes = plan.copies.iter().map(|c| c.size).sum();
+		summary.unchanged = plan.unchanged;
+		summary.skipped = plan.skipped;
+		summary.deleted = delete_count;
+	}
+	if let Ok(plan) = &res {
+		for copy in plan.copies.iter() {
+			println!("copy {} -> {}/{}", copy.src_key, dst.name(), copy.dst_key);
+		}
+		println!(
+			"Synced {} objects to {}/{prefix} ({} unchanged, {} skipped, {delete_count} deleted)",
+			plan.copies.len(),
+			dst.name(),
+			plan.unchanged,
+			plan.skipped
+		);
+	}
+	finish_run(summary, "copied", cli.report.as_deref(), notifier.as_ref()).await?;
+	res?;
+
+	Ok(())
+}
+
+/// Print the files/objects `command` would transfer or delete, with the total size,
+/// using only read requests (list, head).
+async fn dry_run(bucket: &S3Bucket, command: Command) -> Result<()> {
+	// (label, source, destination, size)
+	let mut actions: Vec<(&str, String, String, Option<u64>)> = Vec::new();
+
+	match command {
+		Command::Upload {
+			prefix,
+			overwrite,
+			batch: BatchArgs {
+				from_manifest: Some(manifest),
+				..
+			},
+			..
+		} => {
+			for transfer in upload_batch_plan(&manifest, prefix)? {
+				if will_transfer(bucket, transfer.clone(), overwrite.to_overwrite()).await? {
+					actions.push(transfer_action(&transfer, &HashMap::new()));
+				}
+			}
+		}
+		Command::Download {
+			dir,
+			overwrite,
+			batch: BatchArgs {
+				from_manifest: Some(manifest),
+				..
+			},
+			..
+		} => {
+			for transfer in download_batch_plan(&manifest, &dir)? {
+				if will_transfer(bucket, transfer.clone(), overwrite.to_overwrite()).await? {
+					let size = bucket.stat(transfer.key()).await?.size;
+					let (label, src, dst, _) = transfer_action(&transfer, &HashMap::new());
+					actions.push((label, src, dst, Some(size)));
+				}
+			}
+		}
+		Command::Upload {
+			path: Some(path), key, ..
+		} if path == Path::new(STDIN_PATH) => {
+			let key = key.ok_or_else(|| usage_error("--key is required to upload from stdin"))?;
+			actions.push(("upload", "stdin".to_string(), key, None));
+		}
+		Command::Upload {
+			path: Some(path),
+			key: None,
+			prefix,
+			hidden,
+			no_ignore_file,
+			filter,
+			overwrite,
+			skip_identical,
+			..
+		} if path.is_dir() => {
+			let prefix = match prefix {
+				Some(prefix) => prefix,
+				None => path_key(&path).ok_or_else(|| usage_error(format!("Invalid path {path:?}")))?,
+			};
+			let opts = UploadDirOptions {
+				include_hidden: hidden,
+				filter: filter.to_filter()?,
+				symlinks: bucket.symlinks(),
+				no_ignore_file,
+			};
+			let transfers = upload_dir_plan(&path, &prefix, &opts)?;
+			let (mut transfers, _) = bucket
+				.skip_existing(transfers, &prefix, overwrite.to_overwrite())
+				.await?;
+			if skip_identical {
+				transfers = bucket.skip_identical(transfers).await?.0;
+			}
+			actions.extend(transfers.iter().map(|t| transfer_action(t, &HashMap::new())));
+		}
+		Command::Upload {
+			path: Some(path),
+			key,
+			prefix,
+			overwrite,
+			skip_identical,
+			..
+		} => {
+			let key = upload_key(&path, key, prefix)?;
+			let transfer = Transfer::Upload {
+				path: path.clone(),
+				key: key.clone(),
+			};
+			let identical = skip_identical && bucket.is_identical(&path, &key).await?;
+			if !identical && will_transfer(bucket, transfer, overwrite.to_overwrite()).await? {
+				let size = path.metadata()?.len();
+				actions.push(("upload", path.display().to_string(), key, Some(size)));
+			}
+		}
+		Command::Download {
+			key: Some(key),
+			dir,
+			recursive: true,
+			overwrite,
+			..
+		} => {
+			let transfers = bucket.download_prefix_plan(&key, &dir).await?;
+			let (transfers, _) = bucket.skip_existing(transfers, &key, overwrite.to_overwrite()).await?;
+			let sizes = remote_sizes(bucket, &key).await?;
+			actions.extend(transfers.iter().map(|t| transfer_action(t, &sizes)));
+		}
+		Command::Download {
+			key: Some(key),
+			dir,
+			version_id: Some(version_id),
+			..
+		} => {
+			let versions = bucket.list_key_versions(&key).await?;
+			let version = versions
+				.iter()
+				.find(|v| v.version_id == version_id)
+				.ok_or_else(|| anyhow!("No version {version_id} of {key}"))?;
+			let path = key_path(&dir, &key)?.display().to_string();
+			actions.push(("download", format!("{key} ({version_id})"), path, Some(version.size)));
+		}
+		Command::Download {
+			key: Some(key),
+			dir,
+			overwrite,
+			..
+		} => {
+			let stat = bucket.stat(&key).await?;
+			let path = key_path(&dir, &key)?;
+			let transfer = Transfer::Download {
+				key: key.clone(),
+				path: path.clone(),
+			};
+			if will_transfer(bucket, transfer, overwrite.to_overwrite()).await? {
+				actions.push(("download", key, path.display().to_string(), Some(stat.size)));
+			}
+		}
+		Command::Sync {
+			dir,
+			prefix,
+			down,
+			hidden,
+			no_ignore_file,
+			delete,
+			index,
+			filter,
+			overwrite,
+			..
+		} => {
+			let direction = if down { SyncDirection::Down } else { SyncDirection::Up };
+			let opts = SyncOptions {
+				include_hidden: hidden,
+				filter: filter.to_filter()?,
+				delete,
+				overwrite: overwrite.to_overwrite(),
+				index: index.then(default_sync_index_path),
+				no_ignore_file,
+			};
+			let plan = bucket.sync_plan(&dir, &prefix, direction, &opts).await?;
+			let sizes = remote_sizes(bucket, &prefix).await?;
+			actions.extend(plan.transfers.iter().map(|t| transfer_action(t, &sizes)));
+			for key in plan.delete_keys {
+				let size = sizes.get(&key).copied();
+				actions.push(("delete", key, String::new(), size));
+			}
+			for path in plan.delete_paths {
+				let size = path.metadata().ok().map(|m| m.len());
+				actions.push(("delete", path.display().to_string(), String::new(), size));
+			}
+		}
+		Command::Rm {
+			key,
+			recursive: true,
+			trash,
+			filter,
+			..
+		} => {
+			let filter = filter.to_filter()?;
+			let label = if trash { "trash" } else { "delete" };
+			let objects = bucket.list_objects(&join_key(&key, "")).await?;
+			for o in objects.into_iter().filter(|o| filter.is_match(&o.key)) {
+				actions.push((label, o.key, String::new(), Some(o.size)));
+			}
+		}
+		Command::Rm {
+			key,
+			version_id: Some(version_id),
+			..
+		} => {
+			actions.push(("delete", format!("{key} ({version_id})"), String::new(), None));
+		}
+		Command::Multipart {
+			command: MultipartCommand::Abort { prefix, older_than },
+		} => {
+			let uploads = bucket.list_multipart_uploads(&prefix).await?;
+			for upload in uploads.into_iter().filter(|u| u.is_older_than(older_than)) {
+				actions.push((
+					"abort",
+					format!("{} ({})", upload.key, upload.upload_id),
+					String::new(),
+					None,
+				));
+			}
+		}
+		Command::Rm { key, trash, .. } => {
+			let label = if trash { "trash" } else { "delete" };
+			let size = bucket.stat(&key).await.ok().map(|s| s.size);
+			actions.push((label, key, String::new(), size));
+		}
+		Command::Cp {
+			src_key,
+			dst_key,
+			dest_bucket,
+		}
+		| Command::Mv {
+			src_key,
+			dst_key,
+			dest_bucket,
+		} => {
+			let dst_bucket = dest_bucket.as_deref().unwrap_or(bucket.name());
+			let stat = bucket.stat(&src_key).await?;
+			actions.push(("copy", src_key, format!("{dst_bucket}/{dst_key}"), Some(stat.size)));
+		}
+		Command::Find {
+			prefix,
+			query,
+			filter,
+			delete,
+			copy_to,
+			..
+		} => {
+			let prefix = prefix.unwrap_or_default();
+			let objects = bucket.find(&prefix, &query.to_query(filter.to_filter()?)?).await?;
+			match copy_to {
+				Some(copy_to) => {
+					let sizes = objects.iter().map(|o| o.size);
+					for ((src_key, dst_key), size) in find_copies(&prefix, &copy_to, &objects).zip(sizes) {
+						actions.push(("copy", src_key, dst_key, Some(size)));
+					}
+				}
+				None => {
+					let label = if delete { "delete" } else { "find" };
+					for o in objects {
+						actions.push((label, o.key, String::new(), Some(o.size)));
+					}
+				}
+			}
+		}
+		_ => {
+			return Err(usage_error(
+				"--dry-run is only supported by upload, download, sync, rm, cp, mv, find and multipart abort",
+			))
+		}
+	}
+
+	print_dry_run(&actions);
+
+	Ok(())
+}
+
+/// Print the dry run `actions` (label, source, destination, size) and their total size.
+fn print_dry_run(actions: &[(&str, String, String, Option<u64>)]) {
+	let mut total = 0;
+	for (label, src, dst, size) in actions.iter() {
+		total += size.unwrap_or(0);
+		let size = size.map(human_size).unwrap_or_else(|| "?".to_string());
+		let dst = if dst.is_empty() {
+			String::new()
+		} else {
+			format!(" -> {dst}")
+		};
+		println!("{label:<8} {size:>10}  {src}{dst}");
+	}
+	println!(
+		"Dry run: {} files/objects, {} total (nothing changed)",
+		actions.len(),
+		human_size(total)
+	);
 }
 
-fn get_aws_client(region: &str) -> Result<Client> {
-	// get the id/secret from env
-	let key_id = env::var(ENV_CRED_KEY_ID).context("Missing S3_KEY_ID")?;
-	let key_secret = env::var(ENV_CRED_KEY_SECRET).context("Missing S3_KEY_SECRET")?;
+/// The sizes of the objects under the `prefix` "directory", by key.
+async fn remote_sizes(bucket: &S3Bucket, prefix: &str) -> Result<HashMap<String, u64>> {
+	let objects = bucket.list_objects(&join_key(prefix, "")).await?;
+	Ok(objects.into_iter().map(|o| (o.key, o.size)).collect())
+}
+
+/// The dry run action of a transfer (with the local size for uploads, or the remote one from `sizes`).
+fn transfer_action(transfer: &Transfer, sizes: &HashMap<String, u64>) -> (&'static str, String, String, Option<u64>) {
+	let path = transfer.path().display().to_string();
+	let key = transfer.key().to_string();
+	match transfer {
+		Transfer::Upload { path: p, .. } => {
+			let size = p.metadata().ok().map(|m| m.len());
+			("upload", path, key, size)
+		}
+		Transfer::Download { .. } => {
+			let size = sizes.get(&key).copied();
+			("download", key, path, size)
+		}
+	}
+}
 
-	// build the aws cred
-	let cred = Credentials::new(key_id, key_secret, None, None, "loaded-from-custom-env");
+/// The (source, destination) keys of `find --copy-to`: the key relative to the searched "directory"
+/// (`prefix` up to its last `/`) under `copy_to`.
+fn find_copies<'a>(
+	prefix: &str,
+	copy_to: &'a str,
+	objects: &'a [ObjectInfo],
+) -> impl Iterator<Item = (String, String)> + 'a {
+	let base_len = prefix.rfind('/').map(|i| i + 1).unwrap_or(0);
+	objects.iter().map(move |o| {
+		let rel_key = o.key.get(base_len..).unwrap_or(&o.key);
+		(o.key.clone(), join_key(copy_to, rel_key))
+	})
+}
 
-	// build the aws client
-	let region = Region::new(region.to_string());
-	let conf_builder = config::Builder::new().region(region).credentials_provider(cred);
-	let conf = conf_builder.build();
+/// Whether a single `transfer` runs per the overwrite policy (i.e., its destination is missing or can be overwritten).
+async fn will_transfer(bucket: &S3Bucket, transfer: Transfer, overwrite: Overwrite) -> Result<bool> {
+	let key = transfer.key().to_string();
+	let (transfers, _) = bucket.skip_existing(vec![transfer], &key, overwrite).await?;
+	Ok(!transfers.is_empty())
+}
 
-	// build aws client
-	let client = Client::from_conf(conf);
-	Ok(client)
+/// The skipped counts suffix of a transfer summary: the existing destinations kept (per the overwrite policy),
+/// and the uploads identical to their object.
+fn skipped_label(existing: usize, identical: usize) -> String {
+	match (existing, identical) {
+		(0, 0) => String::new(),
+		(existing, 0) => format!(" ({existing} existing skipped)"),
+		(0, identical) => format!(" ({identical} identical skipped)"),
+		(existing, identical) => format!(" ({existing} existing, {identical} identical skipped)"),
+	}
+}
+
+/// Print the differences of a `diff`, one path per line, then their counts.
+fn print_diff(diff: &DirDiff) {
+	for path in diff.only_local.iter() {
+		println!("only-local   {path}");
+	}
+	for path in diff.only_remote.iter() {
+		println!("only-remote  {path}");
+	}
+	for entry in diff.different.iter() {
+		let reason = match entry.reason {
+			DiffReason::Size => format!(
+				"size: {} local, {} remote",
+				human_size(entry.local_size),
+				human_size(entry.remote_size)
+			),
+			DiffReason::Checksum => "checksum".to_string(),
+		};
+		println!("different    {} ({reason})", entry.path);
+	}
+	for path in diff.unverified.iter() {
+		println!("unverified   {path} (same size, no checksum to compare)");
+	}
+	println!(
+		"{} only local, {} only remote, {} different, {} unverified, {} identical",
+		diff.only_local.len(),
+		diff.only_remote.len(),
+		diff.different.len(),
+		diff.unverified.len(),
+		diff.identical
+	);
+}
+
+/// Ask a yes/no question on stdin (no when stdin is closed, e.g., not a terminal).
+fn confirm(question: &str) -> Result<bool> {
+	eprint!("{question} [y/N] ");
+	io::stderr().flush()?;
+	let mut answer = String::new();
+	io::stdin().read_line(&mut answer)?;
+	Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Confirm the deletes of the `targets` (`what` being deleted), each printed first, unless `yes`.
+/// Fails if there are more than `max_delete` targets (the safety valve of --max-delete, even with `yes`).
+fn confirm_deletes(targets: &[String], what: &str, yes: bool, max_delete: Option<usize>) -> Result<bool> {
+	let count = targets.len();
+	if let Some(max_delete) = max_delete.filter(|max| count > *max) {
+		bail!(
+			"{} {what} would be deleted, more than --max-delete {} (nothing done)",
+			human_count(count),
+			human_count(max_delete)
+		);
+	}
+	if count == 0 || yes {
+		return Ok(true);
+	}
+	for target in targets {
+		println!("delete {target}");
+	}
+	confirm(&format!("Delete {} {what}?", human_count(count)))
+}
+
+fn restore_label(status: &RestoreStatus) -> String {
+	match status {
+		RestoreStatus::NotArchived => "not archived".to_string(),
+		RestoreStatus::NotRestored => "archived (not restored)".to_string(),
+		RestoreStatus::InProgress => "restore in progress".to_string(),
+		RestoreStatus::Restored { expiry: Some(expiry) } => format!("restored until {expiry}"),
+		RestoreStatus::Restored { expiry: None } => "restored".to_string(),
+	}
+}
+
+fn is_broken_pipe(ex: &S3DemoError) -> bool {
+	matches!(ex, S3DemoError::Io(ex) if ex.kind() == io::ErrorKind::BrokenPipe)
+}
+
+fn transfer_label(transfer: &Transfer) -> &'static str {
+	match transfer {
+		Transfer::Upload { .. } => "upload",
+		Transfer::Download { .. } => "download",
+	}
+}
+
+fn print_stat(stat: &ObjectStat, output: OutputFormat) -> Result<()> {
+	match output {
+		OutputFormat::Json => println!("{}", serde_json::to_string_pretty(stat)?),
+		OutputFormat::Text => {
+			println!("key:           {}", stat.key);
+			println!("size:          {} ({})", stat.size, human_size(stat.size));
+			println!("content-type:  {}", stat.content_type.as_deref().unwrap_or_default());
+			let headers = [
+				("cache-control:", &stat.cache_control),
+				("content-disposition:", &stat.content_disposition),
+				("content-encoding:", &stat.content_encoding),
+				("content-language:", &stat.content_language),
+			];
+			for (name, value) in headers {
+				if let Some(value) = value {
+					println!("{name:<14} {value}");
+				}
+			}
+			println!("etag:          {}", stat.e_tag.as_deref().unwrap_or_default());
+			println!("last-modified: {}", human_time(stat.last_modified));
+			println!("storage-class: {}", stat.storage_class.as_deref().unwrap_or("STANDARD"));
+			match (
+				&stat.server_side_encryption,
+				&stat.sse_kms_key_id,
+				&stat.sse_customer_algorithm,
+			) {
+				(_, _, Some(algorithm)) => println!("encryption:    SSE-C ({algorithm})"),
+				(Some(sse), Some(key_id), _) => println!("encryption:    {sse} ({key_id})"),
+				(Some(sse), None, _) => println!("encryption:    {sse}"),
+				(None, _, _) => println!("encryption:    none"),
+			}
+			let restore = RestoreStatus::from_stat(stat);
+			if restore != RestoreStatus::NotArchived {
+				println!("restore:       {}", restore_label(&restore));
+			}
+			if !stat.metadata.is_empty() {
+				println!("metadata:");
+				let mut metadata = stat.metadata.iter().collect::<Vec<_>>();
+				metadata.sort();
+				for (name, value) in metadata {
+					println!("  {name}: {value}");
+				}
+			}
+		}
+	}
+	Ok(())
 }
@@ -0,0 +1,107 @@
+use crate::checksum::md5_hex;
+use crate::error::{io_context, Result, S3DemoError};
+use crate::store::ObjectStore;
+use crate::transfer::DEFAULT_CONCURRENCY;
+use crate::ObjectInfo;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// An in-memory [`ObjectStore`] (e.g., to test the directory and sync logic without S3).
+///
+/// The ETags are the content MD5 (like S3 single part uploads), and the last modified the put time.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+	name: String,
+	objects: Mutex<BTreeMap<String, MemoryObject>>,
+}
+
+#[derive(Debug, Clone)]
+struct MemoryObject {
+	data: Vec<u8>,
+	last_modified: SystemTime,
+}
+
+impl MemoryStore {
+	pub fn new(name: impl Into<String>) -> Self {
+		MemoryStore {
+			name: name.into(),
+			objects: Mutex::default(),
+		}
+	}
+
+	/// Put (or replace) the object `key`.
+	pub fn put(&self, key: impl Into<String>, data: impl Into<Vec<u8>>) {
+		let object = MemoryObject {
+			data: data.into(),
+			last_modified: SystemTime::now(),
+		};
+		self.lock().insert(key.into(), object);
+	}
+
+	/// The content of the object `key` (if any).
+	pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+		self.lock().get(key).map(|o| o.data.clone())
+	}
+
+	/// All the keys, sorted.
+	pub fn keys(&self) -> Vec<String> {
+		self.lock().keys().cloned().collect()
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, MemoryObject>> {
+		self.objects.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl ObjectStore for MemoryStore {
+	fn name(&self) -> &str {
+		&self.name
+	}
+
+	fn concurrency(&self) -> usize {
+		DEFAULT_CONCURRENCY
+	}
+
+	async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+		let objects = self
+			.lock()
+			.iter()
+			.filter(|(key, _)| key.starts_with(prefix))
+			.map(|(key, object)| ObjectInfo {
+				key: key.clone(),
+				size: object.data.len() as u64,
+				last_modified: Some(object.last_modified),
+				e_tag: Some(format!("\"{}\"", md5_hex(&object.data))),
+				storage_class: None,
+				mtime: None,
+			})
+			.collect();
+		Ok(objects)
+	}
+
+	async fn upload_to(&self, path: &Path, key: &str) -> Result<()> {
+		let data = fs::read(path).map_err(|ex| io_context(ex, format!("Cannot read {}", path.display())))?;
+		self.put(key, data);
+		Ok(())
+	}
+
+	async fn download_to(&self, key: &str, file_path: &Path) -> Result<()> {
+		let data = self.get(key).ok_or_else(|| S3DemoError::NotFound(key.to_string()))?;
+		if let Some(parent_dir) = file_path.parent() {
+			fs::create_dir_all(parent_dir)?;
+		}
+		fs::write(file_path, data)?;
+		Ok(())
+	}
+
+	async fn delete_objects(&self, keys: &[String]) -> Result<()> {
+		let mut objects = self.lock();
+		for key in keys {
+			objects.remove(key);
+		}
+		Ok(())
+	}
+}
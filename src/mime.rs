@@ -0,0 +1,51 @@
+use crate::error::{io_context, Result, S3DemoError};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Custom file extension to MIME type mapping, taking precedence over the `mime_guess` defaults.
+///
+/// Loaded from a TOML file of `extension = "mime/type"` entries, e.g.:
+///
+/// ```toml
+/// webmanifest = "application/manifest+json"
+/// md = "text/markdown; charset=utf-8"
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MimeMap {
+	by_ext: HashMap<String, String>,
+}
+
+impl MimeMap {
+	pub fn from_file(path: &Path) -> Result<Self> {
+		let content = fs::read_to_string(path)
+			.map_err(|ex| io_context(ex, format!("Cannot read MIME map {}", path.display())))?;
+		Self::from_toml(&content)
+			.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid MIME map {}: {ex}", path.display())))
+	}
+
+	pub fn from_toml(content: &str) -> Result<Self> {
+		let entries: HashMap<String, String> =
+			toml::from_str(content).map_err(|ex| S3DemoError::InvalidInput(ex.to_string()))?;
+		let by_ext = entries
+			.into_iter()
+			.map(|(ext, mime)| (normalize_ext(&ext), mime))
+			.collect();
+		Ok(MimeMap { by_ext })
+	}
+
+	/// Add (or replace) the MIME type of the extension `ext` (with or without the leading dot).
+	pub fn insert(&mut self, ext: &str, mime: impl Into<String>) {
+		self.by_ext.insert(normalize_ext(ext), mime.into());
+	}
+
+	/// The custom MIME type of `path` (from its extension), if any.
+	pub fn get(&self, path: &Path) -> Option<&str> {
+		let ext = path.extension()?.to_str()?;
+		self.by_ext.get(&normalize_ext(ext)).map(|s| s.as_str())
+	}
+}
+
+fn normalize_ext(ext: &str) -> String {
+	ext.trim_start_matches('.').to_lowercase()
+}
@@ -0,0 +1,393 @@
+use crate::audit::{AuditEntry, AuditOperation};
+use crate::bucket::{until_deadline, PutAttrs};
+use crate::checksum::md5_base64;
+use crate::conditional::write_error;
+use crate::error::{Result, S3DemoError};
+use crate::key::listing_key;
+use crate::tagging::tagging_header;
+use crate::upload_state::{mtime_secs, PartState, UploadState};
+use crate::S3Bucket;
+use aws_sdk_s3::model::{CompletedMultipartUpload, CompletedPart, EncodingType};
+use aws_sdk_s3::ByteStream;
+use futures::stream::{self, StreamExt};
+use std::io::SeekFrom;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+use tracing::{debug, info, instrument, warn};
+
+// -- constants
+/// S3 minimum part size (except for the last part).
+pub const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+/// S3 maximum number of parts for one upload.
+pub const MAX_PARTS: usize = 10_000;
+pub const DEFAULT_PART_SIZE: usize = 8 * 1024 * 1024;
+pub const DEFAULT_MULTIPART_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// An in-progress (not completed nor aborted) multipart upload.
+#[derive(Debug, Clone)]
+pub struct MultipartUploadInfo {
+	pub key: String,
+	pub upload_id: String,
+	pub initiated: Option<SystemTime>,
+}
+
+impl MultipartUploadInfo {
+	/// Whether the upload was initiated more than `age` ago (false when the initiation time is unknown).
+	pub fn is_older_than(&self, age: Duration) -> bool {
+		self.initiated
+			.and_then(|initiated| SystemTime::now().duration_since(initiated).ok())
+			.is_some_and(|elapsed| elapsed > age)
+	}
+}
+
+impl S3Bucket {
+	/// Upload the file at `path` to `key` with a multipart upload of `part_size` parts.
+	///
+	/// With resumable uploads, the upload state is persisted after each part, and an interrupted
+	/// upload of the same (unchanged) file resumes from the last completed part.
+	/// Otherwise, on failure, the multipart upload is aborted so no orphan parts are left behind.
+	pub(crate) async fn upload_multipart(&self, path: &Path, key: &str, attrs: &PutAttrs) -> Result<()> {
+		let store = self.upload_states();
+		let file_size = path.metadata()?.len();
+		let file_mtime = mtime_secs(path);
+		let part_size = self.part_size_for(file_size);
+
+		// RESUME - or create the multipart upload
+		let resumed =
+			store.and_then(|store| store.load_resumable(self.name(), key, path, file_size, file_mtime, part_size));
+		let mut state = match resumed {
+			Some(state) => {
+				info!(upload_id = %state.upload_id, parts = state.parts.len(), "resuming multipart upload");
+				state
+			}
+			None => {
+				let upload_id = until_deadline(attrs.deadline, key, self.create_multipart(key, attrs)).await?;
+				debug!(%upload_id, "created multipart upload");
+				UploadState {
+					bucket: self.name().to_string(),
+					key: key.to_string(),
+					path: path.canonicalize()?,
+					upload_id,
+					file_size,
+					file_mtime,
+					part_size,
+					parts: Vec::new(),
+				}
+			}
+		};
+		if let Some(store) = store {
+			store.save(&state)?;
+		}
+
+		// UPLOAD - the parts
+		let res = until_deadline(attrs.deadline, key, self.upload_parts(path, &mut state)).await;
+		if let Err(ex) = res {
+			return Err(self.on_multipart_error(&state, ex).await);
+		}
+
+		// EXECUTE - complete the multipart upload
+		let res = until_deadline(
+			attrs.deadline,
+			key,
+			self.complete_multipart(key, &state.upload_id, &state.parts, state.file_size),
+		)
+		.await;
+		if let Err(ex) = res {
+			return Err(self.on_multipart_error(&state, ex).await);
+		}
+
+		if let Some(store) = store {
+			store.remove(self.name(), key, path);
+		}
+
+		Ok(())
+	}
+
+	pub(crate) async fn create_multipart(&self, key: &str, attrs: &PutAttrs) -> Result<String> {
+		// BUILD & EXECUTE - aws request
+		let ck = self.customer_key();
+		let headers = self.content_headers();
+		let res = self
+			.client()
+			.create_multipart_upload()
+			.bucket(self.name())
+			.key(key)
+			.content_type(&attrs.content_type)
+			.set_metadata(Some(attrs.metadata.clone()))
+			.set_server_side_encryption(self.encryption().and_then(|e| e.server_side_encryption()))
+			.set_ssekms_key_id(self.encryption().and_then(|e| e.kms_key_id()))
+			.set_storage_class(self.storage_class().cloned())
+			.set_tagging(tagging_header(self.tags()))
+			.set_cache_control(headers.cache_control.clone())
+			.set_content_disposition(headers.content_disposition.clone())
+			.set_content_encoding(attrs.content_encoding.clone())
+			.set_content_language(headers.content_language.clone())
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()))
+			.send()
+			.await?;
+
+		let upload_id = res
+			.upload_id()
+			.ok_or_else(|| S3DemoError::InvalidResponse(format!("No upload_id for multipart upload of {key}")))?;
+
+		Ok(upload_id.to_string())
+	}
+
+	/// Upload one part (with its `Content-MD5` when checksums are on).
+	#[instrument(level = "debug", skip_all, fields(key = %key, part_number, bytes = buf.len()))]
+	pub(crate) async fn upload_part(
+		&self,
+		key: &str,
+		upload_id: &str,
+		part_number: i32,
+		buf: Vec<u8>,
+	) -> Result<PartState> {
+		let content_md5 = self.checksums().then(|| md5_base64(&buf));
+
+		// BUILD & EXECUTE - aws request
+		let ck = self.customer_key();
+		let res = self
+			.client()
+			.upload_part()
+			.bucket(self.name())
+			.key(key)
+			.upload_id(upload_id)
+			.part_number(part_number)
+			.set_content_md5(content_md5)
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()))
+			.body(ByteStream::from(buf))
+			.send()
+			.await?;
+
+		Ok(PartState {
+			part_number,
+			e_tag: res.e_tag().map(|s| s.to_string()),
+		})
+	}
+
+	/// Complete the multipart upload of `parts`. Returns the ETag of the object (if any).
+	pub(crate) async fn complete_multipart(
+		&self,
+		key: &str,
+		upload_id: &str,
+		parts: &[PartState],
+		size: u64,
+	) -> Result<Option<String>> {
+		let parts = parts
+			.iter()
+			.map(|p| {
+				CompletedPart::builder()
+					.set_e_tag(p.e_tag.clone())
+					.part_number(p.part_number)
+					.build()
+			})
+			.collect::<Vec<_>>();
+		let completed = CompletedMultipartUpload::builder().set_parts(Some(parts)).build();
+
+		// BUILD & EXECUTE - aws request
+		let res = self
+			.client()
+			.complete_multipart_upload()
+			.bucket(self.name())
+			.key(key)
+			.upload_id(upload_id)
+			.multipart_upload(completed)
+			.send()
+			.await
+			.map_err(|err| write_error(key, err.into()))?;
+
+		// AUDIT
+		let mut entry = AuditEntry::new(AuditOperation::Put, self.name(), key);
+		entry.version_id = res.version_id().map(|v| v.to_string());
+		entry.size = Some(size);
+		entry.etag = res.e_tag().map(|s| s.to_string());
+		self.audit(entry)?;
+
+		Ok(res.e_tag().map(|s| s.to_string()))
+	}
+
+	/// Upload the remaining parts of `state`, [`S3Bucket::part_concurrency`] at a time, recording each completed part.
+	///
+	/// Note: the parts are recorded in order, so the parts of the state are always the first parts of the file.
+	async fn upload_parts(&self, path: &Path, state: &mut UploadState) -> Result<()> {
+		let part_size = state.part_size;
+		let part_count = state.file_size.div_ceil(part_size as u64) as usize;
+		let key = state.key.clone();
+		let upload_id = state.upload_id.clone();
+		let store = self.upload_states();
+		if part_count > MAX_PARTS {
+			return Err(S3DemoError::InvalidInput(format!(
+				"File {} needs more than {MAX_PARTS} parts of {part_size} bytes",
+				path.display()
+			)));
+		}
+
+		// SKIP - the parts already uploaded
+		let done_bytes = (state.parts.len() * part_size) as u64;
+		self.progress_advance(&key, done_bytes.min(state.file_size));
+
+		// UPLOAD - the other parts, each read at its offset
+		let (key, upload_id) = (key.as_str(), upload_id.as_str());
+		let mut parts = stream::iter(state.parts.len()..part_count)
+			.map(|index| async move {
+				self.check_shutdown(key)?;
+				let mut file = File::open(path).await?;
+				file.seek(SeekFrom::Start((index * part_size) as u64)).await?;
+				let buf = read_part(&mut file, part_size).await?;
+				let buf_len = buf.len() as u64;
+				let part = self.upload_part(key, upload_id, index as i32 + 1, buf).await?;
+				Ok::<_, S3DemoError>((part, buf_len))
+			})
+			.buffered(self.part_concurrency());
+		while let Some(res) = parts.next().await {
+			let (part, buf_len) = res?;
+			state.parts.push(part);
+			if let Some(store) = store {
+				store.save(state)?;
+			}
+			self.progress_advance(key, buf_len);
+		}
+
+		Ok(())
+	}
+
+	/// Resumable uploads are kept for the next run, the others are aborted.
+	async fn on_multipart_error(&self, state: &UploadState, ex: S3DemoError) -> S3DemoError {
+		if self.upload_states().is_some() {
+			warn!(key = %state.key, parts = state.parts.len(), error = %ex, "multipart upload interrupted, kept to resume");
+			S3DemoError::MultipartInterrupted {
+				key: state.key.clone(),
+				parts: state.parts.len(),
+				source: Box::new(ex),
+			}
+		} else {
+			// Note: best effort, the original error is the one reported.
+			warn!(key = %state.key, upload_id = %state.upload_id, error = %ex, "aborting failed multipart upload");
+			let _ = self.abort_multipart_upload(&state.key, &state.upload_id).await;
+			ex
+		}
+	}
+}
+
+impl S3Bucket {
+	/// Abort the multipart upload `upload_id` of `key` (S3 deletes its uploaded parts).
+	pub async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+		// BUILD & EXECUTE - aws request
+		self.client()
+			.abort_multipart_upload()
+			.bucket(self.name())
+			.key(key)
+			.upload_id(upload_id)
+			.send()
+			.await?;
+
+		// CLEAN - the persisted state (if any)
+		if let Some(store) = self.upload_states() {
+			for state in store.find(self.name(), key) {
+				if state.upload_id == upload_id {
+					store.remove(self.name(), key, &state.path);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Abort all the in-progress multipart uploads of `key`. Returns the aborted upload ids.
+	pub async fn abort_multipart_uploads(&self, key: &str) -> Result<Vec<String>> {
+		let uploads = self.list_multipart_uploads(key).await?;
+
+		let mut upload_ids = Vec::new();
+		for upload in uploads.into_iter().filter(|u| u.key == key) {
+			self.abort_multipart_upload(key, &upload.upload_id).await?;
+			upload_ids.push(upload.upload_id);
+		}
+
+		// CLEAN - the stale persisted states (uploads already completed or aborted elsewhere)
+		if let Some(store) = self.upload_states() {
+			for state in store.find(self.name(), key) {
+				store.remove(self.name(), key, &state.path);
+			}
+		}
+
+		Ok(upload_ids)
+	}
+
+	/// Abort the in-progress multipart uploads of the keys starting with `prefix` initiated more than `older_than`
+	/// ago (e.g., abandoned by a failed resumable upload, their parts still billed). Returns the aborted uploads.
+	pub async fn abort_stale_multipart_uploads(
+		&self,
+		prefix: &str,
+		older_than: Duration,
+	) -> Result<Vec<MultipartUploadInfo>> {
+		let uploads = self.list_multipart_uploads(prefix).await?;
+
+		let mut aborted = Vec::new();
+		for upload in uploads.into_iter().filter(|u| u.is_older_than(older_than)) {
+			self.abort_multipart_upload(&upload.key, &upload.upload_id).await?;
+			aborted.push(upload);
+		}
+
+		info!(aborted = aborted.len(), "stale multipart uploads aborted");
+		Ok(aborted)
+	}
+
+	/// List the in-progress multipart uploads of the keys starting with `prefix`.
+	pub async fn list_multipart_uploads(&self, prefix: &str) -> Result<Vec<MultipartUploadInfo>> {
+		let mut uploads: Vec<MultipartUploadInfo> = Vec::new();
+		let mut markers: (Option<String>, Option<String>) = (None, None);
+
+		loop {
+			// BUILD - aws request
+			let req = self
+				.client()
+				.list_multipart_uploads()
+				.bucket(self.name())
+				.prefix(prefix)
+				.encoding_type(EncodingType::Url)
+				.set_key_marker(markers.0.take())
+				.set_upload_id_marker(markers.1.take());
+
+			// EXECUTE
+			let res = req.send().await?;
+
+			// COLLECT
+			for upload in res.uploads().unwrap_or_default() {
+				if let (Some(key), Some(upload_id)) = (upload.key(), upload.upload_id()) {
+					uploads.push(MultipartUploadInfo {
+						key: listing_key(key, res.encoding_type())?,
+						upload_id: upload_id.to_string(),
+						initiated: upload.initiated().and_then(|d| SystemTime::try_from(*d).ok()),
+					});
+				}
+			}
+
+			// NEXT - page (if any)
+			if !res.is_truncated() {
+				break;
+			}
+			markers = (
+				res.next_key_marker()
+					.map(|s| listing_key(s, res.encoding_type()))
+					.transpose()?,
+				res.next_upload_id_marker().map(|s| s.to_string()),
+			);
+		}
+
+		Ok(uploads)
+	}
+}
+
+/// Read up to `part_size` bytes (less only at end of file/stream).
+pub(crate) async fn read_part<R: AsyncRead + Unpin>(reader: &mut R, part_size: usize) -> Result<Vec<u8>> {
+	let mut buf = Vec::with_capacity(part_size);
+	let mut reader = reader.take(part_size as u64);
+	reader.read_to_end(&mut buf).await?;
+	Ok(buf)
+}
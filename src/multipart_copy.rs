@@ -0,0 +1,245 @@
+use crate::copy::copy_source;
+use crate::error::{Result, S3DemoError};
+use crate::multipart::MAX_PARTS;
+use crate::tagging::tagging_header;
+use crate::upload_state::PartState;
+use crate::S3Bucket;
+use aws_sdk_s3::model::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::output::HeadObjectOutput;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use tracing::{debug, instrument, warn};
+
+// -- constants
+/// S3 maximum size of a CopyObject source (above, the copy is a multipart copy).
+pub const MAX_COPY_SIZE: u64 = 5 * 1024 * 1024 * 1024;
+
+/// The source object of a copy.
+pub(crate) struct CopySource<'a> {
+	pub bucket: &'a str,
+	pub key: &'a str,
+	pub version_id: Option<&'a str>,
+}
+
+impl CopySource<'_> {
+	/// The `x-amz-copy-source` header value.
+	fn header(&self) -> String {
+		copy_source(self.bucket, self.key, self.version_id)
+	}
+}
+
+impl S3Bucket {
+	/// The HEAD of the copy source (with this bucket client and customer key).
+	pub(crate) async fn head_copy_source(&self, src: &CopySource<'_>) -> Result<HeadObjectOutput> {
+		// BUILD & EXECUTE - aws request
+		let ck = self.customer_key();
+		let res = self
+			.client()
+			.head_object()
+			.bucket(src.bucket)
+			.set_request_payer(self.request_payer().cloned())
+			.key(src.key)
+			.set_version_id(src.version_id.map(|s| s.to_string()))
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()))
+			.send()
+			.await
+			.map_err(|ex| match S3DemoError::from(ex) {
+				S3DemoError::NotFound(_) => S3DemoError::NotFound(src.key.to_string()),
+				ex => ex,
+			})?;
+
+		Ok(res)
+	}
+
+	/// Server-side copy of a source over [`MAX_COPY_SIZE`] with a multipart copy (one UploadPartCopy per range,
+	/// concurrently). The content headers, metadata and tags of the source (read with the `reader` client)
+	/// are copied, like CopyObject does.
+	#[instrument(level = "debug", skip_all, fields(key = %src.key, size = head.content_length()))]
+	pub(crate) async fn copy_object_multipart(
+		&self,
+		reader: &S3Bucket,
+		src: &CopySource<'_>,
+		head: &HeadObjectOutput,
+		dst_bucket: &str,
+		dst_key: &str,
+	) -> Result<()> {
+		let size = head.content_length().max(0) as u64;
+		let part_size = (self.part_size() as u64).max(size.div_ceil(MAX_PARTS as u64));
+		let tags = reader.copy_source_tags(src).await?;
+
+		// EXECUTE - the multipart copy (aborted on failure)
+		let upload_id = self.create_multipart_copy(head, &tags, dst_bucket, dst_key).await?;
+		debug!(%upload_id, part_size, "created multipart copy");
+		let copy_source = src.header();
+		let res = stream::iter((0..size.div_ceil(part_size)).map(|index| {
+			let start = index * part_size;
+			let end = (start + part_size).min(size) - 1;
+			let part_number = index as i32 + 1;
+			let copy_source = copy_source.as_str();
+			let upload_id = upload_id.as_str();
+			async move {
+				self.check_shutdown(dst_key)?;
+				self.upload_part_copy(dst_bucket, dst_key, upload_id, part_number, copy_source, (start, end))
+					.await
+			}
+		}))
+		.buffered(self.concurrency())
+		.try_collect::<Vec<_>>()
+		.await;
+		let res = match res {
+			Ok(parts) => {
+				self.complete_multipart_copy(dst_bucket, dst_key, &upload_id, &parts)
+					.await
+			}
+			Err(ex) => Err(ex),
+		};
+
+		if let Err(ex) = res {
+			// Note: best effort, the original error is the one reported.
+			warn!(key = %dst_key, upload_id = %upload_id, error = %ex, "aborting failed multipart copy");
+			let _ = self
+				.client()
+				.abort_multipart_upload()
+				.bucket(dst_bucket)
+				.key(dst_key)
+				.upload_id(&upload_id)
+				.send()
+				.await;
+			return Err(ex);
+		}
+
+		Ok(())
+	}
+
+	/// Copy the `range` (inclusive bytes) of `copy_source` as the part `part_number` of the `upload_id` upload.
+	#[instrument(level = "debug", skip_all, fields(key = %key, part_number))]
+	pub(crate) async fn upload_part_copy(
+		&self,
+		bucket: &str,
+		key: &str,
+		upload_id: &str,
+		part_number: i32,
+		copy_source: &str,
+		(start, end): (u64, u64),
+	) -> Result<PartState> {
+		// BUILD & EXECUTE - aws request
+		// Note: with SSE-C, the source object is encrypted with the same key.
+		let ck = self.customer_key();
+		let res = self
+			.client()
+			.upload_part_copy()
+			.bucket(bucket)
+			.key(key)
+			.upload_id(upload_id)
+			.part_number(part_number)
+			.copy_source(copy_source)
+			.copy_source_range(format!("bytes={start}-{end}"))
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()))
+			.set_copy_source_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_copy_source_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_copy_source_sse_customer_key_md5(ck.map(|k| k.key_md5()))
+			.send()
+			.await?;
+
+		Ok(PartState {
+			part_number,
+			e_tag: res.copy_part_result().and_then(|r| r.e_tag()).map(|s| s.to_string()),
+		})
+	}
+
+	async fn copy_source_tags(&self, src: &CopySource<'_>) -> Result<HashMap<String, String>> {
+		// BUILD & EXECUTE - aws request
+		let res = self
+			.client()
+			.get_object_tagging()
+			.bucket(src.bucket)
+			.set_request_payer(self.request_payer().cloned())
+			.key(src.key)
+			.set_version_id(src.version_id.map(|s| s.to_string()))
+			.send()
+			.await?;
+
+		// COLLECT
+		let tags = res
+			.tag_set()
+			.unwrap_or_default()
+			.iter()
+			.filter_map(|t| Some((t.key()?.to_string(), t.value()?.to_string())))
+			.collect();
+
+		Ok(tags)
+	}
+
+	async fn create_multipart_copy(
+		&self,
+		head: &HeadObjectOutput,
+		tags: &HashMap<String, String>,
+		dst_bucket: &str,
+		dst_key: &str,
+	) -> Result<String> {
+		// BUILD & EXECUTE - aws request
+		// Note: the destination encryption and storage class are the ones of this bucket (like for CopyObject).
+		let ck = self.customer_key();
+		let res = self
+			.client()
+			.create_multipart_upload()
+			.bucket(dst_bucket)
+			.key(dst_key)
+			.set_content_type(head.content_type().map(|s| s.to_string()))
+			.set_cache_control(head.cache_control().map(|s| s.to_string()))
+			.set_content_disposition(head.content_disposition().map(|s| s.to_string()))
+			.set_content_encoding(head.content_encoding().map(|s| s.to_string()))
+			.set_content_language(head.content_language().map(|s| s.to_string()))
+			.set_metadata(head.metadata().cloned())
+			.set_tagging(tagging_header(tags))
+			.set_server_side_encryption(self.encryption().and_then(|e| e.server_side_encryption()))
+			.set_ssekms_key_id(self.encryption().and_then(|e| e.kms_key_id()))
+			.set_storage_class(self.storage_class().cloned())
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()))
+			.send()
+			.await?;
+
+		let upload_id = res
+			.upload_id()
+			.ok_or_else(|| S3DemoError::InvalidResponse(format!("No upload_id for multipart copy to {dst_key}")))?;
+
+		Ok(upload_id.to_string())
+	}
+
+	async fn complete_multipart_copy(
+		&self,
+		dst_bucket: &str,
+		dst_key: &str,
+		upload_id: &str,
+		parts: &[PartState],
+	) -> Result<()> {
+		let parts = parts
+			.iter()
+			.map(|p| {
+				CompletedPart::builder()
+					.set_e_tag(p.e_tag.clone())
+					.part_number(p.part_number)
+					.build()
+			})
+			.collect::<Vec<_>>();
+		let completed = CompletedMultipartUpload::builder().set_parts(Some(parts)).build();
+
+		// BUILD & EXECUTE - aws request
+		self.client()
+			.complete_multipart_upload()
+			.bucket(dst_bucket)
+			.key(dst_key)
+			.upload_id(upload_id)
+			.multipart_upload(completed)
+			.send()
+			.await?;
+
+		Ok(())
+	}
+}
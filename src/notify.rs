@@ -0,0 +1,235 @@
+use crate::error::{Result, S3DemoError};
+use crate::ClientConfig;
+use aws_sdk_s3::Region;
+use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SigningParams, SigningSettings};
+use aws_smithy_client::conns;
+use aws_smithy_client::erase::DynConnector;
+use aws_smithy_client::hyper_ext::Adapter;
+use aws_smithy_http::body::SdkBody;
+use aws_smithy_http::byte_stream::ByteStream;
+use aws_types::credentials::{ProvideCredentials, SharedCredentialsProvider};
+use http::header::{CONTENT_TYPE, HOST};
+use http::{HeaderValue, Method, Request, Uri};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Serialize;
+use std::str::FromStr;
+use std::time::SystemTime;
+use tower::ServiceExt;
+use tracing::{debug, instrument};
+
+// -- constants
+const SNS_ARN_PREFIX: &str = "arn:aws:sns:";
+const SNS_API_VERSION: &str = "2010-03-31";
+/// The SNS maximum subject size (in characters).
+const MAX_SUBJECT_SIZE: usize = 100;
+/// The response characters kept in the error of a failed notification.
+const MAX_ERROR_BODY_SIZE: usize = 200;
+
+/// The summary of a finished multi-file run (directory upload, recursive download, sync or batch), sent by
+/// a [`Notifier`] (as JSON).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunSummary {
+	/// The command (e.g., `sync`, `upload`), or `daemon` for a job.
+	pub command: String,
+	/// The daemon job name (if any).
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub job: Option<String>,
+	/// `ok` or `failed`.
+	pub status: String,
+	pub source: String,
+	pub destination: String,
+	/// Number of files/objects transferred (or copied).
+	pub transferred: usize,
+	/// Number of the failed transfers (of a run failing on its transfers).
+	pub failed: usize,
+	/// Bytes of the files/objects transferred (0 when the run failed).
+	pub bytes: u64,
+	/// The average throughput (`bytes` over `duration_secs`).
+	pub bytes_per_sec: f64,
+	pub unchanged: usize,
+	pub skipped: usize,
+	pub deleted: usize,
+	pub errors: Vec<String>,
+	pub duration_secs: f64,
+}
+
+/// Where a [`Notifier`] sends the summaries: a webhook URL (POST of the JSON summary),
+/// or an SNS topic ARN (published as the message, in the region of the topic).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyTarget {
+	Webhook(Uri),
+	Sns { topic_arn: String, region: String },
+}
+
+impl FromStr for NotifyTarget {
+	type Err = S3DemoError;
+
+	/// An `arn:aws:sns:<region>:<account>:<topic>` topic, or an `http(s)://` webhook URL.
+	fn from_str(s: &str) -> Result<Self> {
+		if let Some(rest) = s.strip_prefix(SNS_ARN_PREFIX) {
+			let region = rest.split(':').next().unwrap_or_default();
+			if region.is_empty() || rest.split(':').count() != 3 {
+				return Err(S3DemoError::InvalidInput(format!("Invalid SNS topic ARN {s}")));
+			}
+			return Ok(NotifyTarget::Sns {
+				topic_arn: s.to_string(),
+				region: region.to_string(),
+			});
+		}
+		let uri = s
+			.parse::<Uri>()
+			.ok()
+			.filter(|uri| matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some())
+			.ok_or_else(|| S3DemoError::InvalidInput(format!("Invalid notification target {s} (URL or SNS ARN)")))?;
+		Ok(NotifyTarget::Webhook(uri))
+	}
+}
+
+/// Sends the [`RunSummary`] of the finished multi-file runs to a [`NotifyTarget`].
+pub struct Notifier {
+	target: NotifyTarget,
+	conn: DynConnector,
+	/// The SNS credentials and endpoint (of the client config).
+	credentials: Option<SharedCredentialsProvider>,
+	endpoint: Option<Uri>,
+}
+
+impl Notifier {
+	/// A notifier of `target`, the SNS topics being reached with the credentials and endpoint (if any) of `config`.
+	pub async fn new(target: NotifyTarget, config: &ClientConfig) -> Result<Self> {
+		let (credentials, endpoint) = match &target {
+			NotifyTarget::Webhook(_) => (None, None),
+			NotifyTarget::Sns { region, .. } => {
+				let endpoint = config.endpoint_uri()?;
+				let credentials = config
+					.credentials_provider(Region::new(region.clone()), endpoint.as_ref())
+					.await?
+					.ok_or_else(|| S3DemoError::InvalidInput("SNS notifications require credentials".to_string()))?;
+				(Some(credentials), endpoint)
+			}
+		};
+		Ok(Notifier {
+			target,
+			conn: DynConnector::new(Adapter::builder().build(conns::https())),
+			credentials,
+			endpoint,
+		})
+	}
+
+	pub fn target(&self) -> &NotifyTarget {
+		&self.target
+	}
+
+	/// Send the `summary` (failing on a non 2xx response).
+	#[instrument(level = "debug", skip_all, fields(command = %summary.command, status = %summary.status))]
+	pub async fn notify(&self, summary: &RunSummary) -> Result<()> {
+		let json = serde_json::to_string(summary)?;
+		let req = match &self.target {
+			NotifyTarget::Webhook(uri) => Request::builder()
+				.method(Method::POST)
+				.uri(uri.clone())
+				.header(CONTENT_TYPE, "application/json")
+				.body(SdkBody::from(json))
+				.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid webhook request: {ex}")))?,
+			NotifyTarget::Sns { topic_arn, region } => {
+				self.sns_publish_request(topic_arn, region, summary, json).await?
+			}
+		};
+
+		// EXECUTE
+		let res = self
+			.conn
+			.clone()
+			.oneshot(req)
+			.await
+			.map_err(|ex| S3DemoError::Transport(Box::new(ex)))?;
+		let status = res.status();
+		debug!(%status, "notification sent");
+		if !status.is_success() {
+			let body = ByteStream::new(res.into_body()).collect().await?.into_bytes();
+			let body = String::from_utf8_lossy(&body);
+			let body = body.trim().chars().take(MAX_ERROR_BODY_SIZE).collect::<String>();
+			return Err(S3DemoError::NotificationFailed(format!("status {status}: {body}")));
+		}
+
+		Ok(())
+	}
+
+	/// The signed SNS Publish request (query API) of the `summary` JSON.
+	async fn sns_publish_request(
+		&self,
+		topic_arn: &str,
+		region: &str,
+		summary: &RunSummary,
+		json: String,
+	) -> Result<Request<SdkBody>> {
+		// BUILD - the form request
+		let name = summary.job.as_deref().unwrap_or(&summary.command);
+		let subject = format!("s3demo {name} {}", summary.status)
+			.chars()
+			.take(MAX_SUBJECT_SIZE)
+			.collect::<String>();
+		let form = [
+			("Action", "Publish"),
+			("Version", SNS_API_VERSION),
+			("TopicArn", topic_arn),
+			("Subject", &subject),
+			("Message", &json),
+		]
+		.iter()
+		.map(|(name, value)| format!("{name}={}", utf8_percent_encode(value, NON_ALPHANUMERIC)))
+		.collect::<Vec<_>>()
+		.join("&");
+		let uri = match &self.endpoint {
+			Some(endpoint) => endpoint.clone(),
+			None => format!("https://sns.{region}.amazonaws.com/")
+				.parse::<Uri>()
+				.map_err(|_| S3DemoError::InvalidInput(format!("Invalid SNS region {region}")))?,
+		};
+		let host = uri
+			.authority()
+			.and_then(|a| HeaderValue::from_str(a.as_str()).ok())
+			.ok_or_else(|| S3DemoError::InvalidInput(format!("Invalid SNS endpoint {uri}")))?;
+		let mut req = Request::builder()
+			.method(Method::POST)
+			.uri(uri)
+			.header(HOST, host)
+			.header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+			.body(())
+			.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid SNS request: {ex}")))?;
+
+		// SIGN - SigV4, with the client credentials
+		let Some(credentials) = &self.credentials else {
+			return Err(S3DemoError::InvalidInput(
+				"SNS notifications require credentials".to_string(),
+			));
+		};
+		let creds = credentials
+			.provide_credentials()
+			.await
+			.map_err(|ex| S3DemoError::Transport(Box::new(ex)))?;
+		let mut params = SigningParams::builder()
+			.access_key(creds.access_key_id())
+			.secret_key(creds.secret_access_key())
+			.region(region)
+			.service_name("sns")
+			.time(SystemTime::now())
+			.settings(SigningSettings::default());
+		params.set_security_token(creds.session_token());
+		let params = params
+			.build()
+			.map_err(|ex| S3DemoError::InvalidInput(format!("Invalid SNS signing params: {ex}")))?;
+		let signable = SignableRequest::new(
+			req.method(),
+			req.uri(),
+			req.headers(),
+			SignableBody::Bytes(form.as_bytes()),
+		);
+		let (instructions, _) = sign(signable, &params)
+			.map_err(|ex| S3DemoError::InvalidInput(format!("SNS request signing failed: {ex}")))?
+			.into_parts();
+		instructions.apply_to_request(&mut req);
+
+		Ok(req.map(|_| SdkBody::from(form)))
+	}
+}
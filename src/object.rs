@@ -0,0 +1,63 @@
+use aws_sdk_s3::model::Object;
+use serde::{Serialize, Serializer};
+use std::time::SystemTime;
+
+/// The metadata of a listed object.
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectInfo {
+	pub key: String,
+	pub size: u64,
+	#[serde(serialize_with = "ser_time")]
+	pub last_modified: Option<SystemTime>,
+	pub e_tag: Option<String>,
+	pub storage_class: Option<String>,
+	/// The mtime of the uploaded file (see [`crate::META_MTIME`]), only when read with the object metadata
+	/// (not in the listings, see [`crate::ObjectStore::object_mtime`]).
+	#[serde(skip_serializing_if = "Option::is_none", serialize_with = "ser_time")]
+	pub mtime: Option<SystemTime>,
+}
+
+/// A "directory" level of a listing (see [`crate::S3Bucket::list_dir`]).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DirListing {
+	/// The common prefixes, i.e., the "sub-directories" (ending with `/`).
+	pub prefixes: Vec<String>,
+	/// The immediate objects.
+	pub objects: Vec<ObjectInfo>,
+}
+
+impl ObjectInfo {
+	/// The content MD5 (hex), when the ETag is one (i.e., not a multipart or SSE-KMS ETag).
+	pub fn content_md5(&self) -> Option<&str> {
+		etag_md5(self.e_tag.as_deref())
+	}
+}
+
+impl From<&Object> for ObjectInfo {
+	fn from(obj: &Object) -> Self {
+		ObjectInfo {
+			key: obj.key().unwrap_or_default().to_string(),
+			size: obj.size().max(0) as u64,
+			last_modified: obj.last_modified().and_then(|d| SystemTime::try_from(*d).ok()),
+			e_tag: obj.e_tag().map(|s| s.to_string()),
+			storage_class: obj.storage_class().map(|s| s.as_str().to_string()),
+			mtime: None,
+		}
+	}
+}
+
+/// The ETag as a content MD5 (hex, without the quotes), when it looks like one
+/// (multipart ETags have a `-<parts>` suffix).
+pub(crate) fn etag_md5(e_tag: Option<&str>) -> Option<&str> {
+	e_tag
+		.map(|s| s.trim_matches('"'))
+		.filter(|s| s.len() == 32 && !s.contains('-'))
+}
+
+/// Serialize a time as a RFC 3339 string (e.g., `2022-01-30T10:20:30Z`).
+pub(crate) fn ser_time<S: Serializer>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error> {
+	match time {
+		Some(time) => serializer.collect_str(&humantime::format_rfc3339_seconds(*time)),
+		None => serializer.serialize_none(),
+	}
+}
@@ -0,0 +1,85 @@
+use crate::error::Result;
+use crate::store::ObjectStore;
+use crate::transfer::Transfer;
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+/// Whether the transfers overwrite the existing destination files/objects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Overwrite {
+	/// Overwrite the different ones (sync compares the size, ETag or mtime), all of them for upload and download.
+	#[default]
+	Changed,
+	/// Never overwrite (no clobber).
+	Never,
+	/// Only overwrite the ones older than their source (and different, for sync).
+	IfNewer,
+	/// Overwrite all of them, even the identical ones.
+	Always,
+}
+
+impl Overwrite {
+	/// Whether an existing destination last modified at `dst_modified` can be overwritten by a source
+	/// last modified at `src_modified` (an unknown time counts as older for the destination).
+	pub fn allows(self, src_modified: Option<SystemTime>, dst_modified: Option<SystemTime>) -> bool {
+		match self {
+			Overwrite::Changed | Overwrite::Always => true,
+			Overwrite::Never => false,
+			Overwrite::IfNewer => match (src_modified, dst_modified) {
+				(Some(src), Some(dst)) => is_newer(src, dst),
+				_ => true,
+			},
+		}
+	}
+}
+
+/// See [`ObjectStore::skip_existing`].
+pub(crate) async fn skip_existing<S: ObjectStore + ?Sized>(
+	store: &S,
+	transfers: Vec<Transfer>,
+	prefix: &str,
+	overwrite: Overwrite,
+) -> Result<(Vec<Transfer>, usize)> {
+	if matches!(overwrite, Overwrite::Changed | Overwrite::Always) {
+		return Ok((transfers, 0));
+	}
+
+	// COLLECT - the last modified of the objects under the prefix (the upload destinations or download sources)
+	let remotes = store
+		.list_objects(prefix)
+		.await?
+		.into_iter()
+		.map(|o| (o.key, o.last_modified))
+		.collect::<HashMap<_, _>>();
+
+	// FILTER - the transfers to an existing destination
+	let mut kept = Vec::new();
+	let mut skipped = 0;
+	for transfer in transfers {
+		let local_modified = transfer.path().metadata().ok().map(|m| m.modified().ok());
+		let remote_modified = remotes.get(transfer.key()).copied();
+		let allowed = match (&transfer, local_modified, remote_modified) {
+			(Transfer::Upload { .. }, local, Some(remote)) => overwrite.allows(local.flatten(), remote),
+			(Transfer::Download { .. }, Some(local), remote) => overwrite.allows(remote.flatten(), local),
+			// Note: the destination does not exist.
+			_ => true,
+		};
+		if allowed {
+			kept.push(transfer);
+		} else {
+			skipped += 1;
+		}
+	}
+
+	Ok((kept, skipped))
+}
+
+/// S3 last modified has a second precision, so compare at the second.
+pub(crate) fn is_newer(a: SystemTime, b: SystemTime) -> bool {
+	let secs = |t: SystemTime| {
+		t.duration_since(SystemTime::UNIX_EPOCH)
+			.map(|d| d.as_secs())
+			.unwrap_or(0)
+	};
+	secs(a) > secs(b)
+}
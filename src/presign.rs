@@ -0,0 +1,44 @@
+use crate::error::{Result, S3DemoError};
+use crate::key::validate_key;
+use crate::S3Bucket;
+use aws_sdk_s3::presigning::config::PresigningConfig;
+use std::time::Duration;
+
+impl S3Bucket {
+	/// Presigned GET URL for `key`, valid for `expires` (at most 7 days).
+	/// Anyone with the URL can download the object until it expires.
+	pub async fn presign_get(&self, key: &str, expires: Duration) -> Result<String> {
+		// BUILD - presigning config
+		let config = PresigningConfig::expires_in(expires).map_err(|ex| S3DemoError::InvalidInput(ex.to_string()))?;
+
+		// EXECUTE - sign the aws request (no network call)
+		let req = self.client().get_object().bucket(self.name()).key(key);
+		let presigned = req.presigned(config).await?;
+
+		Ok(presigned.uri().to_string())
+	}
+}
+
+impl S3Bucket {
+	/// Presigned PUT URL for `key`, valid for `expires` (at most 7 days).
+	/// When `content_type` is set, it is part of the signature, so the uploader must send
+	/// the same `Content-Type` header.
+	pub async fn presign_put(&self, key: &str, expires: Duration, content_type: Option<&str>) -> Result<String> {
+		// VALIDATE
+		validate_key(key)?;
+
+		// BUILD - presigning config
+		let config = PresigningConfig::expires_in(expires).map_err(|ex| S3DemoError::InvalidInput(ex.to_string()))?;
+
+		// EXECUTE - sign the aws request (no network call)
+		let req = self
+			.client()
+			.put_object()
+			.bucket(self.name())
+			.key(key)
+			.set_content_type(content_type.map(|s| s.to_string()));
+		let presigned = req.presigned(config).await?;
+
+		Ok(presigned.uri().to_string())
+	}
+}
@@ -0,0 +1,16 @@
+use crate::error::S3DemoError;
+
+/// Listener of the transfer progress, set with [`crate::S3Bucket::with_progress`].
+///
+/// Each transfer calls `start`, then `advance` as bytes are sent/received, and `finish` when done
+/// (success or failure, possibly without `start` when failing early). Transfers can run in parallel, so calls for different keys interleave.
+pub trait TransferProgress: Send + Sync {
+	/// A transfer of `total` bytes starts (0 when unknown).
+	fn start(&self, key: &str, total: u64);
+
+	/// `bytes` more bytes were transferred.
+	fn advance(&self, key: &str, bytes: u64);
+
+	/// The transfer is done, with the `error` of a failed one.
+	fn finish(&self, key: &str, error: Option<&S3DemoError>);
+}
@@ -0,0 +1,276 @@
+use crate::checksum::ExpectedChecksums;
+use crate::download_state::DownloadState;
+use crate::error::Result;
+use crate::S3Bucket;
+use aws_sdk_s3::error::GetObjectError;
+use aws_sdk_s3::output::GetObjectOutput;
+use aws_sdk_s3::{ByteStream, SdkError};
+use aws_smithy_types::DateTime;
+use bytes::Bytes;
+use futures::future::try_join;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, IoSlice, Seek, SeekFrom, Write};
+use std::path::Path;
+use tracing::instrument;
+
+// -- constants
+/// Number of ranges downloaded in parallel for one object.
+pub const DEFAULT_PART_CONCURRENCY: usize = 4;
+/// The body bytes gathered before a (vectored) write to the file.
+const WRITE_BATCH_SIZE: usize = 1024 * 1024;
+/// HTTP 304, returned for a conditional request on an unchanged object.
+const STATUS_NOT_MODIFIED: u16 = 304;
+/// HTTP 416, returned for a range request on an empty object.
+const STATUS_RANGE_NOT_SATISFIABLE: u16 = 416;
+
+/// A completed [`S3Bucket::download_ranged`].
+#[derive(Debug)]
+pub(crate) struct RangedDownload {
+	/// The checksums to verify the file against.
+	pub expected: ExpectedChecksums,
+	/// The object user metadata.
+	pub metadata: HashMap<String, String>,
+	pub content_encoding: Option<String>,
+	pub e_tag: Option<String>,
+	/// The object last modified (epoch seconds).
+	pub last_modified: Option<i64>,
+}
+
+impl S3Bucket {
+	/// Download `key` (the `version_id` version, else the latest) to `file_path` as `part_size`
+	/// byte ranges fetched in parallel,
+	/// each written at its offset in the file.
+	///
+	/// The first range request also gives the object size, so objects smaller than one part
+	/// still take a single request. With a `previous` download, it is conditional, and None is
+	/// returned (nothing written) when the object is not modified since.
+	pub(crate) async fn download_ranged(
+		&self,
+		key: &str,
+		version_id: Option<&str>,
+		file_path: &Path,
+		previous: Option<&DownloadState>,
+	) -> Result<Option<RangedDownload>> {
+		let part_size = self.part_size() as u64;
+
+		// EXECUTE - first range (conditional on the previous download, if any)
+		let first = match self
+			.get_range(key, version_id, Some((0, part_size)), None, previous)
+			.await
+		{
+			Ok(first) => first,
+			Err(SdkError::ServiceError { raw, .. }) if raw.http().status() == STATUS_NOT_MODIFIED => {
+				return Ok(None);
+			}
+			Err(SdkError::ServiceError { raw, .. }) if raw.http().status() == STATUS_RANGE_NOT_SATISFIABLE => {
+				// Note: an empty object (nothing to range), so without range (for its metadata and checksums).
+				self.get_range(key, version_id, None, None, None).await?
+			}
+			Err(ex) => return Err(ex.into()),
+		};
+
+		// PREPARE - the file at the full size
+		let total = first
+			.content_range()
+			.and_then(content_range_total)
+			.unwrap_or_else(|| first.content_length().max(0) as u64);
+		self.progress_start(key, total);
+		File::create(file_path)?.set_len(total)?;
+
+		let download = RangedDownload {
+			expected: ExpectedChecksums::from_get_output(&first),
+			metadata: first.metadata().cloned().unwrap_or_default(),
+			content_encoding: first.content_encoding().map(|s| s.to_string()),
+			e_tag: first.e_tag().map(|s| s.to_string()),
+			last_modified: first.last_modified().map(|d| d.secs()),
+		};
+
+		// DOWNLOAD - the first range body and the other ranges, in parallel
+		// Note: if-match, so all the ranges are from the same object version.
+		let e_tag = download.e_tag.clone();
+		let ranges = (1..).map(|i| i * part_size).take_while(|start| *start < total);
+		let first_fut = self.write_body(key, first.body, file_path, 0);
+		let rest_fut = stream::iter(ranges)
+			.map(|start| {
+				let e_tag = e_tag.clone();
+				async move {
+					let res = self
+						.get_range(key, version_id, Some((start, part_size)), e_tag, None)
+						.await?;
+					self.write_body(key, res.body, file_path, start).await
+				}
+			})
+			.buffer_unordered(self.part_concurrency())
+			.try_collect::<Vec<_>>();
+		try_join(first_fut, rest_fut).await?;
+
+		Ok(Some(download))
+	}
+
+	/// Get the `range` (start, length) of the object (the whole object if None), of the `e_tag` version (if any),
+	/// and when modified since the `previous` download (if any, with `If-None-Match` and `If-Modified-Since`).
+	#[instrument(level = "debug", skip_all, fields(key = %key, ?range))]
+	async fn get_range(
+		&self,
+		key: &str,
+		version_id: Option<&str>,
+		range: Option<(u64, u64)>,
+		e_tag: Option<String>,
+		previous: Option<&DownloadState>,
+	) -> std::result::Result<GetObjectOutput, SdkError<GetObjectError>> {
+		// BUILD - aws request
+		let ck = self.customer_key();
+		let req = self
+			.client()
+			.get_object()
+			.bucket(self.name())
+			.set_request_payer(self.request_payer().cloned())
+			.key(key)
+			.set_version_id(version_id.map(|s| s.to_string()))
+			.set_range(range.map(|(start, len)| format!("bytes={start}-{}", start + len - 1)))
+			.set_if_match(e_tag)
+			.set_if_none_match(previous.map(|p| p.e_tag.clone()))
+			.set_if_modified_since(previous.and_then(|p| p.last_modified).map(DateTime::from_secs))
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()));
+
+		// EXECUTE
+		req.send().await
+	}
+
+	/// Stream `body` into the file at `offset`.
+	async fn write_body(&self, key: &str, mut body: ByteStream, file_path: &Path, offset: u64) -> Result<()> {
+		let mut file = OpenOptions::new().write(true).open(file_path)?;
+		file.seek(SeekFrom::Start(offset))?;
+		let mut writer = ChunkWriter::new(file);
+		while let Some(bytes) = body.try_next().await? {
+			let len = bytes.len() as u64;
+			writer.push(bytes)?;
+			self.progress_advance(key, len);
+		}
+		writer.finish()?;
+
+		Ok(())
+	}
+}
+
+/// The object size of a `Content-Range` (e.g., `bytes 0-99/1234`), if known (not `*`).
+fn content_range_total(content_range: &str) -> Option<u64> {
+	content_range.rsplit('/').next()?.parse().ok()
+}
+
+/// A writer of the body chunks without copying them (unlike a `BufWriter`): the chunks are kept as received
+/// until [`WRITE_BATCH_SIZE`] bytes, then written with vectored writes (one syscall for many chunks).
+///
+/// Note: like a `BufWriter`, the pending chunks are written on drop (best effort), so an interrupted or failed
+/// download keeps all the bytes received (e.g., for [`S3Bucket::download_resume`] to resume after them).
+pub(crate) struct ChunkWriter<W: Write> {
+	inner: W,
+	chunks: Vec<Bytes>,
+	len: usize,
+}
+
+impl<W: Write> ChunkWriter<W> {
+	pub fn new(inner: W) -> Self {
+		ChunkWriter {
+			inner,
+			chunks: Vec::new(),
+			len: 0,
+		}
+	}
+
+	pub fn push(&mut self, bytes: Bytes) -> io::Result<()> {
+		if bytes.is_empty() {
+			return Ok(());
+		}
+		self.len += bytes.len();
+		self.chunks.push(bytes);
+		if self.len >= WRITE_BATCH_SIZE {
+			self.write_chunks()?;
+		}
+		Ok(())
+	}
+
+	/// Write the pending chunks, and flush the inner writer.
+	pub fn finish(mut self) -> io::Result<()> {
+		self.write_chunks()?;
+		self.inner.flush()
+	}
+
+	fn write_chunks(&mut self) -> io::Result<()> {
+		let mut slices = self.chunks.iter().map(|c| IoSlice::new(c)).collect::<Vec<_>>();
+		let mut slices = &mut slices[..];
+		while !slices.is_empty() {
+			match self.inner.write_vectored(slices) {
+				Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write the body")),
+				Ok(n) => IoSlice::advance_slices(&mut slices, n),
+				Err(ex) if ex.kind() == io::ErrorKind::Interrupted => (),
+				Err(ex) => return Err(ex),
+			}
+		}
+		self.chunks.clear();
+		self.len = 0;
+		Ok(())
+	}
+}
+
+impl<W: Write> Drop for ChunkWriter<W> {
+	fn drop(&mut self) {
+		// Note: not while panicking, as the chunks could be in an inconsistent state.
+		if !self.chunks.is_empty() && !std::thread::panicking() {
+			let _ = self.write_chunks();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_content_range_total() {
+		assert_eq!(content_range_total("bytes 0-99/1234"), Some(1234));
+		assert_eq!(content_range_total("bytes 0-0/1"), Some(1));
+		assert_eq!(content_range_total("bytes 0-99/*"), None);
+		assert_eq!(content_range_total(""), None);
+	}
+
+	#[test]
+	fn test_chunk_writer_batches() -> io::Result<()> {
+		let mut writer = ChunkWriter::new(Vec::new());
+		writer.push(Bytes::from_static(b"abc"))?;
+		writer.push(Bytes::new())?;
+		assert!(writer.inner.is_empty(), "written by batches");
+
+		// Note: a full batch is written at once, in order.
+		let big = Bytes::from(vec![b'x'; WRITE_BATCH_SIZE]);
+		writer.push(big)?;
+		assert_eq!(writer.inner.len(), WRITE_BATCH_SIZE + 3);
+		assert!(writer.chunks.is_empty());
+		assert_eq!(&writer.inner[..4], b"abcx");
+
+		writer.push(Bytes::from_static(b"end"))?;
+		assert_eq!(writer.inner.len(), WRITE_BATCH_SIZE + 3, "pending until the next batch");
+		writer.finish()?;
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_chunk_writer_drop() -> io::Result<()> {
+		let dir = tempfile::TempDir::new()?;
+		let path = dir.path().join("part");
+		{
+			let mut writer = ChunkWriter::new(File::create(&path)?);
+			writer.push(Bytes::from_static(b"received "))?;
+			writer.push(Bytes::from_static(b"bytes"))?;
+			// Note: dropped without finish, as on a failed or interrupted download.
+		}
+		assert_eq!(std::fs::read(&path)?, b"received bytes");
+
+		Ok(())
+	}
+}
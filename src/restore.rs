@@ -0,0 +1,98 @@
+use crate::error::Result;
+use crate::stat::ObjectStat;
+use crate::S3Bucket;
+use aws_sdk_s3::model::{GlacierJobParameters, RestoreRequest, Tier};
+use aws_sdk_s3::SdkError;
+use serde::Serialize;
+use std::time::Duration;
+
+// -- constants
+/// Error code of a RestoreObject on an object already being restored.
+const RESTORE_ALREADY_IN_PROGRESS: &str = "RestoreAlreadyInProgress";
+/// Storage classes of the archived objects, which have to be restored before download.
+const ARCHIVE_STORAGE_CLASSES: [&str; 2] = ["GLACIER", "DEEP_ARCHIVE"];
+
+/// The restore status of an object, from the `x-amz-restore` header.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "status")]
+pub enum RestoreStatus {
+	/// Not in an archive storage class, readable as is.
+	NotArchived,
+	/// Archived, and no restore requested (or the restored copy expired).
+	NotRestored,
+	InProgress,
+	/// Temporary copy available until the `expiry` date (e.g., `Fri, 21 Dec 2012 00:00:00 GMT`).
+	Restored {
+		expiry: Option<String>,
+	},
+}
+
+impl RestoreStatus {
+	pub fn from_stat(stat: &ObjectStat) -> Self {
+		match stat.restore.as_deref() {
+			Some(restore) if restore.contains(r#"ongoing-request="true""#) => RestoreStatus::InProgress,
+			Some(restore) => RestoreStatus::Restored {
+				expiry: restore
+					.split_once(r#"expiry-date=""#)
+					.and_then(|(_, rest)| rest.split('"').next())
+					.map(|s| s.to_string()),
+			},
+			None if stat
+				.storage_class
+				.as_deref()
+				.is_some_and(|sc| ARCHIVE_STORAGE_CLASSES.contains(&sc)) =>
+			{
+				RestoreStatus::NotRestored
+			}
+			None => RestoreStatus::NotArchived,
+		}
+	}
+
+	/// Whether the object content can be downloaded.
+	pub fn is_readable(&self) -> bool {
+		matches!(self, RestoreStatus::NotArchived | RestoreStatus::Restored { .. })
+	}
+}
+
+impl S3Bucket {
+	/// Request a temporary copy of the archived (GLACIER, DEEP_ARCHIVE) object `key` for `days`,
+	/// retrieved at `tier` (Expedited, Standard or Bulk).
+	/// Requesting a restore already in progress is not an error.
+	pub async fn restore_object(&self, key: &str, days: i32, tier: Tier) -> Result<()> {
+		// BUILD - aws request
+		let job = GlacierJobParameters::builder().tier(tier).build();
+		let restore = RestoreRequest::builder().days(days).glacier_job_parameters(job).build();
+		let req = self
+			.client()
+			.restore_object()
+			.bucket(self.name())
+			.key(key)
+			.restore_request(restore);
+
+		// EXECUTE
+		match req.send().await {
+			Ok(_) => Ok(()),
+			Err(SdkError::ServiceError { err, .. }) if err.code() == Some(RESTORE_ALREADY_IN_PROGRESS) => Ok(()),
+			Err(ex) => Err(ex.into()),
+		}
+	}
+
+	/// The restore status of the object `key`.
+	pub async fn restore_status(&self, key: &str) -> Result<RestoreStatus> {
+		let stat = self.stat(key).await?;
+		Ok(RestoreStatus::from_stat(&stat))
+	}
+
+	/// Poll the restore status of `key` every `interval` until it is readable (or not being restored).
+	/// Returns the final status.
+	/// Note: restores take minutes (Expedited) to hours (Standard, Bulk), so poll accordingly.
+	pub async fn wait_restored(&self, key: &str, interval: Duration) -> Result<RestoreStatus> {
+		loop {
+			let status = self.restore_status(key).await?;
+			if status.is_readable() || status == RestoreStatus::NotRestored {
+				return Ok(status);
+			}
+			tokio::time::sleep(interval).await;
+		}
+	}
+}
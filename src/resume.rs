@@ -0,0 +1,182 @@
+use crate::bucket::{part_path, until_deadline};
+use crate::checksum::{verify_file, ExpectedChecksums, META_SHA256};
+use crate::cse::cse_download_path;
+use crate::error::{Result, S3DemoError};
+use crate::file_attrs::restore_file_attrs;
+use crate::ranged::ChunkWriter;
+use crate::split::META_SPLIT_CHUNKS;
+use crate::symlink::{restore_symlink, SymlinkMode, META_SYMLINK_TARGET};
+use crate::{ObjectStat, S3Bucket};
+use aws_sdk_s3::ByteStream;
+use std::fs::{create_dir_all, remove_file, rename, OpenOptions};
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+use tokio_stream::StreamExt;
+use tracing::{info, instrument};
+
+impl S3Bucket {
+	/// Download the object `key` to `file_path`, resuming from the existing partial file (if any)
+	/// with a ranged GET rather than starting from byte zero.
+	///
+	/// The partial file is `file_path` + `.part` (see [`S3Bucket::download_to`]), renamed to `file_path`
+	/// once complete and verified against the object size and checksum (see [`S3Bucket::with_checksums`]).
+	/// On mismatch (e.g., the partial file was from another object), it is deleted, so a retry starts over.
+	///
+	/// With client-side encryption, the partial file is the ciphertext next to `file_path`, which is
+	/// decrypted once complete.
+	#[instrument(name = "download", skip_all, fields(bucket = %self.name(), key = %key, resume = true))]
+	pub async fn download_resume(&self, key: &str, file_path: &Path) -> Result<()> {
+		// Note: on a deadline, the partial file is kept to be resumed.
+		let res = until_deadline(self.deadline(), key, self.exec_download_resume(key, file_path)).await;
+		self.progress_finish(key, &res);
+		res
+	}
+
+	async fn exec_download_resume(&self, key: &str, file_path: &Path) -> Result<()> {
+		let stat = self.stat(key).await?;
+		let part_path = part_path(file_path);
+		if stat.metadata.contains_key(META_SPLIT_CHUNKS) {
+			// REASSEMBLE - the chunks of a split file (this object being its manifest), downloaded again
+			// Note: the partial file is then the reassembled one, which cannot be resumed as the manifest.
+			let _ = remove_file(&part_path);
+			self.download_resume_stat(&stat, &part_path).await?;
+			let res = self.download_split(key, &part_path, &part_path).await;
+			if res.is_err() {
+				let _ = remove_file(&part_path);
+			}
+			res?;
+		} else {
+			match self.client_encryption() {
+				Some(cse) => {
+					let cse_path = cse_download_path(file_path);
+					self.download_resume_stat(&stat, &cse_path).await?;
+					cse.decrypt_download(key, &stat.metadata, &cse_path, &part_path)?;
+				}
+				None => self.download_resume_stat(&stat, &part_path).await?,
+			}
+
+			// DECOMPRESS - (if the object was compressed)
+			if let Some(compression) = self.decompression_for(stat.content_encoding.as_deref()) {
+				compression.decompress_file(key, &part_path)?;
+			}
+		}
+
+		// COMPLETE - atomically replace the file (with the mtime of the uploaded one)
+		rename(&part_path, file_path)?;
+		let symlink_target = stat.metadata.get(META_SYMLINK_TARGET);
+		if let Some(target) = symlink_target.filter(|_| self.symlinks() == SymlinkMode::Preserve) {
+			return restore_symlink(file_path, target);
+		}
+		restore_file_attrs(file_path, &stat.metadata, self.preserve_mode())?;
+
+		Ok(())
+	}
+
+	async fn download_resume_stat(&self, stat: &ObjectStat, file_path: &Path) -> Result<()> {
+		let key = stat.key.as_str();
+
+		// PREPARE - remote and local sizes
+		let local_len = file_path.metadata().map(|m| m.len()).unwrap_or(0);
+		let offset = resume_offset(local_len, stat.size);
+
+		// create parent dir(s)
+		let parent_dir = file_path
+			.parent()
+			.ok_or_else(|| S3DemoError::InvalidInput(format!("Invalid parent dir for {:?}", file_path)))?;
+		if !parent_dir.exists() {
+			create_dir_all(parent_dir)?;
+		}
+
+		// OPEN - the file, appending from offset
+		let mut file = OpenOptions::new()
+			.create(true)
+			.write(true)
+			.truncate(false)
+			.open(file_path)?;
+		file.set_len(offset)?;
+		file.seek(SeekFrom::Start(offset))?;
+		let mut writer = ChunkWriter::new(file);
+
+		if offset > 0 {
+			info!(offset, size = stat.size, "resuming download");
+		}
+		self.progress_start(key, stat.size);
+		self.progress_advance(key, offset);
+
+		if offset < stat.size {
+			// BUILD - aws request (if-match so the remaining bytes are from the same object)
+			let ck = self.customer_key();
+			let req = self
+				.client()
+				.get_object()
+				.bucket(self.name())
+				.set_request_payer(self.request_payer().cloned())
+				.key(key)
+				.range(format!("bytes={offset}-"))
+				.set_if_match(stat.e_tag.clone())
+				.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+				.set_sse_customer_key(ck.map(|k| k.key_base64()))
+				.set_sse_customer_key_md5(ck.map(|k| k.key_md5()));
+
+			// EXECUTE
+			let res = req.send().await?;
+
+			// STREAM result to file
+			let mut data: ByteStream = res.body;
+			while let Some(bytes) = data.try_next().await? {
+				let len = bytes.len() as u64;
+				writer.push(bytes)?;
+				self.progress_advance(key, len);
+			}
+		}
+		writer.finish()?;
+
+		// VERIFY - size and checksum (delete the corrupted file on mismatch)
+		let final_len = file_path.metadata()?.len();
+		if final_len != stat.size {
+			let _ = remove_file(file_path);
+			return Err(S3DemoError::SizeMismatch {
+				key: key.to_string(),
+				expected: stat.size,
+				actual: final_len,
+			});
+		}
+		if self.checksums() {
+			let expected = ExpectedChecksums {
+				sha256: stat.metadata.get(META_SHA256).cloned(),
+				content_md5: stat.content_md5().map(|s| s.to_string()),
+			};
+			if let Err(ex) = verify_file(key, file_path, &expected) {
+				let _ = remove_file(file_path);
+				return Err(ex);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// The offset to resume the download of an object of `size` from, with a partial file of `local_len`.
+/// Note: a local file larger than the object cannot be a partial of it, so restart.
+fn resume_offset(local_len: u64, size: u64) -> u64 {
+	if local_len <= size {
+		local_len
+	} else {
+		0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_resume_offset() {
+		assert_eq!(resume_offset(0, 100), 0);
+		assert_eq!(resume_offset(40, 100), 40);
+		// Note: complete, so nothing left to fetch (only verified).
+		assert_eq!(resume_offset(100, 100), 100);
+		assert_eq!(resume_offset(101, 100), 0);
+		assert_eq!(resume_offset(0, 0), 0);
+	}
+}
@@ -0,0 +1,268 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use aws_smithy_client::erase::DynConnector;
+use aws_smithy_http::body::SdkBody;
+use aws_smithy_http::result::ConnectorError;
+use http::{Request, Response, StatusCode};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Service, ServiceExt};
+use tracing::warn;
+
+// -- constants
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+pub const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+pub const DEFAULT_RETRY_MAX_DELAY: Duration = Duration::from_secs(20);
+
+/// Retry policy of the S3 requests, applied to every HTTP request of the client
+/// (including each multipart part), with exponential backoff and full jitter:
+/// the delay before the retry `n` is random in `0..min(max_delay, base_delay * 2^n)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+	/// Attempts per request, including the first one (1 disables the retries).
+	pub max_attempts: u32,
+	pub base_delay: Duration,
+	pub max_delay: Duration,
+	/// Retry the throttled requests (429, 503 SlowDown).
+	pub retry_throttling: bool,
+	/// Retry the server errors (500, 502, 504) and the network errors (connection, timeout).
+	pub retry_server_errors: bool,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		RetryPolicy {
+			max_attempts: DEFAULT_MAX_ATTEMPTS,
+			base_delay: DEFAULT_RETRY_BASE_DELAY,
+			max_delay: DEFAULT_RETRY_MAX_DELAY,
+			retry_throttling: true,
+			retry_server_errors: true,
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// No retry at all.
+	pub fn disabled() -> Self {
+		RetryPolicy {
+			max_attempts: 1,
+			..Default::default()
+		}
+	}
+
+	fn is_retryable_status(&self, status: StatusCode) -> bool {
+		match status {
+			StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => self.retry_throttling,
+			StatusCode::INTERNAL_SERVER_ERROR | StatusCode::BAD_GATEWAY | StatusCode::GATEWAY_TIMEOUT => {
+				self.retry_server_errors
+			}
+			_ => false,
+		}
+	}
+
+	fn is_retryable_error(&self, err: &ConnectorError) -> bool {
+		self.retry_server_errors && (err.is_io() || err.is_timeout())
+	}
+
+	/// The (jittered) delay before the retry `retry` (starting at 1).
+	fn backoff(&self, retry: u32) -> Duration {
+		let ceiling = self
+			.base_delay
+			.saturating_mul(2u32.saturating_pow(retry))
+			.min(self.max_delay);
+		let jitter = OsRng.next_u32() as f64 / u32::MAX as f64;
+		ceiling.mul_f64(jitter)
+	}
+}
+
+/// Connector retrying the requests of the wrapped connector per the [`RetryPolicy`].
+/// Note: the requests are already signed, so they are resent as is.
+#[derive(Clone)]
+pub(crate) struct RetryConnector {
+	inner: DynConnector,
+	policy: RetryPolicy,
+}
+
+impl RetryConnector {
+	pub(crate) fn new(inner: DynConnector, policy: RetryPolicy) -> Self {
+		RetryConnector { inner, policy }
+	}
+}
+
+type ConnectorFuture = Pin<Box<dyn Future<Output = Result<Response<SdkBody>, ConnectorError>> + Send>>;
+
+impl Service<Request<SdkBody>> for RetryConnector {
+	type Response = Response<SdkBody>;
+	type Error = ConnectorError;
+	type Future = ConnectorFuture;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, req: Request<SdkBody>) -> Self::Future {
+		let inner = self.inner.clone();
+		let policy = self.policy.clone();
+
+		Box::pin(async move {
+			let mut req = req;
+			let mut attempt = 1;
+			loop {
+				// Note: streaming bodies that cannot be replayed are sent only once.
+				let next_req = try_clone_request(&req).filter(|_| attempt < policy.max_attempts);
+				let res = inner.clone().oneshot(req).await;
+
+				let Some(next_req) = next_req else {
+					return res;
+				};
+				let retryable = match &res {
+					Ok(res) => policy.is_retryable_status(res.status()),
+					Err(err) => policy.is_retryable_error(err),
+				};
+				if !retryable {
+					return res;
+				}
+
+				let delay = policy.backoff(attempt);
+				match &res {
+					Ok(res) => warn!(status = res.status().as_u16(), attempt, ?delay, "retrying request"),
+					Err(err) => warn!(error = %err, attempt, ?delay, "retrying request"),
+				}
+				tokio::time::sleep(delay).await;
+				req = next_req;
+				attempt += 1;
+			}
+		})
+	}
+}
+
+fn try_clone_request(req: &Request<SdkBody>) -> Option<Request<SdkBody>> {
+	let body = req.body().try_clone()?;
+	let mut clone = Request::builder()
+		.method(req.method().clone())
+		.uri(req.uri().clone())
+		.version(req.version())
+		.body(body)
+		.ok()?;
+	*clone.headers_mut() = req.headers().clone();
+	Some(clone)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io;
+	use std::sync::atomic::{AtomicU32, Ordering};
+	use std::sync::Arc;
+
+	#[test]
+	fn test_backoff_bounds() {
+		let policy = RetryPolicy {
+			base_delay: Duration::from_millis(100),
+			max_delay: Duration::from_secs(1),
+			..Default::default()
+		};
+		for _ in 0..100 {
+			assert!(policy.backoff(1) <= Duration::from_millis(200));
+			assert!(policy.backoff(3) <= Duration::from_millis(800));
+			// Note: capped by max_delay, and no overflow on the big retries.
+			assert!(policy.backoff(4) <= Duration::from_secs(1));
+			assert!(policy.backoff(u32::MAX) <= Duration::from_secs(1));
+		}
+	}
+
+	#[test]
+	fn test_backoff_jitter() {
+		let policy = RetryPolicy::default();
+		let delays = (0..20)
+			.map(|_| policy.backoff(5))
+			.collect::<std::collections::HashSet<_>>();
+		assert!(delays.len() > 1, "the delays should be random");
+	}
+
+	#[test]
+	fn test_retryable_status() {
+		let policy = RetryPolicy::default();
+		for status in [429, 500, 502, 503, 504] {
+			assert!(
+				policy.is_retryable_status(StatusCode::from_u16(status).unwrap()),
+				"{status}"
+			);
+		}
+		for status in [200, 301, 400, 403, 404, 412, 501] {
+			assert!(
+				!policy.is_retryable_status(StatusCode::from_u16(status).unwrap()),
+				"{status}"
+			);
+		}
+
+		let policy = RetryPolicy {
+			retry_throttling: false,
+			..Default::default()
+		};
+		assert!(!policy.is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+		assert!(!policy.is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+		assert!(policy.is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+
+		let policy = RetryPolicy {
+			retry_server_errors: false,
+			..Default::default()
+		};
+		assert!(policy.is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+		assert!(!policy.is_retryable_status(StatusCode::BAD_GATEWAY));
+	}
+
+	#[test]
+	fn test_retryable_error() {
+		let io_error = || ConnectorError::io(Box::new(io::Error::from(io::ErrorKind::ConnectionReset)));
+		let timeout = || ConnectorError::timeout(Box::new(io::Error::from(io::ErrorKind::TimedOut)));
+		let user = || ConnectorError::user(Box::new(io::Error::other("invalid request")));
+
+		let policy = RetryPolicy::default();
+		assert!(policy.is_retryable_error(&io_error()));
+		assert!(policy.is_retryable_error(&timeout()));
+		assert!(!policy.is_retryable_error(&user()));
+
+		let policy = RetryPolicy {
+			retry_server_errors: false,
+			..Default::default()
+		};
+		assert!(!policy.is_retryable_error(&io_error()));
+		assert!(!policy.is_retryable_error(&timeout()));
+	}
+
+	/// A connector answering the statuses in turn (the last one repeated), counting the requests.
+	fn status_connector(statuses: &'static [u16], count: Arc<AtomicU32>) -> DynConnector {
+		DynConnector::new(tower::service_fn(move |_req: Request<SdkBody>| {
+			let n = count.fetch_add(1, Ordering::SeqCst) as usize;
+			let status = statuses[n.min(statuses.len() - 1)];
+			async move { Ok::<_, ConnectorError>(Response::builder().status(status).body(SdkBody::empty()).unwrap()) }
+		}))
+	}
+
+	async fn send(policy: RetryPolicy, statuses: &'static [u16], body: SdkBody) -> (u16, u32) {
+		let count = Arc::new(AtomicU32::new(0));
+		let mut connector = RetryConnector::new(status_connector(statuses, count.clone()), policy);
+		let req = Request::builder().uri("http://localhost/key").body(body).unwrap();
+		let res = connector.call(req).await.unwrap();
+		(res.status().as_u16(), count.load(Ordering::SeqCst))
+	}
+
+	#[tokio::test]
+	async fn test_retry_connector() {
+		let policy = RetryPolicy {
+			base_delay: Duration::from_millis(1),
+			..Default::default()
+		};
+		// Note: retried until success, or until the max attempts.
+		assert_eq!(send(policy.clone(), &[503, 500, 200], SdkBody::empty()).await, (200, 3));
+		assert_eq!(send(policy.clone(), &[503], SdkBody::empty()).await, (503, 3));
+		assert_eq!(send(policy.clone(), &[404], SdkBody::empty()).await, (404, 1));
+		assert_eq!(send(RetryPolicy::disabled(), &[503], SdkBody::empty()).await, (503, 1));
+
+		// Note: the streaming bodies which cannot be replayed are sent once.
+		assert_eq!(send(policy, &[503, 200], SdkBody::taken()).await, (503, 1));
+	}
+}
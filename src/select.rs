@@ -0,0 +1,202 @@
+use crate::error::Result;
+use crate::S3Bucket;
+use aws_sdk_s3::model::{
+	CompressionType, CsvInput, CsvOutput, ExpressionType, FileHeaderInfo, InputSerialization, JsonInput, JsonOutput,
+	JsonType, OutputSerialization, ParquetInput, SelectObjectContentEventStream,
+};
+use std::io::Write;
+use tracing::{info, instrument};
+
+/// The serialization of the queried objects and of the results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectFormat {
+	Csv,
+	/// JSON Lines (one JSON object per line).
+	Json,
+	/// Input only (the results are then JSON).
+	Parquet,
+}
+
+/// An S3 Select query (`SELECT * FROM S3Object s WHERE ...`) of an object.
+#[derive(Debug, Clone)]
+pub struct SelectQuery {
+	sql: String,
+	input: SelectFormat,
+	output: SelectFormat,
+	compression: CompressionType,
+	csv_header: bool,
+	field_delimiter: Option<String>,
+}
+
+/// The bytes scanned, processed (uncompressed) and returned by a query (S3 Select is billed on them).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SelectStats {
+	pub bytes_scanned: u64,
+	pub bytes_processed: u64,
+	pub bytes_returned: u64,
+}
+
+impl SelectQuery {
+	/// The query of the object `key`, its format guessed from the extension (`.csv`, `.tsv`, `.json`, `.jsonl`,
+	/// `.ndjson`, `.parquet`, otherwise CSV), compressed when `.gz` or `.bz2`. The results are in the same format
+	/// (JSON for Parquet).
+	pub fn new(sql: impl Into<String>, key: &str) -> Self {
+		let key = key.to_lowercase();
+		let (name, compression) = match key.rsplit_once('.') {
+			Some((name, "gz")) => (name, CompressionType::Gzip),
+			Some((name, "bz2")) => (name, CompressionType::Bzip2),
+			_ => (key.as_str(), CompressionType::None),
+		};
+		let (input, field_delimiter) = match name.rsplit_once('.').map(|(_, ext)| ext) {
+			Some("json" | "jsonl" | "ndjson") => (SelectFormat::Json, None),
+			Some("parquet") => (SelectFormat::Parquet, None),
+			Some("tsv") => (SelectFormat::Csv, Some("\t".to_string())),
+			_ => (SelectFormat::Csv, None),
+		};
+		SelectQuery {
+			sql: sql.into(),
+			input,
+			output: default_output(input),
+			compression,
+			csv_header: true,
+			field_delimiter,
+		}
+	}
+
+	/// Set the object format (and the results one, unless set after with [`SelectQuery::with_output`]).
+	pub fn with_input(mut self, input: SelectFormat) -> Self {
+		self.input = input;
+		self.output = default_output(input);
+		if input != SelectFormat::Csv {
+			self.field_delimiter = None;
+		}
+		self
+	}
+
+	/// Set the results format (CSV or JSON).
+	pub fn with_output(mut self, output: SelectFormat) -> Self {
+		self.output = default_output(output);
+		self
+	}
+
+	/// Whether the first CSV line is the header, so the columns can be referenced by name (default: true).
+	pub fn with_csv_header(mut self, csv_header: bool) -> Self {
+		self.csv_header = csv_header;
+		self
+	}
+
+	/// The CSV field delimiter (default: `,`, or tab for `.tsv`), also the one of the CSV results.
+	pub fn with_field_delimiter(mut self, field_delimiter: impl Into<String>) -> Self {
+		self.field_delimiter = Some(field_delimiter.into());
+		self
+	}
+
+	pub fn sql(&self) -> &str {
+		&self.sql
+	}
+
+	fn input_serialization(&self) -> InputSerialization {
+		let builder = InputSerialization::builder().compression_type(self.compression.clone());
+		let builder = match self.input {
+			SelectFormat::Csv => {
+				let header = if self.csv_header {
+					FileHeaderInfo::Use
+				} else {
+					FileHeaderInfo::None
+				};
+				let csv = CsvInput::builder()
+					.file_header_info(header)
+					.set_field_delimiter(self.field_delimiter.clone())
+					.build();
+				builder.csv(csv)
+			}
+			SelectFormat::Json => builder.json(JsonInput::builder().r#type(JsonType::Lines).build()),
+			SelectFormat::Parquet => builder.parquet(ParquetInput::builder().build()),
+		};
+		builder.build()
+	}
+
+	fn output_serialization(&self) -> OutputSerialization {
+		let builder = OutputSerialization::builder();
+		let builder = match self.output {
+			SelectFormat::Csv => builder.csv(
+				CsvOutput::builder()
+					.set_field_delimiter(self.field_delimiter.clone())
+					.build(),
+			),
+			_ => builder.json(JsonOutput::builder().record_delimiter("\n").build()),
+		};
+		builder.build()
+	}
+}
+
+/// The results of a Parquet query can only be JSON.
+fn default_output(format: SelectFormat) -> SelectFormat {
+	match format {
+		SelectFormat::Csv => SelectFormat::Csv,
+		_ => SelectFormat::Json,
+	}
+}
+
+impl S3Bucket {
+	/// Run the S3 Select `query` on the object `key` (server-side, only the matching records are sent),
+	/// streaming the results to `writer` (e.g., stdout) as they are received.
+	///
+	/// Note: a query error can come after some results were written (e.g., a malformed record further down).
+	#[instrument(name = "select", skip_all, fields(bucket = %self.name(), key = %key))]
+	pub async fn select_to_writer<W: Write>(
+		&self,
+		key: &str,
+		query: &SelectQuery,
+		writer: &mut W,
+	) -> Result<SelectStats> {
+		// BUILD & EXECUTE - aws request
+		let ck = self.customer_key();
+		let mut res = self
+			.client()
+			.select_object_content()
+			.bucket(self.name())
+			.key(key)
+			.expression(query.sql())
+			.expression_type(ExpressionType::Sql)
+			.input_serialization(query.input_serialization())
+			.output_serialization(query.output_serialization())
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()))
+			.send()
+			.await?;
+
+		// STREAM - the records to writer
+		let mut stats = SelectStats::default();
+		while let Some(event) = res.payload.recv().await? {
+			match event {
+				SelectObjectContentEventStream::Records(records) => {
+					if let Some(payload) = records.payload() {
+						writer.write_all(payload.as_ref())?;
+					}
+				}
+				SelectObjectContentEventStream::Stats(event) => {
+					if let Some(details) = event.details() {
+						stats = SelectStats {
+							bytes_scanned: details.bytes_scanned().max(0) as u64,
+							bytes_processed: details.bytes_processed().max(0) as u64,
+							bytes_returned: details.bytes_returned().max(0) as u64,
+						};
+					}
+				}
+				SelectObjectContentEventStream::End(_) => break,
+				_ => (),
+			}
+		}
+		writer.flush()?;
+
+		info!(
+			bytes_scanned = stats.bytes_scanned,
+			bytes_processed = stats.bytes_processed,
+			bytes_returned = stats.bytes_returned,
+			"select done"
+		);
+		Ok(stats)
+	}
+}
@@ -0,0 +1,285 @@
+use crate::bucket::PutAttrs;
+use crate::checksum::{md5_base64, sha256_hex, verify_file, ExpectedChecksums, META_SHA256};
+use crate::error::{Result, S3DemoError};
+use crate::S3Bucket;
+use aws_sdk_s3::ByteStream;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, SeekFrom, Write};
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tracing::{debug, info, instrument};
+
+// -- constants
+/// The metadata of a split file manifest (the number of chunks), see [`S3Bucket::with_split_size`].
+pub const META_SPLIT_CHUNKS: &str = "split-chunks";
+const MANIFEST_CONTENT_TYPE: &str = "application/json";
+
+/// The manifest of a split file, stored as the object of the file key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SplitManifest {
+	pub size: u64,
+	pub chunk_size: u64,
+	/// The SHA-256 of the whole file, verified once reassembled.
+	pub sha256: String,
+	pub chunks: Vec<SplitChunk>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SplitChunk {
+	pub key: String,
+	pub size: u64,
+	pub sha256: String,
+}
+
+/// The key of the chunk `index` (from 0) of the split file `key`, e.g., `backups/db.dump.part-0001`.
+fn chunk_key(key: &str, index: usize) -> String {
+	format!("{key}.part-{:04}", index + 1)
+}
+
+/// Whether `key` is the key of a chunk (`<file key>.part-NNNN`) of a file key for which `is_file_key` is true.
+pub(crate) fn is_chunk_key_of(key: &str, is_file_key: impl Fn(&str) -> bool) -> bool {
+	let Some((file_key, index)) = key.rsplit_once(".part-") else {
+		return false;
+	};
+	index.len() >= 4 && index.bytes().all(|b| b.is_ascii_digit()) && is_file_key(file_key)
+}
+
+impl S3Bucket {
+	/// Upload the file at `path` as chunks of `split_size` bytes (`<key>.part-0001`, `<key>.part-0002`, ...),
+	/// and their manifest as the object `key` (see [`META_SPLIT_CHUNKS`]), which [`S3Bucket::download_to`]
+	/// reassembles and verifies.
+	///
+	/// The chunks already uploaded (same size and SHA-256) are skipped, so a failed upload can be run again
+	/// without sending them again.
+	#[instrument(level = "debug", skip_all, fields(key = %key, split_size))]
+	pub(crate) async fn upload_split(&self, path: &Path, key: &str, split_size: u64) -> Result<()> {
+		// VALIDATE
+		if self.client_encryption().is_some() || self.compression().is_some() {
+			return Err(S3DemoError::InvalidInput(
+				"Split uploads do not support client-side encryption or compression".to_string(),
+			));
+		}
+
+		// PREPARE - the checksums of the chunks and of the file
+		let manifest = split_manifest(path, key, split_size)?;
+		debug!(chunks = manifest.chunks.len(), "split upload");
+
+		// UPLOAD - the chunks, one at a time (each a multipart upload above the part size)
+		let mut offset = 0;
+		for chunk in &manifest.chunks {
+			if self.is_chunk_uploaded(chunk).await? {
+				debug!(chunk = %chunk.key, "chunk already uploaded");
+			} else {
+				let mut file = tokio::fs::File::open(path).await?;
+				file.seek(SeekFrom::Start(offset)).await?;
+				let metadata = HashMap::from([(META_SHA256.to_string(), chunk.sha256.clone())]);
+				let mut reader = file.take(chunk.size);
+				self.upload_from_reader_with(&mut reader, &chunk.key, None, metadata)
+					.await?;
+			}
+			offset += chunk.size;
+			self.progress_advance(key, chunk.size);
+		}
+
+		// COMPLETE - the manifest (last, so a partial upload is not downloadable)
+		let json = serde_json::to_vec_pretty(&manifest)?;
+		let mut attrs = PutAttrs {
+			content_type: MANIFEST_CONTENT_TYPE.to_string(),
+			metadata: self.file_metadata(path)?,
+			content_encoding: None,
+			deadline: self.deadline(),
+		};
+		attrs
+			.metadata
+			.insert(META_SPLIT_CHUNKS.to_string(), manifest.chunks.len().to_string());
+		attrs.metadata.insert(META_SHA256.to_string(), sha256_hex(&json));
+		let content_md5 = Some(md5_base64(&json));
+		self.put_object_body(key, ByteStream::from(json), content_md5, attrs)
+			.await?;
+
+		// CLEAN - the chunks of a previous (larger) upload
+		let chunk_prefix = format!("{key}.part-");
+		let current = manifest.chunks.iter().map(|c| c.key.as_str()).collect::<HashSet<_>>();
+		let stale = self
+			.list(&chunk_prefix)
+			.await?
+			.into_iter()
+			.filter(|k| {
+				let index = &k[chunk_prefix.len()..];
+				!index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()) && !current.contains(k.as_str())
+			})
+			.collect::<Vec<_>>();
+		if !stale.is_empty() {
+			self.delete_objects(&stale).await?;
+		}
+
+		info!(
+			chunks = manifest.chunks.len(),
+			size = manifest.size,
+			"split upload done"
+		);
+		Ok(())
+	}
+
+	async fn is_chunk_uploaded(&self, chunk: &SplitChunk) -> Result<bool> {
+		match self.stat(&chunk.key).await {
+			Ok(stat) => Ok(stat.size == chunk.size && stat.metadata.get(META_SHA256) == Some(&chunk.sha256)),
+			Err(S3DemoError::NotFound(_)) => Ok(false),
+			Err(ex) => Err(ex),
+		}
+	}
+
+	/// Download the chunks of the split file `key`, whose manifest was downloaded to `manifest_path`,
+	/// to `file_path`, verifying each chunk (size and SHA-256, as written) and the reassembled file.
+	#[instrument(level = "debug", skip_all, fields(key = %key))]
+	pub(crate) async fn download_split(&self, key: &str, manifest_path: &Path, file_path: &Path) -> Result<()> {
+		let manifest = parse_manifest(key, &fs::read(manifest_path)?)?;
+
+		// DOWNLOAD - the chunks, appended in order
+		self.progress_start(key, manifest.size);
+		let mut writer = BufWriter::new(File::create(file_path)?);
+		self.write_chunks(key, &manifest, &mut writer).await?;
+		writer.flush()?;
+		drop(writer);
+
+		// VERIFY - the reassembled file
+		let expected = ExpectedChecksums {
+			sha256: Some(manifest.sha256),
+			content_md5: None,
+		};
+		verify_file(key, file_path, &expected)?;
+
+		Ok(())
+	}
+
+	/// Stream the chunks of the split file `key`, of the `manifest` content, to `writer`
+	/// (see [`S3Bucket::download_to_writer`]), verifying each chunk and their whole content (once all written).
+	/// Returns the number of bytes written.
+	pub(crate) async fn download_split_to_writer<W: Write>(
+		&self,
+		key: &str,
+		manifest: &[u8],
+		writer: &mut W,
+	) -> Result<u64> {
+		let manifest = parse_manifest(key, manifest)?;
+
+		// DOWNLOAD - the chunks, in order
+		let mut writer = HashWriter {
+			writer,
+			hasher: Sha256::new(),
+		};
+		self.write_chunks(key, &manifest, &mut writer).await?;
+		writer.flush()?;
+
+		// VERIFY - the whole content
+		let sha256 = format!("{:x}", writer.hasher.finalize());
+		if sha256 != manifest.sha256 {
+			return Err(S3DemoError::ChecksumMismatch {
+				key: key.to_string(),
+				algorithm: "SHA-256",
+				expected: manifest.sha256,
+				actual: sha256,
+			});
+		}
+
+		Ok(manifest.size)
+	}
+
+	/// Write the chunks of `manifest` to `writer`, in order, verifying the size and SHA-256 of each (as written).
+	async fn write_chunks<W: Write>(&self, key: &str, manifest: &SplitManifest, writer: &mut W) -> Result<()> {
+		for chunk in &manifest.chunks {
+			let mut chunk_writer = HashWriter {
+				writer: &mut *writer,
+				hasher: Sha256::new(),
+			};
+			let written = self.download_chunk_to_writer(&chunk.key, &mut chunk_writer).await?;
+			if written != chunk.size {
+				return Err(S3DemoError::InvalidResponse(format!(
+					"Chunk {} is {written} bytes, expected {}",
+					chunk.key, chunk.size
+				)));
+			}
+			let sha256 = format!("{:x}", chunk_writer.hasher.finalize());
+			if sha256 != chunk.sha256 {
+				return Err(S3DemoError::ChecksumMismatch {
+					key: chunk.key.clone(),
+					algorithm: "SHA-256",
+					expected: chunk.sha256.clone(),
+					actual: sha256,
+				});
+			}
+			self.progress_advance(key, chunk.size);
+		}
+
+		Ok(())
+	}
+}
+
+fn parse_manifest(key: &str, content: &[u8]) -> Result<SplitManifest> {
+	let manifest: SplitManifest = serde_json::from_slice(content)
+		.map_err(|ex| S3DemoError::InvalidResponse(format!("Invalid split manifest {key}: {ex}")))?;
+	debug!(chunks = manifest.chunks.len(), "split download");
+	Ok(manifest)
+}
+
+/// The manifest of the file at `path` split in chunks of `split_size` bytes (reading the file once).
+fn split_manifest(path: &Path, key: &str, split_size: u64) -> Result<SplitManifest> {
+	let mut file = File::open(path)?;
+	let mut file_hasher = Sha256::new();
+	let mut chunks = Vec::new();
+	let mut buf = vec![0; 1024 * 1024];
+	let mut size = 0;
+	loop {
+		let mut chunk_hasher = Sha256::new();
+		let mut chunk_size = 0;
+		let mut chunk_reader = (&mut file).take(split_size);
+		loop {
+			let len = match chunk_reader.read(&mut buf) {
+				Ok(0) => break,
+				Ok(len) => len,
+				Err(ex) if ex.kind() == io::ErrorKind::Interrupted => continue,
+				Err(ex) => return Err(ex.into()),
+			};
+			chunk_hasher.update(&buf[..len]);
+			file_hasher.update(&buf[..len]);
+			chunk_size += len as u64;
+		}
+		if chunk_size == 0 {
+			break;
+		}
+		chunks.push(SplitChunk {
+			key: chunk_key(key, chunks.len()),
+			size: chunk_size,
+			sha256: format!("{:x}", chunk_hasher.finalize()),
+		});
+		size += chunk_size;
+	}
+
+	Ok(SplitManifest {
+		size,
+		chunk_size: split_size,
+		sha256: format!("{:x}", file_hasher.finalize()),
+		chunks,
+	})
+}
+
+/// The writer hashing the bytes written through it (the SHA-256 of a chunk, as downloaded).
+struct HashWriter<'a, W: Write> {
+	writer: &'a mut W,
+	hasher: Sha256,
+}
+
+impl<W: Write> Write for HashWriter<'_, W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let len = self.writer.write(buf)?;
+		self.hasher.update(&buf[..len]);
+		Ok(len)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.writer.flush()
+	}
+}
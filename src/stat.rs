@@ -0,0 +1,95 @@
+use crate::error::{Result, S3DemoError};
+use crate::file_attrs::{parse_mtime, META_MTIME};
+use crate::object::{etag_md5, ser_time};
+use crate::S3Bucket;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::SystemTime;
+use tracing::instrument;
+
+/// The metadata of an object, from a HeadObject.
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectStat {
+	pub key: String,
+	pub size: u64,
+	pub content_type: Option<String>,
+	pub cache_control: Option<String>,
+	pub content_disposition: Option<String>,
+	pub content_encoding: Option<String>,
+	pub content_language: Option<String>,
+	pub e_tag: Option<String>,
+	#[serde(serialize_with = "ser_time")]
+	pub last_modified: Option<SystemTime>,
+	/// None for STANDARD (S3 does not return the header for it).
+	pub storage_class: Option<String>,
+	/// `AES256` (SSE-S3) or `aws:kms` (SSE-KMS), None when not encrypted.
+	pub server_side_encryption: Option<String>,
+	/// The KMS key of SSE-KMS objects.
+	pub sse_kms_key_id: Option<String>,
+	/// `AES256` for SSE-C objects.
+	pub sse_customer_algorithm: Option<String>,
+	/// The raw `x-amz-restore` header of archived objects (see [`crate::RestoreStatus`]).
+	pub restore: Option<String>,
+	/// The user metadata (`x-amz-meta-*` headers, without the prefix).
+	pub metadata: HashMap<String, String>,
+}
+
+impl ObjectStat {
+	/// The content MD5 (hex), when the ETag is one (i.e., not a multipart, SSE-KMS or SSE-C ETag).
+	pub fn content_md5(&self) -> Option<&str> {
+		let encrypted =
+			self.sse_customer_algorithm.is_some() || self.server_side_encryption.as_deref() == Some("aws:kms");
+		etag_md5(self.e_tag.as_deref()).filter(|_| !encrypted)
+	}
+
+	/// The mtime of the uploaded file (see [`crate::META_MTIME`]), if stored.
+	pub fn mtime(&self) -> Option<SystemTime> {
+		self.metadata.get(META_MTIME).and_then(|v| parse_mtime(v))
+	}
+}
+
+impl S3Bucket {
+	/// Get the metadata of the object `key` (without downloading it).
+	#[instrument(name = "stat", skip_all, fields(bucket = %self.name(), key = %key))]
+	pub async fn stat(&self, key: &str) -> Result<ObjectStat> {
+		// BUILD - aws request
+		let ck = self.customer_key();
+		let req = self
+			.client()
+			.head_object()
+			.bucket(self.name())
+			.set_request_payer(self.request_payer().cloned())
+			.key(key)
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()));
+
+		// EXECUTE
+		// Note: HEAD responses have no body, so the key is the only detail of a 404.
+		let res = req.send().await.map_err(|ex| match S3DemoError::from(ex) {
+			S3DemoError::NotFound(_) => S3DemoError::NotFound(key.to_string()),
+			ex => ex,
+		})?;
+
+		// BUILD - the stat
+		let stat = ObjectStat {
+			key: key.to_string(),
+			size: res.content_length().max(0) as u64,
+			content_type: res.content_type().map(|s| s.to_string()),
+			cache_control: res.cache_control().map(|s| s.to_string()),
+			content_disposition: res.content_disposition().map(|s| s.to_string()),
+			content_encoding: res.content_encoding().map(|s| s.to_string()),
+			content_language: res.content_language().map(|s| s.to_string()),
+			e_tag: res.e_tag().map(|s| s.to_string()),
+			last_modified: res.last_modified().and_then(|d| SystemTime::try_from(*d).ok()),
+			storage_class: res.storage_class().map(|s| s.as_str().to_string()),
+			server_side_encryption: res.server_side_encryption().map(|s| s.as_str().to_string()),
+			sse_kms_key_id: res.ssekms_key_id().map(|s| s.to_string()),
+			sse_customer_algorithm: res.sse_customer_algorithm().map(|s| s.to_string()),
+			restore: res.restore().map(|s| s.to_string()),
+			metadata: res.metadata().cloned().unwrap_or_default(),
+		};
+
+		Ok(stat)
+	}
+}
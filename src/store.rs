@@ -0,0 +1,158 @@
+use crate::checksum::META_SHA256;
+use crate::diff::{self, DirDiff};
+use crate::error::Result;
+use crate::overwrite::{self, Overwrite};
+use crate::sync::{self, SyncDirection, SyncOptions, SyncPlan};
+use crate::transfer::{into_transfers, Transfer, TransferManager};
+use crate::{dir, ObjectInfo, S3Bucket, UploadDirOptions};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The object operations the multi-object transfers (directory upload, prefix download, sync)
+/// are built on, implemented by [`S3Bucket`], and by [`crate::MemoryStore`] to test them without S3.
+///
+/// Note: the futures are not required to be `Send` for generic stores (those of [`S3Bucket`] are).
+#[allow(async_fn_in_trait)]
+pub trait ObjectStore {
+	/// The bucket name (for the messages).
+	fn name(&self) -> &str;
+
+	/// Number of parallel transfers of the multi-object operations.
+	fn concurrency(&self) -> usize;
+
+	/// Whether the transfers not started yet are to be skipped (see [`S3Bucket::with_shutdown`]).
+	fn is_shutting_down(&self) -> bool {
+		false
+	}
+
+	/// All the objects (with their metadata) with a key starting with `prefix`.
+	async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>>;
+
+	/// Upload the file at `path` to the object `key`.
+	async fn upload_to(&self, path: &Path, key: &str) -> Result<()>;
+
+	/// Download the object `key` to the file `file_path` (parent dirs are created if needed).
+	async fn download_to(&self, key: &str, file_path: &Path) -> Result<()>;
+
+	/// Delete the `keys`, failing if any of them could not be deleted.
+	async fn delete_objects(&self, keys: &[String]) -> Result<()>;
+
+	/// The mtime of the file uploaded to the object `key` (see [`crate::META_MTIME`]), if stored.
+	async fn object_mtime(&self, _key: &str) -> Result<Option<SystemTime>> {
+		Ok(None)
+	}
+
+	/// The content SHA-256 (hex) of the object `key` (see [`crate::META_SHA256`]), if stored.
+	async fn object_sha256(&self, _key: &str) -> Result<Option<String>> {
+		Ok(None)
+	}
+
+	/// Upload all the files under `local_dir`, using `key_prefix` + the relative path as key.
+	/// Returns the uploaded keys.
+	async fn upload_dir(&self, local_dir: &Path, key_prefix: &str, opts: &UploadDirOptions) -> Result<Vec<String>> {
+		dir::upload_dir(self, local_dir, key_prefix, opts).await
+	}
+
+	/// Download all the objects under `prefix` into `dest_dir`, recreating the key hierarchy.
+	/// Returns the downloaded file paths.
+	async fn download_prefix(&self, prefix: &str, dest_dir: &Path) -> Result<Vec<PathBuf>> {
+		dir::download_prefix(self, prefix, dest_dir).await
+	}
+
+	/// The downloads of a [`ObjectStore::download_prefix`], without executing them.
+	async fn download_prefix_plan(&self, prefix: &str, dest_dir: &Path) -> Result<Vec<Transfer>> {
+		dir::download_prefix_plan(self, prefix, dest_dir).await
+	}
+
+	/// Split `transfers` into the ones to run and the number of the ones skipped, as their destination
+	/// exists and `overwrite` does not allow overwriting it. The transfer objects are listed under `prefix`.
+	async fn skip_existing(
+		&self,
+		transfers: Vec<Transfer>,
+		prefix: &str,
+		overwrite: Overwrite,
+	) -> Result<(Vec<Transfer>, usize)> {
+		overwrite::skip_existing(self, transfers, prefix, overwrite).await
+	}
+
+	/// Run the `transfers` concurrently, failing if any of them failed (once they all ran).
+	/// Returns the executed transfers.
+	async fn run_transfers(&self, transfers: Vec<Transfer>) -> Result<Vec<Transfer>> {
+		into_transfers(TransferManager::new(self).run(transfers).await)
+	}
+
+	/// Sync `local_dir` and the `prefix` "directory" in the given direction,
+	/// transferring only the files that are missing or different (size, ETag, or newer mtime).
+	/// Returns the executed plan.
+	async fn sync(
+		&self,
+		local_dir: &Path,
+		prefix: &str,
+		direction: SyncDirection,
+		opts: &SyncOptions,
+	) -> Result<SyncPlan> {
+		let plan = self.sync_plan(local_dir, prefix, direction, opts).await?;
+		self.sync_apply(plan).await
+	}
+
+	/// Compute the transfers of a [`ObjectStore::sync`] without executing them.
+	async fn sync_plan(
+		&self,
+		local_dir: &Path,
+		prefix: &str,
+		direction: SyncDirection,
+		opts: &SyncOptions,
+	) -> Result<SyncPlan> {
+		sync::sync_plan(self, local_dir, prefix, direction, opts).await
+	}
+
+	/// Compare the files of `local_dir` (listed as by [`ObjectStore::upload_dir`]) with the objects under
+	/// the `prefix` "directory", without transferring anything.
+	async fn diff(&self, local_dir: &Path, prefix: &str, opts: &UploadDirOptions) -> Result<DirDiff> {
+		diff::dir_diff(self, local_dir, prefix, opts).await
+	}
+
+	/// Execute a plan from [`ObjectStore::sync_plan`] (e.g., once its deletions are confirmed):
+	/// the transfers first, then the deletions. Returns the executed plan.
+	async fn sync_apply(&self, plan: SyncPlan) -> Result<SyncPlan> {
+		sync::sync_apply(self, plan).await
+	}
+}
+
+impl ObjectStore for S3Bucket {
+	fn name(&self) -> &str {
+		S3Bucket::name(self)
+	}
+
+	fn concurrency(&self) -> usize {
+		S3Bucket::concurrency(self)
+	}
+
+	fn is_shutting_down(&self) -> bool {
+		S3Bucket::is_shutting_down(self)
+	}
+
+	async fn list_objects(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+		S3Bucket::list_objects(self, prefix).await
+	}
+
+	async fn upload_to(&self, path: &Path, key: &str) -> Result<()> {
+		S3Bucket::upload_to(self, path, key).await
+	}
+
+	async fn download_to(&self, key: &str, file_path: &Path) -> Result<()> {
+		S3Bucket::download_to(self, key, file_path).await
+	}
+
+	async fn delete_objects(&self, keys: &[String]) -> Result<()> {
+		S3Bucket::delete_objects(self, keys).await
+	}
+
+	async fn object_mtime(&self, key: &str) -> Result<Option<SystemTime>> {
+		Ok(S3Bucket::stat(self, key).await?.mtime())
+	}
+
+	async fn object_sha256(&self, key: &str) -> Result<Option<String>> {
+		Ok(S3Bucket::stat(self, key).await?.metadata.get(META_SHA256).cloned())
+	}
+}
@@ -0,0 +1,393 @@
+use crate::bucket::{until_deadline, PutAttrs};
+use crate::checksum::{md5_base64, sha256_hex, ExpectedChecksums, StreamChecksums, META_SHA256};
+use crate::compress::decoder;
+use crate::error::{Result, S3DemoError};
+use crate::key::validate_key;
+use crate::multipart::{read_part, MAX_PARTS};
+use crate::split::META_SPLIT_CHUNKS;
+use crate::upload_state::PartState;
+use crate::S3Bucket;
+use aws_sdk_s3::output::GetObjectOutput;
+use aws_sdk_s3::ByteStream;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use tokio::io::AsyncRead;
+use tracing::{instrument, Span};
+
+/// A byte range of an object (HTTP `Range: bytes=...`), e.g., `0-1048575`, `1000-` or `-500` (the last 500 bytes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+	/// From `start` to `end` (inclusive), or to the end of the object.
+	From { start: u64, end: Option<u64> },
+	/// The last bytes of the object.
+	Last(u64),
+}
+
+impl ByteRange {
+	/// The first `len` bytes.
+	pub fn head(len: u64) -> Self {
+		ByteRange::From {
+			start: 0,
+			end: Some(len.saturating_sub(1)),
+		}
+	}
+
+	fn header(&self) -> String {
+		match self {
+			ByteRange::From { start, end: Some(end) } => format!("bytes={start}-{end}"),
+			ByteRange::From { start, end: None } => format!("bytes={start}-"),
+			ByteRange::Last(len) => format!("bytes=-{len}"),
+		}
+	}
+}
+
+impl FromStr for ByteRange {
+	type Err = S3DemoError;
+
+	fn from_str(s: &str) -> Result<Self> {
+		let invalid = || S3DemoError::InvalidInput(format!("Invalid byte range '{s}' (e.g., 0-1048575, 1000-, -500)"));
+		let (start, end) = s.trim().split_once('-').ok_or_else(invalid)?;
+		let parse = |n: &str| n.parse::<u64>().map_err(|_| invalid());
+		let range = match (start, end) {
+			("", "") => return Err(invalid()),
+			("", len) => ByteRange::Last(parse(len)?),
+			(start, "") => ByteRange::From {
+				start: parse(start)?,
+				end: None,
+			},
+			(start, end) => ByteRange::From {
+				start: parse(start)?,
+				end: Some(parse(end)?),
+			},
+		};
+		match range {
+			ByteRange::From { start, end: Some(end) } if end < start => Err(invalid()),
+			ByteRange::Last(0) => Err(invalid()),
+			range => Ok(range),
+		}
+	}
+}
+
+impl S3Bucket {
+	/// Stream the `range` of the object `key` to `writer` (only those bytes are fetched, e.g., the header
+	/// of a large file, or the end of a log). Returns the number of bytes written.
+	///
+	/// Note: the checksums are for the whole object, so a range is not verified, and the client-side
+	///       encrypted objects cannot be read by range (in their encrypted form only).
+	#[instrument(name = "download_range", skip_all, fields(bucket = %self.name(), key = %key, range = ?range, bytes))]
+	pub async fn download_range_to_writer<W: Write>(&self, key: &str, range: ByteRange, writer: &mut W) -> Result<u64> {
+		// VALIDATE
+		if self.client_encryption().is_some() {
+			return Err(S3DemoError::InvalidInput(
+				"Client-side encrypted objects cannot be downloaded by range".to_string(),
+			));
+		}
+
+		let fut = self.exec_download_range_to_writer(key, range, writer);
+		let written = until_deadline(self.deadline(), key, fut).await?;
+		Span::current().record("bytes", written);
+		Ok(written)
+	}
+
+	async fn exec_download_range_to_writer<W: Write>(
+		&self,
+		key: &str,
+		range: ByteRange,
+		writer: &mut W,
+	) -> Result<u64> {
+		// BUILD & EXECUTE - aws request
+		let ck = self.customer_key();
+		let req = self
+			.client()
+			.get_object()
+			.bucket(self.name())
+			.set_request_payer(self.request_payer().cloned())
+			.key(key)
+			.range(range.header())
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()));
+		let res = req.send().await?;
+
+		// STREAM - the range to writer
+		let mut written = 0;
+		let mut data: ByteStream = res.body;
+		while let Some(bytes) = data.try_next().await? {
+			writer.write_all(&bytes)?;
+			written += bytes.len() as u64;
+		}
+		writer.flush()?;
+
+		Ok(written)
+	}
+}
+
+impl S3Bucket {
+	/// Stream the body of the object `key` to `writer` (e.g., stdout), as it is received.
+	/// Returns the number of bytes written.
+	///
+	/// Note: the checksum (when enabled) can only be verified at the end, once all the bytes
+	///       were written, so a mismatch is reported as an error after the fact.
+	///       (client-side decryption, which authenticates each segment, fails before writing it)
+	#[instrument(name = "download", skip_all, fields(bucket = %self.name(), key = %key, bytes))]
+	pub async fn download_to_writer<W: Write>(&self, key: &str, writer: &mut W) -> Result<u64> {
+		let written = until_deadline(self.deadline(), key, self.exec_download_to_writer(key, writer)).await?;
+		Span::current().record("bytes", written);
+		Ok(written)
+	}
+
+	async fn exec_download_to_writer<W: Write>(&self, key: &str, writer: &mut W) -> Result<u64> {
+		let res = self.get_object(key).await?;
+
+		// REASSEMBLE - the chunks of a split file (this object being its manifest, not encrypted nor compressed)
+		if res.metadata().is_some_and(|m| m.contains_key(META_SPLIT_CHUNKS)) {
+			let manifest = res.body.collect().await?.into_bytes();
+			return self.download_split_to_writer(key, &manifest, writer).await;
+		}
+
+		self.write_object_body(key, res, writer).await
+	}
+
+	/// Stream the chunk `key` of a split file to `writer` (as [`S3Bucket::download_to_writer`], without reassembly).
+	pub(crate) async fn download_chunk_to_writer<W: Write>(&self, key: &str, writer: &mut W) -> Result<u64> {
+		until_deadline(self.deadline(), key, async {
+			let res = self.get_object(key).await?;
+			self.write_object_body(key, res, writer).await
+		})
+		.await
+	}
+
+	async fn get_object(&self, key: &str) -> Result<GetObjectOutput> {
+		// BUILD & EXECUTE - aws request
+		let ck = self.customer_key();
+		let req = self
+			.client()
+			.get_object()
+			.bucket(self.name())
+			.set_request_payer(self.request_payer().cloned())
+			.key(key)
+			.set_sse_customer_algorithm(ck.map(|k| k.algorithm()))
+			.set_sse_customer_key(ck.map(|k| k.key_base64()))
+			.set_sse_customer_key_md5(ck.map(|k| k.key_md5()));
+		Ok(req.send().await?)
+	}
+
+	/// Stream the body of `res` to `writer` (decrypted and decompressed, if needed), verifying its checksum.
+	async fn write_object_body<W: Write>(&self, key: &str, res: GetObjectOutput, writer: &mut W) -> Result<u64> {
+		let expected = ExpectedChecksums::from_get_output(&res);
+
+		let mut decryptor = match (self.client_encryption(), res.metadata()) {
+			(Some(cse), Some(metadata)) => cse.decryptor(key, metadata)?,
+			_ => None,
+		};
+
+		// STREAM - result to writer (decrypted if encrypted client-side, then decompressed if compressed)
+		let mut checksums = StreamChecksums::default();
+		let compression = self.decompression_for(res.content_encoding());
+		let counter = CountWriter {
+			inner: writer,
+			count: 0,
+		};
+		let mut writer = decoder(compression, key, counter)?;
+		let mut data: ByteStream = res.body;
+		while let Some(bytes) = data.try_next().await? {
+			checksums.update(&bytes);
+			match &mut decryptor {
+				Some(decryptor) => decryptor.update(&bytes, &mut writer)?,
+				None => writer.write_all(&bytes)?,
+			}
+		}
+		if let Some(decryptor) = decryptor {
+			decryptor.finish(&mut writer)?;
+		}
+		let mut writer = writer.finish()?;
+		writer.flush()?;
+		let written = writer.count;
+
+		// VERIFY
+		if self.checksums() {
+			checksums.verify(key, &expected)?;
+		}
+
+		Ok(written)
+	}
+}
+
+impl S3Bucket {
+	/// Upload the content of `reader` (e.g., stdin) to the object `key`, until end of stream.
+	/// Returns the number of bytes uploaded.
+	///
+	/// The stream is read one part at a time, so a stream of at most one part is sent as a single
+	/// PUT, and a larger one as a multipart upload (aborted on failure), its parts read as they can be sent:
+	/// at most [`S3Bucket::part_concurrency`] + 1 parts are in memory (e.g., 5 x 8 MiB by default).
+	/// The content type defaults to the one of the key extension (see [`S3Bucket::content_type_for`]).
+	///
+	/// Note: the SHA-256 metadata is only set for single PUT uploads, as the multipart metadata
+	///       has to be set before the content is known.
+	#[instrument(name = "upload", skip_all, fields(bucket = %self.name(), key = %key, bytes))]
+	pub async fn upload_from_reader<R: AsyncRead + Unpin>(
+		&self,
+		reader: &mut R,
+		key: &str,
+		content_type: Option<&str>,
+	) -> Result<u64> {
+		self.upload_from_reader_with(reader, key, content_type, HashMap::new())
+			.await
+	}
+
+	/// [`S3Bucket::upload_from_reader`] with additional `metadata` (e.g., the SHA-256 of a content known beforehand).
+	pub(crate) async fn upload_from_reader_with<R: AsyncRead + Unpin>(
+		&self,
+		reader: &mut R,
+		key: &str,
+		content_type: Option<&str>,
+		metadata: HashMap<String, String>,
+	) -> Result<u64> {
+		// VALIDATE
+		validate_key(key)?;
+		if self.client_encryption().is_some() {
+			return Err(S3DemoError::InvalidInput(
+				"Client-side encryption is not supported for stream uploads, upload a file instead".to_string(),
+			));
+		}
+		if self.compression().is_some() {
+			return Err(S3DemoError::InvalidInput(
+				"Compression is not supported for stream uploads, upload a file instead".to_string(),
+			));
+		}
+
+		let part_size = self.part_size();
+		let content_type = match content_type {
+			Some(content_type) => content_type.to_string(),
+			None => self.content_type_for(Path::new(key)),
+		};
+		let mut attrs = PutAttrs {
+			content_type,
+			metadata: self.metadata().clone().into_iter().chain(metadata).collect(),
+			content_encoding: self.content_headers().content_encoding.clone(),
+			deadline: self.deadline(),
+		};
+		let deadline = attrs.deadline;
+
+		// READ - the first part, and the next one to tell if a multipart upload is needed
+		let first = until_deadline(deadline, key, read_part(reader, part_size)).await?;
+		let next = if first.len() < part_size {
+			Vec::new()
+		} else {
+			until_deadline(deadline, key, read_part(reader, part_size)).await?
+		};
+
+		// SINGLE - put
+		if next.is_empty() {
+			let size = first.len() as u64;
+			let content_md5 = if self.checksums() {
+				attrs.metadata.insert(META_SHA256.to_string(), sha256_hex(&first));
+				Some(md5_base64(&first))
+			} else {
+				None
+			};
+			let put = self.put_object_body(key, ByteStream::from(first), content_md5, attrs);
+			until_deadline(deadline, key, put).await?;
+			Span::current().record("bytes", size);
+			return Ok(size);
+		}
+
+		// MULTIPART - upload (aborted on failure)
+		let upload_id = until_deadline(deadline, key, self.create_multipart(key, &attrs)).await?;
+		let res = until_deadline(deadline, key, async {
+			let (parts, size) = self
+				.upload_reader_parts(reader, key, &upload_id, vec![first, next])
+				.await?;
+			self.complete_multipart(key, &upload_id, &parts, size).await?;
+			Ok(size)
+		})
+		.await;
+		if let Ok(size) = res {
+			Span::current().record("bytes", size);
+		}
+		if res.is_err() {
+			// Note: best effort, the original error is the one reported.
+			let _ = self.abort_multipart_upload(key, &upload_id).await;
+		}
+
+		res
+	}
+
+	/// Upload the `pending` parts, then the rest of `reader`, [`S3Bucket::part_concurrency`] parts at a time.
+	/// Returns the uploaded parts and the total size.
+	///
+	/// Note: the reader is only read when a part upload slot is free, so at most `part_concurrency` + 1 parts
+	///       are in memory, whatever the stream length.
+	async fn upload_reader_parts<R: AsyncRead + Unpin>(
+		&self,
+		reader: &mut R,
+		key: &str,
+		upload_id: &str,
+		pending: Vec<Vec<u8>>,
+	) -> Result<(Vec<PartState>, u64)> {
+		let part_size = self.part_size();
+
+		// READ - the parts, as the uploads are ready for them
+		let bufs = stream::unfold(
+			(reader, pending.into_iter(), 0),
+			move |(reader, mut pending, index)| async move {
+				let buf = match pending.next() {
+					Some(buf) => Ok(buf),
+					None => read_part(reader, part_size).await,
+				};
+				match buf {
+					Ok(buf) if buf.is_empty() => None,
+					Ok(_) if index >= MAX_PARTS => {
+						let ex = S3DemoError::InvalidInput(format!(
+							"Stream for {key} needs more than {MAX_PARTS} parts of {part_size} bytes"
+						));
+						Some((Err(ex), (reader, pending, index)))
+					}
+					res => Some((res.map(|buf| (index, buf)), (reader, pending, index + 1))),
+				}
+			},
+		);
+
+		// UPLOAD - the parts, recorded in order
+		let uploads = bufs
+			.map(|buf| async move {
+				let (index, buf) = buf?;
+				self.check_shutdown(key)?;
+				let buf_len = buf.len() as u64;
+				let part = self.upload_part(key, upload_id, index as i32 + 1, buf).await?;
+				Ok::<_, S3DemoError>((part, buf_len))
+			})
+			.buffered(self.part_concurrency());
+		let mut uploads = std::pin::pin!(uploads);
+		let mut parts: Vec<PartState> = Vec::new();
+		let mut size = 0;
+		while let Some(res) = uploads.next().await {
+			let (part, buf_len) = res?;
+			parts.push(part);
+			size += buf_len;
+		}
+
+		Ok((parts, size))
+	}
+}
+
+/// Count the bytes written through (the decrypted size for client-side encrypted objects).
+struct CountWriter<'a, W: Write> {
+	inner: &'a mut W,
+	count: u64,
+}
+
+impl<W: Write> Write for CountWriter<'_, W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let len = self.inner.write(buf)?;
+		self.count += len as u64;
+		Ok(len)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
@@ -0,0 +1,84 @@
+use crate::bucket::PutAttrs;
+use crate::checksum::md5_base64;
+use crate::dir::path_key;
+use crate::error::{Result, S3DemoError};
+use crate::S3Bucket;
+use aws_sdk_s3::ByteStream;
+use std::fs::{read_link, remove_file};
+use std::io;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR_STR};
+use tracing::{debug, instrument};
+
+// -- constants
+/// The metadata of the target of a symbolic link uploaded as an empty object (see [`SymlinkMode::Preserve`]).
+pub const META_SYMLINK_TARGET: &str = "symlink-target";
+const SYMLINK_CONTENT_TYPE: &str = "inode/symlink";
+
+/// What the directory uploads do with the symbolic links (see [`crate::UploadDirOptions`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkMode {
+	/// Skip the links, with a warning.
+	#[default]
+	Skip,
+	/// Upload the link targets (files, and directories content) as if they were in the directory.
+	/// The broken links and the link loops are skipped, with a warning.
+	Follow,
+	/// Upload each link as an empty object with its target in the [`META_SYMLINK_TARGET`] metadata,
+	/// recreated as a link on download (see [`S3Bucket::with_symlinks`]).
+	Preserve,
+}
+
+impl S3Bucket {
+	/// Upload the symbolic link at `path` as the empty object `key`, with its target in the
+	/// [`META_SYMLINK_TARGET`] metadata (with `/` separators).
+	#[instrument(level = "debug", skip_all, fields(key = %key))]
+	pub(crate) async fn upload_symlink(&self, path: &Path, key: &str) -> Result<()> {
+		let target = read_link(path)?;
+		let target = path_key(&target)
+			.ok_or_else(|| S3DemoError::InvalidInput(format!("Invalid symlink target {target:?} of {path:?}")))?;
+		debug!(%target, "symlink upload");
+
+		let mut attrs = PutAttrs {
+			content_type: SYMLINK_CONTENT_TYPE.to_string(),
+			metadata: self.metadata().clone(),
+			content_encoding: None,
+			deadline: self.deadline(),
+		};
+		attrs.metadata.insert(META_SYMLINK_TARGET.to_string(), target);
+		let content_md5 = Some(md5_base64(&[]));
+		self.put_object_body(key, ByteStream::from(Vec::new()), content_md5, attrs)
+			.await
+	}
+}
+
+/// Replace the file at `path` by a symbolic link to `target` (with `/` separators).
+///
+/// Note: the target is not checked, so a link restored from an untrusted bucket can point anywhere.
+pub(crate) fn restore_symlink(path: &Path, target: &str) -> Result<()> {
+	let target = PathBuf::from(target.replace('/', MAIN_SEPARATOR_STR));
+	match remove_file(path) {
+		Err(ex) if ex.kind() != io::ErrorKind::NotFound => return Err(ex.into()),
+		_ => (),
+	}
+	create_symlink(&target, path)?;
+	Ok(())
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> io::Result<()> {
+	std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &Path, path: &Path) -> io::Result<()> {
+	// Note: Windows links are to a file or to a directory, so per the target (relative to the link).
+	let resolved = path
+		.parent()
+		.map(|dir| dir.join(target))
+		.unwrap_or_else(|| target.to_path_buf());
+	if resolved.is_dir() {
+		std::os::windows::fs::symlink_dir(target, path)
+	} else {
+		std::os::windows::fs::symlink_file(target, path)
+	}
+}
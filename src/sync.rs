@@ -0,0 +1,316 @@
+use crate::checksum::file_md5;
+use crate::dir::{dir_prefix, is_hidden_key, join_key, key_path, list_files, load_ignore_rules};
+use crate::error::{Result, S3DemoError};
+use crate::filter::KeyFilter;
+use crate::overwrite::{is_newer, Overwrite};
+use crate::split::is_chunk_key_of;
+use crate::store::ObjectStore;
+use crate::sync_index::{load_index, save_index, IndexEntry};
+use crate::transfer::{into_transfers, Transfer, TransferManager};
+use crate::{ObjectInfo, UploadDirOptions};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::collections::{HashMap, HashSet};
+use std::fs::{remove_file, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{info, instrument};
+
+/// Direction of a [`ObjectStore::sync`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDirection {
+	/// local directory → bucket prefix
+	Up,
+	/// bucket prefix → local directory
+	Down,
+}
+
+/// Options for [`ObjectStore::sync`].
+#[derive(Debug, Clone, Default)]
+pub struct SyncOptions {
+	/// Also sync the hidden local files (local → remote only).
+	pub include_hidden: bool,
+	/// Filter on the paths/keys relative to the directory/prefix.
+	pub filter: KeyFilter,
+	/// Also delete the destination objects/files missing from the source (matching the filter).
+	pub delete: bool,
+	/// Which existing destination objects/files are overwritten (default: the different ones).
+	pub overwrite: Overwrite,
+	/// Do not honor the [`crate::IGNORE_FILE`] of the local directory (if any), whose ignored files and keys
+	/// are otherwise neither transferred nor deleted.
+	pub no_ignore_file: bool,
+	/// SQLite index of the files found identical to their object (e.g., [`crate::default_sync_index_path`]),
+	/// so the next syncs do not hash them again while the file and the object are unchanged.
+	pub index: Option<PathBuf>,
+}
+
+/// The transfers needed to bring the destination in sync.
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+	pub transfers: Vec<Transfer>,
+	/// Number of files/objects already identical.
+	pub unchanged: usize,
+	/// Number of different files/objects kept as is, per [`SyncOptions::overwrite`].
+	pub skipped: usize,
+	/// Remote keys missing locally, to delete (up with [`SyncOptions::delete`]).
+	pub delete_keys: Vec<String>,
+	/// Local files missing remotely, to delete (down with [`SyncOptions::delete`]).
+	pub delete_paths: Vec<PathBuf>,
+}
+
+impl SyncPlan {
+	/// Number of objects/files to delete.
+	pub fn delete_count(&self) -> usize {
+		self.delete_keys.len() + self.delete_paths.len()
+	}
+}
+
+/// See [`ObjectStore::sync_apply`].
+#[instrument(name = "sync", skip_all, fields(bucket = %store.name(), transfers = plan.transfers.len(), deletes = plan.delete_count()))]
+pub(crate) async fn sync_apply<S: ObjectStore + ?Sized>(store: &S, plan: SyncPlan) -> Result<SyncPlan> {
+	let SyncPlan {
+		transfers,
+		unchanged,
+		skipped,
+		delete_keys,
+		delete_paths,
+	} = plan;
+
+	let results = TransferManager::new(store).run(transfers).await;
+	let transfers = into_transfers(results)?;
+
+	// DELETE - the extraneous objects/files
+	store.delete_objects(&delete_keys).await?;
+	for path in delete_paths.iter() {
+		remove_file(path)?;
+	}
+
+	Ok(SyncPlan {
+		transfers,
+		unchanged,
+		skipped,
+		delete_keys,
+		delete_paths,
+	})
+}
+
+/// See [`ObjectStore::sync_plan`].
+#[instrument(name = "sync_plan", skip_all, fields(bucket = %store.name(), dir = %local_dir.display(), prefix = %prefix, ?direction))]
+pub(crate) async fn sync_plan<S: ObjectStore + ?Sized>(
+	store: &S,
+	local_dir: &Path,
+	prefix: &str,
+	direction: SyncDirection,
+	opts: &SyncOptions,
+) -> Result<SyncPlan> {
+	// VALIDATE
+	if !local_dir.is_dir() {
+		return Err(S3DemoError::InvalidInput(format!(
+			"Path {} is not a directory",
+			local_dir.display()
+		)));
+	}
+
+	// COLLECT - remote objects by relative key
+	let prefix = dir_prefix(prefix);
+	let ignore = load_ignore_rules(local_dir, opts.no_ignore_file)?;
+	let mut remotes = store
+		.list_objects(&prefix)
+		.await?
+		.into_iter()
+		.filter(|o| !o.key.ends_with('/'))
+		.filter_map(|o| Some((o.key.strip_prefix(&prefix)?.to_string(), o)))
+		.filter(|(rel_key, _)| opts.filter.is_match(rel_key))
+		.filter(|(rel_key, _)| !ignore.as_ref().is_some_and(|ignore| ignore.is_ignored_key(rel_key)))
+		.collect::<HashMap<_, _>>();
+
+	// COLLECT - the uploaded file mtimes of the objects only comparable by time (see `is_different`)
+	if opts.overwrite != Overwrite::Always {
+		let by_time = remotes
+			.iter()
+			.filter(|(rel_key, remote)| remote.content_md5().is_none() && is_same_size(local_dir, rel_key, remote))
+			.map(|(rel_key, remote)| (rel_key.clone(), remote.key.clone()))
+			.collect::<Vec<_>>();
+		let mtimes = stream::iter(by_time)
+			.map(|(rel_key, key)| async move { Ok::<_, S3DemoError>((rel_key, store.object_mtime(&key).await?)) })
+			.buffer_unordered(store.concurrency())
+			.try_collect::<Vec<_>>()
+			.await?;
+		for (rel_key, mtime) in mtimes {
+			if let Some(remote) = remotes.get_mut(&rel_key) {
+				remote.mtime = mtime;
+			}
+		}
+	}
+
+	// COMPARE - (the files indexed as identical to their unchanged object are not hashed again)
+	let index = match &opts.index {
+		Some(db_path) => load_index(db_path, store.name(), local_dir)?,
+		None => HashMap::new(),
+	};
+	let mut identical = Vec::new();
+	let mut is_different = |path: &Path, meta: &Metadata, remote: &ObjectInfo| -> Result<bool> {
+		let e_tag = remote.e_tag.as_deref().unwrap_or_default();
+		if index.get(&remote.key).is_some_and(|entry| entry.matches(meta, e_tag)) {
+			return Ok(false);
+		}
+		let different = is_different(path, meta, remote, direction)?;
+		if !different && opts.index.is_some() && !e_tag.is_empty() {
+			identical.push(IndexEntry::new(&remote.key, meta, e_tag));
+		}
+		Ok(different)
+	};
+	let dir_opts = UploadDirOptions {
+		include_hidden: opts.include_hidden,
+		filter: opts.filter.clone(),
+		no_ignore_file: opts.no_ignore_file,
+		..Default::default()
+	};
+	let mut plan = SyncPlan::default();
+	match direction {
+		SyncDirection::Up => {
+			let locals = list_files(local_dir, &dir_opts)?;
+			if opts.delete {
+				let local_paths = locals
+					.iter()
+					.map(|(_, rel_path)| rel_path.as_str())
+					.collect::<HashSet<_>>();
+				// Note: the hidden objects are kept unless synced (like the hidden local files),
+				//       and the chunk objects of the split files with their file.
+				plan.delete_keys = remotes
+					.iter()
+					.filter(|(rel_key, _)| !local_paths.contains(rel_key.as_str()))
+					.filter(|(rel_key, _)| !is_chunk_key_of(rel_key, |file| local_paths.contains(file)))
+					.filter(|(rel_key, _)| opts.include_hidden || !is_hidden_key(rel_key))
+					.map(|(_, remote)| remote.key.clone())
+					.collect();
+				plan.delete_keys.sort();
+			}
+			for (path, rel_path) in locals {
+				let change = match remotes.get(&rel_path) {
+					Some(remote) => {
+						let meta = path.metadata()?;
+						compare(opts.overwrite, meta.modified().ok(), remote_modified(remote), || {
+							is_different(&path, &meta, remote)
+						})?
+					}
+					None => Change::Transfer,
+				};
+				match change {
+					Change::Transfer => {
+						let key = join_key(&prefix, &rel_path);
+						plan.transfers.push(Transfer::Upload { path, key });
+					}
+					Change::Unchanged => plan.unchanged += 1,
+					Change::Skipped => plan.skipped += 1,
+				}
+			}
+		}
+		SyncDirection::Down => {
+			if opts.delete {
+				plan.delete_paths = list_files(local_dir, &dir_opts)?
+					.into_iter()
+					.filter(|(_, rel_path)| !remotes.contains_key(rel_path))
+					.map(|(path, _)| path)
+					.collect();
+			}
+			for (rel_key, remote) in remotes {
+				let path = key_path(local_dir, &rel_key)?;
+				let change = match path.metadata() {
+					Ok(meta) if meta.is_file() => {
+						compare(opts.overwrite, remote_modified(&remote), meta.modified().ok(), || {
+							is_different(&path, &meta, &remote)
+						})?
+					}
+					_ => Change::Transfer,
+				};
+				match change {
+					Change::Transfer => plan.transfers.push(Transfer::Download { key: remote.key, path }),
+					Change::Unchanged => plan.unchanged += 1,
+					Change::Skipped => plan.skipped += 1,
+				}
+			}
+		}
+	}
+
+	// INDEX - the files found identical
+	if let Some(db_path) = &opts.index {
+		save_index(db_path, store.name(), local_dir, &identical)?;
+	}
+
+	info!(
+		transfers = plan.transfers.len(),
+		unchanged = plan.unchanged,
+		skipped = plan.skipped,
+		deletes = plan.delete_count(),
+		"sync plan"
+	);
+
+	Ok(plan)
+}
+
+/// What to do with an existing destination.
+pub(crate) enum Change {
+	Transfer,
+	Unchanged,
+	Skipped,
+}
+
+/// Compare an existing destination with its source (see [`is_different`]), per the overwrite policy.
+pub(crate) fn compare(
+	overwrite: Overwrite,
+	src_modified: Option<SystemTime>,
+	dst_modified: Option<SystemTime>,
+	is_different: impl FnOnce() -> Result<bool>,
+) -> Result<Change> {
+	if overwrite == Overwrite::Always {
+		return Ok(Change::Transfer);
+	}
+	if !is_different()? {
+		return Ok(Change::Unchanged);
+	}
+	if overwrite.allows(src_modified, dst_modified) {
+		Ok(Change::Transfer)
+	} else {
+		Ok(Change::Skipped)
+	}
+}
+
+/// Compare a local file with its remote object.
+/// Size first, then content MD5 when the ETag is one, otherwise the mtime of the uploaded file must be the same
+/// (when stored), or else the source must be newer.
+fn is_different(path: &Path, meta: &Metadata, remote: &ObjectInfo, direction: SyncDirection) -> Result<bool> {
+	if meta.len() != remote.size {
+		return Ok(true);
+	}
+
+	if let Some(remote_md5) = remote.content_md5() {
+		return Ok(file_md5(path)? != remote_md5);
+	}
+
+	// Note: the downloads restore the uploaded file mtime, so the same file has the same mtime on both sides.
+	let local_mtime = meta.modified().ok();
+	if let (Some(local), Some(remote)) = (local_mtime, remote.mtime) {
+		return Ok(is_newer(local, remote) || is_newer(remote, local));
+	}
+
+	let newer = match (direction, local_mtime, remote.last_modified) {
+		(SyncDirection::Up, Some(local), Some(remote)) => is_newer(local, remote),
+		(SyncDirection::Down, Some(local), Some(remote)) => is_newer(remote, local),
+		_ => true,
+	};
+
+	Ok(newer)
+}
+
+/// The modification time of the remote file: the mtime of the uploaded file when known, else the object one.
+fn remote_modified(remote: &ObjectInfo) -> Option<SystemTime> {
+	remote.mtime.or(remote.last_modified)
+}
+
+/// Whether the local file of `rel_key` under `local_dir` exists with the size of its `remote` object.
+fn is_same_size(local_dir: &Path, rel_key: &str, remote: &ObjectInfo) -> bool {
+	key_path(local_dir, rel_key)
+		.ok()
+		.and_then(|path| path.metadata().ok())
+		.is_some_and(|meta| meta.is_file() && meta.len() == remote.size)
+}
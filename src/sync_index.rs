@@ -0,0 +1,116 @@
+use crate::error::{io_context, Result};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// -- constants
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS synced (
+	bucket TEXT NOT NULL,
+	key TEXT NOT NULL,
+	dir TEXT NOT NULL,
+	size INTEGER NOT NULL,
+	mtime INTEGER NOT NULL,
+	e_tag TEXT NOT NULL,
+	PRIMARY KEY (bucket, key, dir)
+)";
+
+/// A local file found identical to its object by a sync: the file fingerprint and the object ETag.
+/// While both are unchanged, the next syncs do not hash the file again.
+#[derive(Debug, Clone)]
+pub(crate) struct IndexEntry {
+	pub key: String,
+	pub size: u64,
+	/// The file modification time (epoch nanoseconds).
+	pub mtime: i64,
+	pub e_tag: String,
+}
+
+impl IndexEntry {
+	pub fn new(key: &str, meta: &Metadata, e_tag: &str) -> Self {
+		IndexEntry {
+			key: key.to_string(),
+			size: meta.len(),
+			mtime: mtime_nanos(meta),
+			e_tag: e_tag.to_string(),
+		}
+	}
+
+	/// Whether the file (`meta`) and its object (`e_tag`) are the ones found identical.
+	pub fn matches(&self, meta: &Metadata, e_tag: &str) -> bool {
+		self.size == meta.len() && self.mtime == mtime_nanos(meta) && self.e_tag == e_tag
+	}
+}
+
+/// The entries of the files under `local_dir` synced with `bucket`, by key.
+pub(crate) fn load_index(db_path: &Path, bucket: &str, local_dir: &Path) -> Result<HashMap<String, IndexEntry>> {
+	let conn = open(db_path)?;
+	let mut stmt = conn.prepare("SELECT key, size, mtime, e_tag FROM synced WHERE bucket = ?1 AND dir = ?2")?;
+	let rows = stmt.query_map(params![bucket, dir_id(local_dir)], |row| {
+		Ok(IndexEntry {
+			key: row.get(0)?,
+			size: row.get(1)?,
+			mtime: row.get(2)?,
+			e_tag: row.get(3)?,
+		})
+	})?;
+
+	let mut entries = HashMap::new();
+	for entry in rows {
+		let entry = entry?;
+		entries.insert(entry.key.clone(), entry);
+	}
+	Ok(entries)
+}
+
+/// Add (or replace) the `entries` of the files under `local_dir`, in one transaction.
+pub(crate) fn save_index(db_path: &Path, bucket: &str, local_dir: &Path, entries: &[IndexEntry]) -> Result<()> {
+	if entries.is_empty() {
+		return Ok(());
+	}
+	let mut conn = open(db_path)?;
+	let tx = conn.transaction()?;
+	{
+		let mut stmt = tx.prepare(
+			"INSERT OR REPLACE INTO synced (bucket, key, dir, size, mtime, e_tag) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+		)?;
+		let dir = dir_id(local_dir);
+		for entry in entries {
+			stmt.execute(params![bucket, entry.key, dir, entry.size, entry.mtime, entry.e_tag])?;
+		}
+	}
+	Ok(tx.commit()?)
+}
+
+/// Default path of the sync index (`<local data dir>/s3demo/sync.db`).
+pub fn default_sync_index_path() -> PathBuf {
+	dirs::data_local_dir()
+		.unwrap_or_else(std::env::temp_dir)
+		.join("s3demo")
+		.join("sync.db")
+}
+
+fn open(db_path: &Path) -> Result<Connection> {
+	if let Some(parent_dir) = db_path.parent() {
+		fs::create_dir_all(parent_dir)
+			.map_err(|ex| io_context(ex, format!("Cannot create dir {}", parent_dir.display())))?;
+	}
+	let conn = Connection::open(db_path)?;
+	conn.execute(CREATE_TABLE, [])?;
+	Ok(conn)
+}
+
+/// The local directory, canonical so the relative and absolute paths share the entries.
+fn dir_id(local_dir: &Path) -> String {
+	let dir = local_dir.canonicalize().unwrap_or_else(|_| local_dir.to_path_buf());
+	dir.to_string_lossy().to_string()
+}
+
+fn mtime_nanos(meta: &Metadata) -> i64 {
+	meta.modified()
+		.ok()
+		.and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+		.map(|d| d.as_nanos() as i64)
+		.unwrap_or(0)
+}
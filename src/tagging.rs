@@ -0,0 +1,92 @@
+use crate::error::Result;
+use crate::S3Bucket;
+use aws_sdk_s3::model::{Tag, Tagging};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::collections::HashMap;
+
+// -- constants
+/// S3 maximum number of tags per object.
+pub const MAX_TAGS: usize = 10;
+/// Characters left as is in the `x-amz-tagging` query string (everything else is percent-encoded).
+const TAGGING_SAFE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'_').remove(b'.').remove(b'~');
+
+impl S3Bucket {
+	/// Get the tags of the object `key`.
+	pub async fn get_object_tags(&self, key: &str) -> Result<HashMap<String, String>> {
+		// BUILD & EXECUTE - aws request
+		let res = self
+			.client()
+			.get_object_tagging()
+			.bucket(self.name())
+			.set_request_payer(self.request_payer().cloned())
+			.key(key)
+			.send()
+			.await?;
+
+		// COLLECT
+		let tags = res
+			.tag_set()
+			.unwrap_or_default()
+			.iter()
+			.filter_map(|t| Some((t.key()?.to_string(), t.value()?.to_string())))
+			.collect();
+
+		Ok(tags)
+	}
+
+	/// Replace the tags of the object `key` with `tags`.
+	pub async fn put_object_tags(&self, key: &str, tags: &HashMap<String, String>) -> Result<()> {
+		// BUILD - aws request
+		let tag_set = tags
+			.iter()
+			.map(|(name, value)| Tag::builder().key(name).value(value).build())
+			.collect::<Vec<_>>();
+		let tagging = Tagging::builder().set_tag_set(Some(tag_set)).build();
+		let req = self
+			.client()
+			.put_object_tagging()
+			.bucket(self.name())
+			.key(key)
+			.tagging(tagging);
+
+		// EXECUTE
+		req.send().await?;
+
+		Ok(())
+	}
+
+	/// Remove all the tags of the object `key`.
+	pub async fn delete_object_tags(&self, key: &str) -> Result<()> {
+		// BUILD & EXECUTE - aws request
+		self.client()
+			.delete_object_tagging()
+			.bucket(self.name())
+			.key(key)
+			.send()
+			.await?;
+
+		Ok(())
+	}
+}
+
+/// The `x-amz-tagging` value of the upload requests (url query string, e.g., `project=demo&team=core`).
+pub(crate) fn tagging_header(tags: &HashMap<String, String>) -> Option<String> {
+	if tags.is_empty() {
+		return None;
+	}
+	let mut tags = tags.iter().collect::<Vec<_>>();
+	tags.sort();
+	let tagging = tags
+		.into_iter()
+		.map(|(name, value)| {
+			format!(
+				"{}={}",
+				utf8_percent_encode(name, TAGGING_SAFE),
+				utf8_percent_encode(value, TAGGING_SAFE)
+			)
+		})
+		.collect::<Vec<_>>()
+		.join("&");
+
+	Some(tagging)
+}
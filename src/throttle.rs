@@ -0,0 +1,239 @@
+use aws_smithy_client::erase::DynConnector;
+use aws_smithy_http::body::SdkBody;
+use aws_smithy_http::result::ConnectorError;
+use bytes::Bytes;
+use http::{HeaderMap, HeaderValue, Request, Response};
+use http_body::combinators::BoxBody;
+use http_body::{Body, SizeHint};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::time::Sleep;
+use tower::{Service, ServiceExt};
+
+// -- constants
+/// Bytes released at once (so big chunks, like a whole part, are still sent at a steady rate).
+const THROTTLE_CHUNK_SIZE: usize = 64 * 1024;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Token bucket shared by all the request and response bodies of a client,
+/// refilled at `rate` bytes per second (with a burst of one second).
+pub(crate) struct RateLimiter {
+	rate: f64,
+	state: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+	pub(crate) fn new(bytes_per_sec: u64) -> Self {
+		let rate = bytes_per_sec.max(1) as f64;
+		RateLimiter {
+			rate,
+			state: Mutex::new((rate, Instant::now())),
+		}
+	}
+
+	/// Take `len` tokens, returns how long to wait before using them (zero if available).
+	fn reserve(&self, len: usize) -> Duration {
+		self.reserve_at(len, Instant::now())
+	}
+
+	/// See [`RateLimiter::reserve`], refilled until `now`.
+	fn reserve_at(&self, len: usize, now: Instant) -> Duration {
+		let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+		let (tokens, last) = &mut *state;
+		*tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.rate).min(self.rate);
+		*last = now;
+		*tokens -= len as f64;
+		if *tokens >= 0.0 {
+			Duration::ZERO
+		} else {
+			Duration::from_secs_f64(-*tokens / self.rate)
+		}
+	}
+}
+
+/// Body releasing the bytes of the inner body per the rate limiter.
+struct ThrottledBody {
+	inner: SdkBody,
+	limiter: Arc<RateLimiter>,
+	/// Bytes read from the inner body, not released yet.
+	buf: Bytes,
+	/// The wait before releasing `ready`.
+	sleep: Option<Pin<Box<Sleep>>>,
+	ready: Option<Bytes>,
+}
+
+impl ThrottledBody {
+	fn sdk_body(inner: SdkBody, limiter: Arc<RateLimiter>) -> SdkBody {
+		let body = ThrottledBody {
+			inner,
+			limiter,
+			buf: Bytes::new(),
+			sleep: None,
+			ready: None,
+		};
+		SdkBody::from_dyn(BoxBody::new(body))
+	}
+}
+
+impl Body for ThrottledBody {
+	type Data = Bytes;
+	type Error = BoxError;
+
+	fn poll_data(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, BoxError>>> {
+		loop {
+			// WAIT - for the tokens of the ready chunk
+			if let Some(sleep) = self.sleep.as_mut() {
+				if sleep.as_mut().poll(cx).is_pending() {
+					return Poll::Pending;
+				}
+				self.sleep = None;
+			}
+			if let Some(ready) = self.ready.take() {
+				return Poll::Ready(Some(Ok(ready)));
+			}
+
+			// NEXT - chunk from the buffer, or from the inner body
+			if self.buf.is_empty() {
+				match Pin::new(&mut self.inner).poll_data(cx) {
+					Poll::Ready(Some(Ok(data))) => self.buf = data,
+					Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err))),
+					Poll::Ready(None) => return Poll::Ready(None),
+					Poll::Pending => return Poll::Pending,
+				}
+				continue;
+			}
+			let len = self.buf.len().min(THROTTLE_CHUNK_SIZE);
+			let chunk = self.buf.split_to(len);
+			let wait = self.limiter.reserve(len);
+			if wait.is_zero() {
+				return Poll::Ready(Some(Ok(chunk)));
+			}
+			self.ready = Some(chunk);
+			self.sleep = Some(Box::pin(tokio::time::sleep(wait)));
+		}
+	}
+
+	fn poll_trailers(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Result<Option<HeaderMap<HeaderValue>>, BoxError>> {
+		Pin::new(&mut self.inner).poll_trailers(cx)
+	}
+
+	fn is_end_stream(&self) -> bool {
+		self.ready.is_none() && self.buf.is_empty() && self.inner.is_end_stream()
+	}
+
+	fn size_hint(&self) -> SizeHint {
+		let pending = (self.buf.len() + self.ready.as_ref().map(|b| b.len()).unwrap_or(0)) as u64;
+		let inner = self.inner.size_hint();
+		let mut hint = SizeHint::new();
+		hint.set_lower(inner.lower() + pending);
+		if let Some(upper) = inner.upper() {
+			hint.set_upper(upper + pending);
+		}
+		hint
+	}
+}
+
+/// Connector throttling the request (upload) and response (download) bodies of the wrapped connector.
+#[derive(Clone)]
+pub(crate) struct ThrottleConnector {
+	inner: DynConnector,
+	limiter: Arc<RateLimiter>,
+}
+
+impl ThrottleConnector {
+	pub(crate) fn new(inner: DynConnector, bytes_per_sec: u64) -> Self {
+		ThrottleConnector {
+			inner,
+			limiter: Arc::new(RateLimiter::new(bytes_per_sec)),
+		}
+	}
+}
+
+type ConnectorFuture = Pin<Box<dyn Future<Output = Result<Response<SdkBody>, ConnectorError>> + Send>>;
+
+impl Service<Request<SdkBody>> for ThrottleConnector {
+	type Response = Response<SdkBody>;
+	type Error = ConnectorError;
+	type Future = ConnectorFuture;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, req: Request<SdkBody>) -> Self::Future {
+		let inner = self.inner.clone();
+		let limiter = self.limiter.clone();
+
+		// Note: keep the body retryable (when it was) by throttling each of its clones.
+		let req = req.map(|body| match body.try_clone() {
+			Some(_) => {
+				let limiter = limiter.clone();
+				let source = Mutex::new(body);
+				SdkBody::retryable(move || {
+					let source = source.lock().unwrap_or_else(|e| e.into_inner());
+					let body = source.try_clone().unwrap_or_else(SdkBody::taken);
+					ThrottledBody::sdk_body(body, limiter.clone())
+				})
+			}
+			None => ThrottledBody::sdk_body(body, limiter.clone()),
+		});
+
+		Box::pin(async move {
+			let res = inner.oneshot(req).await?;
+			Ok(res.map(|body| ThrottledBody::sdk_body(body, limiter)))
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_rate_limiter_burst() {
+		let limiter = RateLimiter::new(1000);
+		let start = limiter.state.lock().unwrap().1;
+		// Note: a burst of one second of tokens, then the wait for the missing ones.
+		assert_eq!(limiter.reserve_at(600, start), Duration::ZERO);
+		assert_eq!(limiter.reserve_at(400, start), Duration::ZERO);
+		assert_eq!(limiter.reserve_at(500, start), Duration::from_millis(500));
+		// Note: the tokens taken ahead are owed, so the next wait is longer.
+		assert_eq!(limiter.reserve_at(500, start), Duration::from_secs(1));
+	}
+
+	#[test]
+	fn test_rate_limiter_refill() {
+		let limiter = RateLimiter::new(1000);
+		let start = limiter.state.lock().unwrap().1;
+		assert_eq!(limiter.reserve_at(1000, start), Duration::ZERO);
+		assert_eq!(
+			limiter.reserve_at(250, start + Duration::from_millis(250)),
+			Duration::ZERO
+		);
+		assert_eq!(
+			limiter.reserve_at(500, start + Duration::from_millis(500)),
+			Duration::from_millis(250)
+		);
+
+		// Note: the refill is capped at the burst (one second of tokens), however long the idle time.
+		let later = start + Duration::from_secs(60);
+		assert_eq!(limiter.reserve_at(1000, later), Duration::ZERO);
+		assert_eq!(limiter.reserve_at(100, later), Duration::from_millis(100));
+	}
+
+	#[test]
+	fn test_rate_limiter_zero_rate() {
+		// Note: a zero rate is one byte per second, rather than a division by zero.
+		let limiter = RateLimiter::new(0);
+		let start = limiter.state.lock().unwrap().1;
+		assert_eq!(limiter.reserve_at(1, start), Duration::ZERO);
+		assert_eq!(limiter.reserve_at(2, start), Duration::from_secs(2));
+	}
+}
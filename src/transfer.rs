@@ -0,0 +1,126 @@
+use crate::error::{Result, S3DemoError};
+use crate::store::ObjectStore;
+use crate::S3Bucket;
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::path::{Path, PathBuf};
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+// -- constants
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// One file/object transfer.
+#[derive(Debug, Clone)]
+pub enum Transfer {
+	/// Upload the local file `path` to `key`.
+	Upload { path: PathBuf, key: String },
+	/// Download the object `key` to the local file `path`.
+	Download { key: String, path: PathBuf },
+}
+
+impl Transfer {
+	pub fn key(&self) -> &str {
+		match self {
+			Transfer::Upload { key, .. } | Transfer::Download { key, .. } => key,
+		}
+	}
+
+	pub fn path(&self) -> &Path {
+		match self {
+			Transfer::Upload { path, .. } | Transfer::Download { path, .. } => path,
+		}
+	}
+}
+
+/// The outcome of one [`Transfer`] run by a [`TransferManager`].
+#[derive(Debug)]
+pub struct TransferResult {
+	pub transfer: Transfer,
+	pub result: Result<()>,
+}
+
+/// Runs uploads/downloads in parallel, with at most `concurrency` transfers in flight.
+pub struct TransferManager<'a, S: ObjectStore + ?Sized = S3Bucket> {
+	store: &'a S,
+	semaphore: Semaphore,
+}
+
+impl<'a, S: ObjectStore + ?Sized> TransferManager<'a, S> {
+	/// New manager with the store concurrency.
+	pub fn new(store: &'a S) -> Self {
+		Self::with_concurrency(store, store.concurrency())
+	}
+
+	pub fn with_concurrency(store: &'a S, concurrency: usize) -> Self {
+		TransferManager {
+			store,
+			semaphore: Semaphore::new(concurrency.max(1)),
+		}
+	}
+
+	/// Run all the transfers, returning one result per transfer (in completion order).
+	/// A failing transfer does not stop the others, while the shutdown of the store (see
+	/// [`S3Bucket::with_shutdown`]) fails the transfers not started yet with [`S3DemoError::Interrupted`].
+	pub async fn run(&self, transfers: Vec<Transfer>) -> Vec<TransferResult> {
+		let mut futs = transfers
+			.into_iter()
+			.map(|transfer| self.run_one(transfer))
+			.collect::<FuturesUnordered<_>>();
+
+		let mut results = Vec::new();
+		while let Some(res) = futs.next().await {
+			results.push(res);
+		}
+		results
+	}
+
+	async fn run_one(&self, transfer: Transfer) -> TransferResult {
+		// Note: The semaphore is never closed, so acquire cannot fail.
+		let _permit = self.semaphore.acquire().await;
+
+		let result = match &transfer {
+			_ if self.store.is_shutting_down() => Err(S3DemoError::Interrupted(transfer.key().to_string())),
+			Transfer::Upload { path, key } => self.store.upload_to(path, key).await,
+			Transfer::Download { key, path } => self.store.download_to(key, path).await,
+		};
+
+		if let Some(ex) = result
+			.as_ref()
+			.err()
+			.filter(|ex| !matches!(ex, S3DemoError::Interrupted(_)))
+		{
+			warn!(key = transfer.key(), error = %ex, "transfer failed");
+		}
+
+		TransferResult { transfer, result }
+	}
+}
+
+/// Turn the results into the list of succeeded transfers, or an error if any failed (or was interrupted).
+pub(crate) fn into_transfers(results: Vec<TransferResult>) -> Result<Vec<Transfer>> {
+	let total = results.len();
+	let mut transfers = Vec::with_capacity(total);
+	let mut errors = Vec::new();
+	let mut interrupted = 0;
+	for TransferResult { transfer, result } in results {
+		match result {
+			Ok(()) => transfers.push(transfer),
+			// Note: counted, rather than listed, as they can be most of the transfers.
+			Err(S3DemoError::Interrupted(_)) => interrupted += 1,
+			Err(ex) => errors.push(format!("{}: {ex}", transfer.key())),
+		}
+	}
+
+	if !errors.is_empty() {
+		if interrupted > 0 {
+			errors.push(format!("{interrupted} transfers interrupted"));
+		}
+		Err(S3DemoError::TransfersFailed { errors, total })
+	} else if interrupted > 0 {
+		Err(S3DemoError::Interrupted(format!(
+			"{interrupted} of {total} transfers not done"
+		)))
+	} else {
+		Ok(transfers)
+	}
+}
@@ -0,0 +1,84 @@
+use crate::audit::{AuditEntry, AuditOperation};
+use crate::error::{Result, S3DemoError};
+use crate::filter::KeyFilter;
+use crate::S3Bucket;
+
+impl S3Bucket {
+	/// Soft delete the object `key`: place a delete marker, so the object can be restored with
+	/// [`S3Bucket::undelete`]. Fails if the bucket versioning is not enabled (the delete would be permanent).
+	/// Returns the version id of the delete marker.
+	pub async fn trash_object(&self, key: &str) -> Result<String> {
+		self.ensure_versioning_enabled().await?;
+
+		// BUILD & EXECUTE - aws request
+		let res = self
+			.client()
+			.delete_object()
+			.bucket(self.name())
+			.key(key)
+			.send()
+			.await?;
+		let marker = res
+			.version_id()
+			.map(|v| v.to_string())
+			.ok_or_else(|| S3DemoError::InvalidResponse(format!("No delete marker placed for {key}")))?;
+
+		// AUDIT
+		let mut entry = AuditEntry::new(AuditOperation::Delete, self.name(), key);
+		entry.version_id = Some(marker.clone());
+		self.audit(entry)?;
+
+		Ok(marker)
+	}
+
+	/// Soft delete all the objects under the `prefix` "directory" matching `filter`
+	/// (see [`S3Bucket::trash_object`]). Returns the deleted keys.
+	pub async fn trash_prefix(&self, prefix: &str, filter: &KeyFilter) -> Result<Vec<String>> {
+		self.ensure_versioning_enabled().await?;
+		self.delete_prefix(prefix, filter).await
+	}
+
+	/// Soft delete the `keys` (see [`S3Bucket::trash_object`]), with batched DeleteObjects requests.
+	pub async fn trash_objects(&self, keys: &[String]) -> Result<()> {
+		self.ensure_versioning_enabled().await?;
+		self.delete_objects(keys).await
+	}
+
+	/// Restore the soft deleted object `key` by removing its delete marker(s) on top of the
+	/// newest version. Returns the version id of the restored (now current) version.
+	pub async fn undelete(&self, key: &str) -> Result<String> {
+		let versions = self.list_key_versions(key).await?;
+
+		// FIND - the delete markers on top, and the version below them
+		let markers = versions
+			.iter()
+			.take_while(|v| v.is_delete_marker)
+			.map(|v| (v.key.clone(), v.version_id.clone()))
+			.collect::<Vec<_>>();
+		if markers.is_empty() {
+			return Err(S3DemoError::InvalidInput(format!(
+				"{key} is not deleted (no delete marker)"
+			)));
+		}
+		let restored = versions
+			.iter()
+			.find(|v| !v.is_delete_marker)
+			.ok_or_else(|| S3DemoError::NotFound(format!("No version of {key} to undelete")))?;
+
+		// DELETE - the delete markers
+		self.delete_object_versions(&markers).await?;
+
+		Ok(restored.version_id.clone())
+	}
+
+	async fn ensure_versioning_enabled(&self) -> Result<()> {
+		let status = self.versioning_status().await?;
+		if status.as_deref() != Some("Enabled") {
+			return Err(S3DemoError::InvalidInput(format!(
+				"Versioning is not enabled on {} (soft delete would be permanent)",
+				self.name()
+			)));
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,49 @@
+use aws_smithy_client::erase::DynConnector;
+use aws_smithy_http::body::SdkBody;
+use aws_smithy_http::result::ConnectorError;
+use http::header::AUTHORIZATION;
+use http::{Request, Response};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Service, ServiceExt};
+
+// -- constants
+/// The SigV4 headers besides `Authorization` (the `x-amz-content-sha256` one is kept, as a payload checksum).
+const SIGNING_HEADERS: [&str; 2] = ["x-amz-date", "x-amz-security-token"];
+
+/// Connector sending the requests unsigned (anonymous), for the public buckets.
+///
+/// Note: this SDK version always signs the requests, so the client signs them with placeholder
+/// credentials, and this connector strips the signature before sending them.
+#[derive(Clone)]
+pub(crate) struct UnsignedConnector {
+	inner: DynConnector,
+}
+
+impl UnsignedConnector {
+	pub(crate) fn new(inner: DynConnector) -> Self {
+		UnsignedConnector { inner }
+	}
+}
+
+type ConnectorFuture = Pin<Box<dyn Future<Output = Result<Response<SdkBody>, ConnectorError>> + Send>>;
+
+impl Service<Request<SdkBody>> for UnsignedConnector {
+	type Response = Response<SdkBody>;
+	type Error = ConnectorError;
+	type Future = ConnectorFuture;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.inner.poll_ready(cx)
+	}
+
+	fn call(&mut self, mut req: Request<SdkBody>) -> Self::Future {
+		let headers = req.headers_mut();
+		headers.remove(AUTHORIZATION);
+		for name in SIGNING_HEADERS {
+			headers.remove(name);
+		}
+		Box::pin(self.inner.clone().oneshot(req))
+	}
+}
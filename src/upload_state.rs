@@ -0,0 +1,243 @@
+use crate::checksum::md5_hex;
+use crate::error::{io_context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The persisted state of an in-progress multipart upload, so it can be resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct UploadState {
+	pub bucket: String,
+	pub key: String,
+	pub path: PathBuf,
+	pub upload_id: String,
+	/// Fingerprint of the source file, which must not change between runs.
+	pub file_size: u64,
+	pub file_mtime: u64,
+	pub part_size: usize,
+	/// The completed parts, in order.
+	pub parts: Vec<PartState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct PartState {
+	pub part_number: i32,
+	pub e_tag: Option<String>,
+}
+
+impl UploadState {
+	/// Whether this state is for the same upload of the same (unchanged) file.
+	pub fn is_resumable(&self, file_size: u64, file_mtime: u64, part_size: usize) -> bool {
+		self.file_size == file_size && self.file_mtime == file_mtime && self.part_size == part_size
+	}
+}
+
+/// Directory of the [`UploadState`] json files (one per bucket/key/path).
+#[derive(Debug, Clone)]
+pub(crate) struct UploadStateStore {
+	dir: PathBuf,
+}
+
+impl UploadStateStore {
+	pub fn new(dir: impl Into<PathBuf>) -> Self {
+		UploadStateStore { dir: dir.into() }
+	}
+
+	pub fn load(&self, bucket: &str, key: &str, path: &Path) -> Option<UploadState> {
+		let content = fs::read_to_string(self.state_file(bucket, key, path)).ok()?;
+		serde_json::from_str(&content).ok()
+	}
+
+	/// The state of `bucket` / `key` / `path` to resume, if it is for the same upload of the same (unchanged) file
+	/// (see [`UploadState::is_resumable`]).
+	pub fn load_resumable(
+		&self,
+		bucket: &str,
+		key: &str,
+		path: &Path,
+		file_size: u64,
+		file_mtime: u64,
+		part_size: usize,
+	) -> Option<UploadState> {
+		self.load(bucket, key, path)
+			.filter(|state| state.is_resumable(file_size, file_mtime, part_size))
+	}
+
+	pub fn save(&self, state: &UploadState) -> Result<()> {
+		fs::create_dir_all(&self.dir)
+			.map_err(|ex| io_context(ex, format!("Cannot create state dir {}", self.dir.display())))?;
+		let file = self.state_file(&state.bucket, &state.key, &state.path);
+
+		// Note: write then rename, so an interruption never leaves a truncated state file.
+		let tmp_file = file.with_extension("json.tmp");
+		fs::write(&tmp_file, serde_json::to_string_pretty(state)?)?;
+		fs::rename(&tmp_file, &file)?;
+
+		Ok(())
+	}
+
+	pub fn remove(&self, bucket: &str, key: &str, path: &Path) {
+		let _ = fs::remove_file(self.state_file(bucket, key, path));
+	}
+
+	/// All the states for `bucket` / `key` (whatever the source path).
+	pub fn find(&self, bucket: &str, key: &str) -> Vec<UploadState> {
+		let Ok(entries) = fs::read_dir(&self.dir) else {
+			return Vec::new();
+		};
+		entries
+			.filter_map(|e| e.ok())
+			.filter(|e| e.path().extension().map(|ext| ext == "json").unwrap_or(false))
+			.filter_map(|e| fs::read_to_string(e.path()).ok())
+			.filter_map(|content| serde_json::from_str::<UploadState>(&content).ok())
+			.filter(|s| s.bucket == bucket && s.key == key)
+			.collect()
+	}
+
+	fn state_file(&self, bucket: &str, key: &str, path: &Path) -> PathBuf {
+		self.dir.join(format!("{}.json", state_file_id(bucket, key, path)))
+	}
+}
+
+/// The state file name (without extension) of a bucket/key/local path.
+pub(crate) fn state_file_id(bucket: &str, key: &str, path: &Path) -> String {
+	let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+	md5_hex(format!("{bucket}\n{key}\n{}", path.display()).as_bytes())
+}
+
+/// Default directory of the multipart upload states (`<local data dir>/s3demo/uploads`).
+pub fn default_upload_state_dir() -> PathBuf {
+	dirs::data_local_dir()
+		.unwrap_or_else(std::env::temp_dir)
+		.join("s3demo")
+		.join("uploads")
+}
+
+/// The file modification time in seconds (0 when unavailable).
+pub(crate) fn mtime_secs(path: &Path) -> u64 {
+	path.metadata()
+		.and_then(|m| m.modified())
+		.ok()
+		.and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+		.map(|d| d.as_secs())
+		.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	fn sample_state(path: &Path) -> UploadState {
+		UploadState {
+			bucket: "bucket".to_string(),
+			key: "videos/a.mp4".to_string(),
+			path: path.to_path_buf(),
+			upload_id: "upload-1".to_string(),
+			file_size: 20_000_000,
+			file_mtime: 1_700_000_000,
+			part_size: 8 * 1024 * 1024,
+			parts: vec![
+				PartState {
+					part_number: 1,
+					e_tag: Some("\"e1\"".to_string()),
+				},
+				PartState {
+					part_number: 2,
+					e_tag: None,
+				},
+			],
+		}
+	}
+
+	#[test]
+	fn test_upload_state_save_load() -> Result<()> {
+		let dir = TempDir::new().unwrap();
+		let store = UploadStateStore::new(dir.path().join("uploads"));
+		let path = dir.path().join("a.mp4");
+		let state = sample_state(&path);
+
+		store.save(&state)?;
+		let loaded = store.load("bucket", "videos/a.mp4", &path).expect("saved state");
+		assert_eq!(loaded.upload_id, "upload-1");
+		assert_eq!(loaded.part_size, state.part_size);
+		let parts = loaded
+			.parts
+			.iter()
+			.map(|p| (p.part_number, p.e_tag.as_deref()))
+			.collect::<Vec<_>>();
+		assert_eq!(parts, [(1, Some("\"e1\"")), (2, None)]);
+		// Note: no temp file left by the write then rename.
+		let files = fs::read_dir(dir.path().join("uploads"))?.count();
+		assert_eq!(files, 1);
+
+		// Note: the state is per bucket, key and path.
+		assert!(store.load("other", "videos/a.mp4", &path).is_none());
+		assert!(store.load("bucket", "videos/b.mp4", &path).is_none());
+		assert!(store
+			.load("bucket", "videos/a.mp4", &dir.path().join("b.mp4"))
+			.is_none());
+
+		store.remove("bucket", "videos/a.mp4", &path);
+		assert!(store.load("bucket", "videos/a.mp4", &path).is_none());
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_upload_state_load_resumable() -> Result<()> {
+		let dir = TempDir::new().unwrap();
+		let store = UploadStateStore::new(dir.path());
+		let path = dir.path().join("a.mp4");
+		let state = sample_state(&path);
+		store.save(&state)?;
+
+		let (size, mtime, part_size) = (state.file_size, state.file_mtime, state.part_size);
+		let resumable = |size, mtime, part_size| {
+			store
+				.load_resumable("bucket", "videos/a.mp4", &path, size, mtime, part_size)
+				.is_some()
+		};
+		assert!(resumable(size, mtime, part_size));
+		// Note: a modified file (size or mtime), or another part size, starts a new upload.
+		assert!(!resumable(size + 1, mtime, part_size));
+		assert!(!resumable(size, mtime + 1, part_size));
+		assert!(!resumable(size, mtime, part_size * 2));
+
+		Ok(())
+	}
+
+	#[test]
+	fn test_upload_state_find_and_corrupted() -> Result<()> {
+		let dir = TempDir::new().unwrap();
+		let store = UploadStateStore::new(dir.path());
+		let path_a = dir.path().join("a.mp4");
+		let path_b = dir.path().join("b.mp4");
+		store.save(&sample_state(&path_a))?;
+		store.save(&UploadState {
+			upload_id: "upload-2".to_string(),
+			..sample_state(&path_b)
+		})?;
+		store.save(&UploadState {
+			key: "other.mp4".to_string(),
+			..sample_state(&path_a)
+		})?;
+
+		let mut ids = store
+			.find("bucket", "videos/a.mp4")
+			.into_iter()
+			.map(|s| s.upload_id)
+			.collect::<Vec<_>>();
+		ids.sort();
+		assert_eq!(ids, ["upload-1", "upload-2"]);
+
+		// Note: a corrupted state file is ignored (a new upload is started).
+		let state_file = store.state_file("bucket", "videos/a.mp4", &path_a);
+		fs::write(&state_file, "{ \"bucket\": ")?;
+		assert!(store.load("bucket", "videos/a.mp4", &path_a).is_none());
+		assert_eq!(store.find("bucket", "videos/a.mp4").len(), 1);
+
+		Ok(())
+	}
+}
@@ -0,0 +1,204 @@
+use crate::error::{Result, S3DemoError};
+use crate::key::listing_key;
+use crate::object::ser_time;
+use crate::S3Bucket;
+use aws_sdk_s3::model::{BucketVersioningStatus, EncodingType, VersioningConfiguration};
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+/// Which non-current versions to prune. With both criteria, only the versions matching both are pruned.
+#[derive(Debug, Clone, Default)]
+pub struct PrunePolicy {
+	/// Prune the versions non-current for longer than this (i.e., since a newer version was written).
+	pub older_than: Option<Duration>,
+	/// Keep the newest `keep` versions of each key (including the current one), prune the others.
+	pub keep: Option<usize>,
+}
+
+/// A version (or delete marker) of an object, from ListObjectVersions.
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectVersion {
+	pub key: String,
+	pub version_id: String,
+	pub is_latest: bool,
+	pub is_delete_marker: bool,
+	/// 0 for delete markers.
+	pub size: u64,
+	#[serde(serialize_with = "ser_time")]
+	pub last_modified: Option<SystemTime>,
+	pub e_tag: Option<String>,
+}
+
+impl S3Bucket {
+	/// Enable (or suspend) the bucket versioning.
+	/// Note: once enabled, versioning can only be suspended (the existing versions are kept).
+	pub async fn set_versioning(&self, enabled: bool) -> Result<()> {
+		// BUILD - aws request
+		let status = if enabled {
+			BucketVersioningStatus::Enabled
+		} else {
+			BucketVersioningStatus::Suspended
+		};
+		let config = VersioningConfiguration::builder().status(status).build();
+		let req = self
+			.client()
+			.put_bucket_versioning()
+			.bucket(self.name())
+			.versioning_configuration(config);
+
+		// EXECUTE
+		req.send().await?;
+
+		Ok(())
+	}
+
+	/// The bucket versioning status, `Enabled` or `Suspended` (None if never enabled).
+	pub async fn versioning_status(&self) -> Result<Option<String>> {
+		// BUILD & EXECUTE - aws request
+		let res = self.client().get_bucket_versioning().bucket(self.name()).send().await?;
+
+		Ok(res.status().map(|s| s.as_str().to_string()))
+	}
+
+	/// The versions and delete markers of exactly `key`, newest first.
+	pub async fn list_key_versions(&self, key: &str) -> Result<Vec<ObjectVersion>> {
+		let versions = self.list_object_versions(key).await?;
+		Ok(versions.into_iter().filter(|v| v.key == key).collect())
+	}
+
+	/// Restore the previous version of `key` (e.g., after an accidental overwrite or delete) by copying
+	/// it over as the new latest version, so no version is lost. Returns the restored version id.
+	pub async fn rollback(&self, key: &str) -> Result<String> {
+		let versions = self.list_key_versions(key).await?;
+
+		// FIND - the newest real version before the latest one (a delete marker or an overwrite)
+		let previous = versions
+			.iter()
+			.skip_while(|v| !v.is_latest)
+			.skip(1)
+			.find(|v| !v.is_delete_marker)
+			.ok_or_else(|| S3DemoError::NotFound(format!("No previous version of {key} to roll back to")))?;
+
+		// COPY - the previous version on top
+		self.copy_object_version(key, &previous.version_id, self.name(), key)
+			.await?;
+
+		Ok(previous.version_id.clone())
+	}
+
+	/// The non-current versions (and delete markers) of the keys starting with `prefix` matching
+	/// `policy`, to preview before [`S3Bucket::prune_versions`]. The current versions are never pruned.
+	pub async fn prune_plan(&self, prefix: &str, policy: &PrunePolicy) -> Result<Vec<ObjectVersion>> {
+		let versions = self.list_object_versions(prefix).await?;
+		let now = SystemTime::now();
+
+		let mut pruned: Vec<ObjectVersion> = Vec::new();
+		let mut index = 0;
+		let mut previous: Option<&ObjectVersion> = None;
+		for v in versions.iter() {
+			// Note: the versions are listed by key, newest first.
+			match previous {
+				Some(p) if p.key == v.key => index += 1,
+				_ => index = 0,
+			}
+			let noncurrent_since = previous.filter(|p| p.key == v.key).and_then(|p| p.last_modified);
+			previous = Some(v);
+
+			if index == 0 || v.is_latest {
+				continue;
+			}
+			let beyond_keep = policy.keep.is_none_or(|keep| index >= keep);
+			let old_enough = match (policy.older_than, noncurrent_since) {
+				(Some(older_than), Some(since)) => now.duration_since(since).is_ok_and(|age| age > older_than),
+				(Some(_), None) => false,
+				(None, _) => true,
+			};
+			if beyond_keep && old_enough {
+				pruned.push(v.clone());
+			}
+		}
+
+		Ok(pruned)
+	}
+
+	/// Permanently delete the `versions` (e.g., from [`S3Bucket::prune_plan`]).
+	pub async fn prune_versions(&self, versions: &[ObjectVersion]) -> Result<()> {
+		let versions = versions
+			.iter()
+			.map(|v| (v.key.clone(), v.version_id.clone()))
+			.collect::<Vec<_>>();
+		self.delete_object_versions(&versions).await
+	}
+
+	/// List all the versions and delete markers of the keys starting with `prefix` (empty for all),
+	/// ordered by key, then newest first.
+	pub async fn list_object_versions(&self, prefix: &str) -> Result<Vec<ObjectVersion>> {
+		let mut versions: Vec<ObjectVersion> = Vec::new();
+		let mut markers: (Option<String>, Option<String>) = (None, None);
+
+		loop {
+			// BUILD - aws request
+			let req = self
+				.client()
+				.list_object_versions()
+				.bucket(self.name())
+				.prefix(prefix)
+				.encoding_type(EncodingType::Url)
+				.set_key_marker(markers.0.take())
+				.set_version_id_marker(markers.1.take());
+
+			// EXECUTE
+			let res = req.send().await?;
+
+			// COLLECT - the versions and the delete markers
+			let mut page: Vec<ObjectVersion> = Vec::new();
+			for v in res.versions().unwrap_or_default() {
+				if let (Some(key), Some(version_id)) = (v.key(), v.version_id()) {
+					page.push(ObjectVersion {
+						key: listing_key(key, res.encoding_type())?,
+						version_id: version_id.to_string(),
+						is_latest: v.is_latest(),
+						is_delete_marker: false,
+						size: v.size().max(0) as u64,
+						last_modified: v.last_modified().and_then(|d| SystemTime::try_from(*d).ok()),
+						e_tag: v.e_tag().map(|s| s.to_string()),
+					});
+				}
+			}
+			for m in res.delete_markers().unwrap_or_default() {
+				if let (Some(key), Some(version_id)) = (m.key(), m.version_id()) {
+					page.push(ObjectVersion {
+						key: listing_key(key, res.encoding_type())?,
+						version_id: version_id.to_string(),
+						is_latest: m.is_latest(),
+						is_delete_marker: true,
+						size: 0,
+						last_modified: m.last_modified().and_then(|d| SystemTime::try_from(*d).ok()),
+						e_tag: None,
+					});
+				}
+			}
+			// Note: S3 returns the versions and the markers in two lists, so merge them back in order.
+			page.sort_by(|a, b| {
+				a.key
+					.cmp(&b.key)
+					.then(b.is_latest.cmp(&a.is_latest))
+					.then(b.last_modified.cmp(&a.last_modified))
+			});
+			versions.extend(page);
+
+			// NEXT - page (if any)
+			if !res.is_truncated() {
+				break;
+			}
+			markers = (
+				res.next_key_marker()
+					.map(|s| listing_key(s, res.encoding_type()))
+					.transpose()?,
+				res.next_version_id_marker().map(|s| s.to_string()),
+			);
+		}
+
+		Ok(versions)
+	}
+}
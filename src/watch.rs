@@ -0,0 +1,157 @@
+use crate::dir::{is_hidden_key, join_key, load_ignore_rules, rel_path};
+use crate::error::{Result, S3DemoError};
+use crate::filter::KeyFilter;
+use crate::ignore::{IgnoreRules, IGNORE_FILE};
+use crate::transfer::{Transfer, TransferManager, TransferResult};
+use crate::S3Bucket;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, Instant};
+use tracing::{info, instrument, warn};
+
+// -- constants
+pub const DEFAULT_WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Options for [`S3Bucket::watch`].
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+	/// Also upload the hidden files and directories (names starting with `.`).
+	pub include_hidden: bool,
+	/// Filter on the file paths relative to the directory (e.g., excludes of the temporary files).
+	pub filter: KeyFilter,
+	/// Do not honor the [`IGNORE_FILE`] of the directory (if any), whose ignored files are otherwise not uploaded.
+	pub no_ignore_file: bool,
+	/// How long a file must stay unchanged before being uploaded (so a file being written is uploaded once).
+	pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+	fn default() -> Self {
+		WatchOptions {
+			include_hidden: false,
+			filter: KeyFilter::default(),
+			no_ignore_file: false,
+			debounce: DEFAULT_WATCH_DEBOUNCE,
+		}
+	}
+}
+
+impl S3Bucket {
+	/// Upload the files under `local_dir` (to `key_prefix` + the relative path) as soon as they are
+	/// created or modified, calling `on_upload` with the result of each upload.
+	///
+	/// A failed upload does not stop the watch, which runs until the watcher fails, the shutdown
+	/// (see [`S3Bucket::with_shutdown`], once the uploads in flight are done), or the future is dropped.
+	/// The existing files are not uploaded (see [`crate::ObjectStore::sync`]), and the deleted files
+	/// are not deleted from the bucket.
+	#[instrument(name = "watch", skip_all, fields(bucket = %self.name(), dir = %local_dir.display(), prefix = %key_prefix))]
+	pub async fn watch(
+		&self,
+		local_dir: &Path,
+		key_prefix: &str,
+		opts: &WatchOptions,
+		mut on_upload: impl FnMut(&TransferResult),
+	) -> Result<()> {
+		// VALIDATE
+		if !local_dir.is_dir() {
+			return Err(S3DemoError::InvalidInput(format!(
+				"Path {} is not a directory",
+				local_dir.display()
+			)));
+		}
+		// Note: the events have the canonical paths on some platforms (e.g., macOS).
+		let local_dir = local_dir.canonicalize()?;
+		let ignore_file = local_dir.join(IGNORE_FILE);
+		let mut ignore = load_ignore_rules(&local_dir, opts.no_ignore_file)?;
+
+		// WATCH - the events are forwarded to the async loop
+		let (tx, mut rx) = mpsc::unbounded_channel::<notify::Result<Event>>();
+		let mut watcher = notify::recommended_watcher(move |res| {
+			// Note: the receiver is only dropped when the watch returns.
+			let _ = tx.send(res);
+		})?;
+		watcher.watch(&local_dir, RecursiveMode::Recursive)?;
+		info!("watching");
+
+		// Last change time of the pending files, by path.
+		let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+		loop {
+			let next_ready = pending.values().min().map(|changed| *changed + opts.debounce);
+			tokio::select! {
+				event = rx.recv() => match event {
+					Some(Ok(event)) => {
+						if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+							continue;
+						}
+						let now = Instant::now();
+						for path in event.paths {
+							// Note: the rules take effect as soon as the ignore file changes (kept if invalid).
+							if path == ignore_file && !opts.no_ignore_file {
+								match IgnoreRules::load(&local_dir) {
+									Ok(rules) => ignore = rules,
+									Err(err) => warn!(error = %err, "ignore file not reloaded"),
+								}
+							}
+							if watched_rel_path(&local_dir, &path, opts, ignore.as_ref()).is_some() {
+								pending.insert(path, now);
+							}
+						}
+					}
+					Some(Err(err)) => warn!(error = %err, "watch error"),
+					None => return Err(S3DemoError::Io(io::Error::other("the file watcher stopped"))),
+				},
+				_ = self.shutdown().cancelled() => {
+					info!("watch stopped");
+					return Ok(());
+				}
+				_ = sleep_until(next_ready.unwrap_or_else(Instant::now)), if next_ready.is_some() => {
+					// COLLECT - the files unchanged for the debounce duration
+					let now = Instant::now();
+					let ready = pending
+						.iter()
+						.filter(|(_, changed)| **changed + opts.debounce <= now)
+						.map(|(path, _)| path.clone())
+						.collect::<Vec<_>>();
+					let mut transfers = Vec::new();
+					for path in ready {
+						pending.remove(&path);
+						// Note: the path can be gone (e.g., a temporary file), or be a directory.
+						if !path.is_file() {
+							continue;
+						}
+						if let Some(rel_path) = watched_rel_path(&local_dir, &path, opts, ignore.as_ref()) {
+							let key = join_key(key_prefix, &rel_path);
+							transfers.push(Transfer::Upload { path, key });
+						}
+					}
+
+					// UPLOAD - concurrently
+					for result in TransferManager::new(self).run(transfers).await {
+						on_upload(&result);
+					}
+				}
+			}
+		}
+	}
+}
+
+/// The relative path of a changed `path`, if it is to be uploaded (neither hidden, nor ignored, and matching the filter).
+fn watched_rel_path(
+	local_dir: &Path,
+	path: &Path,
+	opts: &WatchOptions,
+	ignore: Option<&IgnoreRules>,
+) -> Option<String> {
+	let rel_path = rel_path(local_dir, path).filter(|p| !p.is_empty())?;
+	if !opts.include_hidden && is_hidden_key(&rel_path) {
+		return None;
+	}
+	if ignore.is_some_and(|ignore| ignore.is_ignored_key(&rel_path)) {
+		return None;
+	}
+	opts.filter.is_match(&rel_path).then_some(rel_path)
+}
@@ -0,0 +1,338 @@
+//! End-to-end tests against a local S3 compatible endpoint (LocalStack, MinIO, moto, ...).
+//!
+//! Run with `cargo test --features integration --test integration`, with the endpoint in
+//! `S3_ENDPOINT_URL` (default `http://localhost:4566`, LocalStack) and its credentials in
+//! `S3_KEY_ID` / `S3_KEY_SECRET`. Each test creates (and deletes) its own bucket.
+#![cfg(feature = "integration")]
+
+use rust_aws_sdk_s3::{
+	file_sha256, AuditEntry, AuditLog, AuditOperation, ClientConfig, HookEvent, HookStatus, ObjectStore, Result,
+	S3Bucket, S3DemoError, SyncDirection, SyncOptions, TransferHooks, MAX_KEY_SIZE, MIN_PART_SIZE,
+};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+// -- constants
+const DEFAULT_TEST_ENDPOINT_URL: &str = "http://localhost:4566";
+const DEFAULT_TEST_REGION: &str = "us-east-1";
+
+static BUCKET_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A new (empty) bucket with a unique name.
+async fn new_bucket() -> Result<S3Bucket> {
+	let endpoint = env::var("S3_ENDPOINT_URL").unwrap_or_else(|_| DEFAULT_TEST_ENDPOINT_URL.to_string());
+	let region = env::var("S3_REGION").unwrap_or_else(|_| DEFAULT_TEST_REGION.to_string());
+	let client = ClientConfig::new(&region)
+		.with_endpoint(endpoint)
+		.build_client()
+		.await?;
+
+	let secs = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_secs())
+		.unwrap_or(0);
+	let count = BUCKET_COUNT.fetch_add(1, Ordering::Relaxed);
+	let name = format!("s3demo-it-{secs}-{}-{count}", std::process::id());
+	let bucket = S3Bucket::new(client, name);
+	bucket.create_bucket(&region).await?;
+
+	Ok(bucket)
+}
+
+async fn remove_bucket(bucket: S3Bucket) -> Result<()> {
+	bucket.empty_bucket().await?;
+	bucket.delete_bucket().await
+}
+
+fn write_file(dir: &Path, rel_path: &str, content: &[u8]) {
+	let path = dir.join(rel_path);
+	fs::create_dir_all(path.parent().unwrap()).unwrap();
+	fs::write(path, content).unwrap();
+}
+
+#[tokio::test]
+async fn test_upload_download() -> Result<()> {
+	let bucket = new_bucket().await?;
+	let dir = TempDir::new().unwrap();
+	write_file(dir.path(), "hello.txt", b"hello world");
+
+	bucket
+		.upload_to(&dir.path().join("hello.txt"), "docs/hello.txt")
+		.await?;
+	let stat = bucket.stat("docs/hello.txt").await?;
+	assert_eq!(stat.size, 11);
+
+	let dst = dir.path().join("out/hello.txt");
+	bucket.download_to("docs/hello.txt", &dst).await?;
+	assert_eq!(fs::read(&dst)?, b"hello world");
+
+	remove_bucket(bucket).await
+}
+
+#[tokio::test]
+async fn test_multipart_upload() -> Result<()> {
+	let bucket = new_bucket()
+		.await?
+		.with_part_size(MIN_PART_SIZE)
+		.with_multipart_threshold(MIN_PART_SIZE as u64);
+	let dir = TempDir::new().unwrap();
+	// Note: 2 full parts and a last smaller one.
+	let content = (0..MIN_PART_SIZE * 2 + 1000)
+		.map(|i| (i % 251) as u8)
+		.collect::<Vec<_>>();
+	write_file(dir.path(), "big.bin", &content);
+
+	bucket.upload_to(&dir.path().join("big.bin"), "big.bin").await?;
+	assert!(bucket.list_multipart_uploads("").await?.is_empty());
+	let objects = bucket.list_objects("big.bin").await?;
+	assert_eq!(objects[0].size, content.len() as u64);
+
+	let dst = dir.path().join("big.out");
+	bucket.download_to("big.bin", &dst).await?;
+	assert_eq!(file_sha256(&dst)?, file_sha256(&dir.path().join("big.bin"))?);
+
+	remove_bucket(bucket).await
+}
+
+#[tokio::test]
+async fn test_list_delete() -> Result<()> {
+	let bucket = new_bucket().await?;
+	let dir = TempDir::new().unwrap();
+	write_file(dir.path(), "f.txt", b"f");
+	let file = dir.path().join("f.txt");
+	for key in ["a/1.txt", "a/2.txt", "a/b/3.txt", "c/4.txt"] {
+		bucket.upload_to(&file, key).await?;
+	}
+
+	let mut keys = bucket.list("a/").await?;
+	keys.sort();
+	assert_eq!(keys, ["a/1.txt", "a/2.txt", "a/b/3.txt"]);
+
+	bucket
+		.delete_objects(&["a/1.txt".to_string(), "a/b/3.txt".to_string()])
+		.await?;
+	bucket.delete_object("c/4.txt").await?;
+	assert_eq!(bucket.list("").await?, ["a/2.txt"]);
+
+	let err = bucket.stat("c/4.txt").await.unwrap_err();
+	assert!(matches!(err, S3DemoError::NotFound(_)), "{err:?}");
+
+	remove_bucket(bucket).await
+}
+
+#[tokio::test]
+async fn test_sync() -> Result<()> {
+	let bucket = new_bucket().await?;
+	let src = TempDir::new().unwrap();
+	write_file(src.path(), "a.txt", b"aaa");
+	write_file(src.path(), "sub/b.txt", b"bbb");
+	let opts = SyncOptions::default();
+
+	let plan = bucket.sync(src.path(), "site", SyncDirection::Up, &opts).await?;
+	assert_eq!(plan.transfers.len(), 2);
+	let plan = bucket.sync(src.path(), "site", SyncDirection::Up, &opts).await?;
+	assert_eq!(plan.unchanged, 2);
+
+	let dst = TempDir::new().unwrap();
+	bucket.sync(dst.path(), "site", SyncDirection::Down, &opts).await?;
+	assert_eq!(fs::read(dst.path().join("sub/b.txt"))?, b"bbb");
+
+	remove_bucket(bucket).await
+}
+
+#[tokio::test]
+async fn test_special_keys() -> Result<()> {
+	let bucket = new_bucket().await?;
+	let dir = TempDir::new().unwrap();
+	write_file(dir.path(), "f.txt", b"special");
+	let file = dir.path().join("f.txt");
+	let keys = [
+		"sp/with space.txt",
+		"sp/a+b=c&d.txt",
+		"sp/100%.txt",
+		"sp/%2F not a slash.txt",
+		"sp/unicodé/日本語 🦀.txt",
+		"sp/ctrl\u{1}char.txt",
+		"sp/question?hash#.txt",
+	];
+	for key in keys {
+		bucket.upload_to(&file, key).await?;
+	}
+
+	// LIST - the keys as uploaded (URL encoded listings, decoded)
+	let mut listed = bucket.list("sp/").await?;
+	listed.sort();
+	let mut expected = keys.to_vec();
+	expected.sort();
+	assert_eq!(listed, expected);
+	let listing = bucket.list_dir("sp/").await?;
+	assert_eq!(listing.prefixes, ["sp/unicodé/"]);
+
+	// DOWNLOAD & COPY
+	let dst = TempDir::new().unwrap();
+	let path = bucket.download("sp/a+b=c&d.txt", dst.path()).await?;
+	assert_eq!(fs::read(path)?, b"special");
+	bucket
+		.copy_object("sp/100%.txt", bucket.name(), "copy/100% copy.txt")
+		.await?;
+	assert_eq!(bucket.stat("copy/100% copy.txt").await?.size, 7);
+
+	// PRESIGN - the key percent-encoded in the URL
+	let url = bucket.presign_get("sp/a+b=c&d.txt", Duration::from_secs(60)).await?;
+	assert!(url.contains("/sp/a%2Bb%3Dc%26d.txt?"), "{url}");
+
+	remove_bucket(bucket).await
+}
+
+#[tokio::test]
+async fn test_long_keys() -> Result<()> {
+	let bucket = new_bucket().await?;
+	let dir = TempDir::new().unwrap();
+	write_file(dir.path(), "f.txt", b"long");
+	let file = dir.path().join("f.txt");
+
+	// Note: the limit is in bytes (`é` is 2 bytes), so this key is at the limit.
+	let key = "é".repeat(MAX_KEY_SIZE / 2);
+	bucket.upload_to(&file, &key).await?;
+	assert_eq!(bucket.list("").await?.len(), 1);
+	assert_eq!(bucket.list("").await?[0], key);
+
+	let err = bucket.upload_to(&file, &format!("{key}x")).await.unwrap_err();
+	assert!(matches!(err, S3DemoError::InvalidInput(_)), "{err:?}");
+
+	remove_bucket(bucket).await
+}
+
+#[tokio::test]
+async fn test_mtime_preserved() -> Result<()> {
+	let bucket = new_bucket().await?;
+	let dir = TempDir::new().unwrap();
+	write_file(dir.path(), "old.txt", b"old");
+	let mtime = UNIX_EPOCH + Duration::from_millis(1_600_000_000_250);
+	fs::File::options()
+		.write(true)
+		.open(dir.path().join("old.txt"))?
+		.set_modified(mtime)?;
+
+	bucket.upload_to(&dir.path().join("old.txt"), "old.txt").await?;
+	assert_eq!(bucket.stat("old.txt").await?.mtime(), Some(mtime));
+
+	let dst = TempDir::new().unwrap();
+	let path = bucket.download("old.txt", dst.path()).await?;
+	assert_eq!(path.metadata()?.modified()?, mtime);
+
+	remove_bucket(bucket).await
+}
+
+/// Records the hooked transfers, rejecting the `.exe` uploads.
+#[derive(Default)]
+struct RecordingHooks {
+	events: Mutex<Vec<String>>,
+}
+
+impl TransferHooks for RecordingHooks {
+	fn before(&self, event: &HookEvent) -> Result<()> {
+		let key = event.transfer.key();
+		self.events
+			.lock()
+			.unwrap()
+			.push(format!("before {key} {:?}", event.size));
+		if key.ends_with(".exe") {
+			return Err(S3DemoError::HookFailed(format!("{key} rejected")));
+		}
+		Ok(())
+	}
+
+	fn after(&self, event: &HookEvent, status: &HookStatus) -> Result<()> {
+		let key = event.transfer.key();
+		self.events
+			.lock()
+			.unwrap()
+			.push(format!("after {key} {}", status.as_str()));
+		Ok(())
+	}
+}
+
+#[tokio::test]
+async fn test_transfer_hooks() -> Result<()> {
+	let hooks = Arc::new(RecordingHooks::default());
+	let bucket = new_bucket().await?.with_hooks(hooks.clone());
+	let dir = TempDir::new().unwrap();
+	write_file(dir.path(), "a.txt", b"aaa");
+	write_file(dir.path(), "b.exe", b"bbb");
+
+	bucket.upload_to(&dir.path().join("a.txt"), "a.txt").await?;
+	let err = bucket.upload_to(&dir.path().join("b.exe"), "b.exe").await.unwrap_err();
+	assert!(matches!(err, S3DemoError::HookFailed(_)), "{err:?}");
+	assert_eq!(bucket.list("").await?, ["a.txt"]);
+
+	let dst = TempDir::new().unwrap();
+	bucket.download_to("a.txt", &dst.path().join("a.txt")).await?;
+	assert!(bucket
+		.download_to("missing.txt", &dst.path().join("missing.txt"))
+		.await
+		.is_err());
+
+	// Note: a rejected transfer has no after hook.
+	assert_eq!(
+		*hooks.events.lock().unwrap(),
+		[
+			"before a.txt Some(3)",
+			"after a.txt ok",
+			"before b.exe Some(3)",
+			"before a.txt None",
+			"after a.txt ok",
+			"before missing.txt None",
+			"after missing.txt failed",
+		]
+	);
+
+	remove_bucket(bucket).await
+}
+
+#[tokio::test]
+async fn test_audit_log() -> Result<()> {
+	let log_dir = TempDir::new().unwrap();
+	let log_path = log_dir.path().join("audit.jsonl");
+	let bucket = new_bucket()
+		.await?
+		.with_audit_log(Arc::new(AuditLog::open(&log_path, "tester")?));
+	let dir = TempDir::new().unwrap();
+	write_file(dir.path(), "a.txt", b"aaa");
+
+	bucket.upload_to(&dir.path().join("a.txt"), "a.txt").await?;
+	bucket.copy_object("a.txt", bucket.name(), "b.txt").await?;
+	bucket
+		.delete_objects(&["a.txt".to_string(), "b.txt".to_string()])
+		.await?;
+
+	let entries = fs::read_to_string(&log_path)?
+		.lines()
+		.map(serde_json::from_str::<AuditEntry>)
+		.collect::<std::result::Result<Vec<_>, _>>()?;
+	let ops = entries
+		.iter()
+		.map(|e| (e.operation, e.key.as_str(), e.size))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		ops,
+		[
+			(AuditOperation::Put, "a.txt", Some(3)),
+			(AuditOperation::Copy, "b.txt", Some(3)),
+			(AuditOperation::Delete, "a.txt", None),
+			(AuditOperation::Delete, "b.txt", None),
+		]
+	);
+	assert_eq!(entries[0].sha256, Some(file_sha256(&dir.path().join("a.txt"))?));
+	assert_eq!(entries[1].source, Some(format!("{}/a.txt", bucket.name())));
+	assert!(entries
+		.iter()
+		.all(|e| e.requester == "tester" && e.bucket == bucket.name()));
+
+	remove_bucket(bucket).await
+}
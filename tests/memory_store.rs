@@ -0,0 +1,298 @@
+//! The directory and sync logic, against a `MemoryStore`.
+
+use rust_aws_sdk_s3::{
+	upload_dir_plan, DiffEntry, DiffReason, IgnoreRules, KeyFilter, MemoryStore, ObjectStore, Result, SymlinkMode,
+	SyncDirection, SyncOptions, UploadDirOptions, IGNORE_FILE,
+};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn write_file(dir: &Path, rel_path: &str, content: &str) {
+	let path = dir.join(rel_path);
+	fs::create_dir_all(path.parent().unwrap()).unwrap();
+	fs::write(path, content).unwrap();
+}
+
+fn sample_dir() -> TempDir {
+	let dir = TempDir::new().unwrap();
+	write_file(dir.path(), "a.txt", "aaa");
+	write_file(dir.path(), "docs/b.md", "bbb");
+	write_file(dir.path(), "docs/img/c.jpg", "ccc");
+	write_file(dir.path(), ".hidden/d.txt", "ddd");
+	dir
+}
+
+#[tokio::test]
+async fn test_upload_dir_download_prefix() -> Result<()> {
+	let store = MemoryStore::new("test");
+	let src = sample_dir();
+
+	let mut keys = store
+		.upload_dir(src.path(), "backup", &UploadDirOptions::default())
+		.await?;
+	keys.sort();
+	assert_eq!(keys, ["backup/a.txt", "backup/docs/b.md", "backup/docs/img/c.jpg"]);
+	assert_eq!(store.get("backup/docs/b.md").as_deref(), Some(&b"bbb"[..]));
+
+	let dst = TempDir::new().unwrap();
+	let files = store.download_prefix("backup/docs/", dst.path()).await?;
+	assert_eq!(files.len(), 2);
+	assert_eq!(fs::read_to_string(dst.path().join("backup/docs/img/c.jpg"))?, "ccc");
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_dir_hidden_and_filter() -> Result<()> {
+	let store = MemoryStore::new("test");
+	let src = sample_dir();
+
+	let opts = UploadDirOptions {
+		include_hidden: true,
+		filter: KeyFilter::new(&["**/*.txt".to_string()], &[])?,
+		..Default::default()
+	};
+	store.upload_dir(src.path(), "", &opts).await?;
+	assert_eq!(store.keys(), [".hidden/d.txt", "a.txt"]);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_dir_ignore_file() -> Result<()> {
+	let store = MemoryStore::new("test");
+	let src = sample_dir();
+	write_file(src.path(), "app.log", "log");
+	write_file(src.path(), "keep.log", "log");
+	write_file(src.path(), "secret.env", "secret");
+	write_file(src.path(), "docs/secret.env", "not secret");
+	write_file(src.path(), "target/debug/app", "bin");
+	write_file(src.path(), "docs/target", "a file");
+	write_file(
+		src.path(),
+		IGNORE_FILE,
+		"# build artifacts\ntarget/\n*.log\n!keep.log\n/secret.env\ndocs/img\n",
+	);
+
+	store.upload_dir(src.path(), "", &UploadDirOptions::default()).await?;
+	assert_eq!(
+		store.keys(),
+		["a.txt", "docs/b.md", "docs/secret.env", "docs/target", "keep.log"]
+	);
+
+	// all the files without the ignore file (but the hidden ones, like the ignore file itself)
+	let opts = UploadDirOptions {
+		no_ignore_file: true,
+		..Default::default()
+	};
+	let transfers = upload_dir_plan(src.path(), "", &opts)?;
+	assert_eq!(transfers.len(), 9);
+
+	Ok(())
+}
+
+#[test]
+fn test_ignore_rules() -> Result<()> {
+	let rules = IgnoreRules::parse("build/\n*.o\n!main.o\n")?;
+	assert!(rules.is_ignored_key("build/a.txt"));
+	assert!(rules.is_ignored_key("src/build/a.txt"));
+	assert!(rules.is_ignored_key("src/a.o"));
+	assert!(!rules.is_ignored_key("src/main.o"));
+	assert!(!rules.is_ignored_key("build"));
+	// Note: like git, a file of an ignored directory cannot be re-included.
+	assert!(rules.is_ignored_key("build/main.o"));
+
+	assert!(IgnoreRules::parse("a[b\n").is_err());
+
+	Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_upload_dir_symlinks() -> Result<()> {
+	use std::os::unix::fs::symlink;
+
+	let src = sample_dir();
+	symlink("a.txt", src.path().join("link.txt")).unwrap();
+	symlink("docs", src.path().join("docs-link")).unwrap();
+	symlink("missing.txt", src.path().join("broken.txt")).unwrap();
+	// Note: a loop, when followed.
+	symlink(".", src.path().join("docs/self")).unwrap();
+
+	let keys = |symlinks| -> Result<Vec<String>> {
+		let opts = UploadDirOptions {
+			symlinks,
+			..Default::default()
+		};
+		let mut keys = upload_dir_plan(src.path(), "", &opts)?
+			.iter()
+			.map(|t| t.key().to_string())
+			.collect::<Vec<_>>();
+		keys.sort();
+		Ok(keys)
+	};
+
+	assert_eq!(keys(SymlinkMode::Skip)?, ["a.txt", "docs/b.md", "docs/img/c.jpg"]);
+	assert_eq!(
+		keys(SymlinkMode::Follow)?,
+		[
+			"a.txt",
+			"docs-link/b.md",
+			"docs-link/img/c.jpg",
+			"docs/b.md",
+			"docs/img/c.jpg",
+			"link.txt"
+		]
+	);
+	assert_eq!(
+		keys(SymlinkMode::Preserve)?,
+		[
+			"a.txt",
+			"broken.txt",
+			"docs-link",
+			"docs/b.md",
+			"docs/img/c.jpg",
+			"docs/self",
+			"link.txt"
+		]
+	);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_up_unchanged_and_modified() -> Result<()> {
+	let store = MemoryStore::new("test");
+	let src = sample_dir();
+	let opts = SyncOptions::default();
+
+	let plan = store.sync(src.path(), "site", SyncDirection::Up, &opts).await?;
+	assert_eq!(plan.transfers.len(), 3);
+
+	// nothing changed
+	let plan = store.sync(src.path(), "site", SyncDirection::Up, &opts).await?;
+	assert_eq!(plan.transfers.len(), 0);
+	assert_eq!(plan.unchanged, 3);
+
+	// same size, different content
+	write_file(src.path(), "docs/b.md", "BBB");
+	let plan = store.sync(src.path(), "site", SyncDirection::Up, &opts).await?;
+	let keys = plan.transfers.iter().map(|t| t.key()).collect::<Vec<_>>();
+	assert_eq!(keys, ["site/docs/b.md"]);
+	assert_eq!(store.get("site/docs/b.md").as_deref(), Some(&b"BBB"[..]));
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_up_delete() -> Result<()> {
+	let store = MemoryStore::new("test");
+	let src = sample_dir();
+	store.put("site/old.txt", "old");
+	store.put("site/old.txt.part-0001", "old");
+	store.put("site/.keep", "");
+	store.put("site/a.txt.part-0001", "a");
+	store.put("other/x.txt", "x");
+
+	let opts = SyncOptions {
+		delete: true,
+		..Default::default()
+	};
+	let plan = store.sync_plan(src.path(), "site", SyncDirection::Up, &opts).await?;
+	// Note: the hidden object is kept (like the hidden local files), and the chunk of the local a.txt.
+	assert_eq!(plan.delete_keys, ["site/old.txt", "site/old.txt.part-0001"]);
+
+	store.sync_apply(plan).await?;
+	assert_eq!(
+		store.keys(),
+		[
+			"other/x.txt",
+			"site/.keep",
+			"site/a.txt",
+			"site/a.txt.part-0001",
+			"site/docs/b.md",
+			"site/docs/img/c.jpg"
+		]
+	);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_up_ignore_file() -> Result<()> {
+	let store = MemoryStore::new("test");
+	let src = sample_dir();
+	write_file(src.path(), IGNORE_FILE, "docs/img/\n");
+	store.put("site/docs/img/old.jpg", "old");
+	store.put("site/old.txt", "old");
+
+	let opts = SyncOptions {
+		delete: true,
+		..Default::default()
+	};
+	let plan = store.sync_plan(src.path(), "site", SyncDirection::Up, &opts).await?;
+	// Note: the ignored keys are neither uploaded nor deleted.
+	assert_eq!(plan.delete_keys, ["site/old.txt"]);
+	assert_eq!(plan.transfers.len(), 2);
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_down_delete() -> Result<()> {
+	let store = MemoryStore::new("test");
+	store.put("site/a.txt", "aaa");
+	store.put("site/docs/b.md", "bbb");
+	let dst = TempDir::new().unwrap();
+	write_file(dst.path(), "a.txt", "aaa");
+	write_file(dst.path(), "extra.txt", "extra");
+
+	let opts = SyncOptions {
+		delete: true,
+		..Default::default()
+	};
+	let plan = store.sync(dst.path(), "site", SyncDirection::Down, &opts).await?;
+	assert_eq!(plan.unchanged, 1);
+	assert_eq!(plan.transfers.len(), 1);
+	assert_eq!(fs::read_to_string(dst.path().join("docs/b.md"))?, "bbb");
+	assert!(!dst.path().join("extra.txt").exists());
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_diff() -> Result<()> {
+	let store = MemoryStore::new("test");
+	let src = sample_dir();
+	store.put("site/a.txt", "aaa");
+	store.put("site/docs/b.md", "BBB");
+	store.put("site/docs/old.md", "old");
+	store.put("site/.keep", "");
+
+	let diff = store.diff(src.path(), "site", &UploadDirOptions::default()).await?;
+	// Note: the hidden files and objects are not compared (without include_hidden).
+	assert_eq!(diff.only_local, ["docs/img/c.jpg"]);
+	assert_eq!(diff.only_remote, ["docs/old.md"]);
+	assert_eq!(
+		diff.different,
+		[DiffEntry {
+			path: "docs/b.md".to_string(),
+			reason: DiffReason::Checksum,
+			local_size: 3,
+			remote_size: 3,
+		}]
+	);
+	assert_eq!(diff.identical, 1);
+	assert!(!diff.is_empty());
+
+	write_file(src.path(), "a.txt", "aaaa");
+	let diff = store.diff(src.path(), "site", &UploadDirOptions::default()).await?;
+	assert_eq!(diff.different[0].path, "a.txt");
+	assert_eq!(diff.different[0].reason, DiffReason::Size);
+	assert_eq!(diff.identical, 0);
+	// Note: nothing transferred.
+	assert_eq!(store.get("site/docs/b.md").as_deref(), Some(&b"BBB"[..]));
+
+	Ok(())
+}
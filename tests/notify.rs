@@ -0,0 +1,104 @@
+//! The notifications of the finished runs, to a local webhook.
+
+use rust_aws_sdk_s3::{ClientConfig, Notifier, NotifyTarget, Result, RunSummary, S3DemoError};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+#[test]
+fn test_notify_target_parse() -> Result<()> {
+	let target = "arn:aws:sns:eu-west-1:123456789012:backups".parse::<NotifyTarget>()?;
+	assert_eq!(
+		target,
+		NotifyTarget::Sns {
+			topic_arn: "arn:aws:sns:eu-west-1:123456789012:backups".to_string(),
+			region: "eu-west-1".to_string()
+		}
+	);
+	assert!(matches!(
+		"https://hooks.example.com/s3demo".parse::<NotifyTarget>()?,
+		NotifyTarget::Webhook(_)
+	));
+	for target in [
+		"arn:aws:sns::123456789012:backups",
+		"ftp://example.com",
+		"hooks.example.com",
+	] {
+		assert!(target.parse::<NotifyTarget>().is_err(), "{target} should be rejected");
+	}
+
+	Ok(())
+}
+
+/// A webhook answering the next request with `status`, returning the request.
+async fn webhook(status: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+	let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+	let url = format!("http://{}/hook", listener.local_addr().unwrap());
+	let handle = tokio::spawn(async move {
+		let (mut socket, _) = listener.accept().await.unwrap();
+		let mut request = Vec::new();
+		let mut buf = [0; 4096];
+		// Note: the whole request, per its Content-Length.
+		loop {
+			let n = socket.read(&mut buf).await.unwrap();
+			if n == 0 {
+				break;
+			}
+			request.extend_from_slice(&buf[..n]);
+			let text = String::from_utf8_lossy(&request).to_string();
+			if let Some((head, body)) = text.split_once("\r\n\r\n") {
+				let length = head
+					.lines()
+					.find_map(|l| {
+						l.to_ascii_lowercase()
+							.strip_prefix("content-length: ")
+							.map(|v| v.to_string())
+					})
+					.and_then(|v| v.parse::<usize>().ok())
+					.unwrap_or(0);
+				if body.len() >= length {
+					break;
+				}
+			}
+		}
+		let response = format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+		socket.write_all(response.as_bytes()).await.unwrap();
+		String::from_utf8_lossy(&request).to_string()
+	});
+	(url, handle)
+}
+
+#[tokio::test]
+async fn test_webhook_notification() -> Result<()> {
+	let (url, request) = webhook("200 OK").await;
+	let notifier = Notifier::new(url.parse()?, &ClientConfig::new("us-east-1")).await?;
+	let summary = RunSummary {
+		command: "sync".to_string(),
+		status: "ok".to_string(),
+		transferred: 3,
+		bytes: 42,
+		..Default::default()
+	};
+	notifier.notify(&summary).await?;
+
+	let request = request.await.unwrap();
+	assert!(request.starts_with("POST /hook "), "{request}");
+	assert!(request.to_ascii_lowercase().contains("content-type: application/json"));
+	let (_, body) = request.split_once("\r\n\r\n").unwrap();
+	let json: serde_json::Value = serde_json::from_str(body)?;
+	assert_eq!(json["transferred"], 3);
+	assert_eq!(json["bytes"], 42);
+	assert_eq!(json["failed"], 0);
+	assert_eq!(json["errors"], serde_json::json!([]));
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_webhook_notification_failed() -> Result<()> {
+	let (url, _request) = webhook("503 Service Unavailable").await;
+	let notifier = Notifier::new(url.parse()?, &ClientConfig::new("us-east-1")).await?;
+	let err = notifier.notify(&RunSummary::default()).await.unwrap_err();
+	assert!(matches!(err, S3DemoError::NotificationFailed(_)), "{err:?}");
+
+	Ok(())
+}
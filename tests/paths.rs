@@ -0,0 +1,143 @@
+//! The mapping between the local paths and the keys (always `/` separated).
+
+use rust_aws_sdk_s3::{
+	key_path, path_key, upload_dir_plan, MemoryStore, ObjectStore, Result, S3DemoError, UploadDirOptions,
+};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+#[test]
+fn test_path_key_slashes() {
+	assert_eq!(path_key(Path::new("docs/img/c.jpg")).as_deref(), Some("docs/img/c.jpg"));
+	assert_eq!(path_key(Path::new("a.txt")).as_deref(), Some("a.txt"));
+}
+
+#[cfg(windows)]
+#[test]
+fn test_path_key_windows_separators() {
+	assert_eq!(
+		path_key(Path::new(r"docs\img\c.jpg")).as_deref(),
+		Some("docs/img/c.jpg")
+	);
+	assert_eq!(
+		path_key(Path::new(r"docs/img\c.jpg")).as_deref(),
+		Some("docs/img/c.jpg")
+	);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_path_key_unix_backslash() {
+	// Note: a file name character on Unix, not a separator.
+	assert_eq!(path_key(Path::new(r"docs/a\b.txt")).as_deref(), Some(r"docs/a\b.txt"));
+}
+
+#[test]
+fn test_key_path() -> Result<()> {
+	let dir = Path::new("out");
+	assert_eq!(
+		key_path(dir, "docs/img/c.jpg")?,
+		dir.join("docs").join("img").join("c.jpg")
+	);
+	// Note: the empty and `.` names are skipped.
+	assert_eq!(key_path(dir, "docs//b.md")?, dir.join("docs").join("b.md"));
+	assert_eq!(key_path(dir, "/docs/./b.md")?, dir.join("docs").join("b.md"));
+
+	Ok(())
+}
+
+#[test]
+fn test_key_path_unsafe() {
+	let dir = Path::new("out");
+	for key in [
+		"../../etc/cron.d/x",
+		"docs/../../x",
+		"..",
+		"docs/\u{1b}[2Jb.md",
+		"a\nb",
+		"/",
+		"./",
+	] {
+		assert!(key_path(dir, key).is_err(), "{key:?} should be rejected");
+	}
+}
+
+#[cfg(windows)]
+#[test]
+fn test_key_path_windows_separators() -> Result<()> {
+	let path = key_path(Path::new("out"), "docs/img/c.jpg")?;
+	assert_eq!(path.to_str(), Some(r"out\docs\img\c.jpg"));
+	for key in [r"docs\..\..\x", "C:/Windows/x", "C:x", r"\\server\share\x"] {
+		assert!(key_path(Path::new("out"), key).is_err(), "{key:?} should be rejected");
+	}
+	Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_key_path_unix() -> Result<()> {
+	let path = key_path(Path::new("out"), "docs/img/c.jpg")?;
+	assert_eq!(path.to_str(), Some("out/docs/img/c.jpg"));
+	// Note: a file name on Unix (not a separator, nor a drive).
+	let path = key_path(Path::new("out"), r"docs\..\C:x")?;
+	assert_eq!(path.to_str(), Some(r"out/docs\..\C:x"));
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_download_keys() -> Result<()> {
+	let src = TempDir::new().unwrap();
+	fs::create_dir_all(src.path().join("docs").join("img")).unwrap();
+	fs::write(src.path().join("docs").join("img").join("c.jpg"), "ccc").unwrap();
+
+	// the keys are `/` separated, whatever the platform
+	let transfers = upload_dir_plan(src.path(), "backup", &UploadDirOptions::default())?;
+	let keys = transfers.iter().map(|t| t.key()).collect::<Vec<_>>();
+	assert_eq!(keys, ["backup/docs/img/c.jpg"]);
+
+	// the downloaded paths have the platform separators
+	let store = MemoryStore::new("test");
+	store
+		.upload_dir(src.path(), "backup", &UploadDirOptions::default())
+		.await?;
+	let dst = TempDir::new().unwrap();
+	let files = store.download_prefix("backup/", dst.path()).await?;
+	let expected = dst.path().join("backup").join("docs").join("img").join("c.jpg");
+	assert_eq!(files.len(), 1);
+	assert_eq!(files[0], expected);
+	assert_eq!(fs::read_to_string(expected)?, "ccc");
+
+	Ok(())
+}
+
+#[tokio::test]
+async fn test_download_prefix_unsafe_key() -> Result<()> {
+	let store = MemoryStore::new("test");
+	store.put("backup/a.txt", "aaa");
+	store.put("backup/../../x", "xxx");
+
+	let dst = TempDir::new().unwrap();
+	let res = store.download_prefix("backup/", dst.path()).await;
+	assert!(res.is_err());
+	// Note: rejected when planned, so nothing is downloaded.
+	assert!(fs::read_dir(dst.path())?.next().is_none());
+
+	Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_upload_dir_plan_non_utf8() {
+	use std::ffi::OsStr;
+	use std::os::unix::ffi::OsStrExt;
+
+	let src = TempDir::new().unwrap();
+	fs::write(src.path().join("a.txt"), "aaa").unwrap();
+	fs::write(src.path().join(OsStr::from_bytes(b"bad-\xff.txt")), "bad").unwrap();
+
+	// Note: rejected rather than uploaded to a lossy (`\u{fffd}`) key.
+	let err = upload_dir_plan(src.path(), "backup", &UploadDirOptions::default()).unwrap_err();
+	assert!(matches!(err, S3DemoError::InvalidInput(_)), "{err:?}");
+	assert_eq!(path_key(&src.path().join(OsStr::from_bytes(b"bad-\xff.txt"))), None);
+}